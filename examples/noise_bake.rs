@@ -0,0 +1,151 @@
+// noise_bake.rs
+//
+// Tiempos del ruido horneado (`BakedNoise`) contra el ruido evaluado en vivo:
+//
+// 1. Horneado sin y con domain warping, con la diferencia contra `noise_field` en puntos al
+//    azar del tile que cubre el modelo.
+// 2. Caché en disco: el primer horneado contra la lectura del archivo.
+// 3. Una esfera con `noise_based_fragment_shader` renderizada con NoiseSource::Live y
+//    NoiseSource::Baked, y la diferencia entre las dos imágenes.
+//
+// La calidad, las costuras y la caché las comprueba tests/noise_bake.rs.
+//
+//   cargo run --release --example noise_bake
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use nalgebra_glm::{Vec2, Vec3};
+use renderer::color::Color;
+use renderer::framebuffer::{render, Framebuffer};
+use renderer::material::Material;
+use renderer::math::BoundingBox;
+use renderer::noise_bake::{tile_for_bounds, BakedNoise};
+use renderer::shader::{noise_based_fragment_shader, noise_field, NoiseSettings};
+use renderer::uniforms::{viewport_matrix, NoiseSource, UniformsBuilder};
+use renderer::vertex::Vertex;
+
+const WIDTH: usize = 400;
+const HEIGHT: usize = 400;
+const SAMPLES: usize = 20_000;
+const FRAMES: usize = 10;
+
+// xorshift64*: suficiente para puntos de prueba reproducibles
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+// Esfera UV de radio 1, en triángulos sueltos
+fn sphere(rings: usize, segments: usize) -> Vec<Vertex> {
+    let point = |ring: usize, segment: usize| {
+        let (theta, phi) = (std::f32::consts::PI * ring as f32 / rings as f32, std::f32::consts::TAU * segment as f32 / segments as f32);
+        let normal = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+        let mut vertex = Vertex::new(normal, normal, Vec2::zeros());
+        vertex.color = Color::new(255, 255, 255);
+        vertex
+    };
+    let mut vertices = Vec::new();
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let (a, b, c, d) = (point(ring, segment), point(ring + 1, segment), point(ring + 1, segment + 1), point(ring, segment + 1));
+            vertices.extend([a.clone(), b, c.clone(), a, c, d]);
+        }
+    }
+    vertices
+}
+
+fn main() {
+    let material = Material { noise_scale: 2.0, ..Material::default() };
+    let bounds = BoundingBox { min: Vec3::repeat(-1.0), max: Vec3::repeat(1.0) };
+
+    for warp in [0.0, 0.8] {
+        let settings = NoiseSettings::noise_shader(&material, warp, 0.0);
+        let tile = tile_for_bounds(&bounds, material.noise_scale, warp);
+        let start = Instant::now();
+        let baked = BakedNoise::for_material(&settings, &material, &bounds, None);
+        let bake_time = start.elapsed().as_secs_f64() * 1000.0;
+
+        // Puntos en la parte del tile que cubre el modelo
+        let (low, high) = (bounds.min.x * material.noise_scale, bounds.max.x * material.noise_scale);
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        let (mut total, mut worst, mut seam) = (0.0f32, 0.0f32, 0.0f32);
+        for _ in 0..SAMPLES {
+            let x = low + rng.next() * (high - low);
+            let y = low + rng.next() * (high - low);
+            let error = (baked.sample(x, y, 0.0) - noise_field(x, y, &settings, None)).abs();
+            total += error;
+            worst = worst.max(error);
+            seam = seam.max((baked.sample(x, y, 0.0) - baked.sample(x + tile.period, y - tile.period, 0.0)).abs());
+        }
+        let mean = total / SAMPLES as f32;
+        println!(
+            "warp {} (tile desde {} con período {}): {} capas de {}x{} en {:.0} ms; error promedio {:.4}, máximo {:.4}; diferencia en la costura {:.2e}",
+            warp, tile.origin.x, tile.period, baked.layer_count(), baked.size(), baked.size(), bake_time, mean, worst, seam
+        );
+    }
+
+    // Caché: el segundo horneado se lee del disco
+    let cache_dir = std::env::temp_dir().join(format!("noise_bake_example_{}", std::process::id()));
+    let settings = NoiseSettings::noise_shader(&material, 0.8, 0.0);
+    let tile = tile_for_bounds(&bounds, material.noise_scale, settings.warp);
+    let start = Instant::now();
+    let first = BakedNoise::bake_cached(&settings, tile, 256, 3, 4.0, &cache_dir);
+    let bake_time = start.elapsed().as_secs_f64() * 1000.0;
+    let start = Instant::now();
+    let second = BakedNoise::bake_cached(&settings, tile, 256, 3, 4.0, &cache_dir);
+    let load_time = start.elapsed().as_secs_f64() * 1000.0;
+    let same = (0..1000).all(|i| {
+        let (x, y, time) = (i as f32 * 0.013, i as f32 * 0.007, i as f32 * 0.011);
+        first.sample(x, y, time).to_bits() == second.sample(x, y, time).to_bits()
+    });
+    println!("Caché: horneado {:.1} ms, leído {:.1} ms, mismos valores: {}", bake_time, load_time, same);
+    let _ = std::fs::remove_dir_all(&cache_dir);
+
+    // Render de una esfera con el shader de ruido
+    let settings = NoiseSettings::noise_shader(&material, 0.0, 0.0);
+    let baked = Arc::new(BakedNoise::for_material(&settings, &material, &bounds, None));
+    let vertices = sphere(48, 96);
+    let mut images = Vec::new();
+    let mut times = Vec::new();
+    for source in [NoiseSource::Live, NoiseSource::Baked] {
+        let mut uniforms = UniformsBuilder::new()
+            .viewport(viewport_matrix(WIDTH, HEIGHT, WIDTH as f32 * 0.45))
+            .material(Material {
+                base_color: Color::new(30, 60, 160),
+                rim_color: Color::new(230, 220, 180),
+                fragment_shader: Some(noise_based_fragment_shader),
+                baked_noise: Some(Arc::clone(&baked)),
+                ..material.clone()
+            })
+            .build();
+        uniforms.noise_source = source;
+
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(Color::black());
+        render(&mut framebuffer, &uniforms, &vertices); // Calentamiento
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            framebuffer.clear(Color::black());
+            render(&mut framebuffer, &uniforms, &vertices);
+        }
+        let per_frame = start.elapsed().as_secs_f64() * 1000.0 / FRAMES as f64;
+        println!("{:?}: {:.2} ms por frame", source, per_frame);
+        times.push(per_frame);
+        images.push(framebuffer.as_slice().to_vec());
+    }
+    println!("Aceleración con el ruido horneado: {:.2}x", times[0] / times[1]);
+
+    let channel_difference: u64 = images[0]
+        .iter()
+        .zip(&images[1])
+        .map(|(&a, &b)| (0..3).map(|shift| ((a >> (shift * 8)) & 0xFF).abs_diff((b >> (shift * 8)) & 0xFF) as u64).sum::<u64>())
+        .sum();
+    println!("Diferencia promedio por canal entre las imágenes: {:.3}", channel_difference as f64 / (WIDTH * HEIGHT * 3) as f64);
+}
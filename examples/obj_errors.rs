@@ -0,0 +1,84 @@
+// obj_errors.rs
+//
+// Errores de sintaxis del cargador de OBJ: cada archivo mal formado debe fallar con
+// `LoadError::ParseError` en la línea y la columna del valor que falla, con el texto de esa
+// línea como contexto, y el mensaje debe mostrar la línea con la marca `^` debajo de la
+// columna. Los casos cubren números inválidos, valores que faltan o sobran en `v`/`vt`/`vn`
+// y caras con vértices que faltan o índices fuera de rango. Un archivo válido (con tabs y
+// comentarios) se sigue cargando.
+//
+//   cargo run --example obj_errors
+//
+// Termina con error si algún caso no da la línea, la columna o el contexto esperados.
+
+use std::error::Error;
+
+use renderer::obj::{LoadError, Obj, ParseError};
+
+const HEADER: &str = "# triángulo\nv 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\n";
+
+// (línea mal formada, columna esperada, parte esperada del mensaje)
+const CASES: &[(&str, usize, &str)] = &[
+    ("v 1.0 2.0 x", 11, "no es un número"),
+    ("v 1 2", 7, "al menos 3"),
+    ("v 1 2 3 4", 9, "3 o 6"),
+    ("v 1 2 3 4 5", 9, "tiene 5"),
+    ("v 1 2 3 4 5 6 7", 15, "tiene 7"),
+    ("vt 0.5", 8, "al menos 2"),
+    ("vn 0 1 0 0", 10, "tiene 4"),
+    ("f 1 2", 7, "al menos 3 vértices"),
+    ("f 1 2 9", 7, "índice de vértice"),
+    ("f 1//1 2//1 3//x", 16, "índice de normal"),
+    ("f 1 2/ 3/1/1/1", 8, "vértice de cara inválido"),
+    ("\tf 1 2 0", 8, "índice de vértice"),
+];
+
+fn main() {
+    let mut failed = false;
+
+    for &(bad_line, col, expected) in CASES {
+        let source = format!("{}{}\n", HEADER, bad_line);
+        let line = HEADER.lines().count() + 1;
+        match Obj::from_source(&source) {
+            Err(LoadError::ParseError(error)) => {
+                let ok = error.line == line && error.col == col && error.context == bad_line && error.message.contains(expected);
+                // La segunda línea del mensaje es la del archivo y la tercera la marca
+                let shown = error.to_string();
+                let lines: Vec<&str> = shown.lines().collect();
+                let caret = lines.get(2).and_then(|marker| marker.strip_prefix("    ")).map(|marker| marker.chars().count());
+                let marked = lines.get(1) == Some(&format!("    {}", bad_line).as_str()) && caret == Some(col);
+                println!("{:<18} línea {} columna {}: {} [{}]", format!("`{}`", bad_line.trim()), error.line, error.col, error.message, if ok && marked { "ok" } else { "MAL" });
+                failed |= !ok || !marked;
+            }
+            Err(other) => {
+                println!("{:<18} error inesperado: {}", format!("`{}`", bad_line.trim()), other);
+                failed = true;
+            }
+            Ok(_) => {
+                println!("{:<18} se cargó sin error", format!("`{}`", bad_line.trim()));
+                failed = true;
+            }
+        }
+    }
+
+    // El error se puede tratar como `std::error::Error` y su origen es el `ParseError`
+    let error = Obj::from_source(&format!("{}v 1 2 y\n", HEADER)).err().expect("el archivo es inválido");
+    let source_is_parse_error = error.source().is_some_and(|source| source.downcast_ref::<ParseError>().is_some());
+    println!("Mensaje completo:\n{}\nEl origen es un ParseError: {}", error, source_is_parse_error);
+    failed |= !source_is_parse_error;
+
+    // Archivo válido con tabs, comentarios al final de la línea, colores por vértice y UVs
+    // con w
+    let valid = "v\t0 0 0 1 1 1\nv 1 0 0 1 0 0 # esquina\nv 0 1 0 0 1 0\nvt 0 0\nvt 1 0 0\nvt 0 1\nf 1/1 2/2 3/3\n";
+    match Obj::from_source(valid) {
+        Ok(obj) => println!("Archivo válido: {} vértices", obj.vertices.len()),
+        Err(e) => {
+            println!("El archivo válido no se cargó: {}", e);
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,97 @@
+// parallel_vertices.rs
+//
+// Benchmark de la etapa del vertex shader en paralelo contra un solo hilo, sobre una malla
+// sintética de 200k vértices (triángulos chicos repartidos sobre una esfera, con sombreado
+// Gouraud y datos de vista para que cada vértice haga todo el trabajo del vertex shader):
+// la etapa sola y el render completo con 1 hilo y con uno por núcleo (con un solo núcleo no
+// hay aceleración que medir). Que los dos den lo mismo bit a bit lo comprueba tests/parallel_vertices.rs.
+//
+//   cargo run --release --example parallel_vertices
+
+use std::time::Instant;
+
+use nalgebra_glm::{Vec2, Vec3};
+use renderer::color::Color;
+use renderer::framebuffer::{render, Framebuffer, RenderStats};
+use renderer::light::Light;
+use renderer::pipeline::{PipelineStage, StageContext, StageData, VertexShaderStage};
+use renderer::shader::DefaultVertexShader;
+use renderer::uniforms::{viewport_matrix, ShadingModel, Uniforms, UniformsBuilder};
+use renderer::vertex::Vertex;
+
+const WIDTH: usize = 800;
+const HEIGHT: usize = 600;
+const TRIANGLES: usize = 66_667; // 200.001 vértices
+const RUNS: usize = 10;
+
+// Triángulos chicos sobre una esfera de radio 2.5, cada uno en una dirección de la espiral
+// de Fibonacci
+fn synthetic_mesh() -> Vec<Vertex> {
+    let golden = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+    let mut vertices = Vec::with_capacity(TRIANGLES * 3);
+    for i in 0..TRIANGLES {
+        let y = 1.0 - 2.0 * (i as f32 + 0.5) / TRIANGLES as f32;
+        let ring = (1.0 - y * y).sqrt();
+        let normal = Vec3::new(ring * (golden * i as f32).cos(), y, ring * (golden * i as f32).sin());
+        let tangent = normal.cross(&Vec3::new(0.3, 1.0, 0.2)).normalize();
+        let bitangent = normal.cross(&tangent);
+        let center = normal * 2.5;
+        for offset in [tangent * 0.03, bitangent * 0.03, -tangent * 0.03] {
+            let mut vertex = Vertex::new(center + offset, normal, Vec2::new(y, ring));
+            vertex.color = Color::new(200, 180, 160);
+            vertices.push(vertex);
+        }
+    }
+    vertices
+}
+
+fn transform_all(uniforms: &Uniforms, vertices: &[Vertex]) -> Vec<Vertex> {
+    let mut framebuffer = Framebuffer::new(1, 1);
+    let mut context = StageContext { uniforms, framebuffer: &mut framebuffer, stats: RenderStats::default() };
+    let stage = VertexShaderStage { shader: &DefaultVertexShader };
+    match stage.process(StageData::Vertices(vertices.to_vec()), &mut context) {
+        StageData::Vertices(transformed) => transformed,
+        _ => unreachable!("el vertex shader devuelve vértices"),
+    }
+}
+
+fn render_frame(uniforms: &Uniforms, vertices: &[Vertex]) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    framebuffer.clear(Color::black());
+    render(&mut framebuffer, uniforms, vertices);
+    framebuffer
+}
+
+fn in_pool<T: Send>(threads: usize, job: impl FnOnce() -> T + Send) -> T {
+    rayon::ThreadPoolBuilder::new().num_threads(threads).build().expect("pool de prueba").install(job)
+}
+
+// Milisegundos promedio de `job` en un pool de `threads` hilos
+fn time_in_pool(threads: usize, mut job: impl FnMut() + Send) -> f64 {
+    in_pool(threads, || {
+        job(); // Calentamiento
+        let start = Instant::now();
+        for _ in 0..RUNS {
+            job();
+        }
+        start.elapsed().as_secs_f64() * 1000.0 / RUNS as f64
+    })
+}
+
+fn main() {
+    let vertices = synthetic_mesh();
+    let mut uniforms = UniformsBuilder::new()
+        .viewport(viewport_matrix(WIDTH, HEIGHT, 100.0))
+        .lights(vec![Light::new(Vec3::new(-1.0, -1.0, 1.0), Color::new(255, 255, 255), 1.0)])
+        .build();
+    uniforms.shading_model = ShadingModel::Gouraud;
+    uniforms.fragment_view_data = true;
+
+    let threads = std::thread::available_parallelism().map_or(1, |count| count.get());
+    let stage_serial = time_in_pool(1, || drop(transform_all(&uniforms, &vertices)));
+    let stage_parallel = time_in_pool(threads, || drop(transform_all(&uniforms, &vertices)));
+    println!("Vertex shader: {:.2} ms con 1 hilo, {:.2} ms con {} ({:.2}x)", stage_serial, stage_parallel, threads, stage_serial / stage_parallel);
+    let render_serial = time_in_pool(1, || drop(render_frame(&uniforms, &vertices)));
+    let render_parallel = time_in_pool(threads, || drop(render_frame(&uniforms, &vertices)));
+    println!("Render completo: {:.2} ms con 1 hilo, {:.2} ms con {} ({:.2}x)", render_serial, render_parallel, threads, render_serial / render_parallel);
+}
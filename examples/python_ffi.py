@@ -0,0 +1,46 @@
+# python_ffi.py
+#
+# Renderiza el cubo desde Python usando la API en C del renderer (src/ffi.rs).
+#
+#   cargo build --release --features ffi
+#   python3 examples/python_ffi.py
+#
+# Guarda el frame como frame.ppm y no necesita paquetes fuera de la biblioteca estándar.
+
+import ctypes
+import sys
+from pathlib import Path
+
+WIDTH, HEIGHT = 800, 600
+
+root = Path(__file__).resolve().parent.parent
+library_name = {"win32": "renderer.dll", "darwin": "librenderer.dylib"}.get(sys.platform, "librenderer.so")
+lib = ctypes.CDLL(str(root / "target" / "release" / library_name))
+
+lib.renderer_create.argtypes = [ctypes.c_uint32, ctypes.c_uint32]
+lib.renderer_create.restype = ctypes.c_void_p
+lib.renderer_load_obj.argtypes = [ctypes.c_void_p, ctypes.c_char_p]
+lib.renderer_load_obj.restype = ctypes.c_bool
+lib.renderer_render_frame.argtypes = [ctypes.c_void_p]
+lib.renderer_render_frame.restype = ctypes.POINTER(ctypes.c_uint32)
+lib.renderer_destroy.argtypes = [ctypes.c_void_p]
+lib.renderer_destroy.restype = None
+
+renderer = lib.renderer_create(WIDTH, HEIGHT)
+if not renderer:
+    sys.exit("No se pudo crear el renderer")
+
+try:
+    if not lib.renderer_load_obj(renderer, str(root / "assets" / "cube.obj").encode()):
+        sys.exit("No se pudo cargar assets/cube.obj")
+
+    pixels = lib.renderer_render_frame(renderer)
+    # Copiar antes de la siguiente llamada: el buffer pertenece al renderer
+    frame = pixels[: WIDTH * HEIGHT]
+
+    with open("frame.ppm", "wb") as out:
+        out.write(b"P6 %d %d 255\n" % (WIDTH, HEIGHT))
+        out.write(bytes(c for p in frame for c in ((p >> 16) & 0xFF, (p >> 8) & 0xFF, p & 0xFF)))
+    print("frame.ppm escrito")
+finally:
+    lib.renderer_destroy(renderer)
@@ -0,0 +1,107 @@
+// quad_raster.rs
+//
+// Benchmark de la rasterización por cuadros de 2x2 (RasterizerMode::Quads) con un cuadrado
+// a pantalla completa: solo la etapa de cobertura (filas escalares, filas con SIMD y
+// cuadros) y el render completo con `render`, por Bounding Box y por cuadros. En el render
+// completo la cobertura es una parte chica del tiempo (la construcción y el sombreado de
+// cada fragmento dominan), así que ahí la diferencia es mucho menor. Que las dos rutas den
+// los mismos pixeles lo comprueba tests/quad_raster.rs.
+//
+//   cargo run --release --example quad_raster
+
+use std::time::Instant;
+
+use nalgebra_glm::{Vec2, Vec3};
+use renderer::color::Color;
+use renderer::edge_function::{covered_in_quads, covered_in_row, covered_in_row_scalar, TriangleSetup};
+use renderer::framebuffer::{render, Framebuffer};
+use renderer::material::Material;
+use renderer::uniforms::{viewport_matrix, CullMode, RasterizerMode, UniformsBuilder};
+use renderer::vertex::Vertex;
+
+const WIDTH: usize = 800;
+const HEIGHT: usize = 600;
+const FRAMES: usize = 30;
+
+fn full_screen_quad() -> Vec<Vertex> {
+    let corner = |x: f32, y: f32| {
+        let mut vertex = Vertex::new(Vec3::new(x, y, 0.5 + 0.1 * x), Vec3::new(0.0, 0.0, -1.0), Vec2::zeros());
+        vertex.color = Color::new((128.0 + 100.0 * x) as u8, (128.0 + 100.0 * y) as u8, 200);
+        vertex
+    };
+    let (a, b, c, d) = (corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0));
+    vec![a.clone(), b, c.clone(), a, c, d]
+}
+
+fn main() {
+    // Solo cobertura (y profundidad) de los dos triángulos del cuadrado a pantalla completa
+    let screen = [
+        (Vec3::new(0.0, 0.0, 0.4), Vec3::new(WIDTH as f32, 0.0, 0.6), Vec3::new(WIDTH as f32, HEIGHT as f32, 0.6)),
+        (Vec3::new(0.0, 0.0, 0.4), Vec3::new(WIDTH as f32, HEIGHT as f32, 0.6), Vec3::new(0.0, HEIGHT as f32, 0.4)),
+    ];
+    let (max_x, max_y) = (WIDTH as i32 - 1, HEIGHT as i32 - 1);
+    let mut coverage_times = Vec::new();
+    for name in ["filas escalares", "filas SIMD", "cuadros 2x2"] {
+        let mut pixels = 0;
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            for (a, b, c) in &screen {
+                let setup = TriangleSetup::new(a, b, c).expect("triángulo no degenerado");
+                let depths = Vec3::new(a.z, b.z, c.z);
+                let (mut rows, mut quads) = (Vec::new(), Vec::new());
+                if name == "cuadros 2x2" {
+                    for y in (0..=max_y).step_by(2) {
+                        quads.clear();
+                        covered_in_quads(&setup, &depths, y, 0, max_x, max_y, &mut quads);
+                        pixels += quads.len();
+                    }
+                } else {
+                    for y in 0..=max_y {
+                        rows.clear();
+                        if name == "filas SIMD" {
+                            covered_in_row(&setup, y, 0, max_x, &mut rows);
+                        } else {
+                            covered_in_row_scalar(&setup, y, 0, max_x, &mut rows);
+                        }
+                        // La profundidad va aparte, como en el rasterizador
+                        let depth: f32 = rows.iter().map(|p| p.u * depths.x + p.v * depths.y + p.w * depths.z).sum();
+                        std::hint::black_box(depth);
+                        pixels += rows.len();
+                    }
+                }
+            }
+        }
+        let per_frame = start.elapsed().as_secs_f64() * 1000.0 / FRAMES as f64;
+        println!("Cobertura por {}: {:.2} ms por frame ({} pixeles)", name, per_frame, pixels / FRAMES);
+        coverage_times.push(per_frame);
+    }
+    println!(
+        "Aceleración de la cobertura por cuadros: {:.2}x sobre escalar, {:.2}x sobre filas SIMD",
+        coverage_times[0] / coverage_times[2],
+        coverage_times[1] / coverage_times[2]
+    );
+
+    let quad = full_screen_quad();
+    let mut times = Vec::new();
+    for mode in [RasterizerMode::BoundingBox, RasterizerMode::Quads] {
+        let mut uniforms = UniformsBuilder::new()
+            .viewport(viewport_matrix(WIDTH, HEIGHT, WIDTH as f32 / 2.0))
+            .material(Material { unlit: true, ..Material::default() })
+            .build();
+        uniforms.cull_mode = CullMode::None;
+        uniforms.rasterizer_mode = mode;
+
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(Color::black());
+        render(&mut framebuffer, &uniforms, &quad); // Calentamiento
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            framebuffer.clear(Color::black());
+            render(&mut framebuffer, &uniforms, &quad);
+        }
+        let per_frame = start.elapsed().as_secs_f64() * 1000.0 / FRAMES as f64;
+        println!("{:?}: {:.2} ms por frame", mode, per_frame);
+        times.push(per_frame);
+    }
+    println!("Aceleración del render completo con Quads sobre BoundingBox: {:.2}x", times[0] / times[1]);
+}
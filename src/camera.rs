@@ -0,0 +1,187 @@
+// camera.rs
+//
+// Proyección en perspectiva con planos cercano y lejano configurables. La cámara mira
+// hacia +z en el espacio de vista (igual que VIEW_DIR en los shaders), así que la matriz
+// de vista debe dejar la escena delante, en z positiva.
+
+use nalgebra_glm::{Mat4, Vec3};
+use crate::framebuffer::DepthMode;
+use crate::shader_params::ShaderParams;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Projection {
+    pub fov_y: f32, // Campo de visión vertical en radianes
+    pub near: f32,
+    pub far: f32,
+    // Con DepthMode::Reversed el plano cercano queda en profundidad 1 y el lejano en 0; el
+    // framebuffer debe usar el mismo modo (`Framebuffer::set_depth_mode`)
+    pub depth_mode: DepthMode,
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection { fov_y: 60f32.to_radians(), near: 0.1, far: 100.0, depth_mode: DepthMode::Standard }
+    }
+}
+
+impl Projection {
+    // Lee la tabla [camera] de shaders.toml: fov (grados), near, far y reversed_z
+    pub fn from_params(params: &ShaderParams) -> Self {
+        let default = Projection::default();
+        let near = params.float("camera.near", default.near).max(f32::EPSILON);
+        Projection {
+            fov_y: params.float("camera.fov", default.fov_y.to_degrees()).clamp(1.0, 179.0).to_radians(),
+            near,
+            far: params.float("camera.far", default.far).max(near * 2.0),
+            depth_mode: if params.flag("camera.reversed_z", false) { DepthMode::Reversed } else { DepthMode::Standard },
+        }
+    }
+
+    pub fn matrix(&self, aspect: f32) -> Mat4 {
+        perspective_matrix(self.fov_y, aspect, self.near, self.far, self.depth_mode)
+    }
+
+    // El menor de los campos de visión vertical y horizontal con ese aspecto: lo que hay
+    // que llenar para que algo quepa en los dos ejes
+    pub fn fit_fov(&self, aspect: f32) -> f32 {
+        let horizontal = 2.0 * ((self.fov_y / 2.0).tan() * aspect).atan();
+        self.fov_y.min(horizontal)
+    }
+}
+
+// Cámara en `eye` mirando hacia `center`, con `up` como referencia para la vertical (no
+// hace falta que sea perpendicular a la dirección de la vista)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub eye: Vec3,
+    pub center: Vec3,
+    pub up: Vec3,
+}
+
+impl Camera {
+    // Cámara sin rotación: mira hacia +z con +y hacia arriba
+    pub fn new(eye: Vec3) -> Camera {
+        Camera::looking_at(eye, eye + Vec3::z(), Vec3::y())
+    }
+
+    pub fn looking_at(eye: Vec3, center: Vec3, up: Vec3) -> Camera {
+        Camera { eye, center, up }
+    }
+
+    // Encuadra una esfera: el ojo sobre su centro, hacia -z, a la distancia a la que la
+    // esfera toca los bordes de un campo de visión `fov` (radianes, ver `Projection::fit_fov`)
+    pub fn frame(center: Vec3, radius: f32, fov: f32) -> Camera {
+        Camera::looking_at(center - Vec3::z() * framing_distance(radius, fov), center, Vec3::y())
+    }
+
+    // Base ortonormal de la vista en el mundo (derecha, arriba, adelante): adelante va del
+    // ojo al centro, derecha = up x adelante y arriba = adelante x derecha. Si `up` es
+    // paralelo a la vista se usa otro eje para la derecha
+    pub fn basis(&self) -> (Vec3, Vec3, Vec3) {
+        let forward = (self.center - self.eye).try_normalize(f32::EPSILON).unwrap_or_else(Vec3::z);
+        let right = self.up.cross(&forward).try_normalize(f32::EPSILON).unwrap_or_else(|| {
+            let axis = if forward.x.abs() < 0.9 { Vec3::x() } else { Vec3::y() };
+            axis.cross(&forward).normalize()
+        });
+        (right, forward.cross(&right), forward)
+    }
+
+    // Matriz look-at: lleva el ojo al origen y la base de `basis` a los ejes x, y, z (la
+    // vista mira hacia +z). Las filas de la rotación son la base
+    pub fn view_matrix(&self) -> Mat4 {
+        let (right, up, forward) = self.basis();
+        Mat4::new(
+            right.x,   right.y,   right.z,   -right.dot(&self.eye),
+            up.x,      up.y,      up.z,      -up.dot(&self.eye),
+            forward.x, forward.y, forward.z, -forward.dot(&self.eye),
+            0.0,       0.0,       0.0,       1.0,
+        )
+    }
+}
+
+// Distancia del ojo al centro de una esfera de radio `radius` para que quede tangente a los
+// bordes de un campo de visión `fov`: sin(fov / 2) = radio / distancia
+pub fn framing_distance(radius: f32, fov: f32) -> f32 {
+    radius.max(0.0) / (fov / 2.0).sin().max(f32::EPSILON)
+}
+
+// Proyección en perspectiva: x/y quedan en [-1, 1] dentro del campo de visión y, después
+// de dividir por w (la distancia z), la profundidad va de 0 en `near` a 1 en `far`, o al
+// revés con DepthMode::Reversed. La profundidad es hiperbólica: con float, la precisión
+// estándar se concentra cerca de la cámara y se pierde lejos, mientras que con reversed-Z
+// la precisión relativa de los floats cerca de 0 compensa esa curva.
+pub fn perspective_matrix(fov_y: f32, aspect: f32, near: f32, far: f32, depth_mode: DepthMode) -> Mat4 {
+    let focal = 1.0 / (fov_y / 2.0).tan();
+    // z_ndc = a + b / z: 0 -> 1 (estándar) o 1 -> 0 (reversed) entre near y far
+    let (a, b) = match depth_mode {
+        DepthMode::Standard => (far / (far - near), -far * near / (far - near)),
+        DepthMode::Reversed => (near / (near - far), -far * near / (near - far)),
+    };
+    Mat4::new(
+        focal / aspect, 0.0,   0.0, 0.0,
+        0.0,            focal, 0.0, 0.0,
+        0.0,            0.0,   a,   b,
+        0.0,            0.0,   1.0, 0.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec4;
+
+    fn close(a: Vec4, b: Vec4) -> bool {
+        (a - b).abs().max() < 1e-6
+    }
+
+    fn close3(a: Vec3, b: Vec3) -> bool {
+        (a - b).abs().max() < 1e-5
+    }
+
+    // Sin rotación la vista es solo la traslación del ojo, como antes del look-at
+    #[test]
+    fn unrotated_camera_only_translates() {
+        let camera = Camera::new(Vec3::new(1.5, -2.0, -6.0));
+        assert_eq!(camera.view_matrix(), nalgebra_glm::translation(&Vec3::new(-1.5, 2.0, 6.0)));
+        assert_eq!(camera.basis(), (Vec3::x(), Vec3::y(), Vec3::z()));
+    }
+
+    // Cámara girada con un `up` que no es perpendicular a la vista: la vista inversa (de la
+    // cámara al mundo) tiene en sus columnas la base armada con productos cruz y el ojo, y
+    // la vista lleva el centro al eje +z
+    #[test]
+    fn view_matrix_columns_hold_the_camera_basis() {
+        let (eye, center, up) = (Vec3::new(1.5, -2.0, -6.0), Vec3::new(4.0, 1.0, 2.0), Vec3::new(0.3, 1.0, 0.2));
+        let camera = Camera::looking_at(eye, center, up);
+        let forward = (center - eye).normalize();
+        let right = up.cross(&forward).normalize();
+        let true_up = forward.cross(&right);
+        assert!(right.dot(&true_up).abs() < 1e-6 && true_up.dot(&up) > 0.0);
+
+        let view = camera.view_matrix();
+        let inverse = view.try_inverse().unwrap();
+        let columns = [right.push(0.0), true_up.push(0.0), forward.push(0.0), eye.push(1.0)];
+        for (i, expected) in columns.into_iter().enumerate() {
+            let column: Vec4 = inverse.column(i).into_owned();
+            assert!(close(column, expected), "columna {i}: {column:?} en vez de {expected:?}");
+            if i < 3 {
+                // La rotación es ortonormal: sus filas son las mismas columnas
+                let row: Vec4 = view.row(i).transpose().into_owned();
+                assert!(close3(row.xyz(), expected.xyz()), "fila {i}: {row:?}");
+            }
+        }
+        assert!(close(view * center.push(1.0), Vec4::new(0.0, 0.0, (center - eye).norm(), 1.0)));
+    }
+
+    // Con `up` paralelo a la vista la base sigue siendo ortonormal y mira al centro
+    #[test]
+    fn up_parallel_to_the_view_still_gives_a_basis() {
+        let camera = Camera::looking_at(Vec3::zeros(), Vec3::new(0.0, 5.0, 0.0), Vec3::y());
+        let (right, up, forward) = camera.basis();
+        assert!(close3(forward, Vec3::y()));
+        for (a, b) in [(right, up), (up, forward), (forward, right)] {
+            assert!(a.dot(&b).abs() < 1e-6);
+        }
+        assert!(close3(right.cross(&up), forward));
+    }
+}
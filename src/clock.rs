@@ -0,0 +1,192 @@
+// clock.rs
+
+use std::time::{Duration, Instant};
+
+// Fuente de tiempo del render loop. Permite usar tiempo real o un paso fijo
+// para que los renders headless y las grabaciones sean reproducibles.
+pub trait Clock {
+    // Avanza el reloj un frame y devuelve el delta en segundos
+    fn tick(&mut self) -> f32;
+    // Tiempo acumulado en segundos
+    fn time(&self) -> f32;
+}
+
+// Reloj de pared: el delta es el tiempo real transcurrido entre frames
+pub struct RealClock {
+    start: Instant,
+    last: Instant,
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        RealClock { start: now, last: now }
+    }
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for RealClock {
+    fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        let delta = now.duration_since(self.last).as_secs_f32();
+        self.last = now;
+        delta
+    }
+
+    fn time(&self) -> f32 {
+        self.last.duration_since(self.start).as_secs_f32()
+    }
+}
+
+// Reloj determinista: cada frame avanza exactamente `step` segundos
+pub struct FixedStepClock {
+    step: f32,
+    frames: u64,
+}
+
+impl FixedStepClock {
+    pub fn new(step: f32) -> Self {
+        FixedStepClock { step, frames: 0 }
+    }
+}
+
+impl Clock for FixedStepClock {
+    fn tick(&mut self) -> f32 {
+        self.frames += 1;
+        self.step
+    }
+
+    // Se calcula desde el número de frames para no acumular error de punto flotante
+    fn time(&self) -> f32 {
+        self.frames as f32 * self.step
+    }
+}
+
+// Margen final que se espera activamente: `thread::sleep` puede despertar tarde por
+// alrededor de un milisegundo según el planificador del sistema
+const SPIN_MARGIN: Duration = Duration::from_millis(1);
+
+// Limita el render loop a un FPS objetivo: mide cuánto tardó el frame y duerme solo lo
+// que falta para completar el intervalo, en lugar de una pausa fija
+pub struct FramePacer {
+    interval: Option<Duration>, // None = sin límite
+    frame_start: Instant,
+}
+
+impl FramePacer {
+    // `target_fps` <= 0 desactiva el límite
+    pub fn new(target_fps: f32) -> Self {
+        FramePacer { interval: frame_interval(target_fps), frame_start: Instant::now() }
+    }
+
+    pub fn set_target_fps(&mut self, target_fps: f32) {
+        self.interval = frame_interval(target_fps);
+    }
+
+    // Espera hasta el final del intervalo del frame actual y empieza el siguiente. Si el
+    // frame se pasó del intervalo no espera, y el siguiente cuenta desde ahora para no
+    // intentar recuperar el retraso con frames seguidos
+    pub fn wait(&mut self) {
+        let Some(interval) = self.interval else {
+            self.frame_start = Instant::now();
+            return;
+        };
+        let remaining = remaining_sleep(self.frame_start.elapsed(), interval);
+        if remaining.is_zero() {
+            self.frame_start = Instant::now();
+            return;
+        }
+
+        let deadline = self.frame_start + interval;
+        if remaining > SPIN_MARGIN {
+            std::thread::sleep(remaining - SPIN_MARGIN);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+        self.frame_start = deadline;
+    }
+}
+
+fn frame_interval(target_fps: f32) -> Option<Duration> {
+    (target_fps > 0.0 && target_fps.is_finite()).then(|| Duration::from_secs_f32(1.0 / target_fps))
+}
+
+// Tiempo que falta para completar `interval` después de un frame que tardó `elapsed`
+pub fn remaining_sleep(elapsed: Duration, interval: Duration) -> Duration {
+    interval.saturating_sub(elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::framebuffer::{render, Framebuffer};
+    use crate::material::Material;
+    use crate::uniforms::{viewport_matrix, CullMode, UniformsBuilder};
+    use crate::vertex::Vertex;
+    use nalgebra_glm::{Vec2, Vec3};
+
+    // Quad de 2x2 unidades con el escape del motor, cuyo ruido se mueve con el tiempo
+    fn quad() -> Vec<Vertex> {
+        let corner = |x: f32, y: f32| Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec2::new(x + 1.0, (y + 1.0) / 2.0));
+        vec![corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)]
+    }
+
+    // Renderiza `frames` frames avanzando el tiempo con el reloj
+    fn render_frames(clock: &mut dyn Clock, frames: usize) -> Vec<Vec<u32>> {
+        let mut framebuffer = Framebuffer::new(32, 32);
+        (0..frames)
+            .map(|_| {
+                clock.tick();
+                let uniforms = UniformsBuilder::new()
+                    .viewport(viewport_matrix(32, 32, 14.0))
+                    .material(Material::exhaust())
+                    .cull_mode(CullMode::None)
+                    .throttle(1.0)
+                    .time(clock.time())
+                    .build();
+                framebuffer.clear(Color::black());
+                render(&mut framebuffer, &uniforms, &quad());
+                framebuffer.as_slice().to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fixed_step_renders_are_bit_identical() {
+        let first = render_frames(&mut FixedStepClock::new(1.0 / 30.0), 3);
+        let second = render_frames(&mut FixedStepClock::new(1.0 / 30.0), 3);
+        assert_eq!(first, second);
+        // El shader está animado: si los frames no cambiaran la prueba no diría nada
+        assert_ne!(first[0], first[2]);
+    }
+
+    #[test]
+    fn pacer_sleeps_only_the_rest_of_the_interval() {
+        let interval = frame_interval(60.0).unwrap();
+        assert_eq!(interval, Duration::from_secs_f32(1.0 / 60.0));
+        assert_eq!(remaining_sleep(Duration::from_millis(4), Duration::from_millis(16)), Duration::from_millis(12));
+        assert_eq!(remaining_sleep(Duration::ZERO, interval), interval);
+        // Un frame que se pasó del intervalo no espera
+        assert_eq!(remaining_sleep(Duration::from_millis(25), Duration::from_millis(16)), Duration::ZERO);
+        assert_eq!(frame_interval(0.0), None);
+        assert_eq!(frame_interval(f32::INFINITY), None);
+    }
+
+    #[test]
+    fn pacer_waits_until_the_end_of_the_frame() {
+        let mut pacer = FramePacer::new(50.0);
+        let start = Instant::now();
+        pacer.wait();
+        pacer.wait();
+        // Dos frames de 20 ms (el segundo cuenta desde el final del primero)
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(39), "{elapsed:?}");
+    }
+}
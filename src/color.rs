@@ -1,4 +1,5 @@
 use std::ops::{Add, Mul};
+use nalgebra_glm::Vec3;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Color {
@@ -26,6 +27,76 @@ impl Color {
         Ok(Color { r, g, b })
     }
 
+    // Constructor a partir de un pixel empaquetado 0xAARRGGBB del framebuffer
+    pub fn from_u32(value: u32) -> Color {
+        Color {
+            r: ((value >> 16) & 0xFF) as u8,
+            g: ((value >> 8) & 0xFF) as u8,
+            b: (value & 0xFF) as u8,
+        }
+    }
+
+    // Empaqueta el color como 0xAARRGGBB (alfa opaco), el formato que usa minifb
+    pub fn to_u32(&self) -> u32 {
+        (255 << 24) | ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+    }
+
+    // Luminancia relativa (Rec. 709) en el rango [0, 1]
+    pub fn luminance(&self) -> f32 {
+        (0.2126 * self.r as f32 + 0.7152 * self.g as f32 + 0.0722 * self.b as f32) / 255.0
+    }
+
+    // Color aproximado de un cuerpo negro a la temperatura dada (aproximación de
+    // Tanner Helland). Se limita a 2000K–30000K: rojo -> blanco (~6600K) -> azul.
+    // Las constantes van tal como las publica la aproximación, aunque f32 no las distinga
+    #[allow(clippy::excessive_precision)]
+    pub fn from_temperature(kelvin: f32) -> Color {
+        let t = kelvin.clamp(2000.0, 30000.0) / 100.0;
+
+        let r = if t <= 66.0 {
+            255.0
+        } else {
+            329.698727446 * (t - 60.0).powf(-0.1332047592)
+        };
+        let g = if t <= 66.0 {
+            99.4708025861 * t.ln() - 161.1195681661
+        } else {
+            288.1221695283 * (t - 60.0).powf(-0.0755148492)
+        };
+        let b = if t >= 66.0 {
+            255.0
+        } else if t <= 19.0 {
+            0.0
+        } else {
+            138.5177312231 * (t - 10.0).ln() - 305.0447927307
+        };
+
+        Color::new(Color::clamp(r as i32), Color::clamp(g as i32), Color::clamp(b as i32))
+    }
+
+    // Producto componente a componente (p. ej. un color iluminado por una luz de color)
+    pub fn tint(&self, other: &Color) -> Color {
+        Color {
+            r: ((self.r as u16 * other.r as u16) / 255) as u8,
+            g: ((self.g as u16 * other.g as u16) / 255) as u8,
+            b: ((self.b as u16 * other.b as u16) / 255) as u8,
+        }
+    }
+
+    // Canales en [0, 1]
+    pub fn to_vec3(&self) -> Vec3 {
+        Vec3::new(self.r as f32, self.g as f32, self.b as f32) / 255.0
+    }
+
+    // Multiplica cada canal por su factor (p. ej. la luz acumulada por canal), con clamping
+    pub fn modulate(&self, factors: &Vec3) -> Color {
+        Color {
+            r: Color::clamp((self.r as f32 * factors.x) as i32),
+            g: Color::clamp((self.g as f32 * factors.y) as i32),
+            b: Color::clamp((self.b as f32 * factors.z) as i32),
+        }
+    }
+
     // Método para crear un color negro
     pub fn black() -> Color {
         Color { r: 0, g: 0, b: 0 }
@@ -93,4 +164,110 @@ impl Add for Color {
             b: Color::clamp(self.b as i32 + other.b as i32),
         }
     }
-}
\ No newline at end of file
+}
+
+// Rampa de colores: paradas (t, Color) ordenadas por t. Sirve para mapear un escalar
+// (altura, ruido, temperatura...) a un color sin cadenas de if/else en cada shader.
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    stops: Vec<(f32, Color)>,
+}
+
+impl Gradient {
+    // Crea la rampa a partir de paradas en cualquier orden
+    pub fn from_stops(stops: &[(f32, Color)]) -> Gradient {
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Gradient { stops }
+    }
+
+    // Rampas predefinidas por nombre: "lava", "earth", "ice", "sunset"
+    pub fn preset(name: &str) -> Option<Gradient> {
+        let stops: &[(f32, Color)] = match name {
+            "lava" => &[
+                (0.0, Color::new(20, 0, 0)),
+                (0.4, Color::new(160, 20, 0)),
+                (0.7, Color::new(255, 110, 0)),
+                (1.0, Color::new(255, 240, 120)),
+            ],
+            "earth" => &[
+                (0.0, Color::new(10, 30, 110)),
+                (0.45, Color::new(40, 100, 200)),
+                (0.5, Color::new(210, 200, 140)),
+                (0.6, Color::new(50, 140, 50)),
+                (0.85, Color::new(110, 80, 50)),
+                (1.0, Color::new(250, 250, 250)),
+            ],
+            "ice" => &[
+                (0.0, Color::new(20, 50, 90)),
+                (0.5, Color::new(140, 200, 230)),
+                (1.0, Color::new(255, 255, 255)),
+            ],
+            "sunset" => &[
+                (0.0, Color::new(40, 10, 80)),
+                (0.4, Color::new(200, 50, 90)),
+                (0.7, Color::new(255, 140, 50)),
+                (1.0, Color::new(255, 230, 150)),
+            ],
+            _ => return None,
+        };
+        Some(Gradient::from_stops(stops))
+    }
+
+    // Color en `t`, interpolando linealmente entre las paradas vecinas.
+    // Antes de la primera parada o después de la última se usa el color del extremo.
+    pub fn sample(&self, t: f32) -> Color {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Color::black(),
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        if t >= last.0 {
+            return last.1;
+        }
+
+        // Primera parada con t mayor: el intervalo es [next - 1, next]
+        let next = self.stops.partition_point(|stop| stop.0 <= t);
+        let (t0, c0) = self.stops[next - 1];
+        let (t1, c1) = self.stops[next];
+        let f = (t - t0) / (t1 - t0);
+        let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * f).round() as u8;
+        Color::new(lerp(c0.r, c1.r), lerp(c0.g, c1.g), lerp(c0.b, c1.b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp() -> Gradient {
+        // Desordenada a propósito: `from_stops` ordena las paradas
+        Gradient::from_stops(&[(0.75, Color::new(255, 255, 255)), (0.25, Color::new(0, 0, 0)), (0.5, Color::new(200, 100, 0))])
+    }
+
+    #[test]
+    fn gradient_clamps_outside_the_stops() {
+        assert_eq!(ramp().sample(-3.0).to_u32(), Color::new(0, 0, 0).to_u32());
+        assert_eq!(ramp().sample(0.25).to_u32(), Color::new(0, 0, 0).to_u32());
+        assert_eq!(ramp().sample(0.75).to_u32(), Color::new(255, 255, 255).to_u32());
+        assert_eq!(ramp().sample(7.0).to_u32(), Color::new(255, 255, 255).to_u32());
+    }
+
+    #[test]
+    fn gradient_hits_and_interpolates_stops() {
+        assert_eq!(ramp().sample(0.5).to_u32(), Color::new(200, 100, 0).to_u32());
+        assert_eq!(ramp().sample(0.375).to_u32(), Color::new(100, 50, 0).to_u32());
+        assert_eq!(ramp().sample(0.625).to_u32(), Color::new(228, 178, 128).to_u32());
+    }
+
+    #[test]
+    fn gradient_presets_and_empty_ramp() {
+        for name in ["lava", "earth", "ice", "sunset"] {
+            assert!(Gradient::preset(name).is_some(), "{name}");
+        }
+        assert!(Gradient::preset("plaid").is_none());
+        assert_eq!(Gradient::from_stops(&[]).sample(0.5).to_u32(), Color::black().to_u32());
+    }
+}
@@ -0,0 +1,267 @@
+// edge_function.rs
+//
+// Coordenadas baricéntricas por fila de pixeles, evaluadas con SIMD cuando el CPU lo
+// permite (AVX2: 8 pixeles a la vez, SSE2: 4) y con una versión escalar como referencia.
+// También por cuadros de 2x2 pixeles (`covered_in_quads`, dos filas a la vez, como las
+// GPUs), que además interpolan la profundidad en los pixeles cubiertos. Todas las
+// variantes hacen exactamente las mismas operaciones en el mismo orden, así que producen
+// los mismos pesos y profundidades bit a bit.
+
+use nalgebra_glm::Vec3;
+
+// Pixel cubierto por el triángulo con sus pesos baricéntricos (u, v, w)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoveredPixel {
+    pub x: i32,
+    pub u: f32,
+    pub v: f32,
+    pub w: f32,
+}
+
+// Pixel cubierto en la rasterización por cuadros, con su fila y su profundidad interpolada
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuadPixel {
+    pub x: i32,
+    pub y: i32,
+    pub u: f32,
+    pub v: f32,
+    pub w: f32,
+    pub depth: f32,
+}
+
+// Coeficientes constantes de un triángulo en pantalla:
+// u = (ux * (px - cx) + uy * (py - cy)) / area, v igual con (vx, vy), w = 1 - u - v
+#[derive(Debug, Clone, Copy)]
+pub struct TriangleSetup {
+    ux: f32,
+    uy: f32,
+    vx: f32,
+    vy: f32,
+    cx: f32,
+    cy: f32,
+    inv_area: f32,
+}
+
+impl TriangleSetup {
+    // None si el triángulo es degenerado en pantalla
+    pub fn new(a: &Vec3, b: &Vec3, c: &Vec3) -> Option<Self> {
+        let ux = b.y - c.y;
+        let uy = c.x - b.x;
+        let vx = c.y - a.y;
+        let vy = a.x - c.x;
+        let area = ux * (a.x - c.x) + uy * (a.y - c.y);
+        if area == 0.0 || !area.is_finite() {
+            return None;
+        }
+
+        Some(TriangleSetup { ux, uy, vx, vy, cx: c.x, cy: c.y, inv_area: 1.0 / area })
+    }
+
+    // Pesos baricéntricos del punto (px, py)
+    pub fn weights(&self, px: f32, py: f32) -> (f32, f32, f32) {
+        let dx = px - self.cx;
+        let dy = py - self.cy;
+        let u = (self.ux * dx + self.uy * dy) * self.inv_area;
+        let v = (self.vx * dx + self.vy * dy) * self.inv_area;
+        (u, v, 1.0 - u - v)
+    }
+}
+
+// Agrega a `out` los pixeles de la fila `y` en [min_x, max_x] que caen dentro del triángulo
+pub fn covered_in_row(setup: &TriangleSetup, y: i32, min_x: i32, max_x: i32, out: &mut Vec<CoveredPixel>) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: se acaba de comprobar que el CPU soporta AVX2
+            unsafe { covered_in_row_avx2(setup, y, min_x, max_x, out) };
+        } else {
+            // SAFETY: SSE2 es parte de la base de x86_64
+            unsafe { covered_in_row_sse2(setup, y, min_x, max_x, out) };
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    covered_in_row_scalar(setup, y, min_x, max_x, out);
+}
+
+// Versión escalar de referencia
+pub fn covered_in_row_scalar(setup: &TriangleSetup, y: i32, min_x: i32, max_x: i32, out: &mut Vec<CoveredPixel>) {
+    for x in min_x..=max_x {
+        let (u, v, w) = setup.weights(x as f32, y as f32);
+        if u >= 0.0 && v >= 0.0 && w >= 0.0 {
+            out.push(CoveredPixel { x, u, v, w });
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn covered_in_row_sse2(setup: &TriangleSetup, y: i32, min_x: i32, max_x: i32, out: &mut Vec<CoveredPixel>) {
+    use std::arch::x86_64::*;
+
+    let dy = _mm_set1_ps(y as f32 - setup.cy);
+    let u_row = _mm_mul_ps(_mm_set1_ps(setup.uy), dy);
+    let v_row = _mm_mul_ps(_mm_set1_ps(setup.vy), dy);
+    let (ux, vx) = (_mm_set1_ps(setup.ux), _mm_set1_ps(setup.vx));
+    let (cx, inv_area) = (_mm_set1_ps(setup.cx), _mm_set1_ps(setup.inv_area));
+    let (zero, one) = (_mm_setzero_ps(), _mm_set1_ps(1.0));
+
+    let mut x = min_x;
+    while x + 3 <= max_x {
+        let px = _mm_setr_ps(x as f32, (x + 1) as f32, (x + 2) as f32, (x + 3) as f32);
+        let dx = _mm_sub_ps(px, cx);
+        let u = _mm_mul_ps(_mm_add_ps(_mm_mul_ps(ux, dx), u_row), inv_area);
+        let v = _mm_mul_ps(_mm_add_ps(_mm_mul_ps(vx, dx), v_row), inv_area);
+        let w = _mm_sub_ps(_mm_sub_ps(one, u), v);
+
+        let inside = _mm_and_ps(_mm_and_ps(_mm_cmpge_ps(u, zero), _mm_cmpge_ps(v, zero)), _mm_cmpge_ps(w, zero));
+        let mask = _mm_movemask_ps(inside);
+        if mask != 0 {
+            let (mut us, mut vs, mut ws) = ([0.0f32; 4], [0.0f32; 4], [0.0f32; 4]);
+            _mm_storeu_ps(us.as_mut_ptr(), u);
+            _mm_storeu_ps(vs.as_mut_ptr(), v);
+            _mm_storeu_ps(ws.as_mut_ptr(), w);
+            for lane in 0..4 {
+                if mask & (1 << lane) != 0 {
+                    out.push(CoveredPixel { x: x + lane as i32, u: us[lane], v: vs[lane], w: ws[lane] });
+                }
+            }
+        }
+        x += 4;
+    }
+
+    // Pixeles sobrantes al final de la fila
+    covered_in_row_scalar(setup, y, x, max_x, out);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn covered_in_row_avx2(setup: &TriangleSetup, y: i32, min_x: i32, max_x: i32, out: &mut Vec<CoveredPixel>) {
+    use std::arch::x86_64::*;
+
+    let dy = _mm256_set1_ps(y as f32 - setup.cy);
+    let u_row = _mm256_mul_ps(_mm256_set1_ps(setup.uy), dy);
+    let v_row = _mm256_mul_ps(_mm256_set1_ps(setup.vy), dy);
+    let (ux, vx) = (_mm256_set1_ps(setup.ux), _mm256_set1_ps(setup.vx));
+    let (cx, inv_area) = (_mm256_set1_ps(setup.cx), _mm256_set1_ps(setup.inv_area));
+    let (zero, one) = (_mm256_setzero_ps(), _mm256_set1_ps(1.0));
+    let lanes = _mm256_setr_ps(0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0);
+
+    let mut x = min_x;
+    while x + 7 <= max_x {
+        let px = _mm256_add_ps(_mm256_set1_ps(x as f32), lanes);
+        let dx = _mm256_sub_ps(px, cx);
+        let u = _mm256_mul_ps(_mm256_add_ps(_mm256_mul_ps(ux, dx), u_row), inv_area);
+        let v = _mm256_mul_ps(_mm256_add_ps(_mm256_mul_ps(vx, dx), v_row), inv_area);
+        let w = _mm256_sub_ps(_mm256_sub_ps(one, u), v);
+
+        let inside = _mm256_and_ps(
+            _mm256_and_ps(_mm256_cmp_ps::<_CMP_GE_OQ>(u, zero), _mm256_cmp_ps::<_CMP_GE_OQ>(v, zero)),
+            _mm256_cmp_ps::<_CMP_GE_OQ>(w, zero),
+        );
+        let mask = _mm256_movemask_ps(inside);
+        if mask != 0 {
+            let (mut us, mut vs, mut ws) = ([0.0f32; 8], [0.0f32; 8], [0.0f32; 8]);
+            _mm256_storeu_ps(us.as_mut_ptr(), u);
+            _mm256_storeu_ps(vs.as_mut_ptr(), v);
+            _mm256_storeu_ps(ws.as_mut_ptr(), w);
+            for lane in 0..8 {
+                if mask & (1 << lane) != 0 {
+                    out.push(CoveredPixel { x: x + lane as i32, u: us[lane], v: vs[lane], w: ws[lane] });
+                }
+            }
+        }
+        x += 8;
+    }
+
+    covered_in_row_scalar(setup, y, x, max_x, out);
+}
+
+// Agrega a `out` los pixeles de las filas `y` e `y + 1` (la segunda solo si no pasa de
+// `max_y`) en [min_x, max_x] que caen dentro del triángulo, evaluando cuadros de 2x2
+// pixeles a la vez. `depths` son las z en pantalla de los tres vértices, interpoladas como
+// u * z0 + v * z1 + w * z2. Los pixeles salen cuadro por cuadro, y dentro de cada cuadro
+// fila por fila. Donde no entra un cuadro completo (la última columna o la última fila de
+// un bounding box impar) se usa la versión escalar
+pub fn covered_in_quads(setup: &TriangleSetup, depths: &Vec3, y: i32, min_x: i32, max_x: i32, max_y: i32, out: &mut Vec<QuadPixel>) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        // SAFETY: SSE2 es parte de la base de x86_64
+        unsafe { covered_in_quads_sse2(setup, depths, y, min_x, max_x, max_y, out) };
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    covered_in_quads_scalar(setup, depths, y, min_x, max_x, max_y, out);
+}
+
+// Versión escalar de referencia, en el mismo orden que la de SIMD
+pub fn covered_in_quads_scalar(setup: &TriangleSetup, depths: &Vec3, y: i32, min_x: i32, max_x: i32, max_y: i32, out: &mut Vec<QuadPixel>) {
+    let rows = if y < max_y { 2 } else { 1 };
+    let mut x = min_x;
+    while x <= max_x {
+        let columns = if x < max_x { 2 } else { 1 };
+        for row in 0..rows {
+            for column in 0..columns {
+                let (px, py) = (x + column, y + row);
+                let (u, v, w) = setup.weights(px as f32, py as f32);
+                if u >= 0.0 && v >= 0.0 && w >= 0.0 {
+                    let depth = u * depths.x + v * depths.y + w * depths.z;
+                    out.push(QuadPixel { x: px, y: py, u, v, w, depth });
+                }
+            }
+        }
+        x += 2;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn covered_in_quads_sse2(setup: &TriangleSetup, depths: &Vec3, y: i32, min_x: i32, max_x: i32, max_y: i32, out: &mut Vec<QuadPixel>) {
+    use std::arch::x86_64::*;
+
+    if y >= max_y {
+        covered_in_quads_scalar(setup, depths, y, min_x, max_x, max_y, out);
+        return;
+    }
+
+    // Carriles del cuadro: (x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)
+    let dy = _mm_sub_ps(_mm_setr_ps(y as f32, y as f32, (y + 1) as f32, (y + 1) as f32), _mm_set1_ps(setup.cy));
+    let u_rows = _mm_mul_ps(_mm_set1_ps(setup.uy), dy);
+    let v_rows = _mm_mul_ps(_mm_set1_ps(setup.vy), dy);
+    let (ux, vx) = (_mm_set1_ps(setup.ux), _mm_set1_ps(setup.vx));
+    let (cx, inv_area) = (_mm_set1_ps(setup.cx), _mm_set1_ps(setup.inv_area));
+    let (z0, z1, z2) = (_mm_set1_ps(depths.x), _mm_set1_ps(depths.y), _mm_set1_ps(depths.z));
+    let (zero, one) = (_mm_setzero_ps(), _mm_set1_ps(1.0));
+    let lane_x = [0, 1, 0, 1];
+    let lane_y = [0, 0, 1, 1];
+
+    let mut x = min_x;
+    while x < max_x {
+        let px = _mm_setr_ps(x as f32, (x + 1) as f32, x as f32, (x + 1) as f32);
+        let dx = _mm_sub_ps(px, cx);
+        let u = _mm_mul_ps(_mm_add_ps(_mm_mul_ps(ux, dx), u_rows), inv_area);
+        let v = _mm_mul_ps(_mm_add_ps(_mm_mul_ps(vx, dx), v_rows), inv_area);
+        let w = _mm_sub_ps(_mm_sub_ps(one, u), v);
+
+        let inside = _mm_and_ps(_mm_and_ps(_mm_cmpge_ps(u, zero), _mm_cmpge_ps(v, zero)), _mm_cmpge_ps(w, zero));
+        let mask = _mm_movemask_ps(inside);
+        if mask != 0 {
+            // Profundidad solo para los cuadros con algún pixel cubierto
+            let depth = _mm_add_ps(_mm_add_ps(_mm_mul_ps(u, z0), _mm_mul_ps(v, z1)), _mm_mul_ps(w, z2));
+            let (mut us, mut vs, mut ws, mut zs) = ([0.0f32; 4], [0.0f32; 4], [0.0f32; 4], [0.0f32; 4]);
+            _mm_storeu_ps(us.as_mut_ptr(), u);
+            _mm_storeu_ps(vs.as_mut_ptr(), v);
+            _mm_storeu_ps(ws.as_mut_ptr(), w);
+            _mm_storeu_ps(zs.as_mut_ptr(), depth);
+            for lane in 0..4 {
+                if mask & (1 << lane) != 0 {
+                    out.push(QuadPixel { x: x + lane_x[lane], y: y + lane_y[lane], u: us[lane], v: vs[lane], w: ws[lane], depth: zs[lane] });
+                }
+            }
+        }
+        x += 2;
+    }
+
+    // Última columna de un bounding box de ancho impar
+    covered_in_quads_scalar(setup, depths, y, x, max_x, max_y, out);
+}
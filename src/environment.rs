@@ -0,0 +1,115 @@
+// environment.rs
+//
+// Mapa de entorno en forma de cubemap: seis caras cuadradas de colores que se consultan
+// con una dirección. Lo usan los materiales reflectivos para reflejar el espacio que
+// rodea la escena. Las direcciones están en el espacio transformado (y hacia abajo en
+// pantalla, la cámara mira hacia +z).
+
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::shader::{cellular_noise_seeded, smoothstep};
+
+// Caras en el orden +X, -X, +Y, -Y, +Z, -Z
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    // Dirección que corresponde a las coordenadas (u, v) en [-1, 1] de la cara
+    fn direction(self, u: f32, v: f32) -> Vec3 {
+        match self {
+            CubeFace::PositiveX => Vec3::new(1.0, -v, -u),
+            CubeFace::NegativeX => Vec3::new(-1.0, -v, u),
+            CubeFace::PositiveY => Vec3::new(u, 1.0, v),
+            CubeFace::NegativeY => Vec3::new(u, -1.0, -v),
+            CubeFace::PositiveZ => Vec3::new(u, -v, 1.0),
+            CubeFace::NegativeZ => Vec3::new(-u, -v, -1.0),
+        }
+    }
+
+    // Cara y coordenadas (u, v) en [-1, 1] de una dirección (inversa de `direction`)
+    fn from_direction(d: &Vec3) -> (CubeFace, f32, f32) {
+        let (ax, ay, az) = (d.x.abs(), d.y.abs(), d.z.abs());
+        if ax >= ay && ax >= az {
+            if d.x > 0.0 {
+                (CubeFace::PositiveX, -d.z / ax, -d.y / ax)
+            } else {
+                (CubeFace::NegativeX, d.z / ax, -d.y / ax)
+            }
+        } else if ay >= az {
+            if d.y > 0.0 {
+                (CubeFace::PositiveY, d.x / ay, d.z / ay)
+            } else {
+                (CubeFace::NegativeY, d.x / ay, -d.z / ay)
+            }
+        } else if d.z > 0.0 {
+            (CubeFace::PositiveZ, d.x / az, -d.y / az)
+        } else {
+            (CubeFace::NegativeZ, -d.x / az, -d.y / az)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cubemap {
+    size: usize,
+    faces: [Vec<Color>; 6],
+}
+
+impl Cubemap {
+    // Hornea un cubemap de `size` x `size` pixeles por cara evaluando `f` en la dirección
+    // (normalizada) del centro de cada pixel
+    pub fn from_fn(size: usize, f: impl Fn(&Vec3) -> Color) -> Self {
+        let size = size.max(1);
+        let faces = CubeFace::ALL.map(|face| {
+            let mut pixels = Vec::with_capacity(size * size);
+            for y in 0..size {
+                for x in 0..size {
+                    let u = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                    let v = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                    pixels.push(f(&face.direction(u, v).normalize()));
+                }
+            }
+            pixels
+        });
+        Cubemap { size, faces }
+    }
+
+    // Fondo espacial: negro azulado con una banda de nebulosa y estrellas de celdas de
+    // Worley (una estrella por celda, con brillo aleatorio). `seed` elige las estrellas
+    pub fn space(size: usize, seed: u32) -> Self {
+        Cubemap::from_fn(size, |direction| {
+            let band = 1.0 - smoothstep(0.0, 0.5, (direction.y + 0.3 * direction.x).abs());
+            let background = Color::new(2, 2, 10) + Color::new(40, 20, 60) * band;
+
+            let (distance, value) = cellular_noise_seeded(&(direction * 24.0), seed);
+            let star = 1.0 - smoothstep(0.02, 0.08, distance);
+            background + Color::new(255, 250, 235) * (star * value)
+        })
+    }
+
+    // Color en la dirección dada (no hace falta normalizarla); vecino más cercano
+    pub fn sample(&self, direction: &Vec3) -> Color {
+        if direction.norm() == 0.0 || !direction.iter().all(|c| c.is_finite()) {
+            return Color::black();
+        }
+        let (face, u, v) = CubeFace::from_direction(direction);
+        let to_pixel = |t: f32| (((t + 1.0) * 0.5 * self.size as f32) as usize).min(self.size - 1);
+        self.faces[face as usize][to_pixel(v) * self.size + to_pixel(u)]
+    }
+}
@@ -0,0 +1,124 @@
+// ffi.rs
+//
+// API en C para usar el renderer desde otros lenguajes (ver examples/python_ffi.py).
+// Se compila con el feature `ffi`; la biblioteca dinámica queda en
+// target/release/librenderer.so (renderer.dll en Windows, librenderer.dylib en macOS):
+//
+//   cargo build --release --features ffi
+//
+// El renderer es opaco para el llamador: se crea con `renderer_create`, se libera con
+// `renderer_destroy` y todas las demás funciones reciben el puntero que devolvió.
+//
+// Un panic no puede cruzar una función `extern "C"` (es comportamiento indefinido): cada
+// función lo atrapa con `guard` y devuelve null o false, como con cualquier otro error.
+
+use std::ffi::{c_char, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use crate::color::Color;
+use crate::framebuffer::{render, Framebuffer};
+use crate::obj::Obj;
+use crate::uniforms::{viewport_matrix, Uniforms, UniformsBuilder};
+use crate::vertex::Vertex;
+
+pub struct OpaqueRenderer {
+    framebuffer: Framebuffer,
+    uniforms: Uniforms,
+    vertices: Vec<Vertex>,
+    background_color: Color,
+}
+
+// Ejecuta `body` y devuelve `on_panic` si entra en panic (el mensaje ya salió por stderr)
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+/// Crea un renderer con un framebuffer de `width` x `height` y sin modelo cargado.
+/// Devuelve null si alguna dimensión es 0 o si falla con un panic.
+#[no_mangle]
+pub extern "C" fn renderer_create(width: u32, height: u32) -> *mut OpaqueRenderer {
+    guard(ptr::null_mut(), || {
+        if width == 0 || height == 0 {
+            return ptr::null_mut();
+        }
+        let (width, height) = (width as usize, height as usize);
+
+        // Mismo encuadre que la ventana: modelo centrado y escalado a 100 pixeles por unidad
+        let uniforms = UniformsBuilder::new().viewport(viewport_matrix(width, height, 100.0)).build();
+
+        let renderer = OpaqueRenderer {
+            framebuffer: Framebuffer::new(width, height),
+            uniforms,
+            vertices: Vec::new(),
+            background_color: Color::new(0, 0, 20),
+        };
+        Box::into_raw(Box::new(renderer))
+    })
+}
+
+/// Carga un OBJ (ruta en UTF-8 terminada en NUL) y reemplaza el modelo actual.
+/// Devuelve false si algún puntero es null, la ruta no es UTF-8 o el archivo no carga;
+/// en ese caso el modelo anterior se conserva. También devuelve false si falla con un panic.
+///
+/// # Safety
+/// `renderer` debe venir de `renderer_create` y no haberse destruido, y `path` debe
+/// apuntar a un string de C válido.
+#[no_mangle]
+pub unsafe extern "C" fn renderer_load_obj(renderer: *mut OpaqueRenderer, path: *const c_char) -> bool {
+    guard(false, || {
+        let Some(renderer) = renderer.as_mut() else { return false };
+        if path.is_null() {
+            return false;
+        }
+        let Ok(path) = CStr::from_ptr(path).to_str() else { return false };
+
+        match Obj::load(path) {
+            Ok(obj) => {
+                let mut vertices = obj.get_vertex_array();
+                for vertex in vertices.iter_mut() {
+                    vertex.color = renderer.uniforms.material.base_color;
+                }
+                renderer.vertices = vertices;
+                true
+            }
+            Err(e) => {
+                eprintln!("No se pudo cargar {}: {}", path, e);
+                false
+            }
+        }
+    })
+}
+
+/// Renderiza un frame y devuelve los pixeles (width * height, 0x00RRGGBB por fila desde
+/// arriba). El buffer pertenece al renderer y sigue siendo válido hasta la siguiente
+/// llamada a `renderer_render_frame` o `renderer_destroy`. Devuelve null si `renderer` es null
+/// o si el render falla con un panic.
+///
+/// # Safety
+/// `renderer` debe venir de `renderer_create` y no haberse destruido.
+#[no_mangle]
+pub unsafe extern "C" fn renderer_render_frame(renderer: *mut OpaqueRenderer) -> *const u32 {
+    guard(ptr::null(), || {
+        let Some(renderer) = renderer.as_mut() else { return ptr::null() };
+
+        renderer.framebuffer.clear(renderer.background_color);
+        render(&mut renderer.framebuffer, &renderer.uniforms, &renderer.vertices);
+        renderer.framebuffer.swap_buffers();
+        renderer.uniforms.prev_transformation_matrix = renderer.uniforms.transformation_matrix;
+        renderer.framebuffer.present_buffer().as_ptr()
+    })
+}
+
+/// Libera el renderer. Pasar null no hace nada.
+///
+/// # Safety
+/// `renderer` debe venir de `renderer_create` y no usarse después de esta llamada.
+#[no_mangle]
+pub unsafe extern "C" fn renderer_destroy(renderer: *mut OpaqueRenderer) {
+    guard((), || {
+        if !renderer.is_null() {
+            drop(Box::from_raw(renderer));
+        }
+    })
+}
@@ -1,12 +1,34 @@
 // fragment.rs
 
-use nalgebra_glm::Vec2;
+use nalgebra_glm::{Vec2, Vec3};
 use crate::color::Color;
 
+// Posición y normal en espacio de vista, para efectos en espacio de pantalla (SSAO,
+// matcap, niebla por distancia, luz de borde)
+#[derive(Debug, Clone, Copy)]
+pub struct ViewData {
+    pub position: Vec3,
+    pub normal: Vec3,
+}
+
+#[derive(Debug, Clone)]
 pub struct Fragment {
     pub position: Vec2,
     pub color: Color,
     pub depth: f32,
+    pub velocity: Vec2,
+    pub normal: Vec3,
+    pub intensity: f32, // Cobertura del pixel en líneas antialiasadas (1 = completo)
+    pub light: Vec3, // Luz acumulada por canal (RGB) en el fragment shading
+    pub alpha: f32, // Opacidad (1 = opaco); no cuenta con BlendMode::Opaque
+    pub model_position: Vec3, // Posición en el espacio del modelo, para texturas 3D/triplanares
+    pub tex_coords: Vec2, // Coordenadas de textura con corrección de perspectiva
+    // Coordenadas baricéntricas con corrección de perspectiva (None en líneas y puntos)
+    pub barycentric: Option<Vec3>,
+    pub triangle: u32, // Índice del triángulo que generó el fragmento
+    // Datos en espacio de vista; None salvo con `Uniforms::fragment_view_data`. Ocupa
+    // 28 bytes por fragmento aunque esté apagado (96 -> 124 bytes)
+    pub view: Option<ViewData>,
 }
 
 impl Fragment {
@@ -15,6 +37,16 @@ impl Fragment {
             position: Vec2::new(x, y),
             color,
             depth,
+            velocity: Vec2::zeros(),
+            normal: Vec3::new(0.0, 0.0, -1.0),
+            intensity: 1.0,
+            light: Vec3::repeat(1.0),
+            alpha: 1.0,
+            model_position: Vec3::zeros(),
+            tex_coords: Vec2::zeros(),
+            barycentric: None,
+            triangle: 0,
+            view: None,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,59 @@
+// frame_pipeline.rs
+//
+// Render en paralelo con el hilo principal. Hay dos framebuffers: mientras uno se
+// renderiza en el thread pool, el otro (el frame anterior ya terminado) se presenta y se
+// usa para picking en el hilo principal, que además ya procesa la entrada del siguiente
+// frame. La sincronización es por transferencia de propiedad: el framebuffer se mueve al
+// trabajo del pool y regresa por un canal, así que nunca hay dos hilos tocando el mismo
+// buffer y no hacen falta locks. El costo es un frame de latencia entre entrada y pantalla,
+// a cambio de que la entrada y el cálculo de matrices se solapen con el render.
+
+use std::sync::mpsc::{channel, Receiver};
+use crate::framebuffer::Framebuffer;
+
+pub struct FramePipeline {
+    pool: rayon::ThreadPool,
+    spare: Option<Framebuffer>,
+    in_flight: Option<Receiver<Framebuffer>>,
+}
+
+impl FramePipeline {
+    pub fn new(width: usize, height: usize) -> Self {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .thread_name(|_| "render".to_string())
+            .build()
+            .expect("No se pudo crear el thread pool de render");
+
+        FramePipeline {
+            pool,
+            spare: Some(Framebuffer::new(width, height)),
+            in_flight: None,
+        }
+    }
+
+    // Espera el frame que se está renderizando (si hay uno) y lo devuelve
+    pub fn finish(&mut self) -> Option<Framebuffer> {
+        let receiver = self.in_flight.take()?;
+        Some(receiver.recv().expect("El hilo de render terminó inesperadamente"))
+    }
+
+    // Lanza el render del siguiente frame en el pool, sobre el framebuffer libre
+    pub fn submit<F>(&mut self, job: F)
+    where
+        F: FnOnce(&mut Framebuffer) + Send + 'static,
+    {
+        let mut framebuffer = self.spare.take().expect("No hay framebuffer libre: falta llamar a recycle");
+        let (sender, receiver) = channel();
+        self.pool.spawn(move || {
+            job(&mut framebuffer);
+            let _ = sender.send(framebuffer);
+        });
+        self.in_flight = Some(receiver);
+    }
+
+    // Devuelve un framebuffer ya presentado para reutilizarlo en el próximo render
+    pub fn recycle(&mut self, framebuffer: Framebuffer) {
+        self.spare = Some(framebuffer);
+    }
+}
@@ -1,17 +1,146 @@
 use crate::color::Color;
 use crate::vertex::Vertex;
-use crate::fragment::Fragment;
-use crate::shader::vertex_shader;
-use crate::uniforms::Uniforms;
+use crate::fragment::{Fragment, ViewData};
+use crate::material::BlendMode;
+use crate::edge_function::{covered_in_quads, covered_in_row, CoveredPixel, QuadPixel, TriangleSetup};
+use crate::shader::{reflect_view, view_vector, DefaultVertexShader, OutlineVertexShader, VertexShader};
+use crate::light::incident_light;
+use crate::uniforms::{CullMode, DepthBias, Matrices, PrimitiveTopology, RasterizerMode, ShadingModel, StencilOp, StencilState, Uniforms, Winding};
+use crate::triangle::{triangle_scanline, Span};
+use crate::line::{apply_stipple, clip_segment_rect, line, line_clipped, LineMode, Stipple};
+use crate::math::BoundingBox;
+use crate::pipeline::Pipeline;
+#[cfg(not(target_arch = "wasm32"))]
 use minifb::{Window, WindowOptions, Key};
-use nalgebra_glm::{Vec2, Vec3};
+use std::borrow::Cow;
+use nalgebra_glm::{Mat4, Vec2, Vec3, Vec4};
+
+// Rectángulo en coordenadas de pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Rect { x, y, width, height }
+    }
+
+    // Rectángulo mínimo que contiene a ambos
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Rect::new(x, y, right - x, bottom - y)
+    }
+
+    pub fn contains(&self, x: isize, y: isize) -> bool {
+        x >= self.x as isize && y >= self.y as isize && x < (self.x + self.width) as isize && y < (self.y + self.height) as isize
+    }
+
+    // Mitades izquierda y derecha (la derecha se queda con la columna sobrante)
+    pub fn split_vertical(&self) -> (Rect, Rect) {
+        let left = self.width / 2;
+        (Rect::new(self.x, self.y, left, self.height), Rect::new(self.x + left, self.y, self.width - left, self.height))
+    }
+
+    // Parte común de ambos, None si no se tocan
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        (right > x && bottom > y).then(|| Rect::new(x, y, right - x, bottom - y))
+    }
+
+    // Extiende el rectángulo para incluir el pixel (x, y)
+    fn include(rect: Option<Rect>, x: usize, y: usize) -> Rect {
+        let pixel = Rect::new(x, y, 1, 1);
+        match rect {
+            Some(rect) if x >= rect.x && y >= rect.y && x < rect.x + rect.width && y < rect.y + rect.height => rect,
+            Some(rect) => rect.union(&pixel),
+            None => pixel,
+        }
+    }
+}
+
+// Cómo se guarda la profundidad. Standard: menor = más cerca y el buffer se limpia a
+// infinito. Reversed (reversed-Z): la proyección lleva el plano cercano a 1 y el lejano a
+// 0, el buffer se limpia a 0 y gana la mayor; como los floats tienen más precisión cerca
+// de 0, la precisión se reparte mucho mejor con planos lejanos muy distantes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthMode {
+    Standard,
+    Reversed,
+}
+
+impl DepthMode {
+    // Profundidad de un pixel sin nada dibujado
+    pub fn cleared_depth(self) -> f32 {
+        match self {
+            DepthMode::Standard => f32::INFINITY,
+            DepthMode::Reversed => 0.0,
+        }
+    }
+
+    // Si la profundidad `depth` está más cerca que `other`
+    pub fn is_closer(self, depth: f32, other: f32) -> bool {
+        match self {
+            DepthMode::Standard => depth < other,
+            DepthMode::Reversed => depth > other,
+        }
+    }
+
+    // Si el pixel tiene algo dibujado
+    pub fn is_covered(self, depth: f32) -> bool {
+        depth != self.cleared_depth()
+    }
+}
 
 // Framebuffer para gestionar el buffer de píxeles
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
-    pub buffer: Vec<u32>,
+    buffer: Vec<u32>,
+    zbuffer: Vec<f32>, // Profundidad más cercana escrita en cada pixel
+    depth_mode: DepthMode,
+    pub velocity_buffer: Vec<Vec2>, // Vector de movimiento en pantalla por pixel
+    pub history_buffer: Vec<u32>, // Resultado acumulado del TAA (vacío hasta el primer frame)
+    id_buffer: Vec<u16>, // Id de la entidad dibujada en cada pixel (0 = ninguna)
+    triangle_buffer: Vec<u32>, // Índice + 1 del triángulo visible en cada pixel (0 = ninguno)
+    stencil_buffer: Vec<u8>, // Máscara de 8 bits por pixel; `clear` no la cambia (ver `clear_stencil`)
     current_color: u32,
+    current_id: u16,
+    // Bits del color que escribe `point` (0xAARRGGBB); los demás conservan el valor anterior
+    color_mask: u32,
+    // Región fuera de la cual no se escribe ningún buffer (None = todo el framebuffer)
+    scissor: Option<Rect>,
+    // Doble buffer: se dibuja en `buffer` y se presenta `front_buffer`
+    double_buffered: bool,
+    front_buffer: Vec<u32>,
+    // Región escrita desde el último clear de cada buffer (None = nada escrito)
+    dirty_rect: Option<Rect>,
+    front_dirty_rect: Option<Rect>,
+    // Forzar limpiezas completas (para comparar contra el clear por regiones)
+    pub full_clear: bool,
+    // Color del último clear completo de cada buffer (None = hay que limpiarlo entero)
+    clear_color: Option<u32>,
+    front_clear_color: Option<u32>,
+    // Color del último clear con scissor, para limpiar el buffer de SSAA con el mismo fondo
+    region_clear_color: Option<u32>,
+    // Framebuffer de mayor resolución para SSAA (se crea al primer render con ssaa_factor > 1)
+    supersampled: Option<Box<Framebuffer>>,
+    supersampled_needs_clear: bool,
+    // Aviso si el buffer de fragmentos de un render supera este tamaño (bytes)
+    pub fragment_memory_limit: usize,
+    // Fragmentos del render anterior, para reservar el buffer de una sola vez
+    pub(crate) fragment_capacity_hint: usize,
+    // Matrices del último render (None hasta el primero)
+    pub(crate) matrices: Option<Matrices>,
 }
 
 impl Framebuffer {
@@ -20,32 +149,588 @@ impl Framebuffer {
             width,
             height,
             buffer: vec![0; width * height],
+            zbuffer: vec![f32::INFINITY; width * height],
+            depth_mode: DepthMode::Standard,
+            velocity_buffer: vec![Vec2::zeros(); width * height],
+            history_buffer: Vec::new(),
+            id_buffer: vec![0; width * height],
+            triangle_buffer: vec![0; width * height],
+            stencil_buffer: vec![0; width * height],
             current_color: 0,
+            current_id: 0,
+            color_mask: u32::MAX,
+            scissor: None,
+            double_buffered: false,
+            front_buffer: Vec::new(),
+            dirty_rect: None,
+            front_dirty_rect: None,
+            full_clear: false,
+            clear_color: None,
+            front_clear_color: None,
+            region_clear_color: None,
+            supersampled: None,
+            supersampled_needs_clear: true,
+            fragment_memory_limit: DEFAULT_FRAGMENT_MEMORY_LIMIT,
+            fragment_capacity_hint: 0,
+            matrices: None,
         }
     }
 
-    // Método para limpiar el framebuffer con un color de fondo
+    // Cambia el tamaño del framebuffer (p. ej. con resolución dinámica). Todos los buffers
+    // quedan limpios y se descartan el historial del TAA y el buffer de SSAA; se conservan el
+    // modo de profundidad, el doble buffer y la configuración
+    pub fn resize(&mut self, width: usize, height: usize) {
+        if width == self.width && height == self.height {
+            return;
+        }
+        let mut resized = Framebuffer::new(width, height);
+        resized.set_depth_mode(self.depth_mode);
+        resized.set_double_buffered(self.double_buffered);
+        resized.color_mask = self.color_mask;
+        resized.full_clear = self.full_clear;
+        resized.fragment_memory_limit = self.fragment_memory_limit;
+        *self = resized;
+    }
+
+    pub fn depth_mode(&self) -> DepthMode {
+        self.depth_mode
+    }
+
+    // Modelo, vista, proyección y viewport del último render en este framebuffer, tal como
+    // los recibió (con SSAA, sin la escala del buffer de mayor resolución). Lo registran
+    // `render` y el pipeline, el wireframe, la máscara de stencil, el instancing (con el
+    // modelo común, sin la matriz de cada instancia), las partículas (modelo identidad) y
+    // el pase de geometría del G-buffer (en `GBuffer::albedo`, y de ahí en el destino del
+    // pase de iluminación). Las líneas y polilíneas
+    // sueltas, que reciben una matriz ya compuesta, no lo cambian
+    pub fn current_matrices(&self) -> Option<Matrices> {
+        self.matrices
+    }
+
+    // Cambia cómo se guarda la profundidad; el z-buffer queda limpio
+    pub fn set_depth_mode(&mut self, mode: DepthMode) {
+        if self.depth_mode != mode {
+            self.depth_mode = mode;
+            self.zbuffer.fill(mode.cleared_depth());
+            self.supersampled_needs_clear = true;
+        }
+    }
+
+    // Activa o desactiva el doble buffer
+    pub fn set_double_buffered(&mut self, enabled: bool) {
+        self.double_buffered = enabled;
+        self.front_buffer = if enabled { self.buffer.clone() } else { Vec::new() };
+        self.front_dirty_rect = self.dirty_rect;
+        self.front_clear_color = self.clear_color;
+    }
+
+    // Intercambia el buffer de dibujo con el que se presenta
+    pub fn swap_buffers(&mut self) {
+        if self.double_buffered {
+            std::mem::swap(&mut self.buffer, &mut self.front_buffer);
+            std::mem::swap(&mut self.dirty_rect, &mut self.front_dirty_rect);
+            std::mem::swap(&mut self.clear_color, &mut self.front_clear_color);
+        }
+    }
+
+    // Buffer que se debe entregar a la ventana
+    pub fn present_buffer(&self) -> &[u32] {
+        if self.double_buffered {
+            &self.front_buffer
+        } else {
+            &self.buffer
+        }
+    }
+
+    // Método para limpiar el framebuffer con un color de fondo. Con un scissor activo solo
+    // se limpia esa región (color, profundidad, ids y velocidades)
     pub fn clear(&mut self, color: Color) {
-        let color_u32 = (255 << 24) | ((color.r as u32) << 16) | ((color.g as u32) << 8) | (color.b as u32);
-        for pixel in self.buffer.iter_mut() {
-            *pixel = color_u32;
+        let color_u32 = color.to_u32();
+        if let Some(rect) = self.scissor {
+            self.clear_region(&rect, color_u32);
+            return;
+        }
+
+        // Si cambia el color de fondo no sirve limpiar solo lo que se dibujó
+        let full = self.full_clear || self.clear_color != Some(color_u32);
+        self.clear_color = Some(color_u32);
+
+        if full {
+            self.buffer.fill(color_u32);
+            self.zbuffer.fill(self.depth_mode.cleared_depth());
+            self.id_buffer.fill(0);
+            self.triangle_buffer.fill(0);
+            self.velocity_buffer.fill(Vec2::zeros());
+        } else {
+            // Profundidad y velocidades no tienen doble buffer: se limpia lo escrito en ambos frames
+            let shared_region = match (self.dirty_rect, self.front_dirty_rect) {
+                (Some(a), Some(b)) => Some(a.union(&b)),
+                (a, b) => a.or(b),
+            };
+            if let Some(rect) = self.dirty_rect {
+                self.fill_rows(&rect, color_u32);
+            }
+            if let Some(rect) = shared_region {
+                for row in rect.y..rect.y + rect.height {
+                    let start = row * self.width + rect.x;
+                    self.zbuffer[start..start + rect.width].fill(self.depth_mode.cleared_depth());
+                    self.id_buffer[start..start + rect.width].fill(0);
+                    self.triangle_buffer[start..start + rect.width].fill(0);
+                    self.velocity_buffer[start..start + rect.width].fill(Vec2::zeros());
+                }
+            }
+        }
+
+        self.dirty_rect = None;
+        self.region_clear_color = None;
+        self.supersampled_needs_clear = true;
+    }
+
+    fn clear_region(&mut self, rect: &Rect, color: u32) {
+        self.fill_rows(rect, color);
+        let cleared_depth = self.depth_mode.cleared_depth();
+        for row in rect.y..rect.y + rect.height {
+            let start = row * self.width + rect.x;
+            self.zbuffer[start..start + rect.width].fill(cleared_depth);
+            self.id_buffer[start..start + rect.width].fill(0);
+            self.triangle_buffer[start..start + rect.width].fill(0);
+            self.velocity_buffer[start..start + rect.width].fill(Vec2::zeros());
+        }
+        // El resto conserva lo dibujado, así que la región sucia solo puede crecer; si el color
+        // no es el del último clear completo, el próximo tiene que ser completo
+        self.dirty_rect = Some(self.dirty_rect.map_or(*rect, |dirty| dirty.union(rect)));
+        if self.clear_color != Some(color) {
+            self.clear_color = None;
+        }
+        self.region_clear_color = Some(color);
+        self.supersampled_needs_clear = true;
+    }
+
+    // Limita las escrituras de los draws (color, profundidad, stencil, ids) y los clears al
+    // rectángulo, recortado al framebuffer; None vuelve a permitir todo el framebuffer.
+    // `stencil_circle` y `stencil_rect` escriben donde se les indica. Sirve para dibujar
+    // varias vistas en un mismo frame sin que los triángulos de una se salgan a la otra
+    pub fn set_scissor(&mut self, rect: Option<Rect>) {
+        self.scissor = rect.map(|rect| {
+            let x = rect.x.min(self.width);
+            let y = rect.y.min(self.height);
+            Rect::new(x, y, rect.width.min(self.width - x), rect.height.min(self.height - y))
+        });
+    }
+
+    pub fn scissor(&self) -> Option<Rect> {
+        self.scissor
+    }
+
+    // Región que recorre el rasterizador al dibujar en este framebuffer: el scissor o todo
+    // el framebuffer. Los Bounding Box de los triángulos se recortan a ella
+    pub fn raster_bounds(&self) -> Rect {
+        self.scissor.unwrap_or(Rect::new(0, 0, self.width, self.height))
+    }
+
+    // Limpia el stencil buffer a `value` (solo el scissor, si hay uno). Va aparte de `clear`
+    // para que una máscara pueda durar varios frames
+    pub fn clear_stencil(&mut self, value: u8) {
+        match self.scissor {
+            Some(rect) => self.stencil_rect(&rect, value),
+            None => self.stencil_buffer.fill(value),
+        }
+    }
+
+    pub fn get_stencil(&self, x: isize, y: isize) -> Option<u8> {
+        self.index_of(x, y).map(|index| self.stencil_buffer[index])
+    }
+
+    // Escribe `value` en el stencil de los pixeles dentro del círculo
+    pub fn stencil_circle(&mut self, center_x: f32, center_y: f32, radius: f32, value: u8) {
+        let min_y = ((center_y - radius).floor().max(0.0) as usize).min(self.height);
+        let max_y = ((center_y + radius).ceil().max(0.0) as usize).min(self.height);
+        let min_x = ((center_x - radius).floor().max(0.0) as usize).min(self.width);
+        let max_x = ((center_x + radius).ceil().max(0.0) as usize).min(self.width);
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                // Centro del pixel
+                let (dx, dy) = (x as f32 + 0.5 - center_x, y as f32 + 0.5 - center_y);
+                if dx * dx + dy * dy <= radius * radius {
+                    self.stencil_buffer[y * self.width + x] = value;
+                }
+            }
+        }
+    }
+
+    // Escribe `value` en el stencil de los pixeles del rectángulo (recortado al framebuffer)
+    pub fn stencil_rect(&mut self, rect: &Rect, value: u8) {
+        let right = (rect.x + rect.width).min(self.width);
+        for row in rect.y..(rect.y + rect.height).min(self.height) {
+            let start = row * self.width;
+            self.stencil_buffer[start + rect.x.min(right)..start + right].fill(value);
+        }
+    }
+
+    // Prueba de stencil del pixel (x, y); si falla aplica `state.fail`. Fuera del
+    // framebuffer o del scissor siempre falla
+    pub fn stencil_test(&mut self, x: isize, y: isize, state: &StencilState) -> bool {
+        let Some(index) = self.writable_index(x, y) else { return false };
+        let stored = self.stencil_buffer[index];
+        if state.compare.passes(state.reference, stored) {
+            true
+        } else {
+            self.write_stencil(index, state.fail.apply(state.reference, stored));
+            false
+        }
+    }
+
+    pub fn apply_stencil_op(&mut self, x: isize, y: isize, op: StencilOp, reference: u8) {
+        if let Some(index) = self.writable_index(x, y) {
+            self.write_stencil(index, op.apply(reference, self.stencil_buffer[index]));
+        }
+    }
+
+    // Los cambios del stencil también cuentan en la región sucia: con SSAA solo se resuelve
+    // esa región
+    fn write_stencil(&mut self, index: usize, value: u8) {
+        if self.stencil_buffer[index] != value {
+            self.stencil_buffer[index] = value;
+            self.dirty_rect = Some(Rect::include(self.dirty_rect, index % self.width, index / self.width));
+        }
+    }
+
+    // Rellena las filas de un rectángulo con `slice::fill`
+    fn fill_rows(&mut self, rect: &Rect, color: u32) {
+        for row in rect.y..rect.y + rect.height {
+            let start = row * self.width + rect.x;
+            self.buffer[start..start + rect.width].fill(color);
         }
     }
 
     // Método para dibujar un punto en el framebuffer
     pub fn point(&mut self, x: isize, y: isize) {
+        if let Some(index) = self.writable_index(x, y) {
+            self.buffer[index] = (self.buffer[index] & !self.color_mask) | (self.current_color & self.color_mask);
+            self.dirty_rect = Some(Rect::include(self.dirty_rect, x as usize, y as usize));
+        }
+    }
+
+    // Dibuja un punto solo si está más cerca que lo ya dibujado; devuelve si se escribió
+    pub fn point_with_depth(&mut self, x: isize, y: isize, depth: f32) -> bool {
+        match self.writable_index(x, y) {
+            Some(index) if self.depth_mode.is_closer(depth, self.zbuffer[index]) => {
+                self.zbuffer[index] = depth;
+                self.id_buffer[index] = self.current_id;
+                self.point(x, y);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Mezcla `color` con opacidad `alpha` sobre el pixel si pasa la prueba de profundidad,
+    // sin escribir la profundidad (las capas transparentes no tapan lo que va detrás)
+    pub fn blend_with_depth(&mut self, x: isize, y: isize, depth: f32, color: Color, alpha: f32) -> bool {
+        self.combine_with_depth(x, y, depth, color, alpha, BlendMode::Alpha)
+    }
+
+    // Suma `color` por `alpha` al pixel si pasa la prueba de profundidad, sin escribir la
+    // profundidad; cada canal satura en 255
+    pub fn add_with_depth(&mut self, x: isize, y: isize, depth: f32, color: Color, alpha: f32) -> bool {
+        self.combine_with_depth(x, y, depth, color, alpha, BlendMode::Additive)
+    }
+
+    // Combina `color` con el pixel según `mode` (ver `BlendMode::combine`) si pasa la prueba
+    // de profundidad, sin escribir la profundidad. Un fragmento con `alpha` 0 no cambia nada
+    pub fn combine_with_depth(&mut self, x: isize, y: isize, depth: f32, color: Color, alpha: f32, mode: BlendMode) -> bool {
+        match self.writable_index(x, y) {
+            Some(index) if alpha > 0.0 && self.depth_mode.is_closer(depth, self.zbuffer[index]) => {
+                let existing = Color::from_u32(self.buffer[index]);
+                self.set_current_color(mode.combine(color, alpha, existing));
+                self.point(x, y);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Índice en el buffer de la coordenada (x, y), None si está fuera del framebuffer
+    fn index_of(&self, x: isize, y: isize) -> Option<usize> {
         if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
-            let index = (y as usize) * self.width + (x as usize);
-            self.buffer[index] = self.current_color;
+            Some((y as usize) * self.width + (x as usize))
+        } else {
+            None
+        }
+    }
+
+    // Como `index_of`, pero None también fuera del scissor: para las escrituras
+    fn writable_index(&self, x: isize, y: isize) -> Option<usize> {
+        match self.scissor {
+            Some(rect) if !rect.contains(x, y) => None,
+            _ => self.index_of(x, y),
         }
     }
 
+    // Color del pixel (x, y) en formato 0xAARRGGBB
+    pub fn get_pixel(&self, x: isize, y: isize) -> Option<u32> {
+        self.index_of(x, y).map(|index| self.buffer[index])
+    }
+
+    // Profundidad del pixel (x, y); `depth_mode().cleared_depth()` (INFINITY en el modo
+    // estándar) si no se ha dibujado nada
+    pub fn get_depth(&self, x: isize, y: isize) -> Option<f32> {
+        self.index_of(x, y).map(|index| self.zbuffer[index])
+    }
+
+    // Id de la entidad visible en el pixel (x, y); None si no hay ninguna
+    pub fn get_id(&self, x: isize, y: isize) -> Option<u16> {
+        self.index_of(x, y)
+            .map(|index| self.id_buffer[index])
+            .filter(|&id| id != 0)
+    }
+
+    // Triángulo visible en el pixel (x, y), como índice del triángulo en el vertex array
+    // del render que lo dibujó (junto con `get_id` identifica entidad y triángulo)
+    pub fn pick(&self, x: isize, y: isize) -> Option<u32> {
+        self.index_of(x, y)
+            .map(|index| self.triangle_buffer[index])
+            .filter(|&triangle| triangle != 0)
+            .map(|triangle| triangle - 1)
+    }
+
+    // Copia los pixeles de un rectángulo fila por fila; None si se sale del framebuffer
+    pub fn read_region(&self, rect: &Rect) -> Option<Vec<u32>> {
+        if rect.x + rect.width > self.width || rect.y + rect.height > self.height {
+            return None;
+        }
+
+        let mut pixels = Vec::with_capacity(rect.width * rect.height);
+        for row in rect.y..rect.y + rect.height {
+            let start = row * self.width + rect.x;
+            pixels.extend_from_slice(&self.buffer[start..start + rect.width]);
+        }
+        Some(pixels)
+    }
+
+    // Iteradores sobre las filas del buffer de color
+    pub fn rows(&self) -> std::slice::Chunks<'_, u32> {
+        self.buffer.chunks(self.width)
+    }
+
+    pub fn rows_mut(&mut self) -> std::slice::ChunksMut<'_, u32> {
+        self.buffer.chunks_mut(self.width)
+    }
+
+    // Acceso al buffer de color completo (su largo siempre es width * height)
+    pub fn as_slice(&self) -> &[u32] {
+        &self.buffer
+    }
+
+    // Escritura directa (p. ej. los pases de post-procesado): como no se sabe qué pixeles
+    // cambian, todo el frame queda sucio para el próximo clear por regiones
+    pub fn as_mut_slice(&mut self) -> &mut [u32] {
+        self.dirty_rect = Some(Rect::new(0, 0, self.width, self.height));
+        &mut self.buffer
+    }
+
+    // Método para guardar el vector de movimiento de un pixel
+    pub fn set_velocity(&mut self, x: isize, y: isize, velocity: Vec2) {
+        if let Some(index) = self.writable_index(x, y) {
+            self.velocity_buffer[index] = velocity;
+        }
+    }
+
+    // Guarda el triángulo visible en un pixel (se llama después de pasar la prueba de profundidad)
+    pub fn set_triangle(&mut self, x: isize, y: isize, triangle: u32) {
+        if let Some(index) = self.writable_index(x, y) {
+            self.triangle_buffer[index] = triangle + 1;
+        }
+    }
+
+    // Método para establecer el id de la entidad que se está dibujando (0 = sin id)
+    pub fn set_current_id(&mut self, id: u16) {
+        self.current_id = id;
+    }
+
     // Método para establecer el color actual
     pub fn set_current_color(&mut self, color: Color) {
-        self.current_color = (255 << 24) | ((color.r as u32) << 16) | ((color.g as u32) << 8) | (color.b as u32);
+        self.current_color = color.to_u32();
+    }
+
+    // Canales de color que se escriben al dibujar (true = se escribe); los enmascarados
+    // conservan su valor. `clear` no usa la máscara y limpia todos los canales
+    pub fn set_color_mask(&mut self, r: bool, g: bool, b: bool) {
+        let channel = |enabled: bool, mask: u32| if enabled { mask } else { 0 };
+        self.color_mask = 0xFF00_0000 | channel(r, 0x00FF_0000) | channel(g, 0x0000_FF00) | channel(b, 0x0000_00FF);
+    }
+
+    // Canales que se escriben, como (r, g, b)
+    pub fn color_mask(&self) -> (bool, bool, bool) {
+        let mask = self.color_mask;
+        (mask & 0x00FF_0000 != 0, mask & 0x0000_FF00 != 0, mask & 0x0000_00FF != 0)
+    }
+
+    // Framebuffer de `factor` veces la resolución, limpio desde el último `clear` de este.
+    // El stencil solo se copia si el draw lo usa (`stencil`)
+    pub(crate) fn supersampled_buffer(&mut self, factor: usize, stencil: bool) -> &mut Framebuffer {
+        let (width, height) = (self.width * factor, self.height * factor);
+        let recreate = self.supersampled.as_ref().is_none_or(|fb| fb.width != width || fb.height != height);
+        if recreate {
+            self.supersampled = Some(Box::new(Framebuffer::new(width, height)));
+            self.supersampled_needs_clear = true;
+        }
+
+        let needs_clear = std::mem::take(&mut self.supersampled_needs_clear);
+        let clear_color = self.region_clear_color.or(self.clear_color).unwrap_or(0);
+        let current_id = self.current_id;
+        let depth_mode = self.depth_mode;
+        let color_mask = self.color_mask;
+        let scissor = self.scissor;
+        let supersampled = self.supersampled.as_mut().unwrap();
+        supersampled.set_depth_mode(depth_mode);
+        supersampled.color_mask = color_mask;
+        supersampled.scissor = None;
+        // El stencil se amplía (vecino más cercano) para que las máscaras valgan con SSAA
+        if stencil {
+            for (index, value) in supersampled.stencil_buffer.iter_mut().enumerate() {
+                let (x, y) = (index % width / factor, index / width / factor);
+                *value = self.stencil_buffer[y * self.width + x];
+            }
+        }
+        if needs_clear {
+            supersampled.full_clear = true;
+            supersampled.clear(Color::from_u32(clear_color));
+        }
+        supersampled.scissor = scissor.map(|rect| Rect::new(rect.x * factor, rect.y * factor, rect.width * factor, rect.height * factor));
+        supersampled.set_current_id(current_id);
+        // La región sucia es la del draw que empieza: es lo único que hay que resolver
+        supersampled.dirty_rect = None;
+        supersampled
+    }
+
+    // Corre `draw` con SSAA si `uniforms.ssaa_factor > 1`: sobre el framebuffer de mayor
+    // resolución con el viewport escalado, y después resuelve lo que dibujó. Sin SSAA dibuja
+    // directamente sobre este framebuffer
+    pub(crate) fn draw_supersampled<R>(&mut self, uniforms: &Uniforms, draw: impl FnOnce(&mut Framebuffer, &Uniforms) -> R) -> R {
+        self.draw_supersampled_with_stencil(uniforms, uniforms.stencil.is_some(), draw)
+    }
+
+    // Igual que `draw_supersampled`, indicando si el draw lee o escribe el stencil aunque
+    // `uniforms.stencil` esté apagado (las máscaras de `render_stencil_mask`). Si no lo usa,
+    // el stencil no se amplía ni se resuelve
+    pub(crate) fn draw_supersampled_with_stencil<R>(&mut self, uniforms: &Uniforms, stencil: bool, draw: impl FnOnce(&mut Framebuffer, &Uniforms) -> R) -> R {
+        if uniforms.ssaa_factor <= 1 {
+            return draw(self, uniforms);
+        }
+
+        let factor = uniforms.ssaa_factor;
+        let mut supersampled_uniforms = uniforms.clone();
+        supersampled_uniforms.ssaa_factor = 1;
+        let scale = nalgebra_glm::scaling(&Vec3::new(factor as f32, factor as f32, 1.0));
+        supersampled_uniforms.viewport_matrix = scale * uniforms.viewport_matrix;
+        supersampled_uniforms.transformation_matrix = scale * uniforms.transformation_matrix;
+        supersampled_uniforms.prev_transformation_matrix = scale * uniforms.prev_transformation_matrix;
+
+        let result = draw(self.supersampled_buffer(factor, stencil), &supersampled_uniforms);
+        self.resolve_supersampled(factor, stencil);
+        result
+    }
+
+    // Reduce el framebuffer de alta resolución con un filtro de caja de factor x factor:
+    // color y velocidad se promedian; la profundidad, el id y el triángulo son los de la
+    // muestra más cercana, la correcta para los pases que se dibujan después con prueba de
+    // profundidad (contornos, líneas): en los bordes un promedio quedaría detrás del objeto
+    // cercano. Solo se resuelven los bloques que tocó el último draw (la región sucia del
+    // framebuffer de alta resolución), recortados al scissor. El stencil solo vuelve si el
+    // draw lo usó (`stencil`): si no, el del framebuffer de alta resolución está desactualizado
+    pub(crate) fn resolve_supersampled(&mut self, factor: usize, stencil: bool) {
+        let Some(dirty) = self.supersampled.as_ref().and_then(|fb| fb.dirty_rect) else { return };
+        let (x, y) = (dirty.x / factor, dirty.y / factor);
+        let written = Rect::new(x, y, (dirty.x + dirty.width).div_ceil(factor) - x, (dirty.y + dirty.height).div_ceil(factor) - y);
+        let Some(region) = written.intersection(&self.scissor.unwrap_or(Rect::new(0, 0, self.width, self.height))) else { return };
+        let source = self.supersampled.take().unwrap();
+        let samples = (factor * factor) as f32;
+
+        for y in region.y..region.y + region.height {
+            for x in region.x..region.x + region.width {
+                let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+                let mut velocity = Vec2::zeros();
+                let mut depth = self.depth_mode.cleared_depth();
+                let mut id = 0;
+                let mut triangle = 0;
+
+                for sy in 0..factor {
+                    let row = (y * factor + sy) * source.width + x * factor;
+                    for sample in row..row + factor {
+                        let color = Color::from_u32(source.buffer[sample]);
+                        r += color.r as f32;
+                        g += color.g as f32;
+                        b += color.b as f32;
+                        velocity += source.velocity_buffer[sample];
+                        if self.depth_mode.is_closer(source.zbuffer[sample], depth) {
+                            depth = source.zbuffer[sample];
+                            id = source.id_buffer[sample];
+                            triangle = source.triangle_buffer[sample];
+                        }
+                    }
+                }
+
+                let index = y * self.width + x;
+                // Los canales enmascarados conservan el valor de este framebuffer
+                let color = Color::new((r / samples) as u8, (g / samples) as u8, (b / samples) as u8).to_u32();
+                self.buffer[index] = (self.buffer[index] & !self.color_mask) | (color & self.color_mask);
+                // Las velocidades están en pixeles de alta resolución
+                self.velocity_buffer[index] = velocity / (samples * factor as f32);
+                self.zbuffer[index] = depth;
+                self.id_buffer[index] = id;
+                self.triangle_buffer[index] = triangle;
+                // Stencil de la primera muestra del bloque
+                if stencil {
+                    self.stencil_buffer[index] = source.stencil_buffer[y * factor * source.width + x * factor];
+                }
+            }
+        }
+
+        self.dirty_rect = Some(match self.dirty_rect {
+            Some(dirty) => dirty.union(&region),
+            None => region,
+        });
+        self.supersampled = Some(source);
+    }
+
+    // Memoria reservada por los buffers del framebuffer (incluido el de SSAA)
+    pub fn memory_bytes(&self) -> usize {
+        use std::mem::size_of;
+        let buffers = (self.buffer.capacity() + self.front_buffer.capacity() + self.history_buffer.capacity()) * size_of::<u32>()
+            + self.zbuffer.capacity() * size_of::<f32>()
+            + self.velocity_buffer.capacity() * size_of::<Vec2>()
+            + self.id_buffer.capacity() * size_of::<u16>()
+            + self.triangle_buffer.capacity() * size_of::<u32>()
+            + self.stencil_buffer.capacity();
+        buffers + self.supersampled.as_ref().map_or(0, |fb| fb.memory_bytes())
+    }
+
+    // Guarda el buffer presentado como imagen PNG (RGB de 8 bits)
+    pub fn save_png(&self, path: &str) -> std::io::Result<()> {
+        write_png(path, self.width, self.height, self.present_buffer())
+    }
+
+    // Guarda solo un rectángulo del buffer presentado; error si se sale del framebuffer
+    pub fn save_png_region(&self, path: &str, rect: &Rect) -> std::io::Result<()> {
+        if rect.width == 0 || rect.height == 0 || rect.x + rect.width > self.width || rect.y + rect.height > self.height {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("La región {:?} no cabe en el framebuffer de {}x{}", rect, self.width, self.height),
+            ));
+        }
+
+        let source = self.present_buffer();
+        let mut pixels = Vec::with_capacity(rect.width * rect.height);
+        for row in rect.y..rect.y + rect.height {
+            let start = row * self.width + rect.x;
+            pixels.extend_from_slice(&source[start..start + rect.width]);
+        }
+        write_png(path, rect.width, rect.height, &pixels)
     }
 
     // Método para renderizar la ventana utilizando minifb
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn render_window(&self) {
         let mut window = Window::new(
             "Framebuffer Example",
@@ -59,11 +744,98 @@ impl Framebuffer {
 
         // Mientras la ventana esté abierta y no se presione la tecla ESC
         while window.is_open() && !window.is_key_down(Key::Escape) {
-            window.update_with_buffer(&self.buffer, self.width, self.height).unwrap();
+            window.update_with_buffer(self.present_buffer(), self.width, self.height).unwrap();
         }
     }
 }
 
+// Escribe pixeles 0xAARRGGBB a un archivo PNG
+pub fn write_png(path: &str, width: usize, height: usize, pixels: &[u32]) -> std::io::Result<()> {
+    let file = std::fs::File::create(path)?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = pixels
+        .iter()
+        .flat_map(|&pixel| {
+            let color = Color::from_u32(pixel);
+            [color.r, color.g, color.b]
+        })
+        .collect();
+
+    let mut writer = encoder.write_header().map_err(std::io::Error::other)?;
+    writer.write_image_data(&data).map_err(std::io::Error::other)
+}
+
+// Estadísticas de un render: cuántos vértices y triángulos se descartaron por datos inválidos
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RenderStats {
+    pub vertices: usize,
+    pub invalid_vertices: usize,
+    pub triangles: usize,
+    pub skipped_triangles: usize,    // Descartados por cualquier motivo (incluye los no finitos)
+    pub non_finite_triangles: usize, // Descartados por una coordenada transformada NaN o infinita
+    pub memory: MemoryStats,
+}
+
+// Acumula las estadísticas de otro render (p. ej. los draws de un frame): los contadores
+// se suman y la memoria queda en el máximo de los dos, que es lo que estuvo reservado a la vez
+impl std::ops::AddAssign for RenderStats {
+    fn add_assign(&mut self, other: RenderStats) {
+        self.vertices += other.vertices;
+        self.invalid_vertices += other.invalid_vertices;
+        self.triangles += other.triangles;
+        self.skipped_triangles += other.skipped_triangles;
+        self.non_finite_triangles += other.non_finite_triangles;
+        self.memory = MemoryStats {
+            vertex_buffer_bytes: self.memory.vertex_buffer_bytes.max(other.memory.vertex_buffer_bytes),
+            fragment_buffer_bytes: self.memory.fragment_buffer_bytes.max(other.memory.fragment_buffer_bytes),
+            framebuffer_bytes: self.memory.framebuffer_bytes.max(other.memory.framebuffer_bytes),
+        };
+    }
+}
+
+// Memoria (en bytes, según la capacidad reservada) de los buffers usados en un render
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MemoryStats {
+    pub vertex_buffer_bytes: usize,
+    pub fragment_buffer_bytes: usize,
+    pub framebuffer_bytes: usize,
+}
+
+// Límite por defecto del buffer de fragmentos antes de avisar por stderr
+pub const DEFAULT_FRAGMENT_MEMORY_LIMIT: usize = 512 * 1024 * 1024;
+
+// Área mínima (en píxeles) para considerar que un triángulo no es degenerado
+const MIN_TRIANGLE_AREA: f32 = 1e-6;
+
+// Verifica que las tres componentes de un vector sean finitas (sin NaN ni Inf)
+pub(crate) fn is_finite_vec3(v: &Vec3) -> bool {
+    v.x.is_finite() && v.y.is_finite() && v.z.is_finite()
+}
+
+// Área con signo (doble) del triángulo en pantalla; negativa para las caras frontales
+fn signed_area(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)
+}
+
+// Área del triángulo en pantalla; NaN si alguna coordenada es inválida
+fn triangle_area(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
+    signed_area(a, b, c).abs() * 0.5
+}
+
+// Indica si el triángulo debe descartarse según el modo de culling. En pantalla y crece
+// hacia abajo, así que un triángulo antihorario en el modelo tiene área con signo negativa
+fn is_culled(a: &Vec3, b: &Vec3, c: &Vec3, cull_mode: CullMode, front_face: Winding) -> bool {
+    let front_facing = (signed_area(a, b, c) < 0.0) == (front_face == Winding::CounterClockwise);
+    match cull_mode {
+        CullMode::None => false,
+        CullMode::Back => !front_facing,
+        CullMode::Front => front_facing,
+    }
+}
+
 // Cálculo del Bounding Box que contiene el triángulo
 fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
     let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
@@ -73,93 +845,1246 @@ fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i3
     (min_x, min_y, max_x, max_y)
 }
 
-// Coordenadas baricéntricas para un punto en el triángulo
-fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3) -> (f32, f32, f32) {
-    let v0 = b - a;  // Vector AB
-    let v1 = c - a;  // Vector AC
-    let v2 = p - a;  // Vector AP
-
-    let d00 = v0.dot(&v0);
-    let d01 = v0.dot(&v1);
-    let d11 = v1.dot(&v1);
-    let d20 = v2.dot(&v0);
-    let d21 = v2.dot(&v1);
-
-    let denom = d00 * d11 - d01 * d01;
-    let v = (d11 * d20 - d01 * d21) / denom;
-    let w = (d00 * d21 - d01 * d20) / denom;
-    let u = 1.0 - v - w;
-
-    (u, v, w)
-}
-
-// Rasterización de triángulos usando Bounding Box y las coordenadas baricéntricas
-pub fn primitive_assembly_rasterization(vertex_array: &[Vertex]) -> Vec<Fragment> {
-    let mut fragments: Vec<Fragment> = Vec::new();
-
-    // Recorrer el vertex_array en grupos de 3 (triángulos)
-    for triangle_vertices in vertex_array.chunks(3) {
-        if triangle_vertices.len() == 3 {
-            let v0 = &triangle_vertices[0];
-            let v1 = &triangle_vertices[1];
-            let v2 = &triangle_vertices[2];
-
-            // Calcular el Bounding Box del triángulo
-            let (min_x, min_y, max_x, max_y) = calculate_bounding_box(
-                &v0.transformed_position,
-                &v1.transformed_position,
-                &v2.transformed_position,
-            );
-
-            // Restringimos la rasterización al área dentro del Bounding Box
-            for y in min_y..=max_y {
-                for x in min_x..=max_x {
-                    let p = Vec3::new(x as f32, y as f32, 0.0);
-
-                    // Obtener coordenadas baricéntricas
-                    let (u, v, w) = barycentric_coordinates(&p, &v0.transformed_position, &v1.transformed_position, &v2.transformed_position);
-
-                    // Verificar si el punto está dentro del triángulo
-                    if u >= 0.0 && v >= 0.0 && w >= 0.0 {
-                        // Interpolar color usando las coordenadas baricéntricas
-                        let r = (u * v0.color.r as f32 + v * v1.color.r as f32 + w * v2.color.r as f32) as u8;
-                        let g = (u * v0.color.g as f32 + v * v1.color.g as f32 + w * v2.color.g as f32) as u8;
-                        let b = (u * v0.color.b as f32 + v * v1.color.b as f32 + w * v2.color.b as f32) as u8;
-
-                        // Crear un fragmento interpolado
-                        let fragment = Fragment {
-                            position: Vec2::new(x as f32, y as f32),
-                            color: Color { r, g, b },
-                            depth: u * v0.transformed_position.z + v * v1.transformed_position.z + w * v2.transformed_position.z,
-                        };
-
-                        fragments.push(fragment);
-                    }
-                }
+// Profundidad del pixel: la z después de la división de perspectiva, que es afín en
+// pantalla y se interpola sin corregir por w (como en las GPUs). Con proyección en
+// perspectiva queda distribuida en forma hiperbólica (más precisión cerca), que es lo que
+// aprovecha el modo reversed-Z. Sin proyección coincide con la z transformada.
+fn interpolated_depth(u: f32, v: f32, w: f32, v0: &Vertex, v1: &Vertex, v2: &Vertex) -> f32 {
+    u * v0.transformed_position.z + v * v1.transformed_position.z + w * v2.transformed_position.z
+}
+
+// Pesos baricéntricos con corrección de perspectiva: los pesos de pantalla divididos por
+// la w de cada vértice y normalizados. Sin proyección coinciden con los pesos afines.
+fn perspective_correct_weights(u: f32, v: f32, w: f32, v0: &Vertex, v1: &Vertex, v2: &Vertex) -> Vec3 {
+    let weights = Vec3::new(u / v0.clip_position.w, v / v1.clip_position.w, w / v2.clip_position.w);
+    weights / (weights.x + weights.y + weights.z)
+}
+
+// Primitive Assembly, culling, rasterización (dentro de `bounds`, en pixeles) y Fragment Shading
+pub fn primitive_assembly_rasterization(vertex_array: &[Vertex], uniforms: &Uniforms, bounds: &Rect, stats: &mut RenderStats) -> Vec<Fragment> {
+    primitive_assembly_rasterization_with_capacity(vertex_array, uniforms, bounds, stats, 0)
+}
+
+// Igual que `primitive_assembly_rasterization`, reservando de entrada `capacity` fragmentos
+pub fn primitive_assembly_rasterization_with_capacity(
+    vertex_array: &[Vertex],
+    uniforms: &Uniforms,
+    bounds: &Rect,
+    stats: &mut RenderStats,
+    capacity: usize,
+) -> Vec<Fragment> {
+    let vertex_array = &*clip_near_plane(triangle_list(vertex_array, uniforms.topology), &uniforms.projection_matrix);
+    let triangles = {
+        let _span = trace_span!("primitive_assembly");
+        assemble_triangles(vertex_array, stats)
+    };
+
+    let triangles = {
+        let _span = trace_span!("backface_culling");
+        cull_triangles(vertex_array, triangles, uniforms.cull_mode, uniforms.front_face)
+    };
+
+    let mut fragments = {
+        let _span = trace_span!("rasterization");
+        rasterize_triangles(vertex_array, &triangles, uniforms, bounds, capacity)
+    };
+
+    {
+        let _span = trace_span!("fragment_shading");
+        shade_fragments(&mut fragments, uniforms);
+    }
+
+    fragments
+}
+
+// Los triángulos de `vertex_array` armados según `topology` como una lista de triángulos
+// sueltos (tres vértices por triángulo), que es lo que esperan las etapas siguientes. Las
+// tiras y abanicos copian los vértices compartidos (ya transformados); una lista se
+// devuelve sin copiar
+pub fn triangle_list(vertex_array: &[Vertex], topology: PrimitiveTopology) -> Cow<'_, [Vertex]> {
+    if topology == PrimitiveTopology::TriangleList {
+        return Cow::Borrowed(vertex_array);
+    }
+    Cow::Owned(topology.triangles(vertex_array.len()).flat_map(|triangle| triangle.map(|index| vertex_array[index].clone())).collect())
+}
+
+// w del plano near de una proyección en perspectiva (ver `camera::perspective_matrix`: con
+// w = z y profundidad a + b / z, near es donde vale 0, o 1 con DepthMode::Reversed; el otro
+// extremo es far). Si no se reconoce, un plano apenas delante de la cámara. Sin perspectiva
+// w es siempre 1 y no hay nada que recortar
+fn near_plane_w(projection: &Mat4) -> Option<f32> {
+    const MIN_W: f32 = 1e-4;
+    if projection[(3, 2)] == 0.0 {
+        return None;
+    }
+    let (a, b) = (projection[(2, 2)], projection[(2, 3)]);
+    let near = (-b / a).min(b / (1.0 - a));
+    Some(if near.is_finite() && near > MIN_W { near } else { MIN_W })
+}
+
+// Recorte contra el plano near, antes de armar los triángulos: los que tienen vértices
+// detrás de él se reemplazan por la parte que queda delante (uno o dos triángulos con el
+// mismo winding) y los que quedan detrás por completo se descartan. Sin recorte, un
+// vértice detrás de la cámara no se puede dividir por su w y se perdía el triángulo
+// entero. Si ningún vértice cruza el plano, la lista se devuelve sin copiar
+pub fn clip_near_plane<'a>(vertex_array: Cow<'a, [Vertex]>, projection: &Mat4) -> Cow<'a, [Vertex]> {
+    let Some(near) = near_plane_w(projection) else { return vertex_array };
+    // Un w NaN cuenta como delante: el triángulo llega al ensamblado, que lo descarta como no finito
+    let inside = |vertex: &Vertex| vertex.clip_position.w >= near || vertex.clip_position.w.is_nan();
+    if vertex_array.iter().all(inside) {
+        return vertex_array;
+    }
+
+    let mut clipped = Vec::with_capacity(vertex_array.len());
+    let mut polygon: Vec<Vertex> = Vec::with_capacity(4);
+    for triangle in vertex_array.chunks_exact(3) {
+        if triangle.iter().all(inside) {
+            clipped.extend_from_slice(triangle);
+            continue;
+        }
+
+        // Sutherland-Hodgman contra un solo plano: queda un polígono de 3 o 4 vértices, o nada
+        polygon.clear();
+        for (i, current) in triangle.iter().enumerate() {
+            let next = &triangle[(i + 1) % 3];
+            if inside(current) {
+                polygon.push(current.clone());
             }
+            if inside(current) != inside(next) {
+                polygon.push(near_plane_intersection(current, next, near));
+            }
+        }
+        // Abanico desde el primer vértice, en el mismo orden que el triángulo original
+        for i in 1..polygon.len().saturating_sub(1) {
+            clipped.extend_from_slice(&[polygon[0].clone(), polygon[i].clone(), polygon[i + 1].clone()]);
         }
     }
+    Cow::Owned(clipped)
+}
 
+// Vértice donde la arista a-b cruza el plano w = near. Los atributos se interpolan en
+// coordenadas homogéneas (donde son lineales) y la posición en pantalla sale de dividir
+// por el w del corte
+fn near_plane_intersection(a: &Vertex, b: &Vertex, near: f32) -> Vertex {
+    let t = (a.clip_position.w - near) / (a.clip_position.w - b.clip_position.w);
+    let mut vertex = a.lerp(b, t);
+    vertex.clip_position.w = near;
+    vertex.transformed_position = vertex.clip_position.xyz() / near;
+    // Un extremo detrás de la cámara en el frame anterior no tiene posición previa: ese
+    // vértice queda sin movimiento
+    if !is_finite_vec3(&vertex.prev_transformed_position) {
+        vertex.prev_transformed_position = vertex.transformed_position;
+    }
+    vertex
+}
+
+// Primitive Assembly: índices de los triángulos válidos (el triángulo i son los vértices
+// 3i, 3i+1 y 3i+2), descartando los que tienen coordenadas NaN/Inf (contados aparte en
+// `non_finite_triangles`) o área casi nula
+pub(crate) fn assemble_triangles(vertex_array: &[Vertex], stats: &mut RenderStats) -> Vec<u32> {
+    vertex_array
+        .chunks_exact(3)
+        .enumerate()
+        .filter(|(_, triangle_vertices)| {
+            stats.triangles += 1;
+
+            // Una coordenada no finita (también solo en z, que no entra en el área) haría que
+            // el Bounding Box y los pesos baricéntricos den cualquier cosa
+            let finite = triangle_vertices.iter().all(|vertex| is_finite_vec3(&vertex.transformed_position));
+            #[cfg(feature = "strict-pipeline")]
+            assert!(finite, "Triángulo con coordenadas no finitas");
+            if !finite {
+                stats.non_finite_triangles += 1;
+                stats.skipped_triangles += 1;
+                return false;
+            }
+
+            let (v0, v1, v2) = (&triangle_vertices[0], &triangle_vertices[1], &triangle_vertices[2]);
+            let area = triangle_area(&v0.transformed_position, &v1.transformed_position, &v2.transformed_position);
+            if !area.is_finite() || area < MIN_TRIANGLE_AREA {
+                stats.skipped_triangles += 1;
+                return false;
+            }
+            true
+        })
+        .map(|(index, _)| index as u32)
+        .collect()
+}
+
+// Culling de caras según el modo del material, quitando de `triangles` (sin copiarla) los
+// índices de las caras descartadas
+pub(crate) fn cull_triangles(vertex_array: &[Vertex], mut triangles: Vec<u32>, cull_mode: CullMode, front_face: Winding) -> Vec<u32> {
+    if cull_mode == CullMode::None {
+        return triangles;
+    }
+    triangles.retain(|&index| {
+        let t = triangle_at(vertex_array, index);
+        !is_culled(&t[0].transformed_position, &t[1].transformed_position, &t[2].transformed_position, cull_mode, front_face)
+    });
+    triangles
+}
+
+// Vértices del triángulo `index` de una lista de triángulos sueltos
+fn triangle_at(vertex_array: &[Vertex], index: u32) -> &[Vertex] {
+    &vertex_array[index as usize * 3..index as usize * 3 + 3]
+}
+
+// Rasterización de triángulos usando Bounding Box y las coordenadas baricéntricas.
+// Los fragmentos quedan con el color interpolado, sin iluminar.
+pub(crate) fn rasterize_triangles(vertex_array: &[Vertex], triangles: &[u32], uniforms: &Uniforms, bounds: &Rect, capacity: usize) -> Vec<Fragment> {
+    let mut fragments: Vec<Fragment> = Vec::with_capacity(capacity);
+    rasterize_triangles_into(vertex_array, triangles, uniforms, bounds, &mut fragments);
     fragments
 }
 
+// Igual que `rasterize_triangles`, agregando los fragmentos a `fragments` para reutilizar el
+// buffer entre draws (p. ej. una instancia tras otra)
+pub(crate) fn rasterize_triangles_into(vertex_array: &[Vertex], triangles: &[u32], uniforms: &Uniforms, bounds: &Rect, fragments: &mut Vec<Fragment>) {
+    let (left, top) = (bounds.x as i32, bounds.y as i32);
+    let (right, bottom) = (left + bounds.width as i32 - 1, top + bounds.height as i32 - 1);
+
+    let mut covered: Vec<CoveredPixel> = Vec::new();
+    let mut quads: Vec<QuadPixel> = Vec::new();
+    let mut spans: Vec<Span> = Vec::new();
+
+    for &triangle in triangles {
+        let triangle_vertices = triangle_at(vertex_array, triangle);
+        let v0 = &triangle_vertices[0];
+        let v1 = &triangle_vertices[1];
+        let v2 = &triangle_vertices[2];
+
+        // Calcular el Bounding Box del triángulo, recortado a `bounds`: un triángulo casi
+        // degenerado pero enorme en pantalla (p. ej. con un vértice pegado al plano near) no
+        // recorre millones de pixeles fuera del framebuffer
+        let (min_x, min_y, max_x, max_y) = calculate_bounding_box(
+            &v0.transformed_position,
+            &v1.transformed_position,
+            &v2.transformed_position,
+        );
+        let (min_x, min_y, max_x, max_y) = (min_x.max(left), min_y.max(top), max_x.min(right), max_y.min(bottom));
+        if min_x > max_x || min_y > max_y {
+            continue;
+        }
+
+        // Coeficientes baricéntricos del triángulo en pantalla
+        let setup = match TriangleSetup::new(&v0.transformed_position, &v1.transformed_position, &v2.transformed_position) {
+            Some(setup) => setup,
+            None => continue,
+        };
+
+        // Polygon offset del draw, constante en todo el triángulo
+        let depth_offset = uniforms.effective_depth_bias().offset(&v0.transformed_position, &v1.transformed_position, &v2.transformed_position);
+
+        // Velocidad de cada vértice (posición actual - posición del frame anterior)
+        let velocity0 = (v0.transformed_position - v0.prev_transformed_position).xy();
+        let velocity1 = (v1.transformed_position - v1.prev_transformed_position).xy();
+        let velocity2 = (v2.transformed_position - v2.prev_transformed_position).xy();
+
+        // Fragmento interpolado del pixel (x, y) con pesos de pantalla (u, v, w)
+        let mut emit = |x: i32, y: i32, u: f32, v: f32, w: f32, depth: f32| {
+            // Interpolar color usando las coordenadas baricéntricas
+            let r = (u * v0.color.r as f32 + v * v1.color.r as f32 + w * v2.color.r as f32) as u8;
+            let g = (u * v0.color.g as f32 + v * v1.color.g as f32 + w * v2.color.g as f32) as u8;
+            let b = (u * v0.color.b as f32 + v * v1.color.b as f32 + w * v2.color.b as f32) as u8;
+
+            // Normal interpolada, para la iluminación
+            let normal = v0.transformed_normal * u + v1.transformed_normal * v + v2.transformed_normal * w;
+            let normal = if normal.norm() > 0.0 { normal.normalize() } else { normal };
+
+            // Pesos con corrección de perspectiva, para los atributos que no son de pantalla
+            let weights = perspective_correct_weights(u, v, w, v0, v1, v2);
+            let view = uniforms.fragment_view_data.then(|| {
+                let normal = v0.view_normal * weights.x + v1.view_normal * weights.y + v2.view_normal * weights.z;
+                ViewData {
+                    position: v0.view_position * weights.x + v1.view_position * weights.y + v2.view_position * weights.z,
+                    normal: if normal.norm() > 0.0 { normal.normalize() } else { normal },
+                }
+            });
+
+            fragments.push(Fragment {
+                position: Vec2::new(x as f32, y as f32),
+                color: Color { r, g, b },
+                depth: depth + depth_offset,
+                velocity: velocity0 * u + velocity1 * v + velocity2 * w,
+                normal,
+                intensity: 1.0,
+                light: v0.light * u + v1.light * v + v2.light * w,
+                alpha: 1.0,
+                model_position: v0.position * u + v1.position * v + v2.position * w,
+                tex_coords: v0.tex_coords * weights.x + v1.tex_coords * weights.y + v2.tex_coords * weights.z,
+                barycentric: Some(weights),
+                triangle,
+                view,
+            });
+        };
+
+        // Cuadros de 2x2: dos filas del Bounding Box a la vez, con la profundidad incluida
+        if uniforms.rasterizer_mode == RasterizerMode::Quads {
+            let depths = Vec3::new(v0.transformed_position.z, v1.transformed_position.z, v2.transformed_position.z);
+            for y in (min_y..=max_y).step_by(2) {
+                quads.clear();
+                covered_in_quads(&setup, &depths, y, min_x, max_x, max_y, &mut quads);
+                for &QuadPixel { x, y, u, v, w, depth } in &quads {
+                    emit(x, y, u, v, w, depth);
+                }
+            }
+            continue;
+        }
+
+        // Filas a recorrer: el Bounding Box completo o solo los tramos de cada scanline
+        spans.clear();
+        match uniforms.rasterizer_mode {
+            RasterizerMode::BoundingBox | RasterizerMode::Quads => {
+                spans.extend((min_y..=max_y).map(|y| Span { y, min_x, max_x }));
+            }
+            RasterizerMode::Scanline => {
+                triangle_scanline(&v0.transformed_position, &v1.transformed_position, &v2.transformed_position, min_y, max_y, &mut spans);
+            }
+        }
+
+        // Restringimos la rasterización al área dentro del Bounding Box,
+        // evaluando cada fila con SIMD cuando está disponible
+        for &Span { y, min_x: span_min_x, max_x: span_max_x } in &spans {
+            covered.clear();
+            covered_in_row(&setup, y, span_min_x.max(min_x), span_max_x.min(max_x), &mut covered);
+
+            for &CoveredPixel { x, u, v, w } in &covered {
+                emit(x, y, u, v, w, interpolated_depth(u, v, w, v0, v1, v2));
+            }
+        }
+    }
+}
+
+// Fragment Shading: iluminación difusa con la normal interpolada de cada fragmento. Cada luz
+// multiplica el albedo por su color canal por canal, y los aportes (más la luz ambiental)
+// se suman con clamping. Los materiales reflectivos mezclan el resultado con el mapa de entorno
+pub(crate) fn shade_fragments(fragments: &mut [Fragment], uniforms: &Uniforms) {
+    let material = &uniforms.material;
+
+    for fragment in fragments.iter_mut() {
+        // La textura del material reemplaza el color de los vértices
+        if let Some(texture) = &material.texture {
+            fragment.color = texture.sample(fragment.tex_coords.x, fragment.tex_coords.y);
+        }
+        if let Some(shader) = material.fragment_shader {
+            shader(fragment, uniforms);
+        }
+
+        // Phong: la luz se evalúa con la normal interpolada del fragmento. Gouraud: ya viene
+        // interpolada desde los vértices en `fragment.light`
+        let light = match uniforms.shading_model {
+            ShadingModel::Phong => {
+                let point = Vec3::new(fragment.position.x, fragment.position.y, fragment.depth);
+                incident_light(&point, &fragment.normal, uniforms)
+            }
+            ShadingModel::Gouraud => fragment.light,
+        };
+
+        let model_position = fragment.model_position;
+        let world_position = || (uniforms.model_matrix * model_position.push(1.0)).xyz();
+        fragment.color = lit_color(fragment.color, &light, &fragment.normal, world_position, uniforms);
+        fragment.light = light;
+    }
+}
+
+// Color final de una superficie de color `color` que recibe `light`, con la rampa y el
+// reflejo del entorno del material. `world_position` da la posición del punto en el mundo,
+// para la dirección de vista del reflejo; solo se evalúa con materiales reflectivos. Lo
+// comparten el fragment shading y el pase de iluminación del G-buffer
+pub(crate) fn lit_color(
+    color: Color,
+    light: &Vec3,
+    normal: &Vec3,
+    world_position: impl FnOnce() -> Vec3,
+    uniforms: &Uniforms,
+) -> Color {
+    let material = &uniforms.material;
+
+    // Con una rampa en el material, el canal más iluminado elige el color en la rampa
+    // y el color de la luz lo tiñe
+    let color = match &material.gradient {
+        Some(ramp) => {
+            let level = light.max();
+            let tint = if level > 0.0 { light / level } else { *light };
+            ramp.sample(level).modulate(&tint)
+        }
+        None => color.modulate(light),
+    };
+
+    // Reflejo del entorno en la dirección de espejo de la vista
+    match uniforms.environment.as_ref().filter(|_| material.reflectivity > 0.0) {
+        Some(environment) => {
+            let reflectivity = material.reflectivity.min(1.0);
+            let view = view_vector(&world_position(), uniforms);
+            let reflected = environment.sample(&reflect_view(normal, &view));
+            color * (1.0 - reflectivity) + reflected * reflectivity
+        }
+        None => color,
+    }
+}
+
 // Solo la etapa de Fragment Processing con el Vertex Shader y Rasterización
-pub fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex]) {
-    // Vertex Shader Stage: Aplicar transformaciones a los vértices
+pub fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex]) -> RenderStats {
+    render_with_shader(framebuffer, uniforms, vertex_array, &DefaultVertexShader)
+}
+
+// Contorno de selección: la malla extruida con color plano, dibujando solo las caras
+// traseras y con prueba de profundidad contra el z-buffer del pase principal, de modo
+// que solo queda visible el borde alrededor de la silueta (y nunca encima de objetos más cercanos)
+pub fn render_outline(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], color: Color, thickness: f32) -> RenderStats {
+    let mut outline_uniforms = uniforms.clone();
+    outline_uniforms.cull_mode = CullMode::Front;
+    outline_uniforms.material.unlit = true;
+
+    // El grosor está en pixeles de la ventana, no del framebuffer con SSAA
+    let shader = OutlineVertexShader { thickness: thickness * uniforms.ssaa_factor.max(1) as f32, color };
+    render_with_shader(framebuffer, &outline_uniforms, vertex_array, &shader)
+}
+
+// Wireframe de la malla dibujado encima del frame. Sin `depth_bias` no hay prueba de
+// profundidad y se ven todas las aristas; con él, las aristas se prueban (sin escribir el
+// z-buffer) con el polygon offset de su triángulo, de modo que las ocultas no se dibujan y
+// las visibles no pelean con la superficie. Con `hidden` además, las ocultas se dibujan
+// punteadas con ese patrón en lugar de omitirse. En modo Antialiased la cobertura de cada
+// fragmento mezcla su color con el que ya tiene el pixel.
+pub fn render_wireframe(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    mode: LineMode,
+    depth_bias: Option<DepthBias>,
+    hidden: Option<Stipple>,
+) {
+    framebuffer.matrices = Some(uniforms.matrices());
+    let depth_mode = framebuffer.depth_mode();
     let transformed_vertices: Vec<Vertex> = vertex_array
         .iter()
-        .map(|vertex| vertex_shader(vertex, uniforms))
+        .map(|vertex| DefaultVertexShader.transform(vertex, uniforms))
         .collect();
+    let transformed_vertices = clip_near_plane(triangle_list(&transformed_vertices, uniforms.topology), &uniforms.projection_matrix);
+
+    let draw = |framebuffer: &mut Framebuffer, fragment: &Fragment| {
+        let (x, y) = (fragment.position.x as isize, fragment.position.y as isize);
+        let Some(existing) = framebuffer.get_pixel(x, y) else { return };
+        let coverage = fragment.intensity.clamp(0.0, 1.0);
+        let color = fragment.color * coverage + Color::from_u32(existing) * (1.0 - coverage);
+        framebuffer.set_current_color(color);
+        framebuffer.point(x, y);
+    };
+
+    for triangle_vertices in transformed_vertices.chunks(3) {
+        if triangle_vertices.len() < 3 || !triangle_vertices.iter().all(|v| is_finite_vec3(&v.transformed_position)) {
+            continue;
+        }
 
-    // Primitive Assembly y Rasterización
-    let fragments = primitive_assembly_rasterization(&transformed_vertices);
+        let (v0, v1, v2) = (&triangle_vertices[0], &triangle_vertices[1], &triangle_vertices[2]);
+        let depth_offset = depth_bias.map(|bias| bias.offset(&v0.transformed_position, &v1.transformed_position, &v2.transformed_position));
 
-    // Fragment Processing Stage: dibujar los fragmentos en el framebuffer
+        let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+        for (a, b) in [(v0, v1), (v1, v2), (v2, v0)] {
+            let mut occluded = Vec::new();
+            for fragment in line_clipped(a, b, mode, width, height) {
+                let (x, y) = (fragment.position.x as isize, fragment.position.y as isize);
+                if let (Some(offset), Some(depth)) = (depth_offset, framebuffer.get_depth(x, y)) {
+                    if depth_mode.is_closer(depth, fragment.depth + offset) {
+                        occluded.push(fragment);
+                        continue;
+                    }
+                }
+                draw(framebuffer, &fragment);
+            }
+
+            // El patrón se mide desde el inicio de la arista, así los tramos no dependen de
+            // dónde empiece la parte oculta
+            if let Some(stipple) = &hidden {
+                for fragment in apply_stipple(occluded, a, b, stipple) {
+                    draw(framebuffer, &fragment);
+                }
+            }
+        }
+    }
+}
+
+// Polilínea (p. ej. una trayectoria) en coordenadas del modelo, transformada con `matrix`.
+// Tiene prueba de profundidad contra lo ya dibujado pero no escribe el z-buffer, y se
+// desvanece linealmente hasta ser transparente en el último punto. Cada segmento se recorta
+// contra w = 0 antes de dividir (un punto detrás de la cámara no se refleja en la pantalla)
+// y después contra el framebuffer
+pub fn render_polyline(framebuffer: &mut Framebuffer, matrix: &Mat4, points: &[Vec3], color: Color, width: f32) {
+    let to_vertex = |clip: Vec4| {
+        let mut vertex = Vertex::new(Vec3::zeros(), Vec3::zeros(), Vec2::zeros());
+        vertex.transformed_position = clip.xyz() / clip.w;
+        vertex.color = color;
+        vertex
+    };
+    let positions: Vec<Vec4> = points.iter().map(|position| matrix * position.push(1.0)).collect();
+    let segments = positions.len().saturating_sub(1).max(1) as f32;
+
+    for (segment, pair) in positions.windows(2).enumerate() {
+        let Some((a, b)) = clip_homogeneous_w(pair[0], pair[1]) else { continue };
+        let pair = [to_vertex(a), to_vertex(b)];
+        if !is_finite_vec3(&pair[0].transformed_position) || !is_finite_vec3(&pair[1].transformed_position) {
+            continue;
+        }
+
+        let (screen_width, screen_height) = (framebuffer.width as f32, framebuffer.height as f32);
+        for fragment in line_clipped(&pair[0], &pair[1], LineMode::Thick(width), screen_width, screen_height) {
+            let x = fragment.position.x as isize;
+            let y = fragment.position.y as isize;
+            let (Some(existing), Some(depth)) = (framebuffer.get_pixel(x, y), framebuffer.get_depth(x, y)) else { continue };
+            if !framebuffer.depth_mode().is_closer(fragment.depth, depth) {
+                continue;
+            }
+
+            // Opacidad según la distancia a lo largo de la polilínea
+            let along = (fragment.position - pair[0].transformed_position.xy()).norm()
+                / (pair[1].transformed_position.xy() - pair[0].transformed_position.xy()).norm().max(1.0);
+            let alpha = (1.0 - (segment as f32 + along.min(1.0)) / segments) * fragment.intensity;
+
+            framebuffer.set_current_color(fragment.color * alpha + Color::from_u32(existing) * (1.0 - alpha));
+            framebuffer.point(x, y);
+        }
+    }
+}
+
+// Dibuja las 12 aristas de una caja (en coordenadas del modelo) con `line`, proyectando sus
+// esquinas con `uniforms.transformation_matrix`. Va encima de todo, sin profundidad, para
+// depurar volúmenes envolventes. Las aristas se recortan contra el plano w = 0 (lo que
+// queda detrás de la cámara) y contra el framebuffer, así que una caja muy cercana no
+// genera líneas enormes
+pub fn draw_aabb(framebuffer: &mut Framebuffer, uniforms: &Uniforms, aabb: &BoundingBox, color: Color) {
+    let corners = aabb.corners().map(|corner| uniforms.transformation_matrix * corner.push(1.0));
+    framebuffer.set_current_color(color);
+    for (a, b) in BoundingBox::EDGES {
+        draw_clipped_segment(framebuffer, corners[a], corners[b], color);
+    }
+}
+
+// Dibuja la normal de cada vértice como un segmento desde su posición hasta
+// `position + normal * length` (en coordenadas del modelo), proyectados con
+// `uniforms.transformation_matrix`. Como `draw_aabb`, va encima de todo y recorta contra la
+// cámara y el framebuffer. Sirve para revisar la orientación de las normales (las generadas
+// o las del OBJ): en una malla cerrada deben apuntar hacia afuera
+pub fn draw_vertex_normals(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], length: f32, color: Color) {
+    framebuffer.set_current_color(color);
+    for vertex in vertex_array {
+        let tip = vertex.position + vertex.normal * length;
+        let a = uniforms.transformation_matrix * vertex.position.push(1.0);
+        let b = uniforms.transformation_matrix * tip.push(1.0);
+        draw_clipped_segment(framebuffer, a, b, color);
+    }
+}
+
+// Segmento entre dos puntos en coordenadas homogéneas (ya por el viewport), recortado a
+// w > 0 y al framebuffer, con el color actual
+fn draw_clipped_segment(framebuffer: &mut Framebuffer, a: Vec4, b: Vec4, color: Color) {
+    let endpoint = |position: Vec2| {
+        let mut vertex = Vertex::new_with_color(Vec3::zeros(), color);
+        vertex.transformed_position = Vec3::new(position.x, position.y, 0.0);
+        vertex
+    };
+    let Some((a, b)) = clip_segment_w(a, b) else { return };
+    let Some((a, b)) = clip_segment_rect(a, b, framebuffer.width as f32, framebuffer.height as f32) else { return };
+    for fragment in line(&endpoint(a), &endpoint(b)) {
+        framebuffer.point(fragment.position.x as isize, fragment.position.y as isize);
+    }
+}
+
+// Recorta un segmento en coordenadas homogéneas a la parte con w > 0, sin dividir
+fn clip_homogeneous_w(a: Vec4, b: Vec4) -> Option<(Vec4, Vec4)> {
+    const MIN_W: f32 = 1e-4;
+    if a.w < MIN_W && b.w < MIN_W {
+        return None;
+    }
+    let clip = |inside: Vec4, outside: Vec4| {
+        if outside.w >= MIN_W { outside } else { inside + (outside - inside) * ((inside.w - MIN_W) / (inside.w - outside.w)) }
+    };
+    Some((clip(b, a), clip(a, b)))
+}
+
+// Recorta un segmento en coordenadas homogéneas a la parte con w > 0 y lo divide por w
+fn clip_segment_w(a: Vec4, b: Vec4) -> Option<(Vec2, Vec2)> {
+    let (a, b) = clip_homogeneous_w(a, b)?;
+    let (a, b) = (a.xy() / a.w, b.xy() / b.w);
+    (a.x.is_finite() && a.y.is_finite() && b.x.is_finite() && b.y.is_finite()).then_some((a, b))
+}
+
+// Escribe fragmentos con prueba de profundidad: solo quedan los más cercanos. Con los
+// demás modos de BlendMode se combinan con el pixel existente sin escribir profundidad
+pub fn write_fragments(framebuffer: &mut Framebuffer, fragments: &[Fragment], blend_mode: BlendMode, stencil: Option<StencilState>) {
     for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
+        let x = fragment.position.x as isize;
+        let y = fragment.position.y as isize;
+
+        // La prueba de stencil va antes que la de profundidad
+        if let Some(state) = &stencil {
+            if !framebuffer.stencil_test(x, y, state) {
+                continue;
+            }
+        }
+
+        let written = match blend_mode {
+            BlendMode::Opaque => {
+                framebuffer.set_current_color(fragment.color);
+                let written = framebuffer.point_with_depth(x, y, fragment.depth);
+                if written {
+                    framebuffer.set_velocity(x, y, fragment.velocity);
+                    framebuffer.set_triangle(x, y, fragment.triangle);
+                }
+                written
+            }
+            mode => framebuffer.combine_with_depth(x, y, fragment.depth, fragment.color, fragment.alpha, mode),
+        };
+
+        if let (true, Some(state)) = (written, &stencil) {
+            framebuffer.apply_stencil_op(x, y, state.pass, state.reference);
+        }
+    }
+}
+
+// Marca con `value` el stencil de los pixeles que cubre la malla y que no quedan detrás de
+// lo ya dibujado, sin tocar color ni profundidad. Sirve para enmascarar los draws
+// siguientes con la silueta de un objeto (p. ej. `StencilState::equal(value)`)
+pub fn render_stencil_mask(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], value: u8) {
+    framebuffer.matrices = Some(uniforms.matrices());
+    framebuffer.draw_supersampled_with_stencil(uniforms, true, |framebuffer, uniforms| stencil_mask(framebuffer, uniforms, vertex_array, value));
+}
+
+fn stencil_mask(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], value: u8) {
+    let transformed: Vec<Vertex> = vertex_array.iter().map(|vertex| DefaultVertexShader.transform(vertex, uniforms)).collect();
+    let transformed = clip_near_plane(Cow::Owned(transformed), &uniforms.projection_matrix);
+    let mut stats = RenderStats::default();
+    let triangles = assemble_triangles(&transformed, &mut stats);
+    let triangles = cull_triangles(&transformed, triangles, uniforms.cull_mode, uniforms.front_face);
+    let fragments = rasterize_triangles(&transformed, &triangles, uniforms, &framebuffer.raster_bounds(), 0);
+
+    let depth_mode = framebuffer.depth_mode();
+    for fragment in &fragments {
+        let (x, y) = (fragment.position.x as isize, fragment.position.y as isize);
+        if let Some(depth) = framebuffer.get_depth(x, y) {
+            if !depth_mode.is_closer(depth, fragment.depth) {
+                framebuffer.apply_stencil_op(x, y, StencilOp::Replace, value);
+            }
+        }
+    }
+}
+
+// Igual que `render`, pero con un vertex shader personalizado
+pub fn render_with_shader(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    shader: &dyn VertexShader,
+) -> RenderStats {
+    Pipeline::standard(shader).run(framebuffer, uniforms, vertex_array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, Projection};
+    use crate::environment::Cubemap;
+    use crate::light::Light;
+    use crate::material::Material;
+    use crate::uniforms::{viewport_matrix, UniformsBuilder};
+    use crate::viewport::Viewport;
+    use std::sync::Arc;
+
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 64;
+
+    fn vertex(position: Vec3) -> Vertex {
+        let mut vertex = Vertex::new(position, Vec3::new(0.0, 0.0, -1.0), Vec2::zeros());
+        vertex.color = Color::new(220, 120, 40);
+        vertex
+    }
+
+    // Triángulo con el ángulo recto en `corner` y catetos de `size`
+    fn triangle(corner: Vec3, size: f32) -> Vec<Vertex> {
+        vec![vertex(corner), vertex(corner + Vec3::new(size, 0.0, 0.0)), vertex(corner + Vec3::new(0.0, size, 0.0))]
+    }
+
+    // Proyección ortográfica de 20 pixeles por unidad centrada en el framebuffer, sin
+    // iluminar y sin culling
+    fn uniforms() -> Uniforms {
+        UniformsBuilder::new()
+            .viewport(viewport_matrix(WIDTH, HEIGHT, 20.0))
+            .material(Material { unlit: true, ..Material::default() })
+            .cull_mode(CullMode::None)
+            .build()
+    }
+
+    fn rendered(vertex_array: &[Vertex]) -> (RenderStats, Framebuffer) {
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(Color::black());
+        let stats = render(&mut framebuffer, &uniforms(), vertex_array);
+        (stats, framebuffer)
+    }
+
+    fn covered_pixels(framebuffer: &Framebuffer) -> usize {
+        framebuffer.as_slice().iter().filter(|&&pixel| pixel & 0x00FF_FFFF != 0).count()
+    }
+
+    #[test]
+    fn nan_vertex_keeps_the_rest_of_the_frame() {
+        let valid = triangle(Vec3::new(-1.0, -1.0, 0.0), 1.0);
+        let mut vertex_array = triangle(Vec3::new(0.2, 0.2, 0.0), 1.0);
+        vertex_array[1].position.x = f32::NAN;
+        vertex_array.extend_from_slice(&valid);
+
+        let (stats, framebuffer) = rendered(&vertex_array);
+        let (_, expected) = rendered(&valid);
+        assert_eq!(stats.triangles, 2);
+        assert_eq!(stats.non_finite_triangles, 1);
+        assert!(covered_pixels(&framebuffer) > 0);
+        assert_eq!(framebuffer.as_slice(), expected.as_slice());
+    }
+
+    #[test]
+    fn readback_at_the_borders() {
+        let mut framebuffer = Framebuffer::new(4, 3);
+        framebuffer.clear(Color::black());
+        framebuffer.set_current_color(Color::new(255, 255, 255));
+        framebuffer.point_with_depth(3, 2, 0.5);
+
+        assert_eq!(framebuffer.get_pixel(3, 2), Some(0xFFFF_FFFF));
+        assert_eq!(framebuffer.get_depth(3, 2), Some(0.5));
+        assert_eq!(framebuffer.get_depth(0, 0), Some(f32::INFINITY));
+        for (x, y) in [(-1, 0), (0, -1), (4, 0), (0, 3), (4, 3), (isize::MIN, isize::MAX)] {
+            assert_eq!(framebuffer.get_pixel(x, y), None, "({x}, {y})");
+            assert_eq!(framebuffer.get_depth(x, y), None, "({x}, {y})");
+        }
+
+        assert_eq!(framebuffer.read_region(&Rect::new(2, 1, 2, 2)), Some(vec![0xFF00_0000, 0xFF00_0000, 0xFF00_0000, 0xFFFF_FFFF]));
+        assert_eq!(framebuffer.read_region(&Rect::new(3, 0, 2, 1)), None);
+        assert_eq!(framebuffer.read_region(&Rect::new(0, 2, 1, 2)), None);
+
+        assert_eq!(framebuffer.rows().count(), 3);
+        assert!(framebuffer.rows().all(|row| row.len() == 4));
+        assert_eq!(framebuffer.rows().last().unwrap()[3], 0xFFFF_FFFF);
+        framebuffer.rows_mut().next().unwrap()[0] = 0xFF12_3456;
+        assert_eq!(framebuffer.get_pixel(0, 0), Some(0xFF12_3456));
+    }
+
+    #[test]
+    fn two_sided_material_lights_the_back_face() {
+        // La normal apunta lejos de la cámara (que mira hacia +z) y la luz viene de la cámara
+        let mut quad = triangle(Vec3::new(-1.0, -1.0, 0.0), 2.0);
+        quad.iter_mut().for_each(|vertex| vertex.normal = Vec3::new(0.0, 0.0, 1.0));
+        let center_brightness = |two_sided: bool| {
+            let uniforms = UniformsBuilder::new()
+                .viewport(viewport_matrix(WIDTH, HEIGHT, 20.0))
+                .material(Material { two_sided, base_color: Color::new(255, 255, 255), ..Material::default() })
+                .lights(vec![Light::white(Vec3::new(0.0, 0.0, -1.0))])
+                .cull_mode(CullMode::None)
+                .build();
+            let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+            framebuffer.clear(Color::black());
+            render(&mut framebuffer, &uniforms, &quad);
+            let pixel = Color::from_u32(framebuffer.get_pixel(WIDTH as isize / 2 - 5, HEIGHT as isize / 2 - 5).unwrap());
+            pixel.r as u32 + pixel.g as u32 + pixel.b as u32
+        };
+
+        let one_sided = center_brightness(false);
+        let two_sided = center_brightness(true);
+        assert!(two_sided > one_sided + 300, "una cara: {one_sided}, dos caras: {two_sided}");
+    }
+
+    // Triángulo inclinado respecto a la cámara (cada vértice a otra distancia)
+    const TILTED: [Vec3; 3] = [Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, -1.0, 2.0), Vec3::new(0.0, 1.0, 0.5)];
+
+    // Los uniforms de `uniforms` vistos en perspectiva desde (0, 0, -3)
+    fn perspective_uniforms() -> Uniforms {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, -3.0));
+        let viewport = Viewport::perspective(Rect::new(0, 0, WIDTH, HEIGHT), camera.view_matrix(), &Projection::default());
+        viewport.apply(&uniforms())
+    }
+
+    #[test]
+    fn depth_matches_the_projected_surface_on_a_tilted_triangle() {
+        let uniforms = perspective_uniforms();
+        let tilted = TILTED;
+        let vertex_array: Vec<Vertex> = tilted.iter().map(|&position| vertex(position)).collect();
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        render(&mut framebuffer, &uniforms, &vertex_array);
+
+        // Punto de la superficie que se ve en el pixel: el rayo del pixel (dos profundidades
+        // de pantalla llevadas de vuelta al mundo) cortado con el plano del triángulo
+        let matrix = uniforms.transformation_matrix;
+        let inverse = nalgebra_glm::inverse(&matrix);
+        let project = |point: Vec3| {
+            let clip = matrix * point.push(1.0);
+            clip.xyz() / clip.w
+        };
+        let unproject = |x: f32, y: f32, depth: f32| {
+            let world = inverse * Vec4::new(x, y, depth, 1.0);
+            world.xyz() / world.w
+        };
+        let screen: Vec<Vec3> = tilted.iter().map(|&position| project(position)).collect();
+        let setup = TriangleSetup::new(&screen[0], &screen[1], &screen[2]).unwrap();
+        let normal = (tilted[1] - tilted[0]).cross(&(tilted[2] - tilted[0]));
+
+        // Pixeles entre el centro del triángulo en pantalla y cada vértice
+        let center = (screen[0] + screen[1] + screen[2]) / 3.0;
+        let mut checked = 0;
+        for corner in &screen {
+            let (x, y) = (((center.x + corner.x) / 2.0).round() as isize, ((center.y + corner.y) / 2.0).round() as isize);
+            let (u, v, w) = setup.weights(x as f32, y as f32);
+            assert!(u > 0.0 && v > 0.0 && w > 0.0, "({x}, {y}) fuera del triángulo");
+            let (near, far) = (unproject(x as f32, y as f32, 0.2), unproject(x as f32, y as f32, 0.8));
+            let t = (tilted[0] - near).dot(&normal) / (far - near).dot(&normal);
+            let expected = project(near + (far - near) * t).z;
+
+            // Interpolar el punto del mundo con los pesos de pantalla (afín) da otra profundidad
+            let affine = project(tilted[0] * u + tilted[1] * v + tilted[2] * w).z;
+            let depth = framebuffer.get_depth(x, y).unwrap();
+            assert!((depth - expected).abs() < 1e-6, "({x}, {y}): {depth} en vez de {expected}");
+            assert!((affine - expected).abs() > 1e-4, "({x}, {y}): la versión afín también da {affine}");
+            checked += 1;
+        }
+        assert_eq!(checked, 3);
+    }
+
+    #[test]
+    fn supersampling_blends_edge_pixels() {
+        // Triángulo blanco sobre negro con una arista diagonal
+        let mut white = triangle(Vec3::new(-1.0, -1.0, 0.0), 2.0);
+        white.iter_mut().for_each(|vertex| vertex.color = Color::new(255, 255, 255));
+        // Pixeles claramente entre negro y blanco (el color interpolado puede quedar en 254)
+        let partial_pixels = |ssaa_factor: usize| {
+            let mut uniforms = uniforms();
+            uniforms.ssaa_factor = ssaa_factor;
+            let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+            framebuffer.clear(Color::black());
+            render(&mut framebuffer, &uniforms, &white);
+            framebuffer.as_slice().iter().filter(|&&pixel| (32..224).contains(&(pixel & 0xFF))).count()
+        };
+
+        assert_eq!(partial_pixels(1), 0);
+        assert!(partial_pixels(2) >= 20);
+    }
+
+    #[test]
+    fn saved_region_keeps_size_and_corners() {
+        let (_, framebuffer) = rendered(&triangle(Vec3::new(-1.0, -1.0, 0.0), 2.0));
+        let region = Rect::new(10, 12, 20, 16);
+        let path = std::env::temp_dir().join(format!("renderer_region_{}.png", std::process::id()));
+        let path = path.to_str().unwrap();
+        framebuffer.save_png_region(path, &region).unwrap();
+
+        let decoder = png::Decoder::new(std::io::BufReader::new(std::fs::File::open(path).unwrap()));
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!((info.width, info.height), (20, 16));
+
+        let saved = |x: usize, y: usize| {
+            let i = (y * info.width as usize + x) * 3;
+            Color::new(data[i], data[i + 1], data[i + 2]).to_u32()
+        };
+        for (x, y) in [(0, 0), (19, 0), (0, 15), (19, 15)] {
+            let expected = framebuffer.get_pixel((region.x + x) as isize, (region.y + y) as isize).unwrap();
+            assert_eq!(saved(x, y), expected, "esquina ({x}, {y})");
+        }
+        // La región cruza el borde del triángulo: no es un color liso
+        assert_ne!(saved(0, 0), saved(19, 15));
+
+        let outside = framebuffer.save_png_region(path, &Rect::new(50, 0, 20, 10));
+        assert_eq!(outside.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn perspective_correct_barycentrics_on_a_tilted_triangle() {
+        let uniforms = perspective_uniforms();
+        let vertex_array: Vec<Vertex> = TILTED.iter().map(|&position| vertex(position)).collect();
+        let transformed: Vec<Vertex> = vertex_array.iter().map(|vertex| DefaultVertexShader.transform(vertex, &uniforms)).collect();
+        let fragments = primitive_assembly_rasterization(&transformed, &uniforms, &Rect::new(0, 0, WIDTH, HEIGHT), &mut RenderStats::default());
+        let screen: Vec<Vec3> = transformed.iter().map(|vertex| vertex.transformed_position).collect();
+        let setup = TriangleSetup::new(&screen[0], &screen[1], &screen[2]).unwrap();
+
+        assert!(fragments.len() > 100);
+        let mut largest_difference: f32 = 0.0;
+        for fragment in &fragments {
+            let weights = fragment.barycentric.unwrap();
+            assert!((weights.sum() - 1.0).abs() < 1e-5);
+            let (u, v, w) = setup.weights(fragment.position.x, fragment.position.y);
+            largest_difference = largest_difference.max((weights - Vec3::new(u, v, w)).abs().max());
+        }
+        assert!(largest_difference > 0.05, "diferencia máxima con los pesos afines: {largest_difference}");
+    }
+
+    #[test]
+    fn picking_returns_the_entity_and_triangle_under_the_pixel() {
+        let mut first = triangle(Vec3::new(-1.5, -1.0, 0.0), 1.0);
+        first.extend(triangle(Vec3::new(-1.5, 0.2, 0.0), 0.6));
+        let second = triangle(Vec3::new(0.5, -1.0, 0.0), 1.0);
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(Color::black());
+        for (id, vertex_array) in [(1, &first), (2, &second)] {
+            framebuffer.set_current_id(id);
+            render(&mut framebuffer, &uniforms(), vertex_array);
+        }
+
+        assert_eq!((framebuffer.get_id(5, 15), framebuffer.pick(5, 15)), (Some(1), Some(0)));
+        assert_eq!((framebuffer.get_id(4, 38), framebuffer.pick(4, 38)), (Some(1), Some(1)));
+        assert_eq!((framebuffer.get_id(45, 15), framebuffer.pick(45, 15)), (Some(2), Some(0)));
+        assert_eq!((framebuffer.get_id(60, 60), framebuffer.pick(60, 60)), (None, None));
+        assert_eq!(framebuffer.pick(-1, 15), None);
+    }
+
+    #[test]
+    fn mirror_reflects_the_environment_along_the_view_ray() {
+        // Cada texel del entorno codifica su dirección como color
+        let encode = |direction: &Vec3| {
+            let channel = |c: f32| ((c + 1.0) * 127.5) as u8;
+            Color::new(channel(direction.x), channel(direction.y), channel(direction.z))
+        };
+        let environment = Arc::new(Cubemap::from_fn(32, encode));
+        let mut mirror = triangle(Vec3::new(-2.0, -2.0, 0.0), 4.0);
+        mirror.extend([vertex(Vec3::new(2.0, -2.0, 0.0)), vertex(Vec3::new(2.0, 2.0, 0.0)), vertex(Vec3::new(-2.0, 2.0, 0.0))]);
+        let mut uniforms = perspective_uniforms();
+        uniforms.material = Material { reflectivity: 1.0, ..Material::default() };
+        uniforms.environment = Some(environment.clone());
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        render(&mut framebuffer, &uniforms, &mirror);
+
+        let inverse = nalgebra_glm::inverse(&uniforms.transformation_matrix);
+        let unproject = |x: f32, y: f32, depth: f32| {
+            let world = inverse * Vec4::new(x, y, depth, 1.0);
+            world.xyz() / world.w
+        };
+        let eye = Vec3::new(0.0, 0.0, -3.0);
+        let normal = Vec3::new(0.0, 0.0, -1.0);
+        for (x, y) in [(32, 32), (50, 20), (10, 45)] {
+            // Punto del espejo en el pixel: el rayo desde el ojo cortado con el plano z = 0
+            let (near, far) = (unproject(x as f32, y as f32, 0.2), unproject(x as f32, y as f32, 0.8));
+            let point = near + (far - near) * (-near.z / (far.z - near.z));
+            let view = (point - eye).normalize();
+            let expected = environment.sample(&reflect_view(&normal, &view));
+            assert_eq!(framebuffer.get_pixel(x, y), Some(expected.to_u32()), "({x}, {y})");
+        }
+
+        // Lejos del centro el reflejo depende de la posición del ojo, no solo de la orientación
+        let straight = environment.sample(&reflect_view(&normal, &Vec3::z()));
+        assert_ne!(framebuffer.get_pixel(50, 20), Some(straight.to_u32()));
+    }
+
+    #[test]
+    fn supersampled_edges_keep_the_nearest_depth() {
+        let colored = |mut vertex_array: Vec<Vertex>, color: Color| {
+            vertex_array.iter_mut().for_each(|vertex| vertex.color = color);
+            vertex_array
+        };
+        let mut far = triangle(Vec3::new(-1.5, -1.5, 0.5), 3.0);
+        far.extend([vertex(Vec3::new(1.5, -1.5, 0.5)), vertex(Vec3::new(1.5, 1.5, 0.5)), vertex(Vec3::new(-1.5, 1.5, 0.5))]);
+        let far = colored(far, Color::new(0, 0, 255));
+        let near = colored(triangle(Vec3::new(-1.0, -1.0, 0.1), 2.0), Color::new(255, 0, 0));
+
+        let mut uniforms = uniforms();
+        uniforms.ssaa_factor = 2;
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(Color::black());
+        render(&mut framebuffer, &uniforms, &far);
+        render(&mut framebuffer, &uniforms, &near);
+
+        let near_depth = framebuffer.get_depth(25, 25).unwrap();
+        assert_eq!(framebuffer.get_pixel(25, 25), Some(0xFFFF_0000));
+        let mut edges = 0;
+        for y in 0..HEIGHT as isize {
+            for x in 0..WIDTH as isize {
+                let color = Color::from_u32(framebuffer.get_pixel(x, y).unwrap());
+                // Mezcla de las dos superficies: borde del triángulo cercano
+                if color.r > 0 && color.b > 0 {
+                    let depth = framebuffer.get_depth(x, y).unwrap();
+                    assert!((depth - near_depth).abs() < 1e-4, "({x}, {y}): {depth} en vez de {near_depth}");
+                    edges += 1;
+                }
+            }
+        }
+        assert!(edges > 20);
+    }
+
+    // Con SSAA el stencil solo pasa por el framebuffer de alta resolución en los draws que
+    // lo usan: uno sin prueba de stencil no devuelve el que quedó ahí de un draw anterior
+    #[test]
+    fn supersampled_draws_without_stencil_leave_it_alone() {
+        let mut uniforms = uniforms();
+        uniforms.ssaa_factor = 2;
+        let quad = triangle(Vec3::new(-1.0, -1.0, 0.0), 2.0);
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(Color::black());
+
+        // La máscara se escribe a través del framebuffer de alta resolución
+        render_stencil_mask(&mut framebuffer, &uniforms, &quad, 1);
+        assert_eq!(framebuffer.get_stencil(18, 45), Some(1));
+        uniforms.stencil = Some(StencilState::equal(1));
+        render(&mut framebuffer, &uniforms, &quad);
+        assert_eq!(framebuffer.get_pixel(18, 45), Some(Color::new(220, 120, 40).to_u32()));
+
+        framebuffer.clear(Color::black());
+        framebuffer.clear_stencil(0);
+        uniforms.stencil = None;
+        render(&mut framebuffer, &uniforms, &quad);
+        assert_eq!(framebuffer.get_stencil(18, 45), Some(0));
+    }
+
+    // Pixeles del decal (el único sin rojo) y pixeles cubiertos al dibujar un triángulo y un
+    // decal en el mismo plano, con los uniforms del decal ajustados por `decal_uniforms`
+    fn coplanar_decal_pixels(depth_mode: DepthMode, decal_uniforms: impl Fn(&mut Uniforms), decal_first: bool) -> (usize, usize) {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, -3.0));
+        let projection = Projection { depth_mode, ..Projection::default() };
+        let viewport = Viewport::perspective(Rect::new(0, 0, WIDTH, HEIGHT), camera.view_matrix(), &projection);
+        let base: Vec<Vertex> = TILTED.iter().map(|&position| vertex(position)).collect();
+        // El decal es el mismo plano con los vértices en otro orden: otra interpolación
+        let mut decal: Vec<Vertex> = [TILTED[1], TILTED[2], TILTED[0]].iter().map(|&position| vertex(position)).collect();
+        decal.iter_mut().for_each(|vertex| vertex.color = Color::new(0, 255, 0));
+
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.set_depth_mode(depth_mode);
+        framebuffer.clear(Color::black());
+        let base_uniforms = viewport.apply(&uniforms());
+        let mut decal_base_uniforms = base_uniforms.clone();
+        decal_uniforms(&mut decal_base_uniforms);
+        let draws = [(&base_uniforms, &base), (&decal_base_uniforms, &decal)];
+        for (uniforms, vertex_array) in if decal_first { [draws[1], draws[0]] } else { draws } {
+            render(&mut framebuffer, uniforms, vertex_array);
+        }
+        let covered = framebuffer.as_slice().iter().filter(|&&pixel| pixel & 0x00FF_FFFF != 0).count();
+        let decal = framebuffer.as_slice().iter().filter(|&&pixel| pixel & 0x00FF_FFFF != 0 && pixel & 0x00FF_0000 == 0).count();
+        (decal, covered)
+    }
+
+    #[test]
+    fn depth_bias_lets_a_coplanar_decal_win() {
+        for depth_mode in [DepthMode::Standard, DepthMode::Reversed] {
+            let bias = DepthBias::toward_camera(4.0, 1.0, depth_mode);
+            for decal_first in [false, true] {
+                let (decal, covered) = coplanar_decal_pixels(depth_mode, |uniforms| uniforms.depth_bias = bias, decal_first);
+                assert!(covered > 100);
+                assert_eq!(decal, covered, "{depth_mode:?}, decal primero: {decal_first}");
+            }
+            // Sin bias el decal no gana en todos los pixeles
+            let (decal, covered) = coplanar_decal_pixels(depth_mode, |_| {}, false);
+            assert!(decal < covered, "{depth_mode:?}");
+        }
+    }
+
+    // Solo con `polygon_offset`, sin DepthBias, el decal gana en todos los pixeles; con el
+    // signo contrario pierde en todos
+    #[test]
+    fn polygon_offset_alone_moves_the_coplanar_decal() {
+        for depth_mode in [DepthMode::Standard, DepthMode::Reversed] {
+            let toward_camera = DepthBias::toward_camera(8.0, 0.0, depth_mode).constant;
+            for decal_first in [false, true] {
+                let (decal, covered) = coplanar_decal_pixels(depth_mode, |uniforms| uniforms.polygon_offset = toward_camera, decal_first);
+                assert!(covered > 100);
+                assert_eq!(decal, covered, "{depth_mode:?}, decal primero: {decal_first}");
+                let (decal, _) = coplanar_decal_pixels(depth_mode, |uniforms| uniforms.polygon_offset = -toward_camera, decal_first);
+                assert_eq!(decal, 0, "{depth_mode:?} alejando, decal primero: {decal_first}");
+            }
+        }
+    }
+
+    #[test]
+    fn color_mask_keeps_the_masked_channels() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        framebuffer.clear(Color::black());
+        framebuffer.set_color_mask(false, true, true);
+        framebuffer.set_current_color(Color::new(255, 255, 255));
+        framebuffer.point(1, 2);
+        assert_eq!(framebuffer.color_mask(), (false, true, true));
+        assert_eq!(framebuffer.get_pixel(1, 2), Some(0xFF00_FFFF));
+        assert_eq!(framebuffer.get_pixel(2, 2), Some(0xFF00_0000));
+
+        // Lo que ya había en el canal enmascarado se conserva
+        framebuffer.set_color_mask(true, false, true);
+        framebuffer.set_current_color(Color::new(128, 0, 0));
+        framebuffer.point(1, 2);
+        assert_eq!(framebuffer.get_pixel(1, 2), Some(0xFF80_FF00));
+    }
 
-        framebuffer.set_current_color(fragment.color);
-        framebuffer.point(x as isize, y as isize);
+    #[test]
+    fn gouraud_interpolates_vertex_light_where_phong_relights_each_fragment() {
+        // Triángulo equilátero de una esfera burda: cada normal se inclina 60° hacia su vértice,
+        // así la luz frontal da 0.5 en cada vértice y 1 con la normal promedio del centro
+        let corners: Vec<Vec3> = [90.0f32, 210.0, 330.0].iter().map(|angle| {
+            let angle = angle.to_radians();
+            Vec3::new(angle.cos(), angle.sin(), 0.0)
+        }).collect();
+        let mesh: Vec<Vertex> = corners.iter().map(|&outward| {
+            let mut vertex = vertex(outward * 1.4);
+            vertex.normal = (outward * 60f32.to_radians().sin() + Vec3::new(0.0, 0.0, -60f32.to_radians().cos())).normalize();
+            vertex
+        }).collect();
+
+        let red_channel = |shading_model: ShadingModel, point: Vec3| {
+            let mut uniforms = UniformsBuilder::new()
+                .viewport(viewport_matrix(WIDTH, HEIGHT, 20.0))
+                .lights(vec![Light::white(Vec3::new(0.0, 0.0, -1.0))])
+                .cull_mode(CullMode::None)
+                .build();
+            uniforms.shading_model = shading_model;
+            let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+            framebuffer.clear(Color::black());
+            render(&mut framebuffer, &uniforms, &mesh);
+            let screen = uniforms.viewport_matrix * point.push(1.0);
+            (framebuffer.get_pixel(screen.x as isize, screen.y as isize).unwrap() >> 16) & 0xFF
+        };
+
+        let center = Vec3::zeros();
+        for &outward in &corners {
+            let near_vertex = outward * 1.1;
+            // Gouraud: la luz de los vértices es la misma, el triángulo queda plano
+            let (gouraud_center, gouraud_vertex) = (red_channel(ShadingModel::Gouraud, center), red_channel(ShadingModel::Gouraud, near_vertex));
+            assert!(gouraud_center.abs_diff(110) <= 2, "{gouraud_center}");
+            assert!(gouraud_vertex.abs_diff(110) <= 2, "{gouraud_vertex}");
+            // Phong: brillo completo en el centro que cae hacia los vértices
+            let (phong_center, phong_vertex) = (red_channel(ShadingModel::Phong, center), red_channel(ShadingModel::Phong, near_vertex));
+            assert!(phong_center >= 218, "{phong_center}");
+            assert!(phong_vertex < phong_center - 40 && phong_vertex > gouraud_vertex, "{phong_vertex}");
+        }
+    }
+
+    // Renderiza en otro hilo con un tiempo límite, para detectar un bucle enorme sobre un
+    // Bounding Box basura; None si entra en pánico o no termina
+    fn rendered_with_timeout(vertex_array: Vec<Vertex>, model: Mat4) -> Option<(RenderStats, Vec<u32>)> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let uniforms = uniforms().with_model(model);
+            let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+            framebuffer.clear(Color::black());
+            let stats = render(&mut framebuffer, &uniforms, &vertex_array);
+            let _ = sender.send((stats, framebuffer.as_slice().to_vec()));
+        });
+        receiver.recv_timeout(std::time::Duration::from_secs(10)).ok()
+    }
+
+    #[test]
+    fn non_finite_triangles_are_skipped_and_counted() {
+        let valid = triangle(Vec3::new(-0.25, -0.25, 0.0), 0.5);
+        let mut nan_z = triangle(Vec3::new(0.5, -1.0, 0.0), 0.5);
+        nan_z[2].position.z = f32::NAN;
+        let mut infinite = triangle(Vec3::new(-1.0, 0.5, 0.0), 0.5);
+        infinite[0].position = Vec3::new(f32::INFINITY, f32::NEG_INFINITY, 0.0);
+
+        let (stats, image) = rendered_with_timeout([nan_z, valid.clone(), infinite].concat(), Mat4::identity()).expect("el render termina");
+        let (_, expected) = rendered_with_timeout(valid.clone(), Mat4::identity()).expect("el render termina");
+        assert_eq!((stats.triangles, stats.non_finite_triangles, stats.skipped_triangles), (3, 2, 2));
+        assert_eq!(image, expected);
+
+        // Una matriz de modelo con NaN descarta todo
+        let (stats, image) = rendered_with_timeout(valid, Mat4::from_element(f32::NAN)).expect("el render termina");
+        assert_eq!(stats.non_finite_triangles, 1);
+        assert!(image.iter().all(|&pixel| pixel == 0xFF00_0000));
+    }
+
+    #[test]
+    fn blended_writes_keep_depth_and_opaque_draws_ignore_alpha() {
+        let (source, destination) = (Color::new(100, 200, 128), Color::new(200, 100, 40));
+        for mode in [BlendMode::Alpha, BlendMode::Additive, BlendMode::Multiply] {
+            let mut framebuffer = Framebuffer::new(2, 1);
+            framebuffer.clear(destination);
+            framebuffer.set_current_color(Color::new(1, 2, 3));
+            framebuffer.point_with_depth(1, 0, 0.1);
+            let depth_before = framebuffer.get_depth(0, 0);
+            assert!(framebuffer.combine_with_depth(0, 0, 0.5, source, 0.5, mode), "{mode:?}");
+            assert!(!framebuffer.combine_with_depth(1, 0, 0.5, source, 0.5, mode), "{mode:?} detrás de lo más cercano");
+            assert_eq!(framebuffer.get_pixel(0, 0), Some(mode.combine(source, 0.5, destination).to_u32()), "{mode:?}");
+            assert_eq!(framebuffer.get_depth(0, 0), depth_before, "{mode:?}");
+        }
+
+        // Las entidades opacas no miran la opacidad del fragmento y escriben profundidad
+        fn translucent_shader(fragment: &mut Fragment, _uniforms: &Uniforms) {
+            fragment.alpha = 0.3;
+        }
+        let quad = triangle(Vec3::new(-1.0, -1.0, 0.0), 2.0);
+        let draw = |material: Material| {
+            let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+            framebuffer.clear(destination);
+            let uniforms = UniformsBuilder::new()
+                .viewport(viewport_matrix(WIDTH, HEIGHT, 20.0))
+                .material(material)
+                .cull_mode(CullMode::None)
+                .build();
+            render(&mut framebuffer, &uniforms, &quad);
+            framebuffer
+        };
+        let plain = draw(Material::default());
+        let with_alpha = draw(Material { fragment_shader: Some(translucent_shader), ..Material::default() });
+        assert_eq!(plain.as_slice(), with_alpha.as_slice());
+        assert!(plain.get_depth(WIDTH as isize / 2 - 10, HEIGHT as isize / 2).is_some_and(f32::is_finite));
+    }
+
+    // Trayectoria que pasa detrás del ojo (en z = -3): el primer tramo sale de la pantalla
+    // hacia +x y el segundo, todo detrás de la cámara, no se dibuja. Sin recortar contra w
+    // los puntos de atrás se reflejan y el trazo aparece hacia -x
+    #[test]
+    fn polyline_behind_the_eye_is_clipped() {
+        let uniforms = perspective_uniforms();
+        let trajectory = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.5, 0.0, -6.0), Vec3::new(-0.5, 0.0, -8.0)];
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(Color::black());
+        render_polyline(&mut framebuffer, &uniforms.transformation_matrix, &trajectory, Color::new(255, 255, 255), 2.0);
+
+        let lit: Vec<usize> = framebuffer.rows().flat_map(|row| row.iter().enumerate().filter(|(_, &pixel)| pixel & 0x00FF_FFFF != 0).map(|(x, _)| x)).collect();
+        assert!(lit.iter().any(|&x| x > WIDTH / 2 + 4));
+        assert!(lit.iter().all(|&x| x + 2 >= WIDTH / 2), "{lit:?}");
+    }
+
+    // Con cualquier modo de profundidad la trayectoria se ve sobre el framebuffer recién
+    // limpio, y un cuadrado más cercano la tapa donde la cruza
+    #[test]
+    fn polyline_depth_test_follows_the_depth_mode() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, -3.0));
+        let trajectory = [Vec3::new(-1.4, 0.0, 1.0), Vec3::new(1.4, 0.0, 1.0)];
+        for depth_mode in [DepthMode::Standard, DepthMode::Reversed] {
+            let projection = Projection { depth_mode, ..Projection::default() };
+            let uniforms = Viewport::perspective(Rect::new(0, 0, WIDTH, HEIGHT), camera.view_matrix(), &projection).apply(&uniforms());
+            let draw = |occluder: bool| {
+                let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+                framebuffer.set_depth_mode(depth_mode);
+                framebuffer.clear(Color::black());
+                if occluder {
+                    let mut quad = triangle(Vec3::new(-0.5, -0.5, 0.0), 1.0);
+                    quad.extend(triangle(Vec3::new(0.5, 0.5, 0.0), -1.0));
+                    render(&mut framebuffer, &uniforms, &quad);
+                }
+                render_polyline(&mut framebuffer, &uniforms.transformation_matrix, &trajectory, Color::new(255, 255, 255), 2.0);
+                framebuffer
+            };
+
+            let center = (WIDTH as isize / 2, HEIGHT as isize / 2);
+            let visible = draw(false);
+            assert!(covered_pixels(&visible) > 0, "{depth_mode:?}");
+            assert_ne!(visible.get_pixel(center.0, center.1), Some(0xFF00_0000), "{depth_mode:?}");
+
+            let occluded = draw(true);
+            let quad_color = Color::new(220, 120, 40).to_u32();
+            assert_eq!(occluded.get_pixel(center.0, center.1), Some(quad_color), "{depth_mode:?}");
+            assert_ne!(occluded.get_pixel(16, center.1), Some(0xFF00_0000), "{depth_mode:?}");
+        }
+    }
+
+    #[test]
+    fn current_matrices_are_the_last_draw() {
+        let view = Camera::new(Vec3::new(1.5, -2.0, -6.0)).view_matrix();
+        let viewport = Viewport::perspective(Rect::new(0, 0, WIDTH, HEIGHT), view, &Projection::default());
+        let model = nalgebra_glm::translation(&Vec3::new(1.5, -2.0, 0.0));
+        // Con SSAA las matrices no llevan la escala del buffer de mayor resolución
+        for ssaa_factor in [1, 2] {
+            let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+            assert!(framebuffer.current_matrices().is_none());
+            let mut uniforms = viewport.apply(&UniformsBuilder::new().model(model).build());
+            uniforms.ssaa_factor = ssaa_factor;
+            render(&mut framebuffer, &uniforms, &triangle(Vec3::new(-0.5, -0.5, 0.0), 1.0));
+
+            let matrices = framebuffer.current_matrices().expect("hubo un render");
+            assert_eq!(matrices.model, model);
+            assert_eq!(matrices.view, view);
+            assert_eq!(matrices.projection, viewport.projection_matrix);
+            assert_eq!(matrices.viewport, viewport.viewport_matrix);
+            assert_eq!(matrices.transformation(), uniforms.transformation_matrix, "SSAA x{ssaa_factor}");
+        }
     }
 }
@@ -0,0 +1,201 @@
+// gbuffer.rs
+//
+// Deferred shading. En lugar de iluminar cada fragmento al rasterizarlo, un pase de
+// geometría (`render_gbuffer`) escribe por pixel lo que hace falta para iluminarlo después:
+// el color de superficie (albedo, con la profundidad en el z-buffer de esa capa), la
+// normal, un id de material y la emisión. El pase de iluminación (`lighting_pass`) recorre
+// el G-buffer una sola vez y calcula la luz solo de los pixeles visibles; otras técnicas
+// (SSAO, contornos por normales) pueden leer las mismas capas.
+//
+// El shader del pase de geometría es un `GBufferShader`: recibe el fragmento rasterizado y
+// devuelve las salidas de todas las capas (`GBufferFragment`). El G-buffer es opaco: los
+// materiales con otro BlendMode que Opaque se dibujan después, con `render`, sobre el
+// resultado.
+
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::framebuffer::{
+    assemble_triangles, clip_near_plane, cull_triangles, lit_color, rasterize_triangles, triangle_list, Framebuffer,
+    RenderStats,
+};
+use crate::light::incident_light;
+use crate::material::Material;
+use crate::shader::{DefaultVertexShader, VertexShader};
+use crate::uniforms::Uniforms;
+use crate::vertex::Vertex;
+
+// Normal de la superficie visible en cada pixel (cero donde no hay nada)
+#[derive(Debug, Clone)]
+pub struct NormalBuffer {
+    pub width: usize,
+    pub height: usize,
+    normals: Vec<Vec3>,
+}
+
+impl NormalBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        NormalBuffer { width, height, normals: vec![Vec3::zeros(); width * height] }
+    }
+
+    pub fn clear(&mut self) {
+        self.normals.fill(Vec3::zeros());
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Vec3> {
+        (x < self.width && y < self.height).then(|| self.normals[y * self.width + x])
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, normal: Vec3) {
+        if x < self.width && y < self.height {
+            self.normals[y * self.width + x] = normal;
+        }
+    }
+
+    pub fn as_slice(&self) -> &[Vec3] {
+        &self.normals
+    }
+}
+
+// Capas del G-buffer. `albedo` guarda también la profundidad de la superficie visible, y
+// `material_id` indexa la tabla de materiales del pase de iluminación
+pub struct GBuffer {
+    pub albedo: Framebuffer,
+    pub normal: NormalBuffer,
+    pub material_id: Vec<u8>,
+    pub emission: Framebuffer,
+}
+
+impl GBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        GBuffer {
+            albedo: Framebuffer::new(width, height),
+            normal: NormalBuffer::new(width, height),
+            material_id: vec![0; width * height],
+            emission: Framebuffer::new(width, height),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.albedo.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.albedo.height
+    }
+
+    // Deja todas las capas vacías (albedo y emisión negros, profundidad sin nada)
+    pub fn clear(&mut self) {
+        self.albedo.clear(Color::black());
+        self.normal.clear();
+        self.material_id.fill(0);
+        self.emission.clear(Color::black());
+    }
+}
+
+// Salidas de un fragmento en el pase de geometría
+#[derive(Debug, Clone, Copy)]
+pub struct GBufferFragment {
+    pub albedo: Color,
+    pub normal: Vec3,
+    pub material_id: u8,
+    pub emission: Color, // Se suma después de iluminar (brillo propio que no depende de la luz)
+}
+
+pub type GBufferShader = fn(&Fragment, &Uniforms) -> GBufferFragment;
+
+// Shader de G-buffer por defecto: el color de superficie del material (la textura y
+// después `fragment_shader`, como en el render directo), la normal interpolada (o la que
+// deje el fragment shader), el id del parámetro "material_id" (0) y sin emisión
+pub fn gbuffer_shader(fragment: &Fragment, uniforms: &Uniforms) -> GBufferFragment {
+    let material = &uniforms.material;
+    let mut fragment = fragment.clone();
+    if let Some(texture) = &material.texture {
+        fragment.color = texture.sample(fragment.tex_coords.x, fragment.tex_coords.y);
+    }
+    if let Some(shader) = material.fragment_shader {
+        shader(&mut fragment, uniforms);
+    }
+    GBufferFragment {
+        albedo: fragment.color,
+        normal: fragment.normal,
+        material_id: material.param("material_id", 0.0).clamp(0.0, 255.0) as u8,
+        emission: Color::black(),
+    }
+}
+
+// Pase de geometría: rasteriza la malla con los mismos pasos que `render` y escribe las
+// capas del G-buffer de los fragmentos que pasan la prueba de profundidad. El shader solo
+// se evalúa para los fragmentos que quedan delante de lo ya escrito
+pub fn render_gbuffer(gbuffer: &mut GBuffer, uniforms: &Uniforms, vertex_array: &[Vertex], shader: GBufferShader) -> RenderStats {
+    let mut stats = RenderStats { vertices: vertex_array.len(), ..Default::default() };
+    gbuffer.albedo.matrices = Some(uniforms.matrices());
+    let transformed: Vec<Vertex> = vertex_array.iter().map(|vertex| DefaultVertexShader.transform(vertex, uniforms)).collect();
+    let transformed = clip_near_plane(triangle_list(&transformed, uniforms.topology), &uniforms.projection_matrix);
+    let triangles = assemble_triangles(&transformed, &mut stats);
+    let triangles = cull_triangles(&transformed, triangles, uniforms.cull_mode, uniforms.front_face);
+    let fragments = rasterize_triangles(&transformed, &triangles, uniforms, &gbuffer.albedo.raster_bounds(), 0);
+
+    let depth_mode = gbuffer.albedo.depth_mode();
+    for fragment in &fragments {
+        let (x, y) = (fragment.position.x as isize, fragment.position.y as isize);
+        match gbuffer.albedo.get_depth(x, y) {
+            Some(depth) if depth_mode.is_closer(fragment.depth, depth) => {}
+            _ => continue,
+        }
+
+        let output = shader(fragment, uniforms);
+        gbuffer.albedo.set_current_color(output.albedo);
+        if !gbuffer.albedo.point_with_depth(x, y, fragment.depth) {
+            continue; // Fuera del scissor
+        }
+        let (x, y, width) = (x as usize, y as usize, gbuffer.width());
+        gbuffer.normal.set(x, y, output.normal);
+        gbuffer.material_id[y * width + x] = output.material_id;
+        gbuffer.emission.set_current_color(output.emission);
+        gbuffer.emission.point(x as isize, y as isize);
+    }
+    stats
+}
+
+// Pase de iluminación: para cada pixel cubierto del G-buffer, la luz de `uniforms` en su
+// posición y normal con el material `materials[id]` (o el de `uniforms` si el id no está
+// en la tabla), como en el fragment shading por pixel (Phong), más la emisión. Escribe el
+// color y la profundidad en `target`, así lo que se dibuje después (transparencias,
+// overlays) se compone con la prueba de profundidad de siempre
+pub fn lighting_pass(gbuffer: &GBuffer, uniforms: &Uniforms, materials: &[Material], target: &mut Framebuffer) {
+    // Uniforms de cada material, armados una vez y no por pixel
+    let material_uniforms: Vec<Uniforms> =
+        materials.iter().map(|material| Uniforms { material: material.clone(), ..uniforms.clone() }).collect();
+    let depth_mode = gbuffer.albedo.depth_mode();
+    target.matrices = gbuffer.albedo.matrices;
+    // De pantalla a mundo, para la posición de cada pixel en el reflejo del entorno
+    let screen_to_world = (uniforms.viewport_matrix * uniforms.projection_matrix * uniforms.view_matrix)
+        .try_inverse()
+        .unwrap_or_else(Mat4::identity);
+
+    for y in 0..gbuffer.height() {
+        for x in 0..gbuffer.width() {
+            let (px, py) = (x as isize, y as isize);
+            let Some(depth) = gbuffer.albedo.get_depth(px, py).filter(|&depth| depth_mode.is_covered(depth)) else { continue };
+            let (Some(albedo), Some(emission), Some(normal)) =
+                (gbuffer.albedo.get_pixel(px, py), gbuffer.emission.get_pixel(px, py), gbuffer.normal.get(x, y))
+            else {
+                continue;
+            };
+            let id = gbuffer.material_id[y * gbuffer.width() + x] as usize;
+            let pixel_uniforms = material_uniforms.get(id).unwrap_or(uniforms);
+
+            let light = incident_light(&Vec3::new(x as f32, y as f32, depth), &normal, pixel_uniforms);
+            let world_position = || {
+                let position = screen_to_world * Vec4::new(x as f32, y as f32, depth, 1.0);
+                position.xyz() / position.w
+            };
+            let color = lit_color(Color::from_u32(albedo), &light, &normal, world_position, pixel_uniforms)
+                + Color::from_u32(emission);
+            target.set_current_color(color);
+            target.point_with_depth(px, py, depth);
+        }
+    }
+}
@@ -0,0 +1,435 @@
+// gpu.rs
+//
+// Backend opcional (feature `gpu`) que rasteriza con wgpu en lugar del rasterizador por
+// software. Renderiza a una textura fuera de pantalla y copia el resultado a un buffer
+// 0xAARRGGBB, así que se presenta igual que el `Framebuffer` de CPU (`present_buffer()`
+// con minifb) y `render(framebuffer, uniforms, vertices)` tiene la misma interfaz.
+//
+//     cargo run --features gpu
+
+use std::borrow::Cow;
+use bytemuck::{Pod, Zeroable};
+use nalgebra_glm::{Vec3, Vec4};
+use wgpu::util::DeviceExt;
+use crate::color::Color;
+use crate::framebuffer::{clip_near_plane, triangle_list, RenderStats};
+use crate::light::Light;
+use crate::uniforms::{CullMode, Uniforms, Winding};
+use crate::vertex::Vertex;
+
+const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8Unorm;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// Mismo pipeline que el de CPU: la matriz de modelo lleva a coordenadas de pantalla,
+// que aquí se convierten a NDC; la luz es difusa con la opción de doble cara.
+const SHADER: &str = r#"
+struct Globals {
+    model: mat4x4<f32>,
+    light_dir: vec4<f32>,
+    // x: ancho, y: alto, z: profundidad mínima, w: 1 / rango de profundidad
+    viewport: vec4<f32>,
+    // x: two_sided, y: unlit
+    flags: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) position: vec3<f32>, @location(1) normal: vec3<f32>, @location(2) color: vec3<f32>) -> VertexOutput {
+    let transformed = globals.model * vec4<f32>(position, 1.0);
+    let screen = transformed.xyz / transformed.w;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(
+        screen.x / globals.viewport.x * 2.0 - 1.0,
+        1.0 - screen.y / globals.viewport.y * 2.0,
+        (screen.z - globals.viewport.z) * globals.viewport.w,
+        1.0
+    );
+    out.normal = normal;
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var normal = normalize(in.normal);
+    if (globals.flags.x > 0.5 && dot(normal, vec3<f32>(0.0, 0.0, 1.0)) > 0.0) {
+        normal = -normal;
+    }
+    var intensity = max(dot(normal, globals.light_dir.xyz), 0.0);
+    if (globals.flags.y > 0.5) {
+        intensity = 1.0;
+    }
+    return vec4<f32>(in.color * intensity, 1.0);
+}
+"#;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuVertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Globals {
+    model: [[f32; 4]; 4],
+    light_dir: [f32; 4],
+    viewport: [f32; 4],
+    flags: [f32; 4],
+}
+
+// Framebuffer respaldado por la GPU
+pub struct WgpuFramebuffer {
+    pub width: usize,
+    pub height: usize,
+    buffer: Vec<u32>,
+    clear_color: wgpu::Color,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    color_texture: wgpu::Texture,
+    depth_view: wgpu::TextureView,
+    readback: wgpu::Buffer,
+    padded_row_bytes: u32,
+    globals: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    // Un pipeline por modo de culling (índice: None, Back, Front)
+    pipelines: [wgpu::RenderPipeline; 3],
+}
+
+impl WgpuFramebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        pollster::block_on(Self::create(width, height))
+    }
+
+    async fn create(width: usize, height: usize) -> Self {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+            .expect("No se encontró un adaptador gráfico compatible con wgpu");
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("renderer"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::downlevel_defaults(),
+                memory_hints: wgpu::MemoryHints::default(),
+            }, None)
+            .await
+            .expect("No se pudo crear el dispositivo wgpu");
+
+        let size = wgpu::Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        };
+        let color_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("color"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: COLOR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        // Las filas copiadas a un buffer deben estar alineadas a 256 bytes
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row_bytes = (width as u32 * 4).div_ceil(align) * align;
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: (padded_row_bytes * height as u32) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let globals = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("globals"),
+            size: std::mem::size_of::<Globals>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("globals"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("globals"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: globals.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("pipeline"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipelines = [None, Some(wgpu::Face::Back), Some(wgpu::Face::Front)]
+            .map(|cull_mode| create_pipeline(&device, &layout, &shader, cull_mode));
+
+        WgpuFramebuffer {
+            width,
+            height,
+            buffer: vec![0; width * height],
+            clear_color: wgpu::Color::BLACK,
+            device,
+            queue,
+            color_texture,
+            depth_view: depth_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            readback,
+            padded_row_bytes,
+            globals,
+            bind_group,
+            pipelines,
+        }
+    }
+
+    // El color de fondo se aplica al inicio del siguiente render
+    pub fn clear(&mut self, color: Color) {
+        self.clear_color = wgpu::Color {
+            r: color.r as f64 / 255.0,
+            g: color.g as f64 / 255.0,
+            b: color.b as f64 / 255.0,
+            a: 1.0,
+        };
+        self.buffer.fill(color.to_u32());
+    }
+
+    pub fn as_slice(&self) -> &[u32] {
+        &self.buffer
+    }
+
+    // Buffer que se debe entregar a la ventana
+    pub fn present_buffer(&self) -> &[u32] {
+        &self.buffer
+    }
+
+    // Copia la textura de color al buffer de CPU
+    fn read_back(&mut self, encoder: wgpu::CommandEncoder) {
+        let mut encoder = encoder;
+        encoder.copy_texture_to_buffer(
+            self.color_texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_row_bytes),
+                    rows_per_image: None,
+                },
+            },
+            self.color_texture.size(),
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = self.readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device.poll(wgpu::Maintain::Wait);
+
+        {
+            let data = slice.get_mapped_range();
+            for (row, pixels) in self.buffer.chunks_mut(self.width).enumerate() {
+                let start = row * self.padded_row_bytes as usize;
+                let bytes = &data[start..start + self.width * 4];
+                // BGRA en memoria == 0xAARRGGBB en little endian
+                for (pixel, bgra) in pixels.iter_mut().zip(bytes.chunks_exact(4)) {
+                    *pixel = u32::from_le_bytes([bgra[0], bgra[1], bgra[2], bgra[3]]);
+                }
+            }
+        }
+        self.readback.unmap();
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    cull_mode: Option<wgpu::Face>,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("render"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            compilation_options: Default::default(),
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<GpuVertex>() as u64,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: COLOR_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        // En pantalla (y hacia abajo) las caras frontales tienen área negativa;
+        // al invertir y para pasar a NDC quedan en sentido antihorario
+        primitive: wgpu::PrimitiveState {
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode,
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+// Igual que `framebuffer::render`, pero rasterizando en la GPU
+pub fn render(framebuffer: &mut WgpuFramebuffer, uniforms: &Uniforms, vertex_array: &[Vertex]) -> RenderStats {
+    // El pipeline de la GPU dibuja listas de triángulos: las tiras y abanicos se arman (y se
+    // recortan contra el plano near) antes
+    let vertex_array = &*clip_near_plane(triangle_list(vertex_array, uniforms.topology), &uniforms.projection_matrix);
+    let stats = RenderStats {
+        vertices: vertex_array.len(),
+        triangles: vertex_array.len() / 3,
+        ..Default::default()
+    };
+
+    // Rango de profundidad en pantalla, a partir de la caja envolvente transformada
+    let (min, max) = vertex_array.iter().fold(
+        (Vec3::repeat(f32::INFINITY), Vec3::repeat(f32::NEG_INFINITY)),
+        |(min, max), vertex| (min.inf(&vertex.position), max.sup(&vertex.position)),
+    );
+    let mut min_z = f32::INFINITY;
+    let mut max_z = f32::NEG_INFINITY;
+    for i in 0..8 {
+        let corner = Vec3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        );
+        let p = uniforms.transformation_matrix * Vec4::new(corner.x, corner.y, corner.z, 1.0);
+        min_z = min_z.min(p.z / p.w);
+        max_z = max_z.max(p.z / p.w);
+    }
+    // Margen para que la geometría en los extremos no quede recortada
+    let range = (max_z - min_z).max(f32::EPSILON) * 1.01;
+
+    let model: [[f32; 4]; 4] = uniforms.transformation_matrix.into();
+    // El shader de wgpu solo admite una luz direccional blanca: se usa la primera
+    let light_dir = uniforms.lights.iter().find_map(Light::direction).unwrap_or_else(Vec3::zeros);
+    let globals = Globals {
+        model,
+        light_dir: [light_dir.x, light_dir.y, light_dir.z, 0.0],
+        viewport: [framebuffer.width as f32, framebuffer.height as f32, min_z - range * 0.005, 1.0 / range],
+        flags: [
+            uniforms.material.two_sided as u8 as f32,
+            uniforms.material.unlit as u8 as f32,
+            0.0,
+            0.0,
+        ],
+    };
+    framebuffer.queue.write_buffer(&framebuffer.globals, 0, bytemuck::bytes_of(&globals));
+
+    let vertices: Vec<GpuVertex> = vertex_array
+        .iter()
+        .map(|vertex| GpuVertex {
+            position: vertex.position.into(),
+            normal: vertex.normal.into(),
+            color: [
+                vertex.color.r as f32 / 255.0,
+                vertex.color.g as f32 / 255.0,
+                vertex.color.b as f32 / 255.0,
+            ],
+        })
+        .collect();
+    let vertex_buffer = framebuffer.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("vertices"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+
+    // Los pipelines asumen caras frontales antihorarias; con las horarias se intercambian
+    let clockwise = uniforms.front_face == Winding::Clockwise;
+    let pipeline = match uniforms.cull_mode {
+        CullMode::None => &framebuffer.pipelines[0],
+        CullMode::Back if clockwise => &framebuffer.pipelines[2],
+        CullMode::Front if clockwise => &framebuffer.pipelines[1],
+        CullMode::Back => &framebuffer.pipelines[1],
+        CullMode::Front => &framebuffer.pipelines[2],
+    };
+
+    let color_view = framebuffer.color_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let mut encoder = framebuffer.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("frame") });
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("main"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &color_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(framebuffer.clear_color),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &framebuffer.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &framebuffer.bind_group, &[]);
+        pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        pass.draw(0..vertices.len() as u32, 0..1);
+    }
+
+    framebuffer.read_back(encoder);
+    stats
+}
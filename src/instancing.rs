@@ -0,0 +1,278 @@
+// instancing.rs
+//
+// Instancing ligero: una sola malla dibujada muchas veces con una matriz de modelo
+// por instancia (p. ej. un cinturón de asteroides). Las instancias fuera de la pantalla
+// o demasiado pequeñas se descartan antes de transformar sus vértices, y el buffer de
+// vértices transformados se reutiliza entre instancias.
+
+use nalgebra_glm::{Mat4, Vec2, Vec3, Vec4};
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::framebuffer::{
+    assemble_triangles, clip_near_plane, cull_triangles, primitive_assembly_rasterization, rasterize_triangles_into, triangle_list,
+    write_fragments, Framebuffer, Rect, RenderStats,
+};
+use crate::shader::vertex_shader;
+use crate::uniforms::{normal_matrix, Uniforms};
+use crate::vertex::Vertex;
+
+// Transformación de una instancia dentro del cinturón (en unidades del modelo)
+#[derive(Debug, Clone, Copy)]
+pub struct Instance {
+    pub orbit_radius: f32,
+    pub orbit_angle: f32,
+    pub orbit_speed: f32, // Radianes por segundo
+    pub height: f32,      // Desplazamiento fuera del plano del anillo
+    pub rotation_axis: Vec3,
+    pub rotation_speed: f32,
+    pub scale: f32,
+}
+
+impl Instance {
+    // Matriz de modelo local de la instancia en el tiempo `time`
+    pub fn model_matrix(&self, time: f32) -> Mat4 {
+        let angle = self.orbit_angle + self.orbit_speed * time;
+        let position = Vec3::new(angle.cos() * self.orbit_radius, angle.sin() * self.orbit_radius, self.height);
+
+        let translation = nalgebra_glm::translation(&position);
+        let rotation = nalgebra_glm::rotation(self.rotation_speed * time + self.orbit_angle, &self.rotation_axis);
+        let scale = nalgebra_glm::scaling(&Vec3::repeat(self.scale));
+        translation * rotation * scale
+    }
+}
+
+// Datos por instancia, como un buffer de instancias en GPU: solo la matriz de modelo y un
+// color con opacidad; la malla es la misma para todas
+#[derive(Debug, Clone, Copy)]
+pub struct InstanceData {
+    pub transform: Mat4,
+    pub color: Color,
+    pub alpha: f32,
+}
+
+// Generador pseudoaleatorio xorshift, para que el cinturón (y las partículas) sean iguales
+// con la misma semilla
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    // Número en [0, 1)
+    pub(crate) fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub(crate) fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+}
+
+// Instancias repartidas en un toro irregular entre `inner_radius` y `outer_radius`
+pub fn generate_belt(seed: u64, count: usize, inner_radius: f32, outer_radius: f32, thickness: f32) -> Vec<Instance> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .map(|_| {
+            let orbit_radius = rng.range(inner_radius, outer_radius);
+            let axis = Vec3::new(rng.range(-1.0, 1.0), rng.range(-1.0, 1.0), rng.range(-1.0, 1.0));
+            Instance {
+                orbit_radius,
+                orbit_angle: rng.range(0.0, std::f32::consts::TAU),
+                // Las órbitas más cercanas avanzan más rápido
+                orbit_speed: 0.1 / orbit_radius.max(0.1),
+                height: rng.range(-0.5, 0.5) * thickness,
+                rotation_axis: if axis.norm() > 0.0 { axis.normalize() } else { Vec3::z() },
+                rotation_speed: rng.range(-1.0, 1.0),
+                scale: rng.range(0.4, 1.0),
+            }
+        })
+        .collect()
+}
+
+// Roca de 60 triángulos: esfera de 6 anillos y 6 segmentos con el radio perturbado
+pub fn rock_mesh(seed: u64, radius: f32) -> Vec<Vertex> {
+    const RINGS: usize = 6;
+    const SEGMENTS: usize = 6;
+    let mut rng = Rng::new(seed);
+
+    // Posiciones en una grilla (anillo, segmento); los polos se repiten por segmento
+    let mut grid = vec![vec![Vec3::zeros(); SEGMENTS + 1]; RINGS + 1];
+    let north = Vec3::new(0.0, radius * rng.range(0.8, 1.2), 0.0);
+    let south = Vec3::new(0.0, -radius * rng.range(0.8, 1.2), 0.0);
+    for (ring, row) in grid.iter_mut().enumerate() {
+        let theta = std::f32::consts::PI * ring as f32 / RINGS as f32;
+        let jitter: Vec<f32> = (0..SEGMENTS).map(|_| rng.range(0.75, 1.25)).collect();
+        for (segment, position) in row.iter_mut().enumerate() {
+            *position = if ring == 0 {
+                north
+            } else if ring == RINGS {
+                south
+            } else {
+                let phi = std::f32::consts::TAU * segment as f32 / SEGMENTS as f32;
+                let r = radius * jitter[segment % SEGMENTS];
+                Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin()) * r
+            };
+        }
+    }
+
+    let mut vertices = Vec::with_capacity(60 * 3);
+    let mut push_triangle = |a: Vec3, b: Vec3, c: Vec3| {
+        // Caras planas: normal del triángulo en los tres vértices
+        let normal = (b - a).cross(&(c - a));
+        let normal = if normal.norm() > 0.0 { normal.normalize() } else { Vec3::y() };
+        for position in [a, b, c] {
+            vertices.push(Vertex::new(position, normal, Vec2::zeros()));
+        }
+    };
+
+    for ring in 0..RINGS {
+        for segment in 0..SEGMENTS {
+            let (a, b) = (grid[ring][segment], grid[ring][segment + 1]);
+            let (c, d) = (grid[ring + 1][segment], grid[ring + 1][segment + 1]);
+            if ring != 0 {
+                push_triangle(a, b, d);
+            }
+            if ring != RINGS - 1 {
+                push_triangle(a, d, c);
+            }
+        }
+    }
+
+    vertices
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InstanceStats {
+    pub instances: usize,
+    pub drawn: usize,
+    pub culled: usize,
+    pub render: RenderStats,
+}
+
+// Radio de la esfera (centrada en el origen) que contiene a la malla
+pub fn bounding_radius(vertex_array: &[Vertex]) -> f32 {
+    vertex_array.iter().map(|v| v.position.norm()).fold(0.0, f32::max)
+}
+
+// Dibuja la malla una vez por instancia, con la matriz `uniforms.transformation_matrix * instancia`.
+// Se descartan las instancias cuya esfera envolvente queda fuera de la pantalla o mide
+// menos de `min_screen_radius` pixeles. La matriz anterior de cada instancia (para las
+// velocidades) es la del tiempo `uniforms.prev_time`.
+pub fn render_instanced(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    instances: &[Instance],
+    min_screen_radius: f32,
+) -> InstanceStats {
+    framebuffer.matrices = Some(uniforms.matrices());
+    // Con SSAA el radio mínimo se mide en pixeles del framebuffer de mayor resolución
+    let min_screen_radius = min_screen_radius * uniforms.ssaa_factor.max(1) as f32;
+    framebuffer.draw_supersampled(uniforms, |framebuffer, uniforms| draw_instances(framebuffer, uniforms, vertex_array, instances, min_screen_radius))
+}
+
+fn draw_instances(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    instances: &[Instance],
+    min_screen_radius: f32,
+) -> InstanceStats {
+    let mut stats = InstanceStats { instances: instances.len(), ..Default::default() };
+    let mesh_radius = bounding_radius(vertex_array);
+    let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+    let bounds = framebuffer.raster_bounds();
+
+    let mut instance_uniforms = uniforms.clone();
+    let mut transformed_vertices: Vec<Vertex> = Vec::with_capacity(vertex_array.len());
+
+    for instance in instances {
+        let instance_matrix = instance.model_matrix(uniforms.time);
+        let transformation_matrix = uniforms.transformation_matrix * instance_matrix;
+
+        if !is_sphere_on_screen(&transformation_matrix, mesh_radius, width, height, min_screen_radius) {
+            stats.culled += 1;
+            continue;
+        }
+
+        set_instance_matrix(&mut instance_uniforms, uniforms, &instance_matrix, &instance.model_matrix(uniforms.prev_time));
+
+        transformed_vertices.clear();
+        transformed_vertices.extend(vertex_array.iter().map(|vertex| vertex_shader(vertex, &instance_uniforms)));
+
+        let fragments = primitive_assembly_rasterization(&transformed_vertices, &instance_uniforms, &bounds, &mut stats.render);
+        write_fragments(framebuffer, &fragments, instance_uniforms.material.blend_mode, instance_uniforms.stencil);
+        stats.drawn += 1;
+    }
+
+    stats
+}
+
+// Prueba de culling de `render_instanced`: si la esfera de radio `radius` centrada en el
+// origen del modelo, llevada a pantalla con `transformation_matrix`, toca una pantalla de
+// width x height pixeles y mide al menos `min_screen_radius` pixeles
+pub fn is_sphere_on_screen(transformation_matrix: &Mat4, radius: f32, width: f32, height: f32, min_screen_radius: f32) -> bool {
+    let center = transformation_matrix * Vec4::new(0.0, 0.0, 0.0, 1.0);
+    if center.w <= f32::EPSILON {
+        return false;
+    }
+    // Radio en pantalla: el eje del modelo más largo en pantalla, dividido por la w del centro
+    // como la posición (en perspectiva la esfera se achica con la distancia)
+    let axis = |x: f32, y: f32, z: f32| (transformation_matrix * Vec4::new(x, y, z, 0.0)).xy().norm();
+    let screen_radius = axis(radius, 0.0, 0.0).max(axis(0.0, radius, 0.0)).max(axis(0.0, 0.0, radius)) / center.w;
+    let center = center.xyz() / center.w;
+
+    let outside = center.x + screen_radius < 0.0
+        || center.y + screen_radius < 0.0
+        || center.x - screen_radius > width
+        || center.y - screen_radius > height;
+    !outside && screen_radius >= min_screen_radius
+}
+
+// Deja en `instance_uniforms` las matrices de `uniforms` compuestas con las de la instancia
+// (la actual y la del frame anterior)
+fn set_instance_matrix(instance_uniforms: &mut Uniforms, uniforms: &Uniforms, instance_matrix: &Mat4, previous_matrix: &Mat4) {
+    instance_uniforms.model_matrix = uniforms.model_matrix * instance_matrix;
+    instance_uniforms.normal_matrix = normal_matrix(&instance_uniforms.model_matrix);
+    instance_uniforms.transformation_matrix = uniforms.transformation_matrix * instance_matrix;
+    instance_uniforms.model_view_matrix = uniforms.model_view_matrix * instance_matrix;
+    instance_uniforms.prev_transformation_matrix = uniforms.prev_transformation_matrix * previous_matrix;
+}
+
+// Rasteriza la malla una vez por instancia de `instances` y entrega los fragmentos de cada
+// una a `write` junto con su índice, sin sombrearlos (cada llamador decide cómo combinarlos
+// con el color de la instancia). Los vértices transformados y los fragmentos van en buffers
+// que se reutilizan entre instancias, así que la memoria no crece con la cantidad de
+// instancias. Las instancias no se mueven entre frames (sin velocidades). Se rasteriza solo
+// dentro de `bounds` (p. ej. `Framebuffer::raster_bounds`)
+pub fn for_each_instance(
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    instances: &[InstanceData],
+    bounds: &Rect,
+    stats: &mut RenderStats,
+    mut write: impl FnMut(usize, &mut [Fragment]),
+) {
+    let mut instance_uniforms = uniforms.clone();
+    let mut transformed_vertices: Vec<Vertex> = Vec::with_capacity(vertex_array.len());
+    let mut fragments: Vec<Fragment> = Vec::new();
+
+    for (index, instance) in instances.iter().enumerate() {
+        set_instance_matrix(&mut instance_uniforms, uniforms, &instance.transform, &instance.transform);
+
+        transformed_vertices.clear();
+        transformed_vertices.extend(vertex_array.iter().map(|vertex| vertex_shader(vertex, &instance_uniforms)));
+        let triangle_vertices = clip_near_plane(triangle_list(&transformed_vertices, uniforms.topology), &uniforms.projection_matrix);
+        let triangles = assemble_triangles(&triangle_vertices, stats);
+        let triangles = cull_triangles(&triangle_vertices, triangles, instance_uniforms.cull_mode, instance_uniforms.front_face);
+
+        fragments.clear();
+        rasterize_triangles_into(&triangle_vertices, &triangles, &instance_uniforms, bounds, &mut fragments);
+        write(index, &mut fragments);
+    }
+}
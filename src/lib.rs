@@ -0,0 +1,47 @@
+// lib.rs
+//
+// El renderer como biblioteca: el binario (main.rs) la usa para la ventana y el bucle de
+// frames, con el feature `ffi` se exporta una API en C para llamarlo desde otros
+// lenguajes (ver ffi.rs) y con `wasm` se dibuja en un canvas del navegador (ver web.rs).
+
+#[macro_use]
+pub mod profiling;
+pub mod framebuffer;
+pub mod gbuffer;
+pub mod color;
+pub mod vertex;
+pub mod fragment;
+pub mod line;
+pub mod triangle;
+pub mod obj;
+pub mod shader;
+pub mod uniforms;
+pub mod camera;
+pub mod viewport;
+pub mod postprocess;
+pub mod recorder;
+pub mod math;
+pub mod clock;
+pub mod resolution;
+pub mod picking;
+pub mod material;
+pub mod light;
+pub mod environment;
+pub mod texture;
+pub mod noise_bake;
+pub mod seed;
+pub mod frame_pipeline;
+pub mod pipeline;
+pub mod render_queue;
+pub mod edge_function;
+pub mod shader_params;
+pub mod instancing;
+pub mod particles;
+pub mod ship;
+pub mod minimap;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "wasm")]
+pub mod web;
@@ -0,0 +1,292 @@
+// light.rs
+
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::shader::{diffuse_intensity, smoothstep};
+use crate::shader_params::ShaderParams;
+use crate::uniforms::{LightingView, Uniforms};
+
+// Luces de la escena. Cada luz aporta su color por canal, así que una estrella roja
+// ilumina en rojo y dos luces de colores distintos se suman. Las posiciones y direcciones
+// están en el espacio transformado (pixeles en x/y, profundidad en z, y hacia abajo).
+#[derive(Debug, Clone)]
+pub enum Light {
+    // Luz lejana (p. ej. una estrella): misma dirección en toda la escena
+    Directional {
+        direction: Vec3, // Dirección normalizada hacia la luz
+        color: Color,
+        intensity: f32, // Multiplica al color; 1 = el color tal cual
+    },
+    // Foco (p. ej. el faro de la nave): ilumina dentro de un cono que se desvanece entre
+    // `inner_angle` y `outer_angle` (medidos desde el eje, en radianes) y se atenúa hasta
+    // apagarse a `range` de distancia
+    Spot {
+        position: Vec3,
+        direction: Vec3, // Dirección normalizada hacia donde apunta el foco
+        inner_angle: f32,
+        outer_angle: f32,
+        range: f32,
+        color: Color,
+        intensity: f32,
+    },
+}
+
+fn normalized(v: Vec3) -> Vec3 {
+    if v.norm() > 0.0 { v.normalize() } else { v }
+}
+
+impl Light {
+    pub fn new(direction: Vec3, color: Color, intensity: f32) -> Self {
+        Light::Directional { direction: normalized(direction), color, intensity }
+    }
+
+    // Luz direccional blanca de intensidad 1
+    pub fn white(direction: Vec3) -> Self {
+        Light::new(direction, Color::new(255, 255, 255), 1.0)
+    }
+
+    // Foco de intensidad 1
+    pub fn spot(position: Vec3, direction: Vec3, inner_angle: f32, outer_angle: f32, range: f32, color: Color) -> Self {
+        Light::Spot {
+            position,
+            direction: normalized(direction),
+            inner_angle,
+            outer_angle: outer_angle.max(inner_angle),
+            range,
+            color,
+            intensity: 1.0,
+        }
+    }
+
+    // Dirección de una luz direccional (None en los focos)
+    pub fn direction(&self) -> Option<Vec3> {
+        match self {
+            Light::Directional { direction, .. } => Some(*direction),
+            Light::Spot { .. } => None,
+        }
+    }
+
+    // Luz difusa que llega por canal a un punto con la normal dada (RGB en [0, intensidad])
+    pub fn radiance_at(&self, point: &Vec3, normal: &Vec3, two_sided: bool) -> Vec3 {
+        match self {
+            Light::Directional { direction, color, intensity } => {
+                color.to_vec3() * (intensity * diffuse_intensity(normal, direction, two_sided))
+            }
+            Light::Spot { position, direction, inner_angle, outer_angle, range, color, intensity } => {
+                // Fuera del alcance no hay nada que calcular
+                let to_light = position - point;
+                let distance_squared = to_light.norm_squared();
+                if distance_squared >= range * range || distance_squared == 0.0 {
+                    return Vec3::zeros();
+                }
+                let distance = distance_squared.sqrt();
+                let to_light = to_light / distance;
+
+                // Cono: transición suave entre los cosenos de los dos ángulos
+                let cos_angle = -to_light.dot(direction);
+                let cos_outer = outer_angle.cos();
+                if cos_angle <= cos_outer {
+                    return Vec3::zeros();
+                }
+                let cone = smoothstep(cos_outer, inner_angle.cos(), cos_angle);
+
+                // Atenuación que llega a 0 justo en `range`
+                let falloff = (1.0 - distance_squared / (range * range)).powi(2);
+
+                color.to_vec3() * (intensity * cone * falloff * diffuse_intensity(normal, &to_light, two_sided))
+            }
+        }
+    }
+}
+
+// Luz del cielo: mezcla entre `sky_color` (normales hacia `up`) y `ground_color`
+// (normales hacia el lado opuesto), para que las caras en sombra no queden planas
+#[derive(Debug, Clone)]
+pub struct HemisphereLight {
+    pub sky_color: Color,
+    pub ground_color: Color,
+    pub intensity: f32,
+    pub up: Vec3, // Dirección del cielo en el espacio transformado (y crece hacia abajo en pantalla)
+}
+
+// Luz ambiental: un color constante más una luz de hemisferio opcional. Se suma a la luz
+// de las luces direccionales, así que también llega a las caras que no les dan
+#[derive(Debug, Clone)]
+pub struct AmbientLight {
+    pub color: Color,
+    pub intensity: f32,
+    pub hemisphere: Option<HemisphereLight>,
+}
+
+impl AmbientLight {
+    // Sin luz ambiental: las caras que no reciben luz quedan negras
+    pub fn none() -> Self {
+        AmbientLight { color: Color::black(), intensity: 0.0, hemisphere: None }
+    }
+
+    // Lee las tablas [ambient] y [hemisphere] de shaders.toml. Sin ellas no hay luz
+    // ambiental, y el hemisferio solo se usa si su intensidad es mayor que 0
+    pub fn from_params(params: &ShaderParams) -> Self {
+        let hemisphere_intensity = params.float("hemisphere.intensity", 0.0);
+        let hemisphere = (hemisphere_intensity > 0.0).then(|| HemisphereLight {
+            sky_color: params.color("hemisphere.sky_color", Color::new(90, 110, 160)),
+            ground_color: params.color("hemisphere.ground_color", Color::new(40, 30, 25)),
+            intensity: hemisphere_intensity,
+            up: params.vec3("hemisphere.up", Vec3::new(0.0, -1.0, 0.0)),
+        });
+
+        AmbientLight {
+            color: params.color("ambient.color", Color::new(255, 255, 255)),
+            intensity: params.float("ambient.intensity", 0.0),
+            hemisphere,
+        }
+    }
+
+    // Luz ambiental que recibe una superficie con la normal dada, por canal
+    pub fn radiance(&self, normal: &Vec3) -> Vec3 {
+        let flat = self.color.to_vec3() * self.intensity;
+        match &self.hemisphere {
+            Some(hemisphere) => {
+                let up = if hemisphere.up.norm() > 0.0 { hemisphere.up.normalize() } else { hemisphere.up };
+                let t = (normal.dot(&up) * 0.5 + 0.5).clamp(0.0, 1.0);
+                let sky = hemisphere.sky_color.to_vec3();
+                let ground = hemisphere.ground_color.to_vec3();
+                flat + (ground + (sky - ground) * t) * hemisphere.intensity
+            }
+            None => flat,
+        }
+    }
+}
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        AmbientLight::none()
+    }
+}
+
+// Luz que recibe una superficie en `point` (espacio transformado) con la normal dada: las
+// luces de la escena, la ambiental y la parte emisiva del material, con clamping por canal.
+// Los materiales sin iluminación reciben luz completa. Se usa por fragmento (Phong) o por
+// vértice (Gouraud) según `Uniforms::shading_model`
+pub fn incident_light(point: &Vec3, normal: &Vec3, uniforms: &Uniforms) -> Vec3 {
+    let material = &uniforms.material;
+    // La parte emisiva del material no depende de las luces, y los materiales sin
+    // iluminación (emisivos) tampoco reciben la luz ambiental
+    if material.unlit {
+        return Vec3::repeat(1.0);
+    }
+    let ambient = uniforms.ambient.radiance(normal);
+    let light = match uniforms.lighting_view {
+        LightingView::AmbientOnly => ambient,
+        LightingView::Full => {
+            let diffuse = uniforms.lights.iter().fold(Vec3::zeros(), |sum, light| {
+                sum + light.radiance_at(point, normal, material.two_sided)
+            });
+            diffuse + ambient + Vec3::repeat(material.emissive_strength)
+        }
+    };
+    light.map(|c| c.min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::{render, Framebuffer};
+    use crate::uniforms::{viewport_matrix, UniformsBuilder};
+    use crate::vertex::Vertex;
+    use nalgebra_glm::Vec2;
+
+    const SIZE: usize = 64;
+    const PIXELS_PER_UNIT: f32 = 24.0;
+
+    // Esfera UV blanca de radio 1 en el origen, en triángulos sueltos
+    fn sphere() -> Vec<Vertex> {
+        let (rings, segments) = (32, 64);
+        let point = |ring: usize, segment: usize| {
+            let theta = std::f32::consts::PI * ring as f32 / rings as f32;
+            let phi = std::f32::consts::TAU * segment as f32 / segments as f32;
+            let normal = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+            let mut vertex = Vertex::new(normal, normal, Vec2::zeros());
+            vertex.color = Color::new(255, 255, 255);
+            vertex
+        };
+        let mut vertices = Vec::new();
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let (a, b, c, d) = (point(ring, segment), point(ring + 1, segment), point(ring + 1, segment + 1), point(ring, segment + 1));
+                vertices.extend([a.clone(), b, c.clone(), a, c, d]);
+            }
+        }
+        vertices
+    }
+
+    fn rendered(lights: Vec<Light>) -> Framebuffer {
+        let uniforms = UniformsBuilder::new().viewport(viewport_matrix(SIZE, SIZE, PIXELS_PER_UNIT)).lights(lights).build();
+        let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+        framebuffer.clear(Color::black());
+        render(&mut framebuffer, &uniforms, &sphere());
+        framebuffer
+    }
+
+    // Normal de la esfera en el pixel (x, y), si lo cubre
+    fn normal_at(x: usize, y: usize) -> Option<Vec3> {
+        let center = SIZE as f32 / 2.0;
+        let (nx, ny) = ((x as f32 - center) / PIXELS_PER_UNIT, (y as f32 - center) / PIXELS_PER_UNIT);
+        let nz = 1.0 - nx * nx - ny * ny;
+        (nz > 0.0).then(|| Vec3::new(nx, ny, -nz.sqrt()))
+    }
+
+    // Una luz roja desde +x y una azul desde -x, las dos algo hacia la cámara: cada lado
+    // queda de un solo color, y el magenta aparece solo donde llegan las dos
+    #[test]
+    fn red_and_blue_lights_add_per_channel() {
+        let (red, blue) = (Vec3::new(1.0, 0.0, -1.0).normalize(), Vec3::new(-1.0, 0.0, -1.0).normalize());
+        let framebuffer = rendered(vec![Light::new(red, Color::new(255, 0, 0), 1.0), Light::new(blue, Color::new(0, 0, 255), 1.0)]);
+
+        let (mut only_red, mut only_blue, mut magenta) = (0, 0, 0);
+        for (y, row) in framebuffer.rows().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                let color = Color::from_u32(pixel);
+                assert_eq!(color.g, 0, "({x}, {y})");
+                // Lejos del terminador de cada luz, donde la malla facetada puede diferir de
+                // la esfera exacta
+                let Some(normal) = normal_at(x, y) else { continue };
+                let (to_red, to_blue) = (normal.dot(&red), normal.dot(&blue));
+                if to_red.abs() < 0.15 || to_blue.abs() < 0.15 || normal.z > -0.15 {
+                    continue;
+                }
+                assert_eq!((color.r > 0, color.b > 0), (to_red > 0.0, to_blue > 0.0), "({x}, {y}): {color}");
+                match (color.r > 0, color.b > 0) {
+                    (true, false) => only_red += 1,
+                    (false, true) => only_blue += 1,
+                    (true, true) => magenta += 1,
+                    (false, false) => {}
+                }
+            }
+        }
+        assert!(only_red > 20 && only_blue > 20 && magenta > 20, "{only_red} {only_blue} {magenta}");
+        let center = Color::from_u32(framebuffer.get_pixel(SIZE as isize / 2, SIZE as isize / 2).unwrap());
+        assert!(center.r > 0 && center.b > 0 && center.g == 0, "{center}");
+    }
+
+    // Tres veces la intensidad en rojo y dos luces rojas sumadas: el canal se satura en 255
+    // en lugar de dar la vuelta, y los demás canales siguen en 0
+    #[test]
+    fn channels_saturate_without_wrapping() {
+        let toward_camera = Vec3::new(0.0, 0.0, -1.0);
+        for lights in [
+            vec![Light::new(toward_camera, Color::new(255, 0, 0), 3.0)],
+            vec![Light::new(toward_camera, Color::new(255, 0, 0), 1.0), Light::new(toward_camera, Color::new(255, 0, 0), 1.0)],
+        ] {
+            let framebuffer = rendered(lights);
+            let center = Color::from_u32(framebuffer.get_pixel(SIZE as isize / 2, SIZE as isize / 2).unwrap());
+            assert_eq!((center.r, center.g, center.b), (255, 0, 0));
+            // A 8 pixeles del centro la luz sumada también pasa de 1
+            let off_center = Color::from_u32(framebuffer.get_pixel(SIZE as isize / 2 + 8, SIZE as isize / 2).unwrap());
+            assert_eq!(off_center.r, 255);
+        }
+        let uniforms = UniformsBuilder::new().lights(vec![Light::new(toward_camera, Color::new(255, 0, 0), 3.0)]).build();
+        let direct = incident_light(&Vec3::zeros(), &toward_camera, &uniforms);
+        assert_eq!(direct, Vec3::new(1.0, 0.0, 0.0));
+    }
+}
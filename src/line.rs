@@ -1,14 +1,131 @@
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
 use crate::color::Color;
+use crate::edge_function::{covered_in_row, CoveredPixel, TriangleSetup};
+use nalgebra_glm::{Vec2, Vec3};
 
+// Cómo se rasterizan las líneas del wireframe
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineMode {
+    Aliased,      // Bresenham: un pixel por paso, solo aritmética entera
+    Antialiased,  // Wu: dos pixeles por paso con cobertura parcial
+    Thick(f32),   // Quad del ancho indicado en pixeles
+}
+
+// Patrón de punteado: la línea se divide en tramos de `scale` pixeles a lo largo de su
+// longitud, y el tramo i se dibuja si el bit (i mod 16) de `pattern` está encendido.
+// Por ejemplo 0x00FF con escala 1 son rayas de 8 pixeles separadas por 8 pixeles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stipple {
+    pub pattern: u16,
+    pub scale: f32,
+}
+
+impl Stipple {
+    pub const SOLID: Stipple = Stipple { pattern: 0xFFFF, scale: 1.0 };
+
+    pub fn new(pattern: u16, scale: f32) -> Self {
+        Stipple { pattern, scale }
+    }
+
+    // Si el punto a `arc_length` pixeles del inicio de la línea se dibuja
+    pub fn is_on(&self, arc_length: f32) -> bool {
+        if self.scale <= 0.0 {
+            return self.pattern != 0;
+        }
+        let segment = (arc_length / self.scale).floor() as i64;
+        self.pattern & (1 << segment.rem_euclid(16)) != 0
+    }
+}
+
+// Descarta los fragmentos de una línea de `a` a `b` (de cualquier modo) que caen en
+// tramos apagados del patrón. La distancia de cada fragmento se mide sobre el segmento,
+// así el patrón no depende de la pendiente ni del grosor
+pub fn apply_stipple(mut fragments: Vec<Fragment>, a: &Vertex, b: &Vertex, stipple: &Stipple) -> Vec<Fragment> {
+    if stipple.pattern == 0xFFFF {
+        return fragments;
+    }
+
+    let start = a.transformed_position.xy();
+    let direction = b.transformed_position.xy() - start;
+    let length = direction.norm();
+    let direction = if length > 0.0 { direction / length } else { direction };
+
+    fragments.retain(|fragment| stipple.is_on((fragment.position - start).dot(&direction).max(0.0)));
+    fragments
+}
+
+// Línea de `a` a `b` con el modo indicado
+pub fn line_with_mode(a: &Vertex, b: &Vertex, mode: LineMode) -> Vec<Fragment> {
+    match mode {
+        LineMode::Aliased => line(a, b),
+        LineMode::Antialiased => line_antialiased(a, b),
+        LineMode::Thick(width) => thick_line(a, b, width),
+    }
+}
+
+// Igual que `line_with_mode`, recortando antes el segmento a la pantalla de `width` x
+// `height` pixeles (con un margen del grosor de la línea): un segmento largo que sale de la
+// pantalla no recorre pixel por pixel la parte que no se ve. Los extremos recortados
+// interpolan color y profundidad como la línea completa
+pub fn line_clipped(a: &Vertex, b: &Vertex, mode: LineMode, width: f32, height: f32) -> Vec<Fragment> {
+    let margin = match mode {
+        LineMode::Thick(line_width) => line_width.max(1.0) * 0.5 + 1.0,
+        _ => 1.0,
+    };
+    let (start, end) = (a.transformed_position.xy(), b.transformed_position.xy());
+    let min = Vec2::repeat(-margin);
+    let max = Vec2::new(width + margin, height + margin);
+    match clip_segment_params(start, end, min, max) {
+        None => Vec::new(),
+        Some((t0, t1)) if t0 == 0.0 && t1 == 1.0 => line_with_mode(a, b, mode),
+        Some((t0, t1)) => line_with_mode(&a.lerp(b, t0), &a.lerp(b, t1), mode),
+    }
+}
+
+// Recorta un segmento en pantalla al rectángulo [0, width] x [0, height] (Liang-Barsky)
+pub(crate) fn clip_segment_rect(a: Vec2, b: Vec2, width: f32, height: f32) -> Option<(Vec2, Vec2)> {
+    let (t0, t1) = clip_segment_params(a, b, Vec2::zeros(), Vec2::new(width, height))?;
+    let delta = b - a;
+    Some((a + delta * t0, a + delta * t1))
+}
+
+// Parámetros (t0, t1) en [0, 1] de la parte del segmento de `a` a `b` dentro del
+// rectángulo [min, max], o None si queda afuera (o tiene coordenadas no finitas)
+fn clip_segment_params(a: Vec2, b: Vec2, min: Vec2, max: Vec2) -> Option<(f32, f32)> {
+    if a.iter().chain(b.iter()).any(|c| !c.is_finite()) {
+        return None;
+    }
+    let delta = b - a;
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (p, q) in [(-delta.x, a.x - min.x), (delta.x, max.x - a.x), (-delta.y, a.y - min.y), (delta.y, max.y - a.y)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            t0 = t0.max(t);
+        } else {
+            t1 = t1.min(t);
+        }
+        if t0 > t1 {
+            return None;
+        }
+    }
+    Some((t0, t1))
+}
+
+// Línea en coordenadas de pantalla con el algoritmo de Bresenham
 pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
     let mut fragments = Vec::new();
 
-    let x0 = a.position.x as i32;
-    let y0 = a.position.y as i32;
-    let x1 = b.position.x as i32;
-    let y1 = b.position.y as i32;
+    let x0 = a.transformed_position.x as i32;
+    let y0 = a.transformed_position.y as i32;
+    let x1 = b.transformed_position.x as i32;
+    let y1 = b.transformed_position.y as i32;
 
     // Diferencias y direcciones
     let dx = (x1 - x0).abs();
@@ -21,20 +138,18 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
     let mut x = x0;
     let mut y = y0;
 
-    let distance = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
+    // Bresenham avanza exactamente un pixel en el eje mayor por paso
+    let steps = dx.max(dy);
+    let mut step = 0;
 
     while x != x1 || y != y1 {
         // Interpolar el color y la profundidad entre a y b
-        let t = (((x - x0).pow(2) + (y - y0).pow(2)) as f32).sqrt() / distance;
+        let t = step as f32 / steps as f32;
         let color = interpolate_color(&a.color, &b.color, t);
-        let depth = interpolate_f32(a.position.z, b.position.z, t);
+        let depth = interpolate_f32(a.transformed_position.z, b.transformed_position.z, t);
 
         // Crear el fragmento
-        fragments.push(Fragment {
-            position: nalgebra_glm::Vec2::new(x as f32, y as f32),
-            color,
-            depth,
-        });
+        fragments.push(line_fragment(x, y, color, depth, 1.0));
 
         let e2 = 2 * err;
         if e2 > -dy {
@@ -45,18 +160,129 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
             err += dx;
             y += sy;
         }
+        step += 1;
     }
 
     // Asegurarse de incluir el último punto (b)
-    fragments.push(Fragment {
-        position: nalgebra_glm::Vec2::new(x1 as f32, y1 as f32),
-        color: b.color,
-        depth: b.position.z,
-    });
+    fragments.push(line_fragment(x1, y1, b.color, b.transformed_position.z, 1.0));
+
+    fragments
+}
+
+// Línea suavizada con el algoritmo de Wu: en cada columna (o fila, si la línea es
+// empinada) se dibujan los dos pixeles que la línea cruza, con `intensity` igual a
+// la fracción de cobertura de cada uno
+pub fn line_antialiased(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
+    let mut fragments = Vec::new();
+
+    let (mut x0, mut y0) = (a.transformed_position.x, a.transformed_position.y);
+    let (mut x1, mut y1) = (b.transformed_position.x, b.transformed_position.y);
+    let (mut start, mut end) = (a, b);
+
+    // Recorrer siempre el eje mayor, de menor a mayor
+    let steep = (y1 - y0).abs() > (x1 - x0).abs();
+    if steep {
+        std::mem::swap(&mut x0, &mut y0);
+        std::mem::swap(&mut x1, &mut y1);
+    }
+    if x0 > x1 {
+        std::mem::swap(&mut x0, &mut x1);
+        std::mem::swap(&mut y0, &mut y1);
+        std::mem::swap(&mut start, &mut end);
+    }
+
+    let dx = x1 - x0;
+    let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+
+    let first = x0.round() as i32;
+    let last = x1.round() as i32;
+    for major in first..=last {
+        let t = if dx == 0.0 { 0.0 } else { ((major as f32 - x0) / dx).clamp(0.0, 1.0) };
+        let color = interpolate_color(&start.color, &end.color, t);
+        let depth = interpolate_f32(start.transformed_position.z, end.transformed_position.z, t);
+
+        let minor = y0 + gradient * (major as f32 - x0);
+        let minor_floor = minor.floor();
+        let coverage = minor - minor_floor;
+
+        for (offset, weight) in [(0, 1.0 - coverage), (1, coverage)] {
+            if weight <= 0.0 {
+                continue;
+            }
+            let minor_pixel = minor_floor as i32 + offset;
+            let (x, y) = if steep { (minor_pixel, major) } else { (major, minor_pixel) };
+            fragments.push(line_fragment(x, y, color, depth, weight));
+        }
+    }
 
     fragments
 }
 
+// Línea gruesa: un quad alineado a la pantalla, centrado en el segmento y de
+// `width_pixels` de ancho, rasterizado como dos triángulos
+pub fn thick_line(a: &Vertex, b: &Vertex, width_pixels: f32) -> Vec<Fragment> {
+    let p0 = a.transformed_position;
+    let p1 = b.transformed_position;
+    let direction = (p1 - p0).xy();
+    let length_squared = direction.norm_squared();
+
+    // Con un pixel de ancho (o un segmento sin largo) basta Bresenham
+    if width_pixels <= 1.0 || length_squared == 0.0 {
+        return line(a, b);
+    }
+
+    // Desplazamiento perpendicular al segmento, de medio ancho
+    let half_width = Vec2::new(-direction.y, direction.x) * (width_pixels * 0.5 / length_squared.sqrt());
+    let offset = Vec3::new(half_width.x, half_width.y, 0.0);
+    let corners = [p0 + offset, p1 + offset, p1 - offset, p0 - offset];
+
+    let mut fragments = Vec::new();
+    let mut covered: Vec<CoveredPixel> = Vec::new();
+    for [i, j, k] in [[0, 1, 2], [0, 2, 3]] {
+        let (ta, tb, tc) = (&corners[i], &corners[j], &corners[k]);
+        let Some(setup) = TriangleSetup::new(ta, tb, tc) else { continue };
+
+        let min_x = ta.x.min(tb.x).min(tc.x).floor() as i32;
+        let max_x = ta.x.max(tb.x).max(tc.x).ceil() as i32;
+        let min_y = ta.y.min(tb.y).min(tc.y).floor() as i32;
+        let max_y = ta.y.max(tb.y).max(tc.y).ceil() as i32;
+
+        for y in min_y..=max_y {
+            covered.clear();
+            covered_in_row(&setup, y, min_x, max_x, &mut covered);
+
+            for pixel in &covered {
+                // Color y profundidad según la posición proyectada sobre el segmento
+                let t = ((Vec2::new(pixel.x as f32, y as f32) - p0.xy()).dot(&direction) / length_squared).clamp(0.0, 1.0);
+                let color = interpolate_color(&a.color, &b.color, t);
+                let depth = interpolate_f32(p0.z, p1.z, t);
+                fragments.push(line_fragment(pixel.x, y, color, depth, 1.0));
+            }
+        }
+    }
+
+    fragments
+}
+
+// Fragmento de una línea; `intensity` es la cobertura del pixel (1 = completo)
+fn line_fragment(x: i32, y: i32, color: Color, depth: f32, intensity: f32) -> Fragment {
+    Fragment {
+        position: Vec2::new(x as f32, y as f32),
+        color,
+        depth,
+        velocity: Vec2::zeros(),
+        normal: Vec3::new(0.0, 0.0, -1.0),
+        intensity,
+        light: Vec3::repeat(1.0),
+        alpha: 1.0,
+        model_position: Vec3::zeros(),
+        tex_coords: Vec2::zeros(),
+        barycentric: None,
+        triangle: 0,
+        view: None,
+    }
+}
+
 // Función para interpolar los colores entre dos vértices
 fn interpolate_color(c1: &Color, c2: &Color, t: f32) -> Color {
     Color {
@@ -70,3 +296,43 @@ fn interpolate_color(c1: &Color, c2: &Color, t: f32) -> Color {
 fn interpolate_f32(a: f32, b: f32, t: f32) -> f32 {
     (1.0 - t) * a + t * b
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(x: f32, y: f32) -> Vertex {
+        let mut vertex = Vertex::new(Vec3::zeros(), Vec3::z(), Vec2::zeros());
+        vertex.transformed_position = Vec3::new(x, y, 0.5);
+        vertex
+    }
+
+    #[test]
+    fn stipple_alternates_dashes_and_gaps() {
+        let (a, b) = (endpoint(0.0, 3.0), endpoint(47.0, 3.0));
+        // 4 tramos encendidos y 4 apagados de 2 pixeles: rayas y huecos de 8 pixeles
+        let dashed = apply_stipple(line(&a, &b), &a, &b, &Stipple::new(0x0F0F, 2.0));
+        let columns: Vec<i32> = dashed.iter().map(|fragment| fragment.position.x as i32).collect();
+        let expected: Vec<i32> = (0..48).filter(|x| x / 8 % 2 == 0).collect();
+        assert_eq!(columns, expected);
+
+        // El patrón sólido no descarta nada
+        assert_eq!(apply_stipple(line(&a, &b), &a, &b, &Stipple::SOLID).len(), 48);
+    }
+
+    #[test]
+    fn stipple_follows_arc_length_on_diagonals() {
+        let (a, b) = (endpoint(0.0, 0.0), endpoint(30.0, 40.0));
+        let stipple = Stipple::new(0x00FF, 1.0);
+        let dashed = apply_stipple(line(&a, &b), &a, &b, &stipple);
+        let direction = Vec2::new(0.6, 0.8);
+        assert!(!dashed.is_empty());
+        for fragment in &dashed {
+            let distance = fragment.position.dot(&direction);
+            assert!((distance as i32) % 16 < 8, "fragmento a {distance} pixeles en un hueco");
+        }
+        // Los primeros 8 pixeles de recorrido se dibujan y los siguientes 8 no
+        assert!(dashed.iter().any(|fragment| fragment.position.dot(&direction) < 8.0));
+        assert!(!dashed.iter().any(|fragment| (8.0..16.0).contains(&fragment.position.dot(&direction))));
+    }
+}
@@ -1,20 +1,30 @@
-mod framebuffer;
-mod color;
-mod vertex;
-mod fragment;
-mod line;
-mod triangle;
-mod obj;
-mod shader;
-mod uniforms; // Importar uniforms.rs
-
-use framebuffer::Framebuffer;
-use color::Color;
-use nalgebra_glm::{Vec3, Mat4};
+use renderer::{camera, clock, color, frame_pipeline, framebuffer, environment, instancing, light, line, material, math, minimap, noise_bake, obj, particles, postprocess, profiling, recorder, render_queue, resolution, seed, shader, shader_params, ship, texture, uniforms, vertex, viewport};
+use framebuffer::{draw_aabb, draw_vertex_normals, render, render_outline, render_polyline, render_wireframe, DepthMode, Framebuffer, Rect};
+use line::{LineMode, Stipple};
+use frame_pipeline::FramePipeline;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+use recorder::FrameRecorder;
+use render_queue::RenderQueue;
+use clock::{Clock, FramePacer, RealClock};
+use resolution::ResolutionScaler;
+use color::{Color, Gradient};
+use nalgebra_glm::{Vec2, Vec3, Mat4};
 use obj::Obj;
-use shader::vertex_shader;
-use uniforms::Uniforms; // Importar Uniforms desde uniforms.rs
-use vertex::Vertex;     // Asegurarse de importar Vertex correctamente
+use uniforms::{CullMode, DepthBias, LightingView, Matrices, NoiseSource, RasterizerMode, ShadingModel, StencilState, Uniforms, UniformsBuilder, Winding}; // Importar Uniforms desde uniforms.rs
+use material::{CloudSettings, Material};
+use light::{AmbientLight, Light};
+use environment::Cubemap;
+use shader::{GeometryShader, NoiseSettings, ShaderRegistry};
+use noise_bake::BakedNoise;
+use seed::SceneSeed;
+use shader_params::{ShaderParams, ShaderParamsWatcher};
+use camera::{Camera, Projection};
+use math::BoundingBox;
+use texture::RenderTarget;
+use vertex::Vertex;
+use viewport::Viewport;
 
 
 fn create_model_matrix(translation: Vec3, scale: f32, _rotation: Vec3) -> Mat4 {
@@ -27,61 +37,1036 @@ fn create_model_matrix(translation: Vec3, scale: f32, _rotation: Vec3) -> Mat4 {
     transform_matrix
 }
 
+// Semillas del ruido de los materiales del modelo y de las nubes a partir de la semilla de
+// la escena, y su ruido horneado (con `use_cache`, guardado en cache/noise para no
+// recalcularlo al reiniciar)
+fn seed_noise_materials(
+    seed: &SceneSeed,
+    model: &mut Material,
+    clouds: &mut Material,
+    domain_warp: f32,
+    bounds: Option<&BoundingBox>,
+    use_cache: bool,
+) {
+    model.params.insert("seed".to_string(), seed.shader("model_noise") as f32);
+    clouds.params.insert("seed".to_string(), seed.shader("cloud_noise") as f32);
+    let Some(bounds) = bounds else { return };
+
+    let noise_cache = use_cache.then(|| std::path::Path::new("cache/noise"));
+    let bake_start = std::time::Instant::now();
+    let baked = BakedNoise::for_material(&NoiseSettings::noise_shader(model, domain_warp, 0.0), model, bounds, noise_cache);
+    model.baked_noise = Some(Arc::new(baked));
+    let baked = BakedNoise::for_material(&NoiseSettings::clouds(clouds), clouds, bounds, noise_cache);
+    clouds.baked_noise = Some(Arc::new(baked));
+    println!("Ruido horneado en {:.0} ms", bake_start.elapsed().as_secs_f64() * 1000.0);
+}
+
+// Faro de la nave: un foco en la punta del modelo que apunta hacia donde va la nave.
+// `to_screen` lleva del mundo al espacio transformado, donde se evalúan las luces
+fn create_headlight(to_screen: &Mat4, ship_position: Vec3, ship_heading: Vec3) -> Light {
+    let forward = if ship_heading.norm() > 0.0 { ship_heading.normalize() } else { Vec3::new(0.0, -1.0, 0.0) };
+    let nose = to_screen * (ship_position + forward * 0.6).push(1.0);
+    let direction = (to_screen * forward.push(0.0)).xyz();
+    let range = direction.norm() * 4.0; // 4 unidades del mundo
+    Light::spot(nose.xyz() / nose.w, direction, 0.25, 0.45, range, Color::new(255, 240, 200))
+}
+
+// Pantalla del monitor de seguridad: un rectángulo en el plano z = 0 mirando a la cámara,
+// con la esquina superior izquierda en `top_left` y coordenadas de textura (0, 0) ahí
+fn create_monitor_quad(top_left: Vec3, width: f32, height: f32) -> Vec<Vertex> {
+    let corner = |u: f32, v: f32| {
+        let position = top_left + Vec3::new(u * width, v * height, 0.0);
+        Vertex::new(position, Vec3::new(0.0, 0.0, -1.0), Vec2::new(u, v))
+    };
+    let (a, b, c, d) = (corner(0.0, 0.0), corner(1.0, 0.0), corner(1.0, 1.0), corner(0.0, 1.0));
+    vec![a.clone(), b, c.clone(), a, c, d]
+}
+
+// Cámara de una de las vistas de la ventana. La de persecución se centra en la nave; el
+// zoom acerca la vista (más pixeles por unidad, o menos distancia en perspectiva)
+#[derive(Debug, Clone, Copy)]
+struct ViewCamera {
+    perspective: bool,
+    zoom: f32,
+    follow_ship: bool,
+    target_offset: Vec3, // Desplazamiento del punto al que mira (lo fija el encuadre)
+}
+
+impl ViewCamera {
+    // Punto al que mira sin el desplazamiento: la nave si la sigue, si no el origen
+    fn anchor(&self, ship_position: Vec3) -> Vec3 {
+        if self.follow_ship { ship_position } else { Vec3::zeros() }
+    }
+
+    fn viewport(&self, rect: Rect, ship_position: Vec3, projection: &Projection, pixels_per_unit: f32, distance: f32) -> Viewport {
+        let target = self.anchor(ship_position) + self.target_offset;
+        if self.perspective {
+            let camera = Camera::looking_at(target - Vec3::z() * (distance / self.zoom), target, Vec3::y());
+            Viewport::perspective(rect, camera.view_matrix(), projection)
+        } else {
+            Viewport::orthographic(rect, nalgebra_glm::translation(&-target), pixels_per_unit * self.zoom)
+        }
+    }
+
+    // Encuadre (tecla F): mira al centro de la esfera y ajusta el zoom para que la esfera
+    // llene la vista, con el campo de visión actual en perspectiva o con los pixeles por
+    // unidad en ortográfica
+    fn frame(&mut self, (center, radius): (Vec3, f32), rect: Rect, ship_position: Vec3, projection: &Projection, pixels_per_unit: f32, distance: f32) {
+        let radius = radius.max(f32::EPSILON);
+        self.target_offset = center - self.anchor(ship_position);
+        self.zoom = if self.perspective {
+            let aspect = rect.width as f32 / rect.height.max(1) as f32;
+            let camera = Camera::frame(center, radius, projection.fit_fov(aspect));
+            distance / (center - camera.eye).norm()
+        } else {
+            rect.width.min(rect.height) as f32 / (2.0 * radius * pixels_per_unit)
+        };
+    }
+}
+
 fn main() {
+    // Trazas de las etapas del render (solo con el feature `trace`)
+    let _trace_guard = profiling::init();
+
     // Tamaño del framebuffer (ventana)
     let width = 800;
     let height = 600;
 
-    // Crear una instancia del framebuffer
+    // Dos framebuffers: uno se renderiza en segundo plano mientras el otro se presenta
+    let mut pipeline = FramePipeline::new(width, height);
     let mut framebuffer = Framebuffer::new(width, height);
 
     // Establecer un color de fondo (por ejemplo, negro)
     let background_color = Color::new(0, 0, 20);
-    framebuffer.clear(background_color);
 
     // Cargar el archivo OBJ
-    let obj_model = Obj::load("assets/cube.obj").expect("Error cargando el archivo OBJ");
+    let model_path = "assets/cube.obj";
+    let obj_model = match Obj::load(model_path) {
+        Ok(obj) => obj,
+        Err(e) => {
+            eprintln!("No se pudo cargar {}: {}", model_path, e);
+            std::process::exit(1);
+        }
+    };
+
+    // Material del modelo: casco metálico que refleja el entorno
+    let material = Material { reflectivity: 0.35, ..Material::default() };
 
-    // Obtener el array de vértices
-    let vertices = obj_model.get_vertex_array();
+    // Obtener el array de vértices, con el color base del material
+    let mut vertices = obj_model.get_vertex_array();
+    for vertex in vertices.iter_mut() {
+        vertex.color = material.base_color;
+    }
     println!("Número de vértices cargados: {}", vertices.len());
 
-    // Crear la matriz de modelo
-    let translation = Vec3::new(0.0, 0.0, 0.0);  // Sin traslación por ahora
-    let scale = 1.0;  // Escala normal
+    // Winding de las caras frontales del modelo, estimado porque los OBJ de distintas
+    // herramientas no siempre usan el mismo; si no es concluyente se asume antihorario
+    let front_face = math::detect_winding(&vertices, 64).unwrap_or_else(|| {
+        eprintln!("No se pudo estimar el winding de {}; se asume antihorario", model_path);
+        Winding::CounterClockwise
+    });
+    let vertices = Arc::new(vertices);
+    // Esfera envolvente del modelo, para encuadrarlo (tecla F)
+    let (model_center, model_radius) = obj_model.bounding_sphere();
+    let model_bounds = BoundingBox::from_vertices(&vertices);
+
+    // Viewport: el origen al centro de la ventana y 100 pixeles por unidad, para que el modelo
+    // quede dentro de la ventana. El modelo solo se mueve con la nave
+    let scale = 100.0;
     let rotation = Vec3::new(0.0, 0.0, 0.0);  // Sin rotación
+    let viewport = uniforms::viewport_matrix(width, height, scale);
+
+    // Parámetros de shaders desde shaders.toml, recargados cuando el archivo cambia
+    let shader_params = ShaderParams::load(std::path::Path::new("shaders.toml")).unwrap_or_else(|e| {
+        eprintln!("{}; se usan los valores por defecto", e);
+        ShaderParams::default()
+    });
+    let shader_params_watcher = ShaderParamsWatcher::new("shaders.toml")
+        .map_err(|e| eprintln!("No se puede observar shaders.toml: {}", e))
+        .ok();
+
+    // Semilla maestra de todo lo procedural: --seed N, [scene] seed en shaders.toml o la de
+    // siempre. Tecla Q: semilla al azar; tecla Y: mostrar la semilla actual
+    let mut scene_seed = SceneSeed::from_args(std::env::args().skip(1), &shader_params).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    println!("Semilla de la escena: {}", scene_seed.master());
 
-    let model_matrix = create_model_matrix(translation, scale, rotation);
-    
     // Crear la estructura Uniforms
-    let uniforms = Uniforms {
-        model_matrix,
-    };
+    let mut uniforms = UniformsBuilder::new()
+        .model(create_model_matrix(Vec3::zeros(), 1.0, rotation))
+        .viewport(viewport)
+        .ambient(AmbientLight::from_params(&shader_params))
+        .environment(Some(Arc::new(Cubemap::space(128, scene_seed.shader("stars")))))
+        .material(material)
+        .front_face(front_face)
+        .shader_params(shader_params)
+        .build();
+
+    // Ruido horneado para los shaders de ruido del modelo y de las nubes. Tecla E: horneado
+    // o evaluado en vivo
+    let mut cloud_material = Material::clouds(&CloudSettings::default());
+    let domain_warp = uniforms.domain_warp;
+    seed_noise_materials(&scene_seed, &mut uniforms.material, &mut cloud_material, domain_warp, model_bounds.as_ref(), true);
+
+    // Id con el que se dibuja el modelo en el id buffer, para poder seleccionarlo con el mouse
+    let model_id: u16 = 1;
+    let model_name = "cube";
+    let mut selected: Option<u16> = None;
+
+    // Reloj del render loop (FixedStepClock para renders reproducibles)
+    let mut clock: Box<dyn Clock> = Box::new(RealClock::new());
+    // Ritmo de frames: frame.target_fps en shaders.toml (60 por defecto, 0 = sin límite)
+    let mut pacer = FramePacer::new(uniforms.shader_params.float("frame.target_fps", 60.0));
+
+    // Resolución dinámica: el frame se renderiza a una escala de 0.5 a 1.0 del tamaño de la
+    // ventana, ajustada para sostener frame.resolution_target_fps (30 por defecto, 0 =
+    // siempre completa), y se amplía al presentarlo. Las teclas [ y ] fijan la escala a
+    // mano y \ vuelve al ajuste automático
+    let mut resolution_scaler = ResolutionScaler::new(uniforms.shader_params.float("frame.resolution_target_fps", 30.0));
+    let mut render_size = (width, height);
+    let mut upscaled = vec![0u32; width * height];
+
+    // Grabación de frames: la tecla R inicia o detiene la secuencia PNG
+    let mut recorder = FrameRecorder::new("recording", 300);
+
+    let mut window = Window::new("Renderer", width, height, WindowOptions::default())
+        .unwrap_or_else(|e| {
+            panic!("{}", e);
+        });
+    // minifb limita las actualizaciones a 250 FPS por su cuenta; el ritmo lo lleva `pacer`
+    window.set_target_fps(0);
+    let mut mouse_was_down = false;
+
+    // Motion blur (tecla M): usa la matriz del frame anterior para calcular velocidades
+    let mut motion_blur = false;
+    // Vistas del frame anterior (y la del monitor), para las velocidades con su propia cámara
+    let mut previous_viewports: Vec<Viewport> = Vec::new();
+    let mut previous_monitor: Option<Viewport> = None;
+    // TAA (tabla [taa] de shaders.toml): el historial se comparte entre los dos framebuffers
+    // del pipeline, que se alternan, y se descarta en los cortes de cámara (pantalla dividida,
+    // proyección, encuadre, resolución interna o recarga de shaders.toml) para no mezclar la
+    // imagen anterior
+    let taa_history = Arc::new(Mutex::new(Vec::new()));
+    let mut camera_cut = true;
+    // Exposición automática (tabla [exposure] de shaders.toml), al final de la cadena. La
+    // exposición se adapta de un frame al siguiente, así que también se comparte entre los
+    // framebuffers del pipeline
+    let auto_exposure = Arc::new(Mutex::new(postprocess::AutoExposure::new(0.3, 0.05)));
+
+    // Profundidad de campo (tecla D), enfocada en la nave si está seleccionada o la sigue la
+    // cámara principal, si no en el punto al que mira esa cámara. Las teclas , y . acercan
+    // y alejan el plano focal. Bloom (tecla ;): halos alrededor de lo más brillante.
+    // Parámetros en [depth_of_field] y [bloom] de shaders.toml
+    let mut depth_of_field = false;
+    let mut focus_shift = 0.0f32;
+    let mut bloom = false;
+
+    // Wireframe encima del modelo (tecla W): apagado -> Bresenham -> Wu -> grueso -> apagado
+    let mut wireframe: Option<LineMode> = None;
+
+    // Cinturón de asteroides alrededor del modelo (tecla I)
+    let mut show_belt = false;
+    let mut rock = Arc::new(instancing::rock_mesh(scene_seed.derive("rock"), 0.05));
+    let mut rock_bounds = BoundingBox::from_vertices(&rock);
+    let mut rock_radius = instancing::bounding_radius(&rock);
+    let rock_min_screen_radius = 0.5; // Rocas más chicas que esto (pixeles) no se dibujan
+    let (belt_inner_radius, belt_outer_radius) = (2.0, 2.8);
+    let belt_count = 2000;
+    let mut belt = Arc::new(instancing::generate_belt(scene_seed.derive("belt"), belt_count, belt_inner_radius, belt_outer_radius, 0.3));
+    let belt_drawn = Arc::new(AtomicUsize::new(0));
+    let mut shown_title = String::new();
+
+    // Nave controlable (el modelo): las flechas aplican empuje y la tecla T muestra
+    // la trayectoria prevista para los próximos segundos
+    let mut ship_state = ship::ShipState { position: Vec3::zeros(), velocity: Vec3::zeros() };
+    let attractors: Vec<ship::Attractor> = Vec::new();
+    let thrust_acceleration = 2.0;
+    let mut show_trajectory = false;
+    let trajectory_horizon = 5.0;
+    let trajectory_steps = 60;
+
+    // Escape del motor: mientras hay empuje, partículas desde la cola de la nave en sentido
+    // contrario al empuje
+    let mut exhaust = particles::ParticleSystem::new(particles::EmitterConfig::exhaust(), scene_seed.derive("exhaust"));
+    let exhaust_rate = exhaust.emitter.spawn_rate;
+    let exhaust_offset = 1.2; // Distancia del centro de la nave a la cola
+
+    // Penacho del motor: un cono aditivo en la cola cuyo largo y brillo siguen al
+    // acelerador, que sube hacia 1 mientras hay empuje y baja a 0 sin él
+    let plume = Arc::new(ship::plume_mesh(24, 0.45));
+    let mut plume_material = Material::exhaust().with_param("seed", scene_seed.shader("plume") as f32);
+    let (plume_length, plume_radius) = (1.6, 0.18);
+    let throttle_response = 6.0; // Por segundo: qué tan rápido el acelerador sigue al empuje
 
-    // Procesar los vértices mediante el vertex shader y dibujarlos
-    let transformed_vertices: Vec<Vertex> = vertices
-        .iter()
-        .map(|vertex| vertex_shader(&vertex, &uniforms))
-        .collect();
+    // Minimapa del sistema en la esquina superior derecha (tecla N)
+    let mut show_minimap = false;
+    let minimap = minimap::Minimap::default();
+    let mut ship_heading = Vec3::new(0.0, -1.0, 0.0);
 
-    // Escalar para asegurar que los vértices estén dentro de la ventana
-    let scale_factor = 100.0;
-    let offset_x = width as f32 / 2.0;
-    let offset_y = height as f32 / 2.0;
+    // Luces: la estrella (desde la cámara, del color de su temperatura en [star] de
+    // shaders.toml) y el faro de la nave, que se enciende con la tecla L
+    let mut headlight_on = true;
 
-    // Dibujar los vértices en el framebuffer
-    for vertex in transformed_vertices {
-        let x = vertex.position.x * scale_factor + offset_x;
-        let y = vertex.position.y * scale_factor + offset_y;
+    // Capa de nubes alrededor del modelo (tecla C): el mismo modelo un poco más grande,
+    // semitransparente y dibujado después de lo opaco (el material se arma con el ruido
+    // horneado, más arriba)
+    let mut show_clouds = false;
+    let cloud_scale = nalgebra_glm::scaling(&Vec3::repeat(1.08));
 
-        // Nos aseguramos de que los vértices están dentro de los límites de la ventana antes de dibujarlos
-        if x >= 0.0 && x < width as f32 && y >= 0.0 && y < height as f32 {
-            framebuffer.set_current_color(vertex.color);  // Usamos el color del vértice
-            framebuffer.point(x as isize, y as isize);  // Dibujamos el vértice
+    // Pantalla dividida (tecla X): a la izquierda la cámara principal y a la derecha una de
+    // persecución que sigue a la nave. Tab o un click eligen la vista con el foco, que es la
+    // que responde a los controles de cámara (P y +/-)
+    let mut split_screen = false;
+    let mut cameras = [
+        ViewCamera { perspective: false, zoom: 1.0, follow_ship: false, target_offset: Vec3::zeros() },
+        ViewCamera { perspective: false, zoom: 2.0, follow_ship: true, target_offset: Vec3::zeros() },
+    ];
+    let mut focused = 0;
+
+    // Cámara en perspectiva (tecla P) con los planos y el reversed-Z de [camera] en
+    // shaders.toml; apagada se usa la vista ortográfica de siempre. La tecla Z muestra el
+    // z-buffer en lugar del color
+    let mut projection = Projection::from_params(&uniforms.shader_params);
+    let camera_distance = 6.0;
+    let mut show_depth = false;
+
+    // Escáner (tecla V): un círculo en el centro de cada vista, marcado en el stencil, dentro
+    // del cual el modelo se ve con una rampa "térmica"
+    let mut show_scanner = false;
+    let scanner_radius = 140.0;
+    let scanner_material = Material { gradient: Gradient::preset("lava"), reflectivity: 0.0, ..Material::default() };
+
+    // Monitor de seguridad (tecla K): un cuadro en la escena que muestra como textura la
+    // vista de una cámara que orbita la nave, renderizada cada frame en su propio
+    // framebuffer de 256x256. Dentro de esa vista el monitor se ve apagado, así que la
+    // recursión se corta en un nivel
+    let mut show_monitor = false;
+    let monitor_size = 256;
+    let monitor_target = Arc::new(Mutex::new(RenderTarget::new(monitor_size, monitor_size)));
+    let monitor_quad = Arc::new(create_monitor_quad(Vec3::new(-3.7, -2.8, 0.0), 1.6, 1.2));
+    let monitor_bounds = BoundingBox::from_vertices(&monitor_quad);
+    let monitor_off = Material { unlit: true, base_color: Color::new(12, 12, 16), reflectivity: 0.0, ..Material::default() };
+
+    // Shaders de superficie del modelo: las teclas 1-9 eligen uno del registro y la tecla 0
+    // vuelve al color de los vértices
+    let shader_registry = ShaderRegistry::with_builtin_shaders();
+    let number_keys = [Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9];
+
+    let geometry_shaders: [(&str, Option<GeometryShader>); 4] = [
+        ("ninguno", None),
+        ("partículas", Some(shader::point_sprite_geometry_shader)),
+        ("normales", Some(shader::normal_visualizer_geometry_shader)),
+        ("subdivisión", Some(shader::subdivide_geometry_shader)),
+    ];
+    let mut geometry_index = 0;
+
+    // Cajas envolventes de cada objeto de la escena (tecla J; la B ya cambia el
+    // rasterizador): el modelo en amarillo, el monitor en celeste y las rocas en verde si
+    // se dibujan o en rojo si el culling las descarta, que no deberían verse dentro de la vista
+    let mut show_bounds = false;
+    // Normales de los vértices del modelo como segmentos (tecla U; la V ya muestra el
+    // escáner), de largo proporcional al tamaño del modelo
+    let mut show_normals = false;
+    let normal_length = model_radius * 0.08;
+
+    // Mientras la ventana esté abierta y no se presione la tecla ESC
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let dt = clock.tick();
+        uniforms.prev_time = uniforms.time;
+        uniforms.time = clock.time();
+        let frame_start = std::time::Instant::now();
+
+        // Empuje con las flechas (y hacia abajo en pantalla)
+        let mut thrust = Vec3::zeros();
+        for (key, direction) in [
+            (Key::Left, Vec3::new(-1.0, 0.0, 0.0)),
+            (Key::Right, Vec3::new(1.0, 0.0, 0.0)),
+            (Key::Up, Vec3::new(0.0, -1.0, 0.0)),
+            (Key::Down, Vec3::new(0.0, 1.0, 0.0)),
+        ] {
+            if window.is_key_down(key) {
+                thrust += direction * thrust_acceleration;
+            }
+        }
+        ship_state = ship::update(&ship_state, &thrust, &attractors, dt);
+        if ship_state.velocity.norm() > 1e-3 {
+            ship_heading = ship_state.velocity;
         }
-    }
 
-    // Renderizar la ventana con el contenido del framebuffer
-    framebuffer.render_window();
-}
+        // El emisor mira hacia atrás (+y local) en el plano de la nave
+        let backward = if thrust.norm() > 0.0 { -thrust.normalize() } else { -ship_heading.normalize() };
+        let side = Vec3::z().cross(&backward);
+        let tail = ship_state.position + backward * exhaust_offset;
+        let emitter = Mat4::new(
+            side.x, backward.x, 0.0, tail.x,
+            side.y, backward.y, 0.0, tail.y,
+            side.z, backward.z, 1.0, tail.z,
+            0.0,    0.0,        0.0, 1.0,
+        );
+        exhaust.emitter.spawn_rate = if thrust.norm() > 0.0 { exhaust_rate } else { 0.0 };
+        exhaust.update(dt, &emitter, ship_state.velocity);
+        let throttle_target = if thrust.norm() > 0.0 { 1.0 } else { 0.0 };
+        uniforms.throttle += (throttle_target - uniforms.throttle) * (1.0 - (-throttle_response * dt).exp());
+        let plume_model = (uniforms.throttle > 0.01).then(|| ship::plume_matrix(&emitter, uniforms.throttle, plume_length, plume_radius));
+
+        if window.is_key_pressed(Key::X, KeyRepeat::No) {
+            split_screen = !split_screen;
+            focused = 0;
+            camera_cut = true;
+        }
+        if split_screen && window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            focused = (focused + 1) % cameras.len();
+            println!("Vista con el foco: {}", if focused == 0 { "principal" } else { "persecución" });
+        }
+
+        // Controles de la cámara con el foco
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            cameras[focused].perspective = !cameras[focused].perspective;
+            camera_cut = true;
+        }
+        if window.is_key_pressed(Key::Equal, KeyRepeat::Yes) {
+            cameras[focused].zoom = (cameras[focused].zoom * 1.25).min(16.0);
+        }
+        if window.is_key_pressed(Key::Minus, KeyRepeat::Yes) {
+            cameras[focused].zoom = (cameras[focused].zoom / 1.25).max(0.25);
+        }
 
+        if window.is_key_pressed(Key::LeftBracket, KeyRepeat::Yes) {
+            resolution_scaler.pin(Some(resolution_scaler.scale() - 0.05));
+        }
+        if window.is_key_pressed(Key::RightBracket, KeyRepeat::Yes) {
+            resolution_scaler.pin(Some(resolution_scaler.scale() + 0.05));
+        }
+        if window.is_key_pressed(Key::Backslash, KeyRepeat::No) {
+            resolution_scaler.pin(None);
+        }
+        // Todo lo que está en pixeles (vistas, escala ortográfica, escáner, minimapa) se
+        // calcula sobre el framebuffer interno
+        let (render_width, render_height) = resolution_scaler.internal_size(width, height);
+        let render_scale = render_width as f32 / width as f32;
+
+        // Una vista a pantalla completa o dos mitades. Todas comparten el z-buffer, así que
+        // el reversed-Z solo se usa si todas las vistas están en perspectiva
+        let screen = Rect::new(0, 0, render_width, render_height);
+        let rects = if split_screen {
+            let (left, right) = screen.split_vertical();
+            vec![left, right]
+        } else {
+            vec![screen]
+        };
+        let depth_mode = if rects.iter().zip(&cameras).all(|(_, camera)| camera.perspective) {
+            projection.depth_mode
+        } else {
+            DepthMode::Standard
+        };
+        let frame_projection = Projection { depth_mode, ..projection };
+
+        // Tecla F: encuadrar el modelo en la vista con el foco, sea cual sea su escala
+        if window.is_key_pressed(Key::F, KeyRepeat::No) {
+            let model = create_model_matrix(ship_state.position, 1.0, rotation);
+            let sphere = math::transform_sphere(&model, model_center, model_radius);
+            let rect = rects[focused.min(rects.len() - 1)];
+            cameras[focused].frame(sphere, rect, ship_state.position, &frame_projection, scale * render_scale, camera_distance);
+            camera_cut = true;
+        }
+        let viewports: Vec<Viewport> = rects
+            .iter()
+            .zip(&cameras)
+            .map(|(&rect, camera)| camera.viewport(rect, ship_state.position, &frame_projection, scale * render_scale, camera_distance))
+            .collect();
+
+        if window.is_key_pressed(Key::L, KeyRepeat::No) {
+            headlight_on = !headlight_on;
+        }
+        // Las luces se evalúan en pantalla, así que el faro depende de la vista
+        let star_color = Color::from_temperature(uniforms.shader_params.float("star.temperature", 6600.0));
+        let star_light = Light::new(Vec3::new(0.0, 0.0, -1.0), star_color, 1.0);
+        let lights_for = |viewport: &Viewport| {
+            let mut lights = vec![star_light.clone()];
+            if headlight_on {
+                lights.push(create_headlight(&viewport.world_to_screen(), ship_state.position, ship_heading));
+            }
+            lights
+        };
+
+        // La transformación actual pasa a ser la del frame anterior al construir la nueva. Los
+        // uniforms llevan la cámara de la primera vista; las demás se aplican al dibujar
+        let primary = &viewports[0];
+        let previous_model = uniforms.model_matrix;
+        uniforms.next_frame(Matrices {
+            model: create_model_matrix(ship_state.position, 1.0, rotation),
+            view: primary.view_matrix,
+            projection: primary.projection_matrix,
+            viewport: primary.viewport_matrix,
+        });
+        uniforms.lights = lights_for(primary);
+        // Con otra resolución interna la transformación anterior está en otros pixeles: ese
+        // frame sale sin velocidades en lugar de con un salto en el motion blur
+        if (render_width, render_height) != render_size {
+            render_size = (render_width, render_height);
+            uniforms.prev_transformation_matrix = uniforms.transformation_matrix;
+            previous_viewports.clear();
+            previous_monitor = None;
+            camera_cut = true;
+        }
+
+        if let Some(params) = shader_params_watcher.as_ref().and_then(|watcher| watcher.poll()) {
+            println!("shaders.toml recargado");
+            uniforms.ambient = AmbientLight::from_params(&params);
+            projection = Projection::from_params(&params);
+            camera_cut = true;
+            pacer.set_target_fps(params.float("frame.target_fps", 60.0));
+            resolution_scaler.set_target_fps(params.float("frame.resolution_target_fps", 30.0));
+            uniforms.shader_params = params;
+        }
+
+        if window.is_key_pressed(Key::Z, KeyRepeat::No) {
+            show_depth = !show_depth;
+        }
+
+        if window.is_key_pressed(Key::M, KeyRepeat::No) {
+            motion_blur = !motion_blur;
+        }
+
+        if window.is_key_pressed(Key::D, KeyRepeat::No) {
+            depth_of_field = !depth_of_field;
+            println!("Profundidad de campo: {}", if depth_of_field { "encendida" } else { "apagada" });
+        }
+        if window.is_key_pressed(Key::Comma, KeyRepeat::Yes) {
+            focus_shift -= 0.25;
+            println!("Plano focal: {:+.2} desde el foco", focus_shift);
+        }
+        if window.is_key_pressed(Key::Period, KeyRepeat::Yes) {
+            focus_shift += 0.25;
+            println!("Plano focal: {:+.2} desde el foco", focus_shift);
+        }
+        if window.is_key_pressed(Key::Semicolon, KeyRepeat::No) {
+            bloom = !bloom;
+            println!("Bloom: {}", if bloom { "encendido" } else { "apagado" });
+        }
+
+        // Tecla B: rasterizador por Bounding Box -> por scanlines -> por cuadros de 2x2
+        if window.is_key_pressed(Key::B, KeyRepeat::No) {
+            uniforms.rasterizer_mode = match uniforms.rasterizer_mode {
+                RasterizerMode::BoundingBox => RasterizerMode::Scanline,
+                RasterizerMode::Scanline => RasterizerMode::Quads,
+                RasterizerMode::Quads => RasterizerMode::BoundingBox,
+            };
+            println!("Rasterizador: {:?}", uniforms.rasterizer_mode);
+        }
+
+        if window.is_key_pressed(Key::W, KeyRepeat::No) {
+            wireframe = match wireframe {
+                None => Some(LineMode::Aliased),
+                Some(LineMode::Aliased) => Some(LineMode::Antialiased),
+                Some(LineMode::Antialiased) => Some(LineMode::Thick(3.0)),
+                Some(LineMode::Thick(_)) => None,
+            };
+        }
+
+        // Tecla O: exportar el modelo en su pose actual en el mundo (scene.obj) y la geometría
+        // transformada por el vertex shader, en espacio de pantalla (transformed.obj)
+        if window.is_key_pressed(Key::O, KeyRepeat::No) {
+            match obj::export_obj(&vertices, "scene.obj", Some(uniforms.model_matrix)) {
+                Ok(()) => println!("Modelo en el mundo guardado en scene.obj"),
+                Err(e) => eprintln!("No se pudo exportar scene.obj: {}", e),
+            }
+            let transformed: Vec<_> = vertices.iter().map(|v| shader::vertex_shader(v, &uniforms)).collect();
+            match obj::write_transformed_obj("transformed.obj", &transformed, obj::ExportSpace::Screen, render_width, render_height) {
+                Ok(()) => println!("Geometría transformada guardada en transformed.obj"),
+                Err(e) => eprintln!("No se pudo exportar transformed.obj: {}", e),
+            }
+        }
+
+        if window.is_key_pressed(Key::T, KeyRepeat::No) {
+            show_trajectory = !show_trajectory;
+        }
+
+        // Tecla A: ver solo la luz ambiental y de hemisferio, para ajustarlas
+        if window.is_key_pressed(Key::A, KeyRepeat::No) {
+            uniforms.lighting_view = match uniforms.lighting_view {
+                LightingView::Full => LightingView::AmbientOnly,
+                LightingView::AmbientOnly => LightingView::Full,
+            };
+        }
+
+        // Tecla E: ruido de los shaders horneado o evaluado en cada fragmento
+        if window.is_key_pressed(Key::E, KeyRepeat::No) {
+            uniforms.noise_source = match uniforms.noise_source {
+                NoiseSource::Baked => NoiseSource::Live,
+                NoiseSource::Live => NoiseSource::Baked,
+            };
+            println!("Ruido: {:?}", uniforms.noise_source);
+        }
+
+        // Tecla H: iluminación por fragmento (Phong) o por vértice (Gouraud)
+        if window.is_key_pressed(Key::H, KeyRepeat::No) {
+            uniforms.shading_model = match uniforms.shading_model {
+                ShadingModel::Phong => ShadingModel::Gouraud,
+                ShadingModel::Gouraud => ShadingModel::Phong,
+            };
+            println!("Sombreado: {:?}", uniforms.shading_model);
+        }
+
+        if window.is_key_pressed(Key::N, KeyRepeat::No) {
+            show_minimap = !show_minimap;
+        }
+
+        if window.is_key_pressed(Key::I, KeyRepeat::No) {
+            show_belt = !show_belt;
+        }
+
+        if window.is_key_pressed(Key::V, KeyRepeat::No) {
+            show_scanner = !show_scanner;
+        }
+
+        if window.is_key_pressed(Key::C, KeyRepeat::No) {
+            show_clouds = !show_clouds;
+        }
+
+        if window.is_key_pressed(Key::K, KeyRepeat::No) {
+            show_monitor = !show_monitor;
+        }
+
+        if window.is_key_pressed(Key::U, KeyRepeat::No) {
+            show_normals = !show_normals;
+            println!("Normales (U): {}", if show_normals { "visibles" } else { "ocultas" });
+        }
+
+        if window.is_key_pressed(Key::J, KeyRepeat::No) {
+            show_bounds = !show_bounds;
+            println!("Cajas envolventes (J): {}", if show_bounds { "visibles" } else { "ocultas" });
+        }
+
+        // Tecla Q: semilla de la escena al azar, que regenera todo lo procedural
+        if window.is_key_pressed(Key::Q, KeyRepeat::No) {
+            scene_seed = SceneSeed::random();
+            println!("Semilla de la escena: {}", scene_seed.master());
+            let domain_warp = uniforms.domain_warp;
+            // Sin caché en disco: cada semilla al azar dejaría otro horneado de varios MB
+            seed_noise_materials(&scene_seed, &mut uniforms.material, &mut cloud_material, domain_warp, model_bounds.as_ref(), false);
+            uniforms.environment = Some(Arc::new(Cubemap::space(128, scene_seed.shader("stars"))));
+            rock = Arc::new(instancing::rock_mesh(scene_seed.derive("rock"), 0.05));
+            rock_bounds = BoundingBox::from_vertices(&rock);
+            rock_radius = instancing::bounding_radius(&rock);
+            belt = Arc::new(instancing::generate_belt(scene_seed.derive("belt"), belt_count, belt_inner_radius, belt_outer_radius, 0.3));
+            exhaust = particles::ParticleSystem::new(exhaust.emitter.clone(), scene_seed.derive("exhaust"));
+            plume_material = plume_material.with_param("seed", scene_seed.shader("plume") as f32);
+        }
+
+        // Tecla Y: mostrar la semilla actual, para volver a esta escena
+        if window.is_key_pressed(Key::Y, KeyRepeat::No) {
+            println!("Semilla de la escena: {} (para repetirla: --seed {})", scene_seed.master(), scene_seed.master());
+        }
+
+        for (index, &key) in number_keys.iter().enumerate() {
+            if window.is_key_pressed(key, KeyRepeat::No) {
+                if let Some((name, shader)) = shader_registry.get_index(index) {
+                    uniforms.material.fragment_shader = Some(shader);
+                    println!("Shader: {}", name);
+                }
+            }
+        }
+        if window.is_key_pressed(Key::Key0, KeyRepeat::No) {
+            uniforms.material.fragment_shader = None;
+            println!("Shader: color de los vértices");
+        }
+
+        // Tecla G: geometry shader del modelo (ninguno -> partículas -> normales -> subdivisión)
+        if window.is_key_pressed(Key::G, KeyRepeat::No) {
+            geometry_index = (geometry_index + 1) % geometry_shaders.len();
+            let (name, shader) = geometry_shaders[geometry_index];
+            uniforms.pipeline_config.geometry_shader = shader;
+            println!("Geometry shader: {}", name);
+        }
+
+        // Tecla S: anti-aliasing por supersampling 2x
+        if window.is_key_pressed(Key::S, KeyRepeat::No) {
+            uniforms.ssaa_factor = if uniforms.ssaa_factor > 1 { 1 } else { 2 };
+            println!("SSAA: {}x", uniforms.ssaa_factor);
+        }
+
+        if window.is_key_pressed(Key::R, KeyRepeat::No) {
+            if let Err(e) = recorder.toggle() {
+                eprintln!("No se pudo iniciar la grabación: {}", e);
+            }
+        }
+
+        // Recoger el frame que terminó de renderizarse y lanzar el siguiente
+        if let Some(finished) = pipeline.finish() {
+            pipeline.recycle(std::mem::replace(&mut framebuffer, finished));
+        }
+        // Uniforms de cada vista: las matrices y el faro de su cámara. Con la misma
+        // disposición que en el frame anterior, la transformación anterior sale de la cámara
+        // que tenía cada vista
+        let same_layout = previous_viewports.len() == viewports.len();
+        let frame_views: Vec<(Rect, Uniforms)> = viewports
+            .iter()
+            .enumerate()
+            .map(|(index, viewport)| {
+                let mut view_uniforms = match previous_viewports.get(index).filter(|_| same_layout) {
+                    Some(previous) => viewport.apply_with_previous(&uniforms, previous, &previous_model),
+                    None => viewport.apply(&uniforms),
+                };
+                view_uniforms.lights = lights_for(viewport);
+                (viewport.rect, view_uniforms)
+            })
+            .collect();
+        previous_viewports = viewports.clone();
+        // Cámara del monitor: en perspectiva, oscilando alrededor de la nave
+        if !show_monitor {
+            previous_monitor = None;
+        }
+        let frame_monitor = show_monitor.then(|| {
+            let angle = 0.8 + 0.6 * (uniforms.time * 0.3).sin();
+            let view = nalgebra_glm::translation(&Vec3::new(0.0, 0.0, camera_distance))
+                * nalgebra_glm::rotation(angle, &Vec3::y())
+                * nalgebra_glm::translation(&-ship_state.position);
+            let camera = Viewport::perspective(Rect::new(0, 0, monitor_size, monitor_size), view, &projection);
+            let mut monitor_uniforms = match previous_monitor.replace(camera) {
+                Some(previous) => camera.apply_with_previous(&uniforms, &previous, &previous_model),
+                None => camera.apply(&uniforms),
+            };
+            monitor_uniforms.lights = lights_for(&camera);
+            monitor_uniforms.ssaa_factor = 1;
+            (monitor_uniforms, projection.depth_mode)
+        });
+        let frame_monitor_target = Arc::clone(&monitor_target);
+        let frame_taa_history = Arc::clone(&taa_history);
+        let frame_camera_cut = std::mem::take(&mut camera_cut);
+        let frame_auto_exposure = Arc::clone(&auto_exposure);
+        let frame_monitor_quad = Arc::clone(&monitor_quad);
+        let frame_monitor_off = monitor_off.clone();
+        let frame_focus = (split_screen && frame_views.len() > 1).then(|| frame_views[focused].0);
+        let frame_vertices = Arc::clone(&vertices);
+        let outlined = selected == Some(model_id);
+        let frame_rock = Arc::clone(&rock);
+        let frame_belt = Arc::clone(&belt);
+        let frame_belt_drawn = Arc::clone(&belt_drawn);
+        let frame_exhaust = exhaust.clone();
+        let frame_plume = plume_model.map(|model| (model, Arc::clone(&plume), plume_material.clone()));
+        let trajectory = if show_trajectory {
+            ship::predict_trajectory(&ship_state, &attractors, trajectory_horizon, trajectory_steps)
+        } else {
+            Vec::new()
+        };
+        // Distancia del plano focal a lo largo de la vista principal
+        let focus_point = if selected == Some(model_id) {
+            ship_state.position
+        } else {
+            cameras[0].anchor(ship_state.position) + cameras[0].target_offset
+        };
+        let focal_distance = (viewports[0].view_matrix * focus_point.push(1.0)).z + focus_shift;
+        let frame_scanner_material = show_scanner.then(|| scanner_material.clone());
+        let frame_scanner_radius = scanner_radius * render_scale;
+        let frame_cloud_material = show_clouds.then(|| cloud_material.clone());
+        let frame_minimap = show_minimap.then(|| {
+            let bodies: Vec<minimap::MinimapBody> = attractors
+                .iter()
+                .map(|a| minimap::MinimapBody { position: a.position, radius: a.radius, color: Color::new(255, 180, 60) })
+                .collect();
+            // Bordes interior y exterior del cinturón como órbitas
+            let orbits = if show_belt { vec![belt_inner_radius, belt_outer_radius] } else { Vec::new() };
+            let scaled = minimap::Minimap {
+                size: (minimap.size as f32 * render_scale).round() as usize,
+                margin: (minimap.margin as f32 * render_scale).round() as usize,
+                ..minimap.clone()
+            };
+            (scaled, bodies, orbits, ship_state.position, ship_heading)
+        });
+        pipeline.submit(move |framebuffer| {
+            framebuffer.resize(render_width, render_height);
+            framebuffer.set_depth_mode(depth_mode);
+            framebuffer.clear(background_color);
+            let params = &frame_views[0].1.shader_params;
+
+            // Lo que hay en la escena además del modelo; devuelve los asteroides dibujados
+            let draw_scene = |framebuffer: &mut Framebuffer, frame_uniforms: &Uniforms, monitor_screen: Option<&Material>| {
+                let mut belt_drawn = 0;
+                if show_belt {
+                    // Las rocas son mallas cerradas: las caras traseras nunca se ven
+                    let mut belt_uniforms = frame_uniforms.clone();
+                    belt_uniforms.cull_mode = CullMode::Back;
+                    belt_uniforms.front_face = Winding::CounterClockwise;
+                    belt_drawn = instancing::render_instanced(framebuffer, &belt_uniforms, &frame_rock, &frame_belt, rock_min_screen_radius).drawn;
+                }
+
+                // Monitor, nubes y penacho en una cola: lo opaco primero y lo que se mezcla
+                // (nubes, penacho) de atrás hacia adelante
+                let mut queue = RenderQueue::new();
+                if let Some(screen) = monitor_screen {
+                    let mut monitor = frame_uniforms.with_model(Mat4::identity());
+                    monitor.material = screen.clone();
+                    monitor.cull_mode = CullMode::None;
+                    queue.push(monitor, &frame_monitor_quad);
+                }
+
+                if let Some(material) = &frame_cloud_material {
+                    let mut clouds = frame_uniforms.clone();
+                    clouds.model_matrix = frame_uniforms.model_matrix * cloud_scale;
+                    clouds.transformation_matrix = frame_uniforms.transformation_matrix * cloud_scale;
+                    clouds.model_view_matrix = frame_uniforms.model_view_matrix * cloud_scale;
+                    clouds.prev_transformation_matrix = frame_uniforms.prev_transformation_matrix * cloud_scale;
+                    clouds.material = material.clone();
+                    clouds.cull_mode = CullMode::Back;
+                    queue.push(clouds, &frame_vertices);
+                }
+
+                if let Some((model, mesh, material)) = &frame_plume {
+                    let mut plume_uniforms = frame_uniforms.with_model(*model);
+                    plume_uniforms.material = material.clone();
+                    plume_uniforms.cull_mode = CullMode::None;
+                    queue.push(plume_uniforms, mesh);
+                }
+                queue.render(framebuffer);
+
+                particles::render_particles(framebuffer, frame_uniforms, &frame_exhaust);
+                belt_drawn
+            };
+
+            // Render-to-texture: la vista del monitor en su framebuffer, que luego se usa como
+            // textura de la pantalla del monitor en las vistas de la ventana
+            let monitor_screen = frame_monitor.as_ref().map(|(monitor_uniforms, monitor_depth_mode)| {
+                let mut target = frame_monitor_target.lock().unwrap();
+                let monitor_framebuffer = target.framebuffer_mut();
+                monitor_framebuffer.set_depth_mode(*monitor_depth_mode);
+                monitor_framebuffer.clear(background_color);
+                render(monitor_framebuffer, monitor_uniforms, &frame_vertices);
+                draw_scene(monitor_framebuffer, monitor_uniforms, Some(&frame_monitor_off));
+                Material { texture: Some(target.resolve()), ..frame_monitor_off.clone() }
+            });
+
+            // Cada vista se dibuja con el scissor en su rectángulo: ni el color ni la
+            // profundidad de una vista pueden escribirse en la otra
+            for (index, (rect, frame_uniforms)) in frame_views.iter().enumerate() {
+                framebuffer.set_scissor(Some(*rect));
+                let scanner_center = (rect.x as f32 + rect.width as f32 / 2.0, rect.y as f32 + rect.height as f32 / 2.0);
+
+                framebuffer.set_current_id(model_id);
+                match &frame_scanner_material {
+                    // Dentro del círculo (stencil 1) el modelo se ve con el material del escáner
+                    // y fuera con el normal; como no se solapan, comparten el z-buffer
+                    Some(material) => {
+                        let (x, y) = scanner_center;
+                        framebuffer.clear_stencil(0);
+                        framebuffer.stencil_circle(x, y, frame_scanner_radius, 1);
+                        let mut outside = frame_uniforms.clone();
+                        outside.stencil = Some(StencilState::not_equal(1));
+                        let mut scanner = frame_uniforms.clone();
+                        scanner.material = material.clone();
+                        scanner.stencil = Some(StencilState::equal(1));
+                        render(framebuffer, &outside, &frame_vertices);
+                        render(framebuffer, &scanner, &frame_vertices);
+                    }
+                    None => {
+                        render(framebuffer, frame_uniforms, &frame_vertices);
+                    }
+                }
+                framebuffer.set_current_id(0);
+
+                let belt_drawn = draw_scene(framebuffer, frame_uniforms, monitor_screen.as_ref());
+                if index == 0 {
+                    frame_belt_drawn.store(belt_drawn, Ordering::Relaxed);
+                }
+
+                // Contorno de la entidad seleccionada
+                if outlined {
+                    let color = params.color("outline.color", Color::new(255, 200, 0));
+                    let thickness = params.float("outline.thickness", 3.0);
+                    render_outline(framebuffer, frame_uniforms, &frame_vertices, color, thickness);
+                }
+
+                if trajectory.len() > 1 {
+                    let world_matrix = frame_uniforms.viewport_matrix * frame_uniforms.projection_matrix * frame_uniforms.view_matrix;
+                    render_polyline(framebuffer, &world_matrix, &trajectory, Color::new(80, 220, 255), 2.0);
+                }
+
+                // Todas las aristas, o con wireframe.hidden_line las visibles (el polygon
+                // offset evita que peleen con la superficie) y las ocultas punteadas o nada
+                if let Some(mode) = wireframe {
+                    let hidden_line = params.flag("wireframe.hidden_line", false);
+                    let bias = hidden_line.then(|| {
+                        DepthBias::toward_camera(
+                            params.float("wireframe.depth_bias", 8.0),
+                            params.float("wireframe.slope_bias", 1.5),
+                            depth_mode,
+                        )
+                    });
+                    let hidden = (hidden_line && params.flag("wireframe.hidden_dashed", true))
+                        .then(|| Stipple::new(0x0F0F, params.float("wireframe.dash_length", 4.0) / 4.0));
+                    render_wireframe(framebuffer, frame_uniforms, &frame_vertices, mode, bias, hidden);
+                }
+
+                if show_normals {
+                    draw_vertex_normals(framebuffer, frame_uniforms, &frame_vertices, normal_length, Color::new(255, 80, 200));
+                }
+
+                if show_bounds {
+                    if let Some(bounds) = &model_bounds {
+                        draw_aabb(framebuffer, frame_uniforms, bounds, Color::new(255, 220, 0));
+                    }
+                    if let (Some(bounds), true) = (&monitor_bounds, monitor_screen.is_some()) {
+                        draw_aabb(framebuffer, &frame_uniforms.with_model(Mat4::identity()), bounds, Color::new(80, 200, 255));
+                    }
+                    if let (Some(bounds), true) = (&rock_bounds, show_belt) {
+                        // La misma prueba que usa `render_instanced` para descartar cada roca
+                        let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+                        let mut rock_uniforms = frame_uniforms.clone();
+                        for instance in frame_belt.iter() {
+                            rock_uniforms.transformation_matrix = frame_uniforms.transformation_matrix * instance.model_matrix(frame_uniforms.time);
+                            let drawn = instancing::is_sphere_on_screen(&rock_uniforms.transformation_matrix, rock_radius, width, height, rock_min_screen_radius);
+                            let color = if drawn { Color::new(80, 255, 120) } else { Color::new(255, 60, 60) };
+                            draw_aabb(framebuffer, &rock_uniforms, bounds, color);
+                        }
+                    }
+                }
+            }
+            framebuffer.set_scissor(None);
+
+            // Post-procesado en el orden de postprocess.rs: SSAO -> profundidad de campo ->
+            // motion blur -> TAA -> bloom -> exposición automática. La profundidad se linealiza
+            // con las matrices del último draw
+            if params.flag("ssao.enabled", false) {
+                postprocess::apply_ssao(framebuffer, &postprocess::SsaoSettings::from_params(params));
+            }
+
+            if depth_of_field {
+                let settings = postprocess::DofSettings {
+                    focal_distance,
+                    aperture: params.float("depth_of_field.aperture", 6.0),
+                    max_radius: params.float("depth_of_field.max_radius", 8.0),
+                };
+                postprocess::apply_depth_of_field(framebuffer, &settings);
+            }
+
+            if motion_blur {
+                let strength = params.float("motion_blur.strength", 1.0);
+                let samples = params.float("motion_blur.samples", 8.0).max(1.0) as usize;
+                postprocess::apply_motion_blur(framebuffer, strength, samples);
+            }
+
+            // El historial compartido entra al framebuffer solo durante `apply_taa`
+            let mut history = frame_taa_history.lock().unwrap();
+            if params.flag("taa.enabled", false) && !frame_camera_cut {
+                std::mem::swap(&mut framebuffer.history_buffer, &mut history);
+                postprocess::apply_taa(framebuffer, params.float("taa.blend", 0.1));
+                std::mem::swap(&mut framebuffer.history_buffer, &mut history);
+            } else {
+                history.clear();
+            }
+            drop(history);
+
+            if bloom {
+                let settings = postprocess::BloomSettings {
+                    threshold: params.float("bloom.threshold", 0.7),
+                    intensity: params.float("bloom.intensity", 0.8),
+                    radius: params.float("bloom.radius", 6.0).max(0.0) as usize,
+                };
+                postprocess::apply_bloom(framebuffer, &settings);
+            }
+
+            if params.flag("exposure.enabled", false) {
+                let mut exposure = frame_auto_exposure.lock().unwrap();
+                exposure.target_luminance = params.float("exposure.target", 0.3);
+                exposure.adaptation_speed = params.float("exposure.speed", 0.05);
+                exposure.apply(framebuffer);
+            }
+
+            if show_depth {
+                postprocess::visualize_depth(framebuffer);
+            }
+
+            // Borde del escáner en cada vista
+            if frame_scanner_material.is_some() {
+                let samples = (std::f32::consts::TAU * frame_scanner_radius * 2.0) as usize;
+                framebuffer.set_current_color(Color::new(255, 140, 40));
+                for (rect, _) in &frame_views {
+                    framebuffer.set_scissor(Some(*rect));
+                    let (x, y) = (rect.x as f32 + rect.width as f32 / 2.0, rect.y as f32 + rect.height as f32 / 2.0);
+                    for i in 0..samples {
+                        let angle = i as f32 / samples as f32 * std::f32::consts::TAU;
+                        framebuffer.point((x + frame_scanner_radius * angle.cos()) as isize, (y + frame_scanner_radius * angle.sin()) as isize);
+                    }
+                }
+                framebuffer.set_scissor(None);
+            }
+
+            // Con la pantalla dividida, el borde de la vista con el foco
+            if let Some(rect) = frame_focus {
+                let (left, top) = (rect.x as isize, rect.y as isize);
+                let (right, bottom) = (left + rect.width as isize - 1, top + rect.height as isize - 1);
+                framebuffer.set_current_color(Color::new(120, 160, 255));
+                for x in left..=right {
+                    framebuffer.point(x, top);
+                    framebuffer.point(x, bottom);
+                }
+                for y in top..=bottom {
+                    framebuffer.point(left, y);
+                    framebuffer.point(right, y);
+                }
+            }
+
+            // El minimapa va encima de todo, sin prueba de profundidad
+            if let Some((minimap, bodies, orbits, position, heading)) = &frame_minimap {
+                minimap::render_minimap(framebuffer, minimap, bodies, orbits, *position, *heading);
+            }
+        });
+
+        // Click izquierdo: seleccionar la entidad bajo el cursor (o deseleccionar)
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        if mouse_down && !mouse_was_down {
+            if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+                // Del pixel de la ventana al del framebuffer interno
+                let (x, y) = (x * framebuffer.width as f32 / width as f32, y * framebuffer.height as f32 / height as f32);
+                // Con la pantalla dividida, el click también le da el foco a su vista
+                if let Some(index) = viewports.iter().position(|viewport| viewport.contains(x, y)).filter(|_| split_screen) {
+                    focused = index;
+                }
+                selected = framebuffer.get_id(x as isize, y as isize);
+                match selected {
+                    Some(id) if id == model_id => println!(
+                        "Seleccionado: {} (id {}) en posición ({:.1}, {:.1}, {:.1}), escala {}",
+                        model_name, id, ship_state.position.x, ship_state.position.y, ship_state.position.z, scale
+                    ),
+                    Some(id) => println!("Seleccionado: entidad {}", id),
+                    None => println!("Selección vacía"),
+                }
+                if let Some(triangle) = framebuffer.pick(x as isize, y as isize) {
+                    println!("Triángulo bajo el cursor: {}", triangle);
+                }
+            }
+        }
+        mouse_was_down = mouse_down;
+
+        // Estadísticas en el título de la ventana: resolución interna del frame presentado,
+        // semilla de la escena y asteroides dibujados
+        let mut title = format!(
+            "Renderer - resolución {:.0}% ({}x{}{}) - semilla {}",
+            framebuffer.width as f32 / width as f32 * 100.0,
+            framebuffer.width,
+            framebuffer.height,
+            if resolution_scaler.is_pinned() { ", fija" } else { "" },
+            scene_seed.master()
+        );
+        if show_belt {
+            title += &format!(" - asteroides: {} de {}", belt_drawn.load(Ordering::Relaxed), belt.len());
+        }
+        if title != shown_title {
+            window.set_title(&title);
+            shown_title = title;
+        }
+
+        // El frame se amplía al tamaño de la ventana (con la escala 1 es una copia)
+        resolution::upscale_bilinear(framebuffer.present_buffer(), framebuffer.width, framebuffer.height, &mut upscaled, width, height);
+        recorder.capture_pixels(width, height, &upscaled);
+        window.update_with_buffer(&upscaled, width, height).unwrap();
+
+        // El tiempo del frame sin la espera del pacer: lo que de verdad cuesta dibujarlo
+        if let Some(scale) = resolution_scaler.record_frame(frame_start.elapsed().as_secs_f32()) {
+            println!("Resolución dinámica: {:.0}%", scale * 100.0);
+        }
+        pacer.wait();
+    }
+}
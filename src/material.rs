@@ -0,0 +1,196 @@
+// material.rs
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use nalgebra_glm::Vec3;
+use crate::color::{Color, Gradient};
+use crate::noise_bake::BakedNoise;
+use crate::shader::{cloud_fragment_shader, exhaust_fragment_shader, FragmentShader};
+use crate::texture::Texture;
+
+// Cómo se combinan los fragmentos con lo que ya está en el framebuffer. Alpha mezcla
+// según `Fragment::alpha` y no escribe profundidad, para capas semitransparentes (nubes)
+// que se dibujan después de la geometría opaca. Additive suma el color por `alpha` al
+// pixel, para lo que brilla (fuego, escape del motor, corona), y Multiply lo multiplica,
+// para oscurecer lo que hay debajo (sombras pegadas a una superficie); tampoco escriben
+// profundidad. Additive y Multiply dan lo mismo en cualquier orden, así que no necesitan
+// ordenarse como las capas Alpha
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    Opaque,
+    Alpha,
+    Additive,
+    Multiply,
+}
+
+impl BlendMode {
+    // Color resultante de escribir `source` con opacidad `alpha` (0 a 1) sobre `destination`.
+    // Opaque reemplaza el pixel sin mirar `alpha`; Additive satura cada canal en 255 y
+    // Multiply con `alpha` 0 deja el pixel igual
+    pub fn combine(self, source: Color, alpha: f32, destination: Color) -> Color {
+        let alpha = alpha.clamp(0.0, 1.0);
+        match self {
+            BlendMode::Opaque => source,
+            BlendMode::Alpha => source * alpha + destination * (1.0 - alpha),
+            BlendMode::Additive => destination + source * alpha,
+            BlendMode::Multiply => {
+                let factor = |channel: u8| 1.0 - alpha + channel as f32 / 255.0 * alpha;
+                destination.modulate(&Vec3::new(factor(source.r), factor(source.g), factor(source.b)))
+            }
+        }
+    }
+}
+
+// Capa de nubes de un planeta (`cloud_fragment_shader`): `coverage` es la fracción del
+// cielo cubierta (0 a 1), `speed` el desplazamiento del ruido por segundo y `softness` el
+// ancho de la transición del borde de las nubes. `octaves` es cuántas capas de ruido se
+// suman: más octavas dan bordes más detallados a costa de una muestra más por capa
+#[derive(Debug, Clone, Copy)]
+pub struct CloudSettings {
+    pub coverage: f32,
+    pub speed: f32,
+    pub softness: f32,
+    pub octaves: u32,
+}
+
+impl Default for CloudSettings {
+    fn default() -> Self {
+        CloudSettings { coverage: 0.5, speed: 0.05, softness: 0.15, octaves: 5 }
+    }
+}
+
+// Parámetros de superficie de una entidad. Los shaders leen de aquí en lugar de usar
+// constantes propias, así dos entidades pueden compartir shader con distintos valores.
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub two_sided: bool, // Iluminar también las caras traseras (mallas abiertas y delgadas)
+    pub unlit: bool,     // Color plano, sin iluminación (contornos, overlays)
+    pub base_color: Color,
+    pub specular_power: f32,
+    pub rim_color: Color,
+    pub noise_scale: f32,
+    pub emissive_strength: f32,
+    pub reflectivity: f32, // 0 = solo difuso, 1 = espejo que refleja el mapa de entorno
+    pub blend_mode: BlendMode,
+    // Rampa de iluminación: si existe, la intensidad difusa elige el color en la rampa
+    pub gradient: Option<Gradient>,
+    // Textura muestreada con las coordenadas de textura; reemplaza el color de los vértices
+    // y va antes de `fragment_shader`
+    pub texture: Option<Arc<Texture>>,
+    // Ruido precalculado para los shaders de ruido (ver noise_bake.rs); sin él lo evalúan
+    // en cada fragmento
+    pub baked_noise: Option<Arc<BakedNoise>>,
+    // Color de superficie por fragmento (p. ej. `cracked_earth_fragment_shader`); sin él se
+    // usa el color interpolado de los vértices
+    pub fragment_shader: Option<FragmentShader>,
+    // Parámetros propios de cada shader (p. ej. "sea_level"), con valor por defecto en el shader
+    pub params: HashMap<String, f32>,
+}
+
+impl Material {
+    // Parámetro específico de un shader, o `default` si el material no lo define
+    pub fn param(&self, name: &str, default: f32) -> f32 {
+        self.params.get(name).copied().unwrap_or(default)
+    }
+
+    // Material de la capa de nubes: semitransparente, blanco en las nubes densas y gris
+    // claro en las delgadas. Se dibuja sobre una esfera un poco más grande que el planeta
+    pub fn clouds(settings: &CloudSettings) -> Self {
+        Material {
+            base_color: Color::new(255, 255, 255),
+            rim_color: Color::new(200, 205, 215),
+            noise_scale: 3.0,
+            blend_mode: BlendMode::Alpha,
+            fragment_shader: Some(cloud_fragment_shader),
+            ..Material::default()
+        }
+        .with_param("cloud_coverage", settings.coverage)
+        .with_param("cloud_speed", settings.speed)
+        .with_param("cloud_softness", settings.softness)
+        .with_param("noise_octaves", settings.octaves as f32)
+    }
+
+    // Material del escape del motor (`exhaust_fragment_shader`): emisivo y aditivo, blanco
+    // azulado en la tobera y naranja hacia el extremo abierto del cono
+    pub fn exhaust() -> Self {
+        Material {
+            unlit: true,
+            base_color: Color::new(190, 220, 255),
+            rim_color: Color::new(255, 120, 40),
+            noise_scale: 3.0,
+            emissive_strength: 1.0,
+            blend_mode: BlendMode::Additive,
+            fragment_shader: Some(exhaust_fragment_shader),
+            ..Material::default()
+        }
+        .with_param("exhaust_speed", 4.0)
+        .with_param("exhaust_falloff", 1.5)
+    }
+
+    // Define un parámetro específico de un shader
+    pub fn with_param(mut self, name: &str, value: f32) -> Self {
+        self.params.insert(name.to_string(), value);
+        self
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            two_sided: false,
+            unlit: false,
+            base_color: Color::new(200, 200, 200),
+            specular_power: 32.0,
+            rim_color: Color::new(255, 255, 255),
+            noise_scale: 1.0,
+            emissive_strength: 0.0,
+            reflectivity: 0.0,
+            blend_mode: BlendMode::Opaque,
+            gradient: None,
+            texture: None,
+            baked_noise: None,
+            fragment_shader: None,
+            params: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blend_modes_on_a_single_pixel() {
+        let (source, destination) = (Color::new(100, 200, 128), Color::new(200, 100, 40));
+        let cases = [
+            (BlendMode::Opaque, 0.5, Color::new(100, 200, 128)),
+            (BlendMode::Alpha, 0.5, Color::new(150, 150, 84)),
+            (BlendMode::Alpha, 0.0, destination),
+            (BlendMode::Additive, 0.5, Color::new(250, 200, 104)),
+            (BlendMode::Additive, 1.0, Color::new(255, 255, 168)),
+            (BlendMode::Multiply, 1.0, Color::new(78, 78, 20)),
+            (BlendMode::Multiply, 0.5, Color::new(139, 89, 30)),
+            (BlendMode::Multiply, 0.0, destination),
+        ];
+        for (mode, alpha, expected) in cases {
+            assert_eq!(mode.combine(source, alpha, destination).to_u32(), expected.to_u32(), "{mode:?} con alpha {alpha}");
+        }
+    }
+
+    #[test]
+    fn additive_and_multiply_ignore_the_draw_order() {
+        let destination = Color::new(200, 100, 40);
+        let layers = [(Color::new(40, 90, 10), 0.7), (Color::new(120, 30, 60), 0.4), (Color::new(250, 250, 250), 0.2)];
+        for mode in [BlendMode::Additive, BlendMode::Multiply] {
+            let apply = |order: [usize; 3]| order.iter().fold(destination, |pixel, &i| mode.combine(layers[i].0, layers[i].1, pixel));
+            let reference = apply([0, 1, 2]);
+            for order in [[2, 1, 0], [1, 2, 0]] {
+                let pixel = apply(order);
+                // Cada capa redondea a 8 bits por canal: con tres capas puede haber 2 niveles de diferencia
+                for (a, b) in [(pixel.r, reference.r), (pixel.g, reference.g), (pixel.b, reference.b)] {
+                    assert!(a.abs_diff(b) <= 2, "{mode:?} en el orden {order:?}: {pixel} y {reference}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,217 @@
+// math.rs
+
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use crate::uniforms::Winding;
+use crate::vertex::Vertex;
+
+// Extrae los seis planos del frustum de una matriz view-projection (método Gribb-Hartmann).
+// Orden: izquierdo, derecho, inferior, superior, cercano, lejano.
+// Cada plano (a, b, c, d) cumple a*x + b*y + c*z + d >= 0 para los puntos dentro del frustum.
+pub fn extract_frustum_planes(vp: &Mat4) -> [Vec4; 6] {
+    let row = |i: usize| -> Vec4 { vp.row(i).transpose() };
+    let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+    let planes = [
+        r3 + r0, // izquierdo
+        r3 - r0, // derecho
+        r3 + r1, // inferior
+        r3 - r1, // superior
+        r3 + r2, // cercano
+        r3 - r2, // lejano
+    ];
+
+    // Normalizar dividiendo entre la magnitud de la normal
+    planes.map(|plane| {
+        let length = plane.xyz().norm();
+        if length > 0.0 { plane / length } else { plane }
+    })
+}
+
+// Distancia con signo de un punto a un plano normalizado
+fn plane_distance(plane: &Vec4, point: &Vec3) -> f32 {
+    plane.xyz().dot(point) + plane.w
+}
+
+// Una esfera está dentro si no queda completamente detrás de ningún plano
+pub fn is_sphere_inside(planes: &[Vec4; 6], center: Vec3, radius: f32) -> bool {
+    planes.iter().all(|plane| plane_distance(plane, &center) >= -radius)
+}
+
+// Caja alineada con los ejes (AABB)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl BoundingBox {
+    // Las 12 aristas como pares de índices de `corners`: los que difieren en un solo eje
+    pub const EDGES: [(usize, usize); 12] = [
+        (0, 1), (2, 3), (4, 5), (6, 7), // x
+        (0, 2), (1, 3), (4, 6), (5, 7), // y
+        (0, 4), (1, 5), (2, 6), (3, 7), // z
+    ];
+
+    // Caja mínima que contiene los puntos (None si no hay ninguno)
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vec3>) -> Option<Self> {
+        points.into_iter().fold(None, |bounds, point| {
+            Some(match bounds {
+                Some(BoundingBox { min, max }) => BoundingBox { min: min.inf(point), max: max.sup(point) },
+                None => BoundingBox { min: *point, max: *point },
+            })
+        })
+    }
+
+    // Caja de las posiciones (en espacio del modelo) de un array de vértices
+    pub fn from_vertices(vertex_array: &[Vertex]) -> Option<Self> {
+        BoundingBox::from_points(vertex_array.iter().map(|vertex| &vertex.position))
+    }
+
+    // Esquina i: el bit 0 elige x, el 1 y y el 2 z (0 = min, 1 = max)
+    pub fn corners(&self) -> [Vec3; 8] {
+        std::array::from_fn(|i| {
+            Vec3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            )
+        })
+    }
+}
+
+// Una caja está dentro si su vértice más en dirección de cada normal queda delante del plano
+pub fn is_aabb_inside(planes: &[Vec4; 6], min: Vec3, max: Vec3) -> bool {
+    planes.iter().all(|plane| {
+        let positive = Vec3::new(
+            if plane.x >= 0.0 { max.x } else { min.x },
+            if plane.y >= 0.0 { max.y } else { min.y },
+            if plane.z >= 0.0 { max.z } else { min.z },
+        );
+        plane_distance(plane, &positive) >= 0.0
+    })
+}
+
+// Esfera que contiene a otra después de una transformación afín: el centro transformado y
+// el radio por el mayor factor de escala de la matriz (exacta sin escalado no uniforme)
+pub fn transform_sphere(matrix: &Mat4, center: Vec3, radius: f32) -> (Vec3, f32) {
+    let scale = (0..3).map(|i| matrix.fixed_view::<3, 1>(0, i).norm()).fold(0.0, f32::max);
+    ((matrix * center.push(1.0)).xyz(), radius * scale)
+}
+
+// Intersección rayo-triángulo (Möller-Trumbore). Devuelve la distancia `t` a lo largo
+// de `ray_dir` hasta el punto de impacto, o None si el rayo no toca el triángulo.
+pub fn ray_triangle_intersect(ray_origin: Vec3, ray_dir: Vec3, v0: Vec3, v1: Vec3, v2: Vec3) -> Option<f32> {
+    line_triangle_intersect(ray_origin, ray_dir, v0, v1, v2).filter(|&t| t > 1e-7)
+}
+
+// Como `ray_triangle_intersect` pero con la recta completa: `t` también puede ser negativo
+// (impactos detrás de `origin`)
+pub fn line_triangle_intersect(origin: Vec3, direction: Vec3, v0: Vec3, v1: Vec3, v2: Vec3) -> Option<f32> {
+    const EPSILON: f32 = 1e-7;
+
+    let edge1 = v1 - v0;
+    let edge2 = v2 - v0;
+    let h = direction.cross(&edge2);
+    let a = edge1.dot(&h);
+
+    // La recta es paralela al triángulo
+    if a.abs() < EPSILON {
+        return None;
+    }
+
+    let f = 1.0 / a;
+    let s = origin - v0;
+    let u = f * s.dot(&h);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(&edge1);
+    let v = f * direction.dot(&q);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    Some(f * edge2.dot(&q))
+}
+
+// Estima el winding de las caras frontales de una malla cerrada (lista de triángulos en
+// el modelo). Toma hasta `max_samples` triángulos repartidos por la malla y compara su
+// normal geométrica (b - a) x (c - a) con la dirección hacia afuera, del centroide de la
+// malla al del triángulo: si apuntan igual, el triángulo es antihorario visto desde afuera.
+// Devuelve None si no hay una mayoría clara (al menos 2/3 de los votos), p. ej. en mallas
+// abiertas o planas o con winding mezclado, que ningún signo de culling arregla.
+pub fn detect_winding(vertex_array: &[Vertex], max_samples: usize) -> Option<Winding> {
+    let triangle_count = vertex_array.len() / 3;
+    if triangle_count == 0 || max_samples == 0 {
+        return None;
+    }
+    let centroid = vertex_array.iter().fold(Vec3::zeros(), |sum, v| sum + v.position) / vertex_array.len() as f32;
+
+    let step = triangle_count.div_ceil(max_samples).max(1);
+    let (mut counter_clockwise, mut clockwise) = (0usize, 0usize);
+    for triangle in vertex_array.chunks_exact(3).step_by(step) {
+        let (a, b, c) = (triangle[0].position, triangle[1].position, triangle[2].position);
+        let normal = (b - a).cross(&(c - a));
+        let outward = (a + b + c) / 3.0 - centroid;
+        let alignment = normal.dot(&outward);
+        // Triángulos degenerados o de canto respecto al centroide no votan
+        if !alignment.is_finite() || alignment.abs() <= 1e-6 * normal.norm() * outward.norm() {
+            continue;
+        }
+        if alignment > 0.0 {
+            counter_clockwise += 1;
+        } else {
+            clockwise += 1;
+        }
+    }
+
+    let votes = counter_clockwise + clockwise;
+    if votes > 0 && counter_clockwise * 3 >= votes * 2 {
+        Some(Winding::CounterClockwise)
+    } else if votes > 0 && clockwise * 3 >= votes * 2 {
+        Some(Winding::Clockwise)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+
+    // Octaedro unitario con todas las caras antihorarias vistas desde afuera
+    fn octahedron() -> Vec<Vertex> {
+        let mut vertices = Vec::new();
+        for (sx, sy, sz) in [(1.0, 1.0, 1.0), (-1.0, 1.0, 1.0), (1.0, -1.0, 1.0), (1.0, 1.0, -1.0),
+                             (-1.0, -1.0, 1.0), (-1.0, 1.0, -1.0), (1.0, -1.0, -1.0), (-1.0, -1.0, -1.0)] {
+            let (x, y, z) = (Vec3::new(sx, 0.0, 0.0), Vec3::new(0.0, sy, 0.0), Vec3::new(0.0, 0.0, sz));
+            // Un número impar de ejes reflejados invierte el orden
+            let corners = if sx * sy * sz > 0.0 { [x, y, z] } else { [x, z, y] };
+            let normal = Vec3::new(sx, sy, sz).normalize();
+            vertices.extend(corners.map(|corner| Vertex::new(corner, normal, Vec2::zeros())));
+        }
+        vertices
+    }
+
+    fn reversed(vertices: &[Vertex]) -> Vec<Vertex> {
+        vertices.chunks_exact(3).flat_map(|triangle| [triangle[0].clone(), triangle[2].clone(), triangle[1].clone()]).collect()
+    }
+
+    #[test]
+    fn detects_clockwise_and_counter_clockwise_meshes() {
+        let counter_clockwise = octahedron();
+        let clockwise = reversed(&counter_clockwise);
+
+        for max_samples in [1, 3, 8, 100] {
+            assert_eq!(detect_winding(&counter_clockwise, max_samples), Some(Winding::CounterClockwise), "{max_samples} muestras");
+            assert_eq!(detect_winding(&clockwise, max_samples), Some(Winding::Clockwise), "{max_samples} muestras");
+        }
+
+        // Mitad y mitad no da una mayoría clara
+        let mixed: Vec<Vertex> = counter_clockwise[..12].iter().chain(&clockwise[12..]).cloned().collect();
+        assert_eq!(detect_winding(&mixed, 8), None);
+        assert_eq!(detect_winding(&[], 8), None);
+    }
+}
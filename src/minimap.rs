@@ -0,0 +1,170 @@
+// minimap.rs
+//
+// Vista general del sistema en una esquina de la ventana: proyección ortográfica desde
+// arriba del plano de las órbitas (XY en el modelo; Z es la altura fuera del plano).
+// Se dibuja en 2D directamente sobre el framebuffer, después de la escena y sin leer ni
+// escribir el z-buffer.
+
+use nalgebra_glm::{Vec2, Vec3};
+
+use crate::color::Color;
+use crate::edge_function::{covered_in_row_scalar, TriangleSetup};
+use crate::framebuffer::Framebuffer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MinimapCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone)]
+pub struct Minimap {
+    pub corner: MinimapCorner,
+    pub size: usize,   // Lado del recuadro en pixeles
+    pub margin: usize, // Separación con los bordes de la ventana
+    pub extent: f32,   // Radio del sistema (en unidades del modelo) que cabe en el recuadro
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Minimap { corner: MinimapCorner::TopRight, size: 200, margin: 10, extent: 3.5 }
+    }
+}
+
+// Cuerpo del sistema, dibujado como un disco con su color
+#[derive(Debug, Clone)]
+pub struct MinimapBody {
+    pub position: Vec3,
+    pub radius: f32,
+    pub color: Color,
+}
+
+// Recuadro del minimapa dentro del framebuffer: esquina superior izquierda y lado
+struct Inset {
+    x: isize,
+    y: isize,
+    size: isize,
+}
+
+impl Inset {
+    fn contains(&self, x: isize, y: isize) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.size && y < self.y + self.size
+    }
+
+    fn point(&self, framebuffer: &mut Framebuffer, x: isize, y: isize) {
+        if self.contains(x, y) {
+            framebuffer.point(x, y);
+        }
+    }
+}
+
+// Radio en pixeles del disco de un cuerpo: crece con el logaritmo del radio para que un
+// sol y una luna se distingan sin que el sol tape el mapa
+fn disc_radius(radius: f32) -> f32 {
+    2.0 + 2.0 * (1.0 + radius.max(0.0) * 10.0).ln()
+}
+
+// Dibuja el minimapa: fondo oscurecido, órbitas (círculos centrados en el origen con los
+// radios dados), cuerpos y la nave como una flecha en la dirección `ship_heading`
+pub fn render_minimap(
+    framebuffer: &mut Framebuffer,
+    minimap: &Minimap,
+    bodies: &[MinimapBody],
+    orbits: &[f32],
+    ship_position: Vec3,
+    ship_heading: Vec3,
+) {
+    let size = minimap.size.min(framebuffer.width).min(framebuffer.height);
+    if size < 8 {
+        return;
+    }
+    let margin = minimap.margin.min(framebuffer.width - size).min(framebuffer.height - size);
+    let x = match minimap.corner {
+        MinimapCorner::TopLeft | MinimapCorner::BottomLeft => margin,
+        MinimapCorner::TopRight | MinimapCorner::BottomRight => framebuffer.width - size - margin,
+    };
+    let y = match minimap.corner {
+        MinimapCorner::TopLeft | MinimapCorner::TopRight => margin,
+        MinimapCorner::BottomLeft | MinimapCorner::BottomRight => framebuffer.height - size - margin,
+    };
+    let inset = Inset { x: x as isize, y: y as isize, size: size as isize };
+
+    // Del plano de las órbitas a pixeles del recuadro (y hacia abajo, como en la escena)
+    let center = Vec2::new(x as f32 + size as f32 / 2.0, y as f32 + size as f32 / 2.0);
+    let pixels_per_unit = (size as f32 / 2.0 - 4.0) / minimap.extent.max(f32::EPSILON);
+    let to_inset = |p: &Vec3| center + Vec2::new(p.x, p.y) * pixels_per_unit;
+
+    // Fondo: la escena oscurecida, para que el mapa se lea sin taparla del todo
+    for py in inset.y..inset.y + inset.size {
+        for px in inset.x..inset.x + inset.size {
+            let scene = Color::from_u32(framebuffer.get_pixel(px, py).unwrap_or(0));
+            framebuffer.set_current_color(scene.multiply(0.3));
+            framebuffer.point(px, py);
+        }
+    }
+
+    // Borde
+    framebuffer.set_current_color(Color::new(120, 120, 140));
+    for i in 0..inset.size {
+        let last = inset.size - 1;
+        framebuffer.point(inset.x + i, inset.y);
+        framebuffer.point(inset.x + i, inset.y + last);
+        framebuffer.point(inset.x, inset.y + i);
+        framebuffer.point(inset.x + last, inset.y + i);
+    }
+
+    // Órbitas: puntos sobre el círculo, dos por pixel de circunferencia
+    framebuffer.set_current_color(Color::new(70, 70, 90));
+    for &radius in orbits {
+        let radius_px = radius * pixels_per_unit;
+        let samples = ((std::f32::consts::TAU * radius_px * 2.0) as usize).max(8);
+        for i in 0..samples {
+            let angle = i as f32 / samples as f32 * std::f32::consts::TAU;
+            let p = center + Vec2::new(angle.cos(), angle.sin()) * radius_px;
+            inset.point(framebuffer, p.x.round() as isize, p.y.round() as isize);
+        }
+    }
+
+    // Cuerpos
+    for body in bodies {
+        let p = to_inset(&body.position);
+        let r = disc_radius(body.radius);
+        framebuffer.set_current_color(body.color);
+        for py in (p.y - r).floor() as isize..=(p.y + r).ceil() as isize {
+            for px in (p.x - r).floor() as isize..=(p.x + r).ceil() as isize {
+                let (dx, dy) = (px as f32 - p.x, py as f32 - p.y);
+                if dx * dx + dy * dy <= r * r {
+                    inset.point(framebuffer, px, py);
+                }
+            }
+        }
+    }
+
+    // Nave: flecha (triángulo) apuntando en su dirección; hacia arriba si está quieta
+    let heading = Vec2::new(ship_heading.x, ship_heading.y);
+    let forward = if heading.norm() > f32::EPSILON { heading.normalize() } else { Vec2::new(0.0, -1.0) };
+    let side = Vec2::new(-forward.y, forward.x);
+    let tip = to_inset(&ship_position) + forward * 7.0;
+    let back = to_inset(&ship_position) - forward * 4.0;
+    let (left, right) = (back + side * 4.0, back - side * 4.0);
+
+    let corners = [tip, left, right].map(|c| Vec3::new(c.x, c.y, 0.0));
+    if let Some(setup) = TriangleSetup::new(&corners[0], &corners[1], &corners[2]) {
+        let min_x = corners.iter().map(|c| c.x).fold(f32::INFINITY, f32::min).floor() as i32;
+        let max_x = corners.iter().map(|c| c.x).fold(f32::NEG_INFINITY, f32::max).ceil() as i32;
+        let min_y = corners.iter().map(|c| c.y).fold(f32::INFINITY, f32::min).floor() as i32;
+        let max_y = corners.iter().map(|c| c.y).fold(f32::NEG_INFINITY, f32::max).ceil() as i32;
+
+        framebuffer.set_current_color(Color::new(80, 220, 255));
+        let mut covered = Vec::new();
+        for row in min_y..=max_y {
+            covered.clear();
+            covered_in_row_scalar(&setup, row, min_x, max_x, &mut covered);
+            for pixel in &covered {
+                inset.point(framebuffer, pixel.x as isize, row as isize);
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+// noise_bake.rs
+//
+// Ruido precalculado ("horneado") para los shaders de ruido. En lugar de evaluar varias
+// octavas de fBm en cada muestra de cada fragmento, el campo de ruido del shader
+// (`shader::noise_field`, antes del mapeo triplanar) se evalúa una sola vez al cargar la
+// escena en una textura 2D, y el shader la muestrea con filtrado bilineal.
+//
+// La textura cubre un tile cuadrado (`NoiseTile`) que abarca las coordenadas del modelo por
+// `noise_scale`, y el ruido se hornea con una red periódica, así que la textura se repite
+// sin costuras (las nubes, que se desplazan, pueden salir del tile). Dentro del tile el
+// ruido horneado coincide con el evaluado en vivo salvo por el filtrado y el borde del tile.
+//
+// Con domain warping el campo cambia con el tiempo: se hornean varias capas separadas
+// `layer_interval` segundos y se mezclan linealmente, y la animación se vuelve un ciclo de
+// capas * intervalo segundos que pasa de la última capa a la primera.
+//
+// Cada horneado se guarda en disco con un nombre derivado de todos sus parámetros, así al
+// reiniciar se lee en lugar de recalcularse.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use nalgebra_glm::Vec2;
+use rayon::prelude::*;
+
+use crate::material::Material;
+use crate::math::BoundingBox;
+use crate::seed::fnv1a;
+use crate::shader::{noise_field, NoiseSettings, NoiseTile};
+
+const MAGIC: &[u8; 4] = b"NBAK";
+
+#[derive(Debug, Clone)]
+pub struct BakedNoise {
+    settings: NoiseSettings, // Con time = 0; el de cada capa es su índice * layer_interval
+    tile: NoiseTile,
+    size: usize,           // Texels por lado
+    layer_interval: f32,   // Segundos entre capas
+    layers: Vec<Vec<f32>>, // size * size valores por capa, fila por fila
+}
+
+impl BakedNoise {
+    // Evalúa el campo en size x size texels sobre el tile. Sin domain warping el campo no
+    // cambia con el tiempo y se hornea una sola capa
+    pub fn bake(settings: &NoiseSettings, tile: NoiseTile, size: usize, layers: usize, layer_interval: f32) -> Self {
+        let size = size.max(1);
+        let texel = tile.period / size as f32;
+        let layers = (0..layer_count(settings, layers))
+            .map(|layer| {
+                let settings = NoiseSettings { time: layer as f32 * layer_interval, ..*settings };
+                let mut values = vec![0.0; size * size];
+                values.par_chunks_mut(size).enumerate().for_each(|(row, values)| {
+                    let y = tile.origin.y + (row as f32 + 0.5) * texel;
+                    for (column, value) in values.iter_mut().enumerate() {
+                        let x = tile.origin.x + (column as f32 + 0.5) * texel;
+                        *value = noise_field(x, y, &settings, Some(&tile));
+                    }
+                });
+                values
+            })
+            .collect();
+
+        BakedNoise { settings: NoiseSettings { time: 0.0, ..*settings }, tile, size, layer_interval, layers }
+    }
+
+    // Como `bake`, pero primero busca el resultado en `cache_dir` y si no está lo guarda ahí
+    pub fn bake_cached(settings: &NoiseSettings, tile: NoiseTile, size: usize, layers: usize, layer_interval: f32, cache_dir: &Path) -> Self {
+        let size = size.max(1);
+        let header = header(settings, &tile, size, layer_interval, layer_count(settings, layers));
+        let path = cache_dir.join(format!("{:016x}.noise", fnv1a(&header)));
+        if let Ok(baked) = BakedNoise::load(&path, &header) {
+            return baked;
+        }
+
+        let baked = BakedNoise::bake(settings, tile, size, layers, layer_interval);
+        if let Err(error) = fs::create_dir_all(cache_dir).and_then(|_| baked.save(&path, &header)) {
+            eprintln!("No se pudo guardar el ruido horneado en {}: {}", path.display(), error);
+        }
+        baked
+    }
+
+    // Horneado del campo `settings` para un modelo con caja `bounds` y el material que lo
+    // va a usar: "noise_bake_size" texels por lado (512) y, si el campo está animado,
+    // "noise_bake_layers" capas (3) cada "noise_bake_interval" segundos (4). Con `cache_dir`
+    // pasa por la caché en disco
+    pub fn for_material(settings: &NoiseSettings, material: &Material, bounds: &BoundingBox, cache_dir: Option<&Path>) -> Self {
+        let tile = tile_for_bounds(bounds, material.noise_scale, settings.warp);
+        let size = material.param("noise_bake_size", 512.0).clamp(16.0, 4096.0) as usize;
+        let layers = material.param("noise_bake_layers", 3.0).clamp(1.0, 8.0) as usize;
+        let layer_interval = material.param("noise_bake_interval", 4.0).max(0.1);
+        match cache_dir {
+            Some(dir) => BakedNoise::bake_cached(settings, tile, size, layers, layer_interval, dir),
+            None => BakedNoise::bake(settings, tile, size, layers, layer_interval),
+        }
+    }
+
+    // Si se horneó el mismo campo que describe `settings` (el tiempo no cuenta)
+    pub fn matches(&self, settings: &NoiseSettings) -> bool {
+        self.settings.same_field(settings)
+    }
+
+    pub fn tile(&self) -> NoiseTile {
+        self.tile
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    // Valor del campo en (x, y) en el instante `time`, con filtrado bilineal y repitiendo
+    // el tile fuera de él
+    pub fn sample(&self, x: f32, y: f32, time: f32) -> f32 {
+        let count = self.layers.len();
+        if count == 1 || !time.is_finite() {
+            return self.sample_layer(0, x, y);
+        }
+        let position = (time / self.layer_interval).rem_euclid(count as f32);
+        let layer = position as usize % count;
+        let t = position - position.floor();
+        let current = self.sample_layer(layer, x, y);
+        current + (self.sample_layer((layer + 1) % count, x, y) - current) * t
+    }
+
+    fn sample_layer(&self, layer: usize, x: f32, y: f32) -> f32 {
+        let scale = self.size as f32 / self.tile.period;
+        // Centros de los texels en origin + (i + 0.5) * period / size
+        let u = (x - self.tile.origin.x) * scale - 0.5;
+        let v = (y - self.tile.origin.y) * scale - 0.5;
+        if !u.is_finite() || !v.is_finite() {
+            return 0.5;
+        }
+
+        let (u0, v0) = (u.floor(), v.floor());
+        let (tu, tv) = (u - u0, v - v0);
+        let wrap = |i: f32| (i as i64).rem_euclid(self.size as i64) as usize;
+        let (x0, x1, y0, y1) = (wrap(u0), wrap(u0 + 1.0), wrap(v0), wrap(v0 + 1.0));
+
+        let values = &self.layers[layer];
+        let texel = |x: usize, y: usize| values[y * self.size + x];
+        let top = texel(x0, y0) + (texel(x1, y0) - texel(x0, y0)) * tu;
+        let bottom = texel(x0, y1) + (texel(x1, y1) - texel(x0, y1)) * tu;
+        top + (bottom - top) * tv
+    }
+
+    // Archivo: la cabecera (parámetros del horneado) y después los valores de cada capa en
+    // f32 little endian
+    fn save(&self, path: &Path, header: &[u8]) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(header.len() + self.layers.len() * self.size * self.size * 4);
+        bytes.extend_from_slice(header);
+        for value in self.layers.iter().flatten() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        fs::write(path, bytes)
+    }
+
+    // Lee un horneado guardado; falla si no existe o si su cabecera no es `header`
+    fn load(path: &Path, header: &[u8]) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let Some(data) = bytes.strip_prefix(header) else { return Err(invalid("parámetros distintos")) };
+
+        // Campos de la cabecera, en el orden de `header`
+        let word = |index: usize| u32::from_le_bytes(header[8 + index * 4..12 + index * 4].try_into().expect("4 bytes"));
+        let float = |index: usize| f32::from_bits(word(index));
+        let settings = NoiseSettings { octaves: word(0), lacunarity: float(1), gain: float(2), seed: word(3), warp: float(4), time: 0.0 };
+        let tile = NoiseTile { origin: Vec2::new(float(5), float(6)), period: float(7) };
+        let (size, layer_interval, count) = (word(8) as usize, float(9), word(10) as usize);
+        if data.len() != count * size * size * 4 {
+            return Err(invalid("tamaño de datos incorrecto"));
+        }
+
+        let values: Vec<f32> = data.chunks_exact(4).map(|chunk| f32::from_le_bytes(chunk.try_into().expect("4 bytes"))).collect();
+        let layers = values.chunks_exact(size * size).map(|layer| layer.to_vec()).collect();
+        Ok(BakedNoise { settings, tile, size, layer_interval, layers })
+    }
+}
+
+// Tile que cubre las tres proyecciones del mapeo triplanar de un modelo con caja `bounds`
+// y el ruido a `noise_scale`, más `margin` de cada lado (el desplazamiento máximo del domain
+// warping). Las coordenadas se redondean a enteros para que la red periódica coincida con
+// la del ruido en vivo, y se agrega una unidad al final: ahí la red de la primera octava
+// vuelve al principio del tile y el ruido ya no coincide
+pub fn tile_for_bounds(bounds: &BoundingBox, noise_scale: f32, margin: f32) -> NoiseTile {
+    let (a, b) = (bounds.min * noise_scale, bounds.max * noise_scale);
+    let low = a.min().min(b.min()) - margin.abs();
+    let high = a.max().max(b.max()) + margin.abs();
+    let origin = low.floor();
+    NoiseTile { origin: Vec2::repeat(origin), period: high.ceil() - origin + 1.0 }
+}
+
+fn layer_count(settings: &NoiseSettings, layers: usize) -> usize {
+    if settings.warp == 0.0 { 1 } else { layers.max(1) }
+}
+
+// Cabecera de un archivo de caché: todos los parámetros del horneado, de los que también
+// sale el nombre del archivo
+fn header(settings: &NoiseSettings, tile: &NoiseTile, size: usize, layer_interval: f32, layer_count: usize) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    // Versión del formato; la 2 hornea el domain warping con el desplazamiento de la segunda
+    // componente y la 3 con las semillas del material, así los horneados anteriores en la
+    // caché se vuelven a calcular
+    bytes.extend_from_slice(&3u32.to_le_bytes());
+    for value in [
+        settings.octaves,
+        settings.lacunarity.to_bits(),
+        settings.gain.to_bits(),
+        settings.seed,
+        settings.warp.to_bits(),
+        tile.origin.x.to_bits(),
+        tile.origin.y.to_bits(),
+        tile.period.to_bits(),
+        size as u32,
+        layer_interval.to_bits(),
+        layer_count as u32,
+    ] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
@@ -1,6 +1,10 @@
 // obj.rs
 use tobj;
-use nalgebra_glm::{Vec2, Vec3};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use nalgebra_glm::{Mat4, Vec2, Vec3};
+use crate::uniforms::normal_matrix;
 use crate::vertex::Vertex;
 
 pub struct Obj {
@@ -8,47 +12,292 @@ pub struct Obj {
     pub normals: Vec<Vec3>,   // Hacer público
     pub texcoords: Vec<Vec2>, // Hacer público
     pub indices: Vec<u32>,    // Hacer público
+    // Grupos `g`/`o` y grupos de suavizado `s` como rangos de `indices` (que coinciden con
+    // los rangos de `get_vertex_array`). Los triángulos sin grupo de suavizado tienen el 0.
+    pub groups: Vec<(String, Range<usize>)>,
+    pub smoothing_groups: Vec<(u32, Range<usize>)>,
+    // Segundo juego de posiciones/normales para animación por morph target
+    pub morph_vertices: Option<Vec<Vec3>>,
+    pub morph_normals: Option<Vec<Vec3>>,
 }
 
+// Eje que apunta hacia arriba en el archivo. El renderer usa Y hacia arriba; los modelos
+// exportados con Z hacia arriba se rotan al cargarlos: (x, y, z) -> (x, z, -y)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpAxis {
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    // Convierte un vector del sistema del archivo al sistema Y-up del renderer
+    pub fn to_y_up(&self, v: &Vec3) -> Vec3 {
+        match self {
+            UpAxis::Y => *v,
+            UpAxis::Z => Vec3::new(v.x, v.z, -v.y),
+        }
+    }
+}
+
+// Opciones de carga del OBJ
+#[derive(Debug, Clone, Copy)]
+pub struct ObjLoadOptions {
+    // Convierte caras de N vértices en N-2 triángulos (abanico desde el primer vértice).
+    // Si es false, las caras que no son triángulos se descartan.
+    pub triangulate: bool,
+    // Para quads, dividir por la diagonal más corta en lugar de usar el abanico
+    pub triangulate_quads_by_min_diagonal: bool,
+    // Eje hacia arriba del archivo (se convierte a Y-up al cargar)
+    pub up_axis: UpAxis,
+}
+
+impl Default for ObjLoadOptions {
+    fn default() -> Self {
+        ObjLoadOptions {
+            triangulate: true,
+            triangulate_quads_by_min_diagonal: false,
+            up_axis: UpAxis::Y,
+        }
+    }
+}
+
+// Error de sintaxis en una línea del OBJ. `line` y `col` empiezan en 1 (la columna es la
+// del valor que falla, en caracteres) y `context` es el texto de la línea, para mostrarla
+// con una marca debajo de esa columna
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+    pub context: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // La marca copia los tabs de la línea para quedar alineada con ella
+        let padding: String = self
+            .context
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .chain(std::iter::repeat(' '))
+            .take(self.col.saturating_sub(1))
+            .collect();
+        write!(f, "línea {}, columna {}: {}\n    {}\n    {}^", self.line, self.col, self.message, self.context, padding)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Error al cargar un modelo. Los errores de sintaxis y las funciones no soportadas
+// indican la línea del archivo (empezando en 1) donde ocurrieron
+#[derive(Debug)]
+pub enum LoadError {
+    IoError(std::io::Error),
+    ParseError(ParseError),
+    // Un error de tobj que la validación previa no detectó (sin línea)
+    TobjError(tobj::LoadError),
+    UnsupportedFeature { line: usize, feature: String },
+    // Un morph target con un número de vértices distinto al de la malla base
+    TopologyMismatch { expected: usize, found: usize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::IoError(e) => write!(f, "error de lectura: {}", e),
+            LoadError::ParseError(e) => write!(f, "{}", e),
+            LoadError::TobjError(e) => write!(f, "{}", e),
+            LoadError::UnsupportedFeature { line, feature } => {
+                write!(f, "línea {}: `{}` no está soportado", line, feature)
+            }
+            LoadError::TopologyMismatch { expected, found } => {
+                write!(f, "el morph target tiene {} vértices, se esperaban {}", found, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::IoError(e) => Some(e),
+            LoadError::ParseError(e) => Some(e),
+            LoadError::TobjError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LoadError {
+    fn from(e: std::io::Error) -> Self {
+        LoadError::IoError(e)
+    }
+}
 
 impl Obj {
-    pub fn load(filename: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _) = tobj::load_obj(filename, &tobj::LoadOptions {
-            single_index: true,
-            triangulate: true, // Asegura que las caras cuadradas se conviertan en triángulos
-            ..Default::default()
-        })?;
+    pub fn load(filename: &str) -> Result<Self, LoadError> {
+        Obj::load_with_options(filename, &ObjLoadOptions::default())
+    }
+
+    pub fn load_with_options(filename: &str, options: &ObjLoadOptions) -> Result<Self, LoadError> {
+        let contents = std::fs::read_to_string(filename)?;
+        Obj::from_source_with_options(&contents, options)
+    }
+
+    // Igual que `load`, pero lee el archivo mapeándolo en memoria en lugar de copiarlo a un
+    // String: el texto se valida y se parsea directamente desde las páginas del archivo.
+    // Para modelos grandes evita la copia completa al heap, aunque casi todo el tiempo de
+    // carga es el parseo (ver examples/obj_load_bench.rs). Un archivo que no es UTF-8 da el
+    // mismo error de lectura que `load`.
+    //
+    // Precondición: nadie puede modificar ni truncar el archivo mientras se carga. Si otro
+    // proceso lo cambia, el texto ya validado como UTF-8 cambia debajo del `&str`
+    // (comportamiento indefinido), y si lo trunca, leer las páginas que ya no existen mata
+    // el proceso con SIGBUS. Usar solo con archivos que la aplicación controla; si no, `load`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_mmap(filename: &str) -> Result<Self, LoadError> {
+        Obj::load_mmap_with_options(filename, &ObjLoadOptions::default())
+    }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_mmap_with_options(filename: &str, options: &ObjLoadOptions) -> Result<Self, LoadError> {
+        let file = std::fs::File::open(filename)?;
+        // SAFETY: válido solo si el archivo no se modifica ni se trunca mientras `map` vive
+        // (durante esta carga); el llamador lo garantiza según la precondición de `load_mmap`
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        let source = std::str::from_utf8(&map).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Obj::from_source_with_options(source, options)
+    }
+
+    // Carga un OBJ desde su texto (p. ej. incluido con `include_str!` donde no hay sistema
+    // de archivos, como en WebAssembly). Los `mtllib` se ignoran.
+    pub fn from_source(source: &str) -> Result<Self, LoadError> {
+        Obj::from_source_with_options(source, &ObjLoadOptions::default())
+    }
+
+    pub fn from_source_with_options(source: &str, options: &ObjLoadOptions) -> Result<Self, LoadError> {
+        // tobj no dice en qué línea falla: se valida antes para poder reportarla. La misma
+        // pasada recoge los grupos de suavizado, que tobj no expone
+        let face_groups = validate_source(source)?;
+
+        // La triangulación se hace aquí (no en tobj) para poder elegir la estrategia
+        let (models, _) = tobj::load_obj_buf(&mut source.as_bytes(), &Obj::tobj_options(), |_| {
+            Err(tobj::LoadError::OpenFileFailed)
+        })
+        .map_err(LoadError::TobjError)?;
+        Ok(Obj::from_models(&models, &face_groups, options))
+    }
+
+    fn tobj_options() -> tobj::LoadOptions {
+        tobj::LoadOptions { single_index: true, triangulate: false, ..Default::default() }
+    }
+
+    // `face_groups`: grupo de suavizado de cada cara del archivo (vacío si no declara grupos)
+    fn from_models(models: &[tobj::Model], face_groups: &[u32], options: &ObjLoadOptions) -> Self {
         // Verifica que haya al menos un modelo cargado
         if models.is_empty() {
             println!("no hay modelo")
         }
 
-        let mesh = &models[0].mesh;
+        // tobj separa un modelo por cada `o`/`g`: se juntan en una sola malla y cada
+        // modelo queda registrado como un grupo con nombre
+        let mesh = merge_models(models);
 
         println!("Number of vertices: {}", mesh.positions.len() / 3);
         println!("Number of indices: {}", mesh.indices.len());
 
-        let vertices: Vec<Vec3> = mesh.positions.chunks(3)
+        let mut vertices: Vec<Vec3> = mesh.positions.chunks(3)
             .map(|v| Vec3::new(v[0], v[1], v[2]))
             .collect();
 
-        let normals: Vec<Vec3> = mesh.normals.chunks(3)
-            .map(|n| Vec3::new(n[0], n[1], n[2]))
-            .collect();
+        let (mut indices, triangle_faces) = triangulate_faces(&vertices, &mesh.indices, &mesh.face_arities, options);
 
-        let texcoords: Vec<Vec2> = mesh.texcoords.chunks(2)
+        // Las caras `f v` o `f v//vn` no traen UVs; `get_vertex_array` usa (0, 0) por defecto
+        let mut texcoords: Vec<Vec2> = mesh.texcoords.chunks(2)
             .map(|t| Vec2::new(t[0], t[1]))
             .collect();
 
-        let indices = mesh.indices.clone();
+        // Grupo de suavizado de cada triángulo (vacío si el archivo no declara grupos)
+        let triangle_groups: Vec<u32> = if face_groups.is_empty() {
+            Vec::new()
+        } else {
+            triangle_faces
+                .iter()
+                .map(|&face| face_groups.get(face).copied().unwrap_or(0))
+                .collect()
+        };
+
+        // Caras `f v` o `f v/vt` no traen normales: se calculan normales suaves,
+        // respetando los grupos de suavizado (`s`) si el archivo los declara
+        let mut normals: Vec<Vec3> = mesh.normals.chunks(3)
+            .map(|n| Vec3::new(n[0], n[1], n[2]))
+            .collect();
+        if normals.len() != vertices.len() {
+            if triangle_groups.is_empty() {
+                normals = compute_smooth_normals(&vertices, &indices);
+            } else {
+                let grouped = compute_grouped_normals(&vertices, &texcoords, &indices, &triangle_groups);
+                (vertices, texcoords, indices, normals) = grouped;
+            }
+        }
+
+        // Cambio de base al sistema Y-up del renderer (una rotación: no cambia el winding)
+        if options.up_axis != UpAxis::Y {
+            vertices.iter_mut().for_each(|v| *v = options.up_axis.to_y_up(v));
+            normals.iter_mut().for_each(|n| *n = options.up_axis.to_y_up(n));
+        }
+
+        // Rangos de `indices` por grupo; el orden de los triángulos no cambia al separar vértices
+        let triangle_models: Vec<usize> = triangle_faces
+            .iter()
+            .map(|&face| mesh.face_models.partition_point(|&first_face| first_face <= face) - 1)
+            .collect();
+        let groups = index_ranges(&triangle_models)
+            .into_iter()
+            .map(|(model, range)| (models[model].name.clone(), range))
+            .collect();
+        let smoothing_groups = index_ranges(&triangle_groups);
 
-        Ok(Obj {
+        Obj {
             vertices,
             normals,
             texcoords,
             indices,
-        })
+            groups,
+            smoothing_groups,
+            morph_vertices: None,
+            morph_normals: None,
+        }
+    }
+
+    // Carga un morph target: otro OBJ con la misma topología (mismo número de vértices)
+    pub fn load_morph_target(&mut self, filename: &str) -> Result<(), LoadError> {
+        self.load_morph_target_with_options(filename, &ObjLoadOptions::default())
+    }
+
+    // Igual que `load_morph_target`, con las mismas opciones usadas para la malla base
+    pub fn load_morph_target_with_options(&mut self, filename: &str, options: &ObjLoadOptions) -> Result<(), LoadError> {
+        let target = Obj::load_with_options(filename, options)?;
+        if target.vertices.len() != self.vertices.len() {
+            return Err(LoadError::TopologyMismatch { expected: self.vertices.len(), found: target.vertices.len() });
+        }
+
+        self.morph_vertices = Some(target.vertices);
+        self.morph_normals = Some(target.normals);
+        Ok(())
+    }
+
+    // Esfera que contiene todos los vértices: centro en el del bounding box y radio hasta el
+    // vértice más lejano. No es la mínima, pero para cajas y mallas simétricas coincide con
+    // ella. Sin vértices es un punto en el origen
+    pub fn bounding_sphere(&self) -> (Vec3, f32) {
+        let Some(first) = self.vertices.first() else {
+            return (Vec3::zeros(), 0.0);
+        };
+        let (min, max) = self.vertices.iter().fold((*first, *first), |(min, max), v| (min.inf(v), max.sup(v)));
+        let center = (min + max) / 2.0;
+        let radius = self.vertices.iter().map(|v| (v - center).norm()).fold(0.0, f32::max);
+        (center, radius)
     }
 
     pub fn get_vertex_array(&self) -> Vec<Vertex> {
@@ -70,10 +319,577 @@ impl Obj {
                 .unwrap_or(Vec2::new(0.0, 0.0)); // Coordenadas de textura por defecto
 
             // Crear el vértice y añadirlo al array
-            vertices.push(Vertex::new(position, normal, tex_coords));
+            let mut vertex = Vertex::new(position, normal, tex_coords);
+
+            // Posición y normal del morph target, si hay uno cargado
+            if let Some(morph_vertices) = &self.morph_vertices {
+                vertex.morph_position = morph_vertices[index as usize];
+            }
+            if let Some(morph_normal) = self.morph_normals.as_ref().and_then(|normals| normals.get(index as usize)) {
+                vertex.morph_normal = *morph_normal;
+            }
+
+            vertices.push(vertex);
         }
 
         vertices
     }
 
-}
\ No newline at end of file
+}
+
+// Espacio en el que se exportan los vértices transformados
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportSpace {
+    Screen, // Pixeles, tal como los usa el rasterizador
+    Ndc,    // [-1, 1] en x/y (y hacia arriba) según el tamaño del viewport
+}
+
+// Escribe los vértices transformados (la salida del vertex shader) como un OBJ para
+// inspeccionarlos en otra herramienta. Cada grupo de 3 vértices es una cara.
+pub fn write_transformed_obj(
+    path: &str,
+    vertices: &[Vertex],
+    space: ExportSpace,
+    viewport_width: usize,
+    viewport_height: usize,
+) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(out, "# Vértices transformados ({:?}, viewport {}x{})", space, viewport_width, viewport_height)?;
+
+    for vertex in vertices {
+        let p = vertex.transformed_position;
+        let p = match space {
+            ExportSpace::Screen => p,
+            ExportSpace::Ndc => Vec3::new(
+                p.x / viewport_width as f32 * 2.0 - 1.0,
+                1.0 - p.y / viewport_height as f32 * 2.0,
+                p.z,
+            ),
+        };
+        writeln!(out, "v {} {} {}", p.x, p.y, p.z)?;
+    }
+    for vertex in vertices {
+        let n = vertex.transformed_normal;
+        writeln!(out, "vn {} {} {}", n.x, n.y, n.z)?;
+    }
+
+    // Los índices de OBJ empiezan en 1
+    for face in 0..vertices.len() / 3 {
+        let (a, b, c) = (face * 3 + 1, face * 3 + 2, face * 3 + 3);
+        writeln!(out, "f {a}//{a} {b}//{b} {c}//{c}")?;
+    }
+
+    out.flush()
+}
+
+// Escribe un array de vértices (cada 3 vértices una cara, como los de `get_vertex_array`)
+// como OBJ con posiciones, coordenadas de textura y normales. Con `apply_transform` las
+// posiciones pasan por esa matriz de mundo y las normales por su inversa transpuesta, para
+// exportar la pose de la escena en ese momento; sin ella se escribe la malla tal cual.
+// Para la salida del vertex shader (pantalla o NDC) ver `write_transformed_obj`
+pub fn export_obj(vertex_array: &[Vertex], path: &str, apply_transform: Option<Mat4>) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let transform = apply_transform.unwrap_or_else(Mat4::identity);
+    let normals = normal_matrix(&transform);
+
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(out, "# {} triángulos{}", vertex_array.len() / 3, if apply_transform.is_some() { " en espacio del mundo" } else { "" })?;
+
+    for vertex in vertex_array {
+        let p = (transform * vertex.position.push(1.0)).xyz();
+        writeln!(out, "v {} {} {}", p.x, p.y, p.z)?;
+    }
+    for vertex in vertex_array {
+        writeln!(out, "vt {} {}", vertex.tex_coords.x, vertex.tex_coords.y)?;
+    }
+    for vertex in vertex_array {
+        let n = normals * vertex.normal;
+        let n = if n.norm() > 0.0 { n.normalize() } else { n };
+        writeln!(out, "vn {} {} {}", n.x, n.y, n.z)?;
+    }
+
+    // Un vértice sobrante (array que no es múltiplo de 3) no forma cara y se ignora
+    for face in 0..vertex_array.len() / 3 {
+        let (a, b, c) = (face * 3 + 1, face * 3 + 2, face * 3 + 3);
+        writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+    }
+
+    out.flush()
+}
+
+// Malla resultante de juntar todos los modelos de tobj
+struct MergedMesh {
+    positions: Vec<f32>,
+    normals: Vec<f32>,
+    texcoords: Vec<f32>,
+    indices: Vec<u32>,
+    face_arities: Vec<u32>,
+    // Primera cara de cada modelo
+    face_models: Vec<usize>,
+}
+
+fn merge_models(models: &[tobj::Model]) -> MergedMesh {
+    let mut merged = MergedMesh {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        texcoords: Vec::new(),
+        indices: Vec::new(),
+        face_arities: Vec::new(),
+        face_models: Vec::new(),
+    };
+
+    // Normales y UVs solo se conservan si todos los modelos los traen
+    let all_normals = models.iter().all(|m| m.mesh.normals.len() == m.mesh.positions.len());
+    let all_texcoords = models.iter().all(|m| m.mesh.texcoords.len() / 2 == m.mesh.positions.len() / 3);
+
+    for model in models {
+        let mesh = &model.mesh;
+        let offset = (merged.positions.len() / 3) as u32;
+
+        merged.face_models.push(merged.face_arities.len());
+        merged.positions.extend_from_slice(&mesh.positions);
+        if all_normals {
+            merged.normals.extend_from_slice(&mesh.normals);
+        }
+        if all_texcoords {
+            merged.texcoords.extend_from_slice(&mesh.texcoords);
+        }
+        merged.indices.extend(mesh.indices.iter().map(|&index| index + offset));
+
+        // Sin aridades, todas las caras del modelo son triángulos
+        if mesh.face_arities.is_empty() {
+            merged.face_arities.extend(std::iter::repeat_n(3, mesh.indices.len() / 3));
+        } else {
+            merged.face_arities.extend_from_slice(&mesh.face_arities);
+        }
+    }
+
+    merged
+}
+
+// Agrupa valores consecutivos iguales (uno por triángulo) en rangos de índices
+fn index_ranges<T: Copy + PartialEq>(per_triangle: &[T]) -> Vec<(T, Range<usize>)> {
+    let mut ranges: Vec<(T, Range<usize>)> = Vec::new();
+    for (triangle, &value) in per_triangle.iter().enumerate() {
+        match ranges.last_mut() {
+            Some((last, range)) if *last == value => range.end = (triangle + 1) * 3,
+            _ => ranges.push((value, triangle * 3..(triangle + 1) * 3)),
+        }
+    }
+    ranges
+}
+
+// Convierte las caras (de aridad variable) en una lista de triángulos. También devuelve,
+// por cada triángulo, el índice de la cara del archivo de la que proviene.
+fn triangulate_faces(vertices: &[Vec3], indices: &[u32], face_arities: &[u32], options: &ObjLoadOptions) -> (Vec<u32>, Vec<usize>) {
+    // Sin aridades, tobj garantiza que todas las caras son triángulos
+    if face_arities.is_empty() {
+        return (indices.to_vec(), (0..indices.len() / 3).collect());
+    }
+
+    let mut triangles = Vec::with_capacity(indices.len());
+    let mut triangle_faces = Vec::with_capacity(indices.len() / 3);
+    let mut start = 0;
+    for (face_index, &arity) in face_arities.iter().enumerate() {
+        let face = &indices[start..start + arity as usize];
+        start += arity as usize;
+        let first_triangle = triangles.len();
+
+        match face.len() {
+            0..=2 => {}
+            3 => triangles.extend_from_slice(face),
+            _ if !options.triangulate => {}
+            4 if options.triangulate_quads_by_min_diagonal => {
+                // Dividir por la diagonal más corta (evita triángulos largos y delgados)
+                let diagonal_02 = (vertices[face[2] as usize] - vertices[face[0] as usize]).norm();
+                let diagonal_13 = (vertices[face[3] as usize] - vertices[face[1] as usize]).norm();
+                if diagonal_02 <= diagonal_13 {
+                    triangles.extend_from_slice(&[face[0], face[1], face[2], face[0], face[2], face[3]]);
+                } else {
+                    triangles.extend_from_slice(&[face[0], face[1], face[3], face[1], face[2], face[3]]);
+                }
+            }
+            _ => {
+                // Abanico desde el primer vértice: N-2 triángulos
+                for i in 1..face.len() - 1 {
+                    triangles.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                }
+            }
+        }
+
+        let added = (triangles.len() - first_triangle) / 3;
+        triangle_faces.extend(std::iter::repeat_n(face_index, added));
+    }
+
+    (triangles, triangle_faces)
+}
+
+// Geometría de forma libre (curvas y superficies), que el renderer no sabe dibujar
+const FREE_FORM_KEYWORDS: &[&str] = &[
+    "cstype", "deg", "bmat", "step", "curv", "curv2", "surf", "parm", "trim", "hole", "scrv", "sp", "end", "con",
+];
+
+// Revisa el texto del OBJ línea por línea: la cantidad de números en `v`/`vt`/`vn` (sin
+// faltantes ni sobrantes), caras con al menos tres vértices cuyos índices existan, y nada
+// de geometría de forma libre. Las palabras clave desconocidas se ignoran, igual que en tobj.
+// Devuelve el grupo de suavizado de cada cara `f`, en orden (0 = `s off`), o un vector vacío
+// si el archivo no declara grupos.
+fn validate_source(source: &str) -> Result<Vec<u32>, LoadError> {
+    let (mut positions, mut texcoords, mut normals) = (0usize, 0usize, 0usize);
+    let mut face_groups = Vec::new();
+    let mut smoothing_group = 0;
+    let mut declared_groups = false;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let parse_error = |col: usize, message: String| {
+            LoadError::ParseError(ParseError { line: line_number, col, message, context: line.trim_end().to_string() })
+        };
+        let content = line.split('#').next().unwrap_or("");
+        let tokens = tokens_with_columns(content);
+        let Some(&(_, keyword)) = tokens.first() else { continue };
+        let arguments = &tokens[1..];
+        // Columna donde iría el siguiente valor, para los que faltan
+        let end_col = content.trim_end().chars().count() + 2;
+
+        // `counts`: cantidades de valores válidas (p. ej. `v x y z` o `v x y z r g b` con
+        // color por vértice; tobj no acepta la w de `v x y z w`)
+        let check_floats = |counts: &[usize]| -> Result<(), LoadError> {
+            if let Some(&(col, value)) = arguments.iter().find(|(_, value)| value.parse::<f32>().is_err()) {
+                return Err(parse_error(col, format!("`{}` no es un número", value)));
+            }
+            let minimum = counts[0];
+            if arguments.len() < minimum {
+                return Err(parse_error(end_col, format!("`{}` necesita al menos {} valores, tiene {}", keyword, minimum, arguments.len())));
+            }
+            if !counts.contains(&arguments.len()) {
+                // Marca el primer valor que sobra de la cantidad válida más cercana
+                let valid = counts.iter().copied().filter(|&count| count < arguments.len()).max().unwrap_or(minimum);
+                let col = arguments[valid].0;
+                let expected: Vec<String> = counts.iter().map(|count| count.to_string()).collect();
+                return Err(parse_error(col, format!("`{}` lleva {} valores, tiene {}", keyword, expected.join(" o "), arguments.len())));
+            }
+            Ok(())
+        };
+
+        match keyword {
+            "v" => {
+                check_floats(&[3, 6])?;
+                positions += 1;
+            }
+            "vt" => {
+                check_floats(&[2, 3])?;
+                texcoords += 1;
+            }
+            "vn" => {
+                check_floats(&[3])?;
+                normals += 1;
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(parse_error(end_col, format!("una cara necesita al menos 3 vértices, tiene {}", arguments.len())));
+                }
+                for &(col, vertex) in arguments {
+                    // v, v/vt, v//vn o v/vt/vn
+                    let parts: Vec<&str> = vertex.split('/').collect();
+                    if parts.len() > 3 || parts[0].is_empty() {
+                        return Err(parse_error(col, format!("vértice de cara inválido `{}`", vertex)));
+                    }
+                    let counts = [(positions, "vértice"), (texcoords, "UV"), (normals, "normal")];
+                    let mut part_col = col;
+                    for (part, (count, name)) in parts.iter().zip(counts) {
+                        let current_col = part_col;
+                        part_col += part.chars().count() + 1;
+                        if part.is_empty() {
+                            continue; // `v//vn` no trae UV
+                        }
+                        // Índices desde 1; los negativos cuentan desde el último declarado. Hay
+                        // archivos (como assets/cube.obj) que referencian UVs o normales sin
+                        // declarar ninguna: tobj las ignora, así que solo se revisa el rango
+                        // de los vértices y de lo que sí esté declarado
+                        let in_range = |i: i64| (name != "vértice" && count == 0) || i.unsigned_abs() as usize <= count;
+                        let valid = part.parse::<i64>().is_ok_and(|i| i != 0 && in_range(i));
+                        if !valid {
+                            return Err(parse_error(current_col, format!("índice de {} inválido en `{}` (hay {} declarados)", name, vertex, count)));
+                        }
+                    }
+                }
+                face_groups.push(smoothing_group);
+            }
+            "s" => {
+                declared_groups = true;
+                smoothing_group = match arguments.first() {
+                    Some((_, "off")) | None => 0,
+                    Some((_, value)) => value.parse().unwrap_or(0),
+                };
+            }
+            keyword if FREE_FORM_KEYWORDS.contains(&keyword) => {
+                return Err(LoadError::UnsupportedFeature { line: line_number, feature: keyword.to_string() });
+            }
+            _ => {}
+        }
+    }
+
+    if !declared_groups {
+        face_groups.clear();
+    }
+    Ok(face_groups)
+}
+
+// Palabras de la línea separadas por espacios, con la columna (en caracteres, desde 1)
+// donde empieza cada una
+fn tokens_with_columns(content: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start: Option<(usize, usize)> = None; // (byte, columna)
+    for (column, (byte, c)) in content.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((byte, column + 1)),
+            (true, Some((first, col))) => {
+                tokens.push((col, &content[first..byte]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((first, col)) = start {
+        tokens.push((col, &content[first..]));
+    }
+    tokens
+}
+
+// Normales por grupos de suavizado: un vértice compartido por caras de distintos grupos se
+// duplica, y cada copia promedia solo las caras de su grupo. Las caras sin grupo (0) son planas.
+// Devuelve las posiciones, UVs, índices y normales ya separados.
+fn compute_grouped_normals(
+    vertices: &[Vec3],
+    texcoords: &[Vec2],
+    indices: &[u32],
+    triangle_groups: &[u32],
+) -> (Vec<Vec3>, Vec<Vec2>, Vec<u32>, Vec<Vec3>) {
+    let has_texcoords = texcoords.len() == vertices.len();
+    let mut new_vertices = Vec::new();
+    let mut new_texcoords = Vec::new();
+    let mut new_indices = Vec::with_capacity(indices.len());
+    let mut sums: Vec<Vec3> = Vec::new();
+    // (vértice original, grupo) -> vértice nuevo. Las caras planas usan una clave por triángulo.
+    let mut remap: HashMap<(u32, u64), u32> = HashMap::new();
+
+    for (triangle, face) in indices.chunks_exact(3).enumerate() {
+        let (a, b, c) = (face[0] as usize, face[1] as usize, face[2] as usize);
+        let face_normal = (vertices[b] - vertices[a]).cross(&(vertices[c] - vertices[a]));
+
+        let group = triangle_groups.get(triangle).copied().unwrap_or(0);
+        let key = if group != 0 { group as u64 } else { (1 << 32) + triangle as u64 };
+
+        for &index in face {
+            let new_index = *remap.entry((index, key)).or_insert_with(|| {
+                new_vertices.push(vertices[index as usize]);
+                if has_texcoords {
+                    new_texcoords.push(texcoords[index as usize]);
+                }
+                sums.push(Vec3::zeros());
+                (new_vertices.len() - 1) as u32
+            });
+            sums[new_index as usize] += face_normal;
+            new_indices.push(new_index);
+        }
+    }
+
+    let normals = sums
+        .into_iter()
+        .map(|n| if n.norm() > 0.0 { n.normalize() } else { Vec3::new(0.0, 1.0, 0.0) })
+        .collect();
+    (new_vertices, new_texcoords, new_indices, normals)
+}
+
+// Normales suaves: promedio de las normales de las caras que comparten cada vértice
+fn compute_smooth_normals(vertices: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::zeros(); vertices.len()];
+
+    for face in indices.chunks(3) {
+        if face.len() < 3 {
+            continue;
+        }
+        let (a, b, c) = (face[0] as usize, face[1] as usize, face[2] as usize);
+
+        // El producto cruz sin normalizar pondera cada cara por su área
+        let face_normal = (vertices[b] - vertices[a]).cross(&(vertices[c] - vertices[a]));
+        normals[a] += face_normal;
+        normals[b] += face_normal;
+        normals[c] += face_normal;
+    }
+
+    normals
+        .into_iter()
+        .map(|n| if n.norm() > 0.0 { n.normalize() } else { Vec3::new(0.0, 1.0, 0.0) })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.25 0.5\nvt 0.75 0.5\nvt 0.5 1\nvn 0 0 -1\n";
+
+    fn load(source: &str) -> Obj {
+        Obj::from_source(source).expect("el OBJ de prueba es válido")
+    }
+
+    #[test]
+    fn face_formats_fill_missing_uvs_and_normals() {
+        let positions = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+        let uvs = [Vec2::new(0.25, 0.5), Vec2::new(0.75, 0.5), Vec2::new(0.5, 1.0)];
+        // Sin normales en el archivo se calcula la de la cara: (b - a) x (c - a) = +z
+        let computed = Vec3::new(0.0, 0.0, 1.0);
+        let declared = Vec3::new(0.0, 0.0, -1.0);
+        let cases = [
+            ("f 1 2 3", false, computed),
+            ("f 1/1 2/2 3/3", true, computed),
+            ("f 1//1 2//1 3//1", false, declared),
+            ("f 1/1/1 2/2/1 3/3/1", true, declared),
+        ];
+
+        for (face, has_uvs, normal) in cases {
+            let vertices = load(&format!("{TRIANGLE}{face}\n")).get_vertex_array();
+            assert_eq!(vertices.len(), 3, "{face}");
+            for (i, vertex) in vertices.iter().enumerate() {
+                assert_eq!(vertex.position, positions[i], "{face}");
+                assert_eq!(vertex.tex_coords, if has_uvs { uvs[i] } else { Vec2::zeros() }, "{face}");
+                assert_eq!(vertex.normal, normal, "{face}");
+            }
+        }
+    }
+
+    // Dos caras que comparten la arista 1-2 y forman un ángulo recto
+    fn fold(first_group: &str, second_group: &str) -> Vec<Vertex> {
+        load(&format!("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\ns {first_group}\nf 1 2 3\ns {second_group}\nf 2 1 4\n")).get_vertex_array()
+    }
+
+    #[test]
+    fn smoothing_groups_keep_hard_edges() {
+        let (first_normal, second_normal) = (Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0));
+
+        let hard = fold("1", "2");
+        assert!(hard[..3].iter().all(|vertex| vertex.normal == first_normal));
+        assert!(hard[3..].iter().all(|vertex| vertex.normal == second_normal));
+
+        // En el mismo grupo los vértices de la arista compartida promedian ambas caras
+        let smooth = fold("1", "1");
+        let shared = (first_normal + second_normal).normalize();
+        assert!((smooth[0].normal - shared).norm() < 1e-6);
+        assert!((smooth[1].normal - shared).norm() < 1e-6);
+        assert_eq!(smooth[2].normal, first_normal);
+        assert_eq!(smooth[5].normal, second_normal);
+    }
+
+    #[test]
+    fn transformed_vertices_round_trip_through_obj() {
+        use crate::shader::vertex_shader;
+        use crate::uniforms::{viewport_matrix, UniformsBuilder};
+
+        let mesh = load(&format!("{TRIANGLE}v 1 1 0\nf 1 2 3\nf 2 4 3\n")).get_vertex_array();
+        let uniforms = UniformsBuilder::new()
+            .model(nalgebra_glm::translation(&Vec3::new(0.5, 0.0, 1.0)))
+            .viewport(viewport_matrix(100, 80, 10.0))
+            .build();
+        let transformed: Vec<Vertex> = mesh.iter().map(|vertex| vertex_shader(vertex, &uniforms)).collect();
+
+        for space in [ExportSpace::Screen, ExportSpace::Ndc] {
+            let expected = |p: Vec3| match space {
+                ExportSpace::Screen => p,
+                ExportSpace::Ndc => Vec3::new(p.x / 50.0 - 1.0, 1.0 - p.y / 40.0, p.z),
+            };
+            let path = std::env::temp_dir().join(format!("renderer_export_{:?}_{}.obj", space, std::process::id()));
+            let path = path.to_str().unwrap();
+            write_transformed_obj(path, &transformed, space, 100, 80).unwrap();
+            let exported = Obj::load(path).unwrap().get_vertex_array();
+            std::fs::remove_file(path).unwrap();
+
+            assert_eq!(exported.len(), mesh.len());
+            for (vertex, original) in exported.iter().zip(&transformed) {
+                assert!((vertex.position - expected(original.transformed_position)).norm() < 1e-4, "{:?}", space);
+            }
+        }
+        // La traslación del modelo y el viewport quedan en las posiciones exportadas
+        assert_eq!(transformed[0].transformed_position, Vec3::new(55.0, 40.0, 10.0));
+    }
+
+    // Tamaño del bounding box de los vértices en cada eje
+    fn extents(obj: &Obj) -> Vec3 {
+        let (min, max) = obj.vertices.iter().fold((Vec3::repeat(f32::MAX), Vec3::repeat(f32::MIN)), |(min, max), v| (min.inf(v), max.sup(v)));
+        max - min
+    }
+
+    #[test]
+    fn z_up_models_load_upright() {
+        // La misma columna alta, exportada con Z hacia arriba y con Y hacia arriba
+        let z_up = "v 0 0 0\nv 1 0 0\nv 1 2 0\nv 0 0 3\nf 1 2 3\nf 1 3 4\n";
+        let y_up = "v 0 0 0\nv 1 0 0\nv 1 0 -2\nv 0 3 0\nf 1 2 3\nf 1 3 4\n";
+        let options = ObjLoadOptions { up_axis: UpAxis::Z, ..ObjLoadOptions::default() };
+
+        let converted = Obj::from_source_with_options(z_up, &options).unwrap();
+        let reference = load(y_up);
+        assert_eq!(extents(&converted), Vec3::new(1.0, 3.0, 2.0));
+        assert_eq!(extents(&converted), extents(&reference));
+        assert_eq!(converted.vertices, reference.vertices);
+        // Sin la opción la columna queda acostada a lo largo de z
+        assert_eq!(extents(&load(z_up)), Vec3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn malformed_obj_reports_the_offending_line() {
+        let parse_error = |source: &str| match Obj::from_source(source) {
+            Err(LoadError::ParseError(error)) => error,
+            Err(other) => panic!("se esperaba un error de sintaxis, llegó {other}"),
+            Ok(_) => panic!("el OBJ inválido se cargó"),
+        };
+
+        let error = parse_error("v 0 0 0\nv 1 0 0\n# comentario\nv 0 uno 0\nf 1 2 3\n");
+        assert_eq!((error.line, error.col), (4, 5));
+        assert_eq!(error.context, "v 0 uno 0");
+        assert!(error.to_string().starts_with("línea 4, columna 5"));
+
+        // Índice de cara fuera de rango
+        let error = parse_error(&format!("{TRIANGLE}f 1 2 9\n"));
+        assert_eq!((error.line, error.col), (8, 7));
+
+        match Obj::from_source("v 0 0 0\ncurv 0 1 1 2\n") {
+            Err(LoadError::UnsupportedFeature { line, feature }) => assert_eq!((line, feature.as_str()), (2, "curv")),
+            _ => panic!("`curv` debería reportarse como no soportado"),
+        }
+    }
+
+    #[test]
+    fn bounding_sphere_of_a_cube() {
+        use crate::camera::{framing_distance, Camera};
+
+        // assets/cube.obj es el cubo de lado 2 centrado en el origen, rotado
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/cube.obj");
+        let (center, radius) = Obj::load(path).expect("assets/cube.obj se carga").bounding_sphere();
+        assert!(center.norm() < 1e-5, "{center:?}");
+        assert!((radius - 3f32.sqrt()).abs() < 1e-5, "{radius}");
+
+        // Caja de 2 x 4 x 6 desplazada: centro en el del volumen y radio a una esquina
+        let mut source = String::new();
+        for corner in 0..8 {
+            let bit = |axis: usize, size: f32| if corner & (1 << axis) != 0 { size } else { 0.0 };
+            source += &format!("v {} {} {}\n", 3.0 + bit(0, 2.0), -1.0 + bit(1, 4.0), bit(2, 6.0));
+        }
+        source += "f 1 2 4\nf 5 6 8\n";
+        let (center, radius) = load(&source).bounding_sphere();
+        assert_eq!(center, Vec3::new(4.0, 1.0, 3.0));
+        assert!((radius - 14f32.sqrt()).abs() < 1e-6, "{radius}");
+
+        // La cámara que la encuadra queda sobre el centro, con la esfera tangente al campo de visión
+        let fov = 60f32.to_radians();
+        let camera = Camera::frame(center, radius, fov);
+        assert_eq!(camera.eye.xy(), center.xy());
+        assert!(camera.eye.z < center.z);
+        let distance = (center - camera.eye).norm();
+        assert!((distance - framing_distance(radius, fov)).abs() < 1e-5);
+        assert!((radius / distance - (fov / 2.0).sin()).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,186 @@
+// particles.rs
+//
+// Sistema de partículas simple (p. ej. el escape de la nave). Las partículas viven en
+// coordenadas del mundo; cada frame se integran (velocidad + gravedad), se les descuenta
+// vida y las muertas se descartan. Se dibujan como billboards: un cuadrado de dos
+// triángulos que mira siempre a la cámara, con una caída radial de opacidad y mezcla
+// alpha contra la escena (prueba de profundidad sin escribir el z-buffer). Todas las
+// partículas comparten el mismo cuadrado unitario y se dibujan como instancias: por
+// partícula solo se guarda su matriz, color y opacidad.
+
+use nalgebra_glm::{Mat4, Vec2, Vec3};
+
+use crate::color::Color;
+use crate::framebuffer::{Framebuffer, RenderStats};
+use crate::instancing::{for_each_instance, InstanceData, Rng};
+use crate::uniforms::{CullMode, Uniforms};
+use crate::vertex::Vertex;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Particle {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub lifetime: f32,     // Segundos de vida restantes
+    pub max_lifetime: f32, // Vida al nacer, para el desvanecimiento
+    pub color: Color,
+    pub size: f32, // Medio lado del billboard, en unidades del mundo
+}
+
+impl Particle {
+    // Opacidad según la vida restante: 1 al nacer, 0 al morir
+    pub fn fade(&self) -> f32 {
+        (self.lifetime / self.max_lifetime.max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+}
+
+// Emisor: cuántas partículas nacen por segundo y con qué velocidad y vida. La velocidad
+// inicial se elige por componente dentro del rango y está en el espacio local del emisor
+// (ver `ParticleSystem::update`)
+#[derive(Debug, Clone)]
+pub struct EmitterConfig {
+    pub spawn_rate: f32, // Partículas por segundo (0 = no emite)
+    pub initial_velocity_range: (Vec3, Vec3),
+    pub lifetime_range: (f32, f32),
+    pub gravity: Vec3, // Aceleración constante, en el mundo
+    pub color: Color,
+    pub size: f32,
+}
+
+impl EmitterConfig {
+    // Escape de un motor: chorro anaranjado hacia +y local, corto y sin gravedad
+    pub fn exhaust() -> Self {
+        EmitterConfig {
+            spawn_rate: 120.0,
+            initial_velocity_range: (Vec3::new(-0.25, 1.2, -0.1), Vec3::new(0.25, 2.0, 0.1)),
+            lifetime_range: (0.25, 0.6),
+            gravity: Vec3::zeros(),
+            color: Color::new(255, 150, 50),
+            size: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParticleSystem {
+    pub particles: Vec<Particle>,
+    pub emitter: EmitterConfig,
+    spawn_accumulator: f32, // Fracción de partícula pendiente entre frames
+    rng: Rng,
+}
+
+impl ParticleSystem {
+    pub fn new(emitter: EmitterConfig, seed: u64) -> Self {
+        ParticleSystem { particles: Vec::new(), emitter, spawn_accumulator: 0.0, rng: Rng::new(seed) }
+    }
+
+    // Avanza `dt` segundos: mueve y envejece las partículas, descarta las muertas y emite
+    // las nuevas. `emitter` lleva del espacio local del emisor al mundo: las partículas nacen
+    // en su origen y su velocidad inicial se rota con él. `inherited_velocity` se suma a la
+    // velocidad inicial (la del objeto que lleva el emisor)
+    pub fn update(&mut self, dt: f32, emitter: &Mat4, inherited_velocity: Vec3) {
+        let gravity = self.emitter.gravity;
+        for particle in self.particles.iter_mut() {
+            particle.velocity += gravity * dt;
+            particle.position += particle.velocity * dt;
+            particle.lifetime -= dt;
+        }
+        self.particles.retain(|particle| particle.lifetime > 0.0);
+
+        self.spawn_accumulator += self.emitter.spawn_rate.max(0.0) * dt;
+        let origin = (emitter * Vec3::zeros().push(1.0)).xyz();
+        while self.spawn_accumulator >= 1.0 {
+            self.spawn_accumulator -= 1.0;
+
+            let (min, max) = self.emitter.initial_velocity_range;
+            let local = Vec3::new(
+                self.rng.range(min.x, max.x),
+                self.rng.range(min.y, max.y),
+                self.rng.range(min.z, max.z),
+            );
+            let lifetime = self.rng.range(self.emitter.lifetime_range.0, self.emitter.lifetime_range.1);
+            self.particles.push(Particle {
+                position: origin,
+                velocity: (emitter * local.push(0.0)).xyz() + inherited_velocity,
+                lifetime,
+                max_lifetime: lifetime,
+                color: self.emitter.color,
+                size: self.emitter.size,
+            });
+        }
+    }
+
+    // Datos de instancia de cada partícula viva: el cuadrado unitario escalado a su tamaño
+    // en el plano de la cámara (`right` y `up` en el mundo) y trasladado a su posición
+    pub fn instances(&self, right: &Vec3, up: &Vec3) -> Vec<InstanceData> {
+        let normal = right.cross(up);
+        self.particles
+            .iter()
+            .map(|particle| {
+                let (x, y, p) = (right * particle.size, up * particle.size, particle.position);
+                InstanceData {
+                    transform: Mat4::new(
+                        x.x, y.x, normal.x, p.x,
+                        x.y, y.y, normal.y, p.y,
+                        x.z, y.z, normal.z, p.z,
+                        0.0, 0.0, 0.0,      1.0,
+                    ),
+                    color: particle.color,
+                    alpha: particle.fade(),
+                }
+            })
+            .collect()
+    }
+}
+
+// Cuadrado de lado 2 centrado en el origen en el plano XY, en dos triángulos, con
+// coordenadas de textura de -1 a 1 (la distancia al centro da la caída de opacidad)
+pub fn unit_quad() -> Vec<Vertex> {
+    let corner = |x: f32, y: f32| {
+        let mut vertex = Vertex::new(Vec3::new(x, y, 0.0), Vec3::z(), Vec2::new(x, y));
+        vertex.color = Color::new(255, 255, 255);
+        vertex
+    };
+    let (a, b, c, d) = (corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0));
+    vec![a.clone(), b, c.clone(), a, c, d]
+}
+
+// Dibuja las partículas sobre el frame con la vista, proyección y viewport de `uniforms`;
+// la matriz de modelo se ignora porque las partículas ya están en el mundo. Van sin
+// iluminar y se mezclan por orden de nacimiento, sin ordenar por profundidad
+pub fn render_particles(framebuffer: &mut Framebuffer, uniforms: &Uniforms, system: &ParticleSystem) {
+    if system.particles.is_empty() {
+        return;
+    }
+
+    let mut world = uniforms.with_model(Mat4::identity());
+    world.material.unlit = true;
+    world.cull_mode = CullMode::None;
+    framebuffer.matrices = Some(world.matrices());
+    framebuffer.draw_supersampled(&world, |framebuffer, world| draw_particles(framebuffer, world, system));
+}
+
+fn draw_particles(framebuffer: &mut Framebuffer, world: &Uniforms, system: &ParticleSystem) {
+    // Ejes de la cámara en el mundo: filas de la rotación de la vista
+    let view = &world.view_matrix;
+    let right = Vec3::new(view[(0, 0)], view[(0, 1)], view[(0, 2)]);
+    let up = Vec3::new(view[(1, 0)], view[(1, 1)], view[(1, 2)]);
+
+    let quad = unit_quad();
+    let instances = system.instances(&right, &up);
+    let mut stats = RenderStats::default();
+    for_each_instance(world, &quad, &instances, &framebuffer.raster_bounds(), &mut stats, |index, fragments| {
+        let instance = &instances[index];
+        for fragment in fragments.iter() {
+            let Some(weights) = fragment.barycentric else { continue };
+
+            // Distancia al centro del billboard a partir de las coordenadas de textura
+            let corners = &quad[fragment.triangle as usize * 3..fragment.triangle as usize * 3 + 3];
+            let uv = corners[0].tex_coords * weights.x + corners[1].tex_coords * weights.y + corners[2].tex_coords * weights.z;
+            let falloff = (1.0 - uv.norm_squared()).max(0.0);
+
+            let (x, y) = (fragment.position.x as isize, fragment.position.y as isize);
+            let color = instance.color.tint(&fragment.color);
+            framebuffer.blend_with_depth(x, y, fragment.depth, color, instance.alpha * falloff);
+        }
+    });
+}
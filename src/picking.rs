@@ -0,0 +1,48 @@
+// picking.rs
+
+use nalgebra_glm::{Vec3, Vec4};
+use crate::math::{line_triangle_intersect, ray_triangle_intersect};
+use crate::uniforms::Uniforms;
+use crate::vertex::Vertex;
+
+// Devuelve el índice del triángulo (en el orden de `uniforms.topology`) más cercano bajo la
+// posición (x, y) de la ventana.
+// El rayo se construye una sola vez deshaciendo la transformación de `uniforms` (inversa de
+// la MVP), así la intersección se calcula con las posiciones originales de los vértices sin
+// transformar ninguno. En perspectiva el rayo sale del ojo; en ortográfica no hay ojo y se
+// usa la recta completa, así cuenta también la geometría con profundidad negativa.
+pub fn pick(window_x: f32, window_y: f32, uniforms: &Uniforms, vertex_array: &[Vertex]) -> Option<usize> {
+    let inverse = uniforms.transformation_matrix.try_inverse()?;
+    let unproject = |z: f32| -> Option<Vec3> {
+        let p = inverse * Vec4::new(window_x, window_y, z, 1.0);
+        if p.w.abs() > f32::EPSILON { Some(p.xyz() / p.w) } else { None }
+    };
+
+    let perspective = uniforms.projection_matrix[(3, 2)] != 0.0;
+    let (origin, direction) = if perspective {
+        // El ojo en el espacio del modelo: el origen de la vista deshaciendo modelo y vista
+        let eye = uniforms.model_view_matrix.try_inverse()? * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        let eye = eye.xyz() / eye.w;
+        (eye, unproject(0.5)? - eye)
+    } else {
+        let near = unproject(0.0)?;
+        (near, unproject(1.0)? - near)
+    };
+
+    let mut closest: Option<(usize, f32)> = None;
+    for (index, [a, b, c]) in uniforms.topology.triangles(vertex_array.len()).enumerate() {
+        let (a, b, c) = (vertex_array[a].position, vertex_array[b].position, vertex_array[c].position);
+        let hit = if perspective {
+            ray_triangle_intersect(origin, direction, a, b, c)
+        } else {
+            line_triangle_intersect(origin, direction, a, b, c)
+        };
+        if let Some(t) = hit {
+            if closest.is_none_or(|(_, closest_t)| t < closest_t) {
+                closest = Some((index, t));
+            }
+        }
+    }
+
+    closest.map(|(index, _)| index)
+}
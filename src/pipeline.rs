@@ -0,0 +1,347 @@
+// pipeline.rs
+//
+// Las etapas de un render (un draw: vértices -> fragmentos -> framebuffer) como piezas que
+// se pueden encadenar, reemplazar o intercalar sin copiar `render()`. No confundir con
+// frame_pipeline.rs, que solapa frames completos en dos hilos.
+//
+// Cada etapa recibe los datos de la anterior y devuelve los suyos:
+//
+//   VertexShaderStage        Vertices   -> Vertices   (transformados)
+//   TopologyStage            Vertices   -> Vertices   (lista de triángulos según `uniforms.topology`)
+//   GeometryStage            Vertices   -> Vertices   (triángulos generados por el geometry shader)
+//   PrimitiveAssemblyStage   Vertices   -> Triangles  (sin los inválidos ni degenerados)
+//   BackfaceCullStage        Triangles  -> Triangles
+//   RasterizationStage       Triangles  -> Fragments
+//   FragmentShaderStage      Fragments  -> Fragments  (color e iluminación)
+//   FramebufferWriteStage    Fragments  -> Written
+//
+// Una etapa que recibe datos que no le corresponden los deja pasar sin cambios.
+
+use crate::fragment::Fragment;
+use crate::framebuffer::{
+    assemble_triangles, clip_near_plane, cull_triangles, rasterize_triangles, shade_fragments, triangle_list,
+    write_fragments, Framebuffer, MemoryStats, RenderStats,
+};
+use crate::shader::{GeometryShader, VertexShader};
+use crate::uniforms::{PrimitiveTopology, Uniforms};
+use crate::vertex::Vertex;
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Configuración de las etapas opcionales del pipeline, en `Uniforms::pipeline_config`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PipelineConfig {
+    // Geometry shader por triángulo (p. ej. `subdivide_geometry_shader`); None = sin etapa
+    pub geometry_shader: Option<GeometryShader>,
+}
+
+// Datos que pasan de una etapa a la siguiente
+pub enum StageData {
+    Vertices(Vec<Vertex>),
+    // Vértices transformados y los triángulos que siguen vivos: el triángulo i son los
+    // vértices 3i, 3i+1 y 3i+2 (el índice se conserva para el triangle buffer)
+    Triangles { vertices: Vec<Vertex>, triangles: Vec<u32> },
+    Fragments(Vec<Fragment>),
+    // Los fragmentos ya se escribieron en el framebuffer
+    Written,
+}
+
+// Lo que comparten todas las etapas de un render
+pub struct StageContext<'a> {
+    pub uniforms: &'a Uniforms,
+    pub framebuffer: &'a mut Framebuffer,
+    pub stats: RenderStats,
+}
+
+pub trait PipelineStage {
+    // Nombre de la etapa, para `Pipeline::insert_after` y las trazas
+    fn name(&self) -> &str;
+    fn process(&self, input: StageData, context: &mut StageContext) -> StageData;
+}
+
+// Vértices por tarea al transformar en paralelo: con menos, el reparto entre hilos cuesta
+// más que transformarlos (un draw chico queda en un solo hilo)
+const VERTICES_PER_TASK: usize = 2048;
+
+// Vertex Shader: transforma cada vértice y cuenta los que quedan con posición no finita.
+// Los vértices se transforman en paralelo (rayon, en el pool del hilo que llama) sobre el
+// mismo buffer; cada uno depende solo de sí mismo y de los uniforms, así que el resultado
+// es idéntico al de un solo hilo
+pub struct VertexShaderStage<'a> {
+    pub shader: &'a dyn VertexShader,
+}
+
+impl PipelineStage for VertexShaderStage<'_> {
+    fn name(&self) -> &str {
+        "vertex_shading"
+    }
+
+    fn process(&self, input: StageData, context: &mut StageContext) -> StageData {
+        let StageData::Vertices(mut transformed) = input else { return input };
+        let uniforms = context.uniforms;
+        transformed
+            .par_iter_mut()
+            .with_min_len(VERTICES_PER_TASK)
+            .for_each(|vertex| *vertex = self.shader.transform(vertex, uniforms));
+
+        context.stats.vertices = transformed.len();
+        // Los vértices detrás de la cámara (sin posición en pantalla) no cuentan: el recorte
+        // contra el plano near los resuelve al armar los triángulos
+        context.stats.invalid_vertices = transformed.iter().filter(|vertex| !vertex.clip_position.iter().all(|c| c.is_finite())).count();
+        #[cfg(feature = "strict-pipeline")]
+        assert_eq!(context.stats.invalid_vertices, 0, "El vertex shader produjo posiciones no finitas");
+
+        StageData::Vertices(transformed)
+    }
+}
+
+// Arma los triángulos de tiras y abanicos (`uniforms.topology`) como una lista de
+// triángulos sueltos, después de transformar cada vértice una sola vez
+pub struct TopologyStage;
+
+impl PipelineStage for TopologyStage {
+    fn name(&self) -> &str {
+        "topology"
+    }
+
+    fn process(&self, input: StageData, context: &mut StageContext) -> StageData {
+        let topology = context.uniforms.topology;
+        if topology == PrimitiveTopology::TriangleList {
+            return input;
+        }
+        let StageData::Vertices(vertices) = input else { return input };
+        StageData::Vertices(triangle_list(&vertices, topology).into_owned())
+    }
+}
+
+// Geometry shader: reemplaza cada triángulo por los que emita
+// `uniforms.pipeline_config.geometry_shader`. Sin geometry shader deja pasar los vértices
+pub struct GeometryStage;
+
+impl PipelineStage for GeometryStage {
+    fn name(&self) -> &str {
+        "geometry_shading"
+    }
+
+    fn process(&self, input: StageData, context: &mut StageContext) -> StageData {
+        let Some(shader) = context.uniforms.pipeline_config.geometry_shader else { return input };
+        let StageData::Vertices(vertices) = input else { return input };
+
+        let mut emitted = Vec::with_capacity(vertices.len());
+        for triangle in vertices.chunks_exact(3) {
+            let triangle: &[Vertex; 3] = triangle.try_into().expect("chunks_exact(3) da triángulos completos");
+            for output in shader(triangle, context.uniforms) {
+                emitted.extend(output);
+            }
+        }
+        StageData::Vertices(emitted)
+    }
+}
+
+// Primitive Assembly: recorta contra el plano near, agrupa en triángulos y descarta los inválidos
+pub struct PrimitiveAssemblyStage;
+
+impl PipelineStage for PrimitiveAssemblyStage {
+    fn name(&self) -> &str {
+        "primitive_assembly"
+    }
+
+    fn process(&self, input: StageData, context: &mut StageContext) -> StageData {
+        let StageData::Vertices(vertices) = input else { return input };
+        let clipped = match clip_near_plane(Cow::Borrowed(&vertices), &context.uniforms.projection_matrix) {
+            Cow::Owned(clipped) => Some(clipped),
+            Cow::Borrowed(_) => None,
+        };
+        let vertices = clipped.unwrap_or(vertices);
+        let triangles = assemble_triangles(&vertices, &mut context.stats);
+        StageData::Triangles { vertices, triangles }
+    }
+}
+
+// Culling de caras según `uniforms.cull_mode` y `uniforms.front_face`
+pub struct BackfaceCullStage;
+
+impl PipelineStage for BackfaceCullStage {
+    fn name(&self) -> &str {
+        "backface_culling"
+    }
+
+    fn process(&self, input: StageData, context: &mut StageContext) -> StageData {
+        let StageData::Triangles { vertices, triangles } = input else { return input };
+        let triangles = cull_triangles(&vertices, triangles, context.uniforms.cull_mode, context.uniforms.front_face);
+        StageData::Triangles { vertices, triangles }
+    }
+}
+
+// Rasterización, reservando tantos fragmentos como el render anterior en este framebuffer
+pub struct RasterizationStage;
+
+impl PipelineStage for RasterizationStage {
+    fn name(&self) -> &str {
+        "rasterization"
+    }
+
+    fn process(&self, input: StageData, context: &mut StageContext) -> StageData {
+        let StageData::Triangles { vertices, triangles } = input else { return input };
+        let capacity = context.framebuffer.fragment_capacity_hint;
+        let bounds = context.framebuffer.raster_bounds();
+        let fragments = rasterize_triangles(&vertices, &triangles, context.uniforms, &bounds, capacity);
+        context.framebuffer.fragment_capacity_hint = fragments.len();
+        context.stats.memory.vertex_buffer_bytes = vertices.capacity() * std::mem::size_of::<Vertex>();
+        StageData::Fragments(fragments)
+    }
+}
+
+// Fragment Shading: shader de superficie del material e iluminación
+pub struct FragmentShaderStage;
+
+impl PipelineStage for FragmentShaderStage {
+    fn name(&self) -> &str {
+        "fragment_shading"
+    }
+
+    fn process(&self, input: StageData, context: &mut StageContext) -> StageData {
+        let StageData::Fragments(mut fragments) = input else { return input };
+        shade_fragments(&mut fragments, context.uniforms);
+        StageData::Fragments(fragments)
+    }
+}
+
+// Los avisos por stderr salen una sola vez por proceso: se repetirían en cada draw de cada
+// frame. Los números de cada render siguen en `RenderStats`
+static FRAGMENT_MEMORY_WARNED: AtomicBool = AtomicBool::new(false);
+static SKIPPED_GEOMETRY_WARNED: AtomicBool = AtomicBool::new(false);
+
+fn warn_once(warned: &AtomicBool, message: impl FnOnce() -> String) {
+    if !warned.swap(true, Ordering::Relaxed) {
+        eprintln!("{} (no se vuelve a avisar)", message());
+    }
+}
+
+// Escritura en el framebuffer con prueba de profundidad y el modo de mezcla del material.
+// Avisa por stderr (una vez) si el buffer de fragmentos superó `fragment_memory_limit`
+pub struct FramebufferWriteStage;
+
+impl PipelineStage for FramebufferWriteStage {
+    fn name(&self) -> &str {
+        "framebuffer_write"
+    }
+
+    fn process(&self, input: StageData, context: &mut StageContext) -> StageData {
+        let StageData::Fragments(fragments) = input else { return input };
+        let framebuffer = &mut *context.framebuffer;
+
+        let fragment_buffer_bytes = fragments.capacity() * std::mem::size_of::<Fragment>();
+        context.stats.memory.fragment_buffer_bytes = fragment_buffer_bytes;
+        if fragment_buffer_bytes > framebuffer.fragment_memory_limit {
+            let limit = framebuffer.fragment_memory_limit;
+            warn_once(&FRAGMENT_MEMORY_WARNED, || {
+                format!(
+                    "Advertencia: el buffer de fragmentos ocupa {:.1} MB (límite {:.1} MB)",
+                    fragment_buffer_bytes as f64 / (1024.0 * 1024.0),
+                    limit as f64 / (1024.0 * 1024.0)
+                )
+            });
+        }
+
+        write_fragments(framebuffer, &fragments, context.uniforms.material.blend_mode, context.uniforms.stencil);
+        StageData::Written
+    }
+}
+
+// Cadena de etapas de un render
+pub struct Pipeline<'a> {
+    stages: Vec<Box<dyn PipelineStage + 'a>>,
+}
+
+impl<'a> Pipeline<'a> {
+    // Pipeline sin etapas, para armarlo a mano con `push`
+    pub fn new() -> Self {
+        Pipeline { stages: Vec::new() }
+    }
+
+    // Las etapas de `render()`, con el vertex shader dado
+    pub fn standard(shader: &'a dyn VertexShader) -> Self {
+        let mut pipeline = Pipeline::new();
+        pipeline.push(VertexShaderStage { shader });
+        pipeline.push(TopologyStage);
+        pipeline.push(GeometryStage);
+        pipeline.push(PrimitiveAssemblyStage);
+        pipeline.push(BackfaceCullStage);
+        pipeline.push(RasterizationStage);
+        pipeline.push(FragmentShaderStage);
+        pipeline.push(FramebufferWriteStage);
+        pipeline
+    }
+
+    pub fn push(&mut self, stage: impl PipelineStage + 'a) {
+        self.stages.push(Box::new(stage));
+    }
+
+    // Intercala `stage` después de la etapa llamada `after`; false si no existe
+    pub fn insert_after(&mut self, after: &str, stage: impl PipelineStage + 'a) -> bool {
+        match self.stages.iter().position(|existing| existing.name() == after) {
+            Some(index) => {
+                self.stages.insert(index + 1, Box::new(stage));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Reemplaza la etapa llamada `name`; false si no existe
+    pub fn replace(&mut self, name: &str, stage: impl PipelineStage + 'a) -> bool {
+        match self.stages.iter_mut().find(|existing| existing.name() == name) {
+            Some(existing) => {
+                *existing = Box::new(stage);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn stage_names(&self) -> impl Iterator<Item = &str> {
+        self.stages.iter().map(|stage| stage.name())
+    }
+
+    // Pasa los vértices por todas las etapas. Con SSAA las etapas corren sobre el
+    // framebuffer de mayor resolución y luego se reduce
+    pub fn run(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex]) -> RenderStats {
+        framebuffer.matrices = Some(uniforms.matrices());
+        if uniforms.ssaa_factor > 1 {
+            let mut stats = framebuffer.draw_supersampled(uniforms, |supersampled, uniforms| self.run(supersampled, uniforms, vertex_array));
+            stats.memory.framebuffer_bytes = framebuffer.memory_bytes();
+            return stats;
+        }
+
+        let _span = trace_span!("render");
+        let mut context = StageContext { uniforms, framebuffer, stats: RenderStats::default() };
+        let mut data = StageData::Vertices(vertex_array.to_vec());
+        for stage in &self.stages {
+            let _span = trace_span!("stage", name = stage.name());
+            data = stage.process(data, &mut context);
+        }
+
+        let mut stats = context.stats;
+        stats.memory = MemoryStats { framebuffer_bytes: context.framebuffer.memory_bytes(), ..stats.memory };
+
+        // En debug avisamos (la primera vez) si se descartó geometría
+        if cfg!(debug_assertions) && (stats.invalid_vertices > 0 || stats.skipped_triangles > 0) {
+            warn_once(&SKIPPED_GEOMETRY_WARNED, || {
+                format!(
+                    "Advertencia: {} vértices inválidos, {} de {} triángulos descartados ({} con coordenadas no finitas)",
+                    stats.invalid_vertices, stats.skipped_triangles, stats.triangles, stats.non_finite_triangles
+                )
+            });
+        }
+
+        stats
+    }
+}
+
+impl Default for Pipeline<'_> {
+    fn default() -> Self {
+        Pipeline::new()
+    }
+}
@@ -0,0 +1,633 @@
+// postprocess.rs
+//
+// Orden recomendado de la cadena de post-procesado:
+//   SSAO -> profundidad de campo -> motion blur -> TAA -> bloom -> exposición automática
+// La profundidad de campo se aplica antes de los efectos que suman brillo (exposición,
+// bloom): así el desenfoque reparte la luz de forma física y los halos no se vuelven a
+// desenfocar con el radio de un pixel que no les corresponde.
+
+use nalgebra_glm::{Vec2, Vec4};
+use rayon::prelude::*;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::shader_params::ShaderParams;
+
+// Motion blur: promedia `samples` muestras a lo largo del vector de movimiento de cada pixel.
+// Los pixeles sin movimiento se saltan, así que una escena estática no cambia ni cuesta muestreo
+pub fn apply_motion_blur(framebuffer: &mut Framebuffer, strength: f32, samples: usize) {
+    if samples < 2 || strength == 0.0 {
+        return;
+    }
+
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let source = framebuffer.as_slice().to_vec();
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            let velocity: Vec2 = framebuffer.velocity_buffer[index] * strength;
+
+            // Los pixeles estáticos no necesitan muestreo
+            if velocity.x.abs() < 0.5 && velocity.y.abs() < 0.5 {
+                continue;
+            }
+
+            let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+            for i in 0..samples {
+                // Muestras centradas en el pixel, de -velocidad/2 a +velocidad/2
+                let t = i as f32 / (samples - 1) as f32 - 0.5;
+                let sx = (x as f32 + velocity.x * t).round().clamp(0.0, (width - 1) as f32) as usize;
+                let sy = (y as f32 + velocity.y * t).round().clamp(0.0, (height - 1) as f32) as usize;
+
+                let sample = Color::from_u32(source[sy * width + sx]);
+                r += sample.r as f32;
+                g += sample.g as f32;
+                b += sample.b as f32;
+            }
+
+            let n = samples as f32;
+            framebuffer.as_mut_slice()[index] = Color::new((r / n) as u8, (g / n) as u8, (b / n) as u8).to_u32();
+        }
+    }
+}
+
+// Anti-aliasing temporal: mezcla el frame actual con el historial reproyectado.
+// `blend_factor` es el peso del frame actual (0.1 = 10 % frame actual)
+pub fn apply_taa(framebuffer: &mut Framebuffer, blend_factor: f32) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+
+    // Primer frame: no hay historial que reproyectar
+    if framebuffer.history_buffer.len() != width * height {
+        framebuffer.history_buffer = framebuffer.as_slice().to_vec();
+        return;
+    }
+
+    let current = framebuffer.as_slice().to_vec();
+    let blend = blend_factor.clamp(0.0, 1.0);
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+
+            // Reproyectar: dónde estaba este pixel en el frame anterior
+            let velocity = framebuffer.velocity_buffer[index];
+            let prev_x = (x as f32 - velocity.x).round();
+            let prev_y = (y as f32 - velocity.y).round();
+            let color = Color::from_u32(current[index]);
+
+            // Si la posición anterior está fuera de pantalla no hay historial válido
+            if prev_x < 0.0 || prev_y < 0.0 || prev_x >= width as f32 || prev_y >= height as f32 {
+                continue;
+            }
+            let history = Color::from_u32(framebuffer.history_buffer[prev_y as usize * width + prev_x as usize]);
+
+            // Neighbour clamping: limitar el historial al rango de colores de los vecinos 3x3
+            let (mut min, mut max) = ([255u8; 3], [0u8; 3]);
+            for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    let neighbour = Color::from_u32(current[ny * width + nx]);
+                    for (channel, value) in [neighbour.r, neighbour.g, neighbour.b].into_iter().enumerate() {
+                        min[channel] = min[channel].min(value);
+                        max[channel] = max[channel].max(value);
+                    }
+                }
+            }
+            let clamped = Color::new(
+                history.r.clamp(min[0], max[0]),
+                history.g.clamp(min[1], max[1]),
+                history.b.clamp(min[2], max[2]),
+            );
+
+            framebuffer.as_mut_slice()[index] = (color * blend + clamped * (1.0 - blend)).to_u32();
+        }
+    }
+
+    framebuffer.history_buffer = framebuffer.as_slice().to_vec();
+}
+
+// Exposición automática: escala el frame para acercar su luminancia promedio a un objetivo.
+// La exposición se adapta gradualmente entre frames, como el ojo al cambiar de luz.
+pub struct AutoExposure {
+    pub target_luminance: f32, // Luminancia promedio deseada, en [0, 1]
+    pub adaptation_speed: f32, // Fracción de la diferencia que se corrige por frame, en [0, 1]
+    pub min_exposure: f32,
+    pub max_exposure: f32,
+    exposure: f32,
+}
+
+impl AutoExposure {
+    pub fn new(target_luminance: f32, adaptation_speed: f32) -> Self {
+        AutoExposure {
+            target_luminance,
+            adaptation_speed,
+            min_exposure: 0.1,
+            max_exposure: 10.0,
+            exposure: 1.0,
+        }
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    // Mide la luminancia del frame, adapta la exposición y la aplica
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer) {
+        let pixels = framebuffer.as_mut_slice();
+        if pixels.is_empty() {
+            return;
+        }
+
+        let total: f32 = pixels.iter().map(|&pixel| Color::from_u32(pixel).luminance()).sum();
+        let average = total / pixels.len() as f32;
+
+        // Un frame completamente negro no aporta información de exposición
+        if average > f32::EPSILON {
+            let desired = (self.target_luminance / average).clamp(self.min_exposure, self.max_exposure);
+            let speed = self.adaptation_speed.clamp(0.0, 1.0);
+            self.exposure += (desired - self.exposure) * speed;
+        }
+
+        for pixel in pixels.iter_mut() {
+            *pixel = (Color::from_u32(*pixel) * self.exposure).to_u32();
+        }
+    }
+}
+
+// Distancia de cada pixel a la cámara a lo largo de la vista (la z del espacio de vista),
+// fila por fila, a partir del z-buffer y de las matrices del último render
+// (`Framebuffer::current_matrices`); INFINITY en los pixeles sin cubrir. En perspectiva la
+// profundidad guardada es hiperbólica y así vuelve a ser lineal. Sin matrices registradas
+// se usa la profundidad tal cual
+pub fn linear_depths(framebuffer: &Framebuffer) -> Vec<f32> {
+    let mode = framebuffer.depth_mode();
+    let to_view = framebuffer
+        .current_matrices()
+        .and_then(|matrices| (matrices.viewport * matrices.projection).try_inverse());
+    let linear = |depth: f32| match &to_view {
+        Some(inverse) => {
+            let view = inverse * Vec4::new(0.0, 0.0, depth, 1.0);
+            view.z / view.w
+        }
+        None => depth,
+    };
+
+    let (width, height) = (framebuffer.width as isize, framebuffer.height as isize);
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| match framebuffer.get_depth(x, y).filter(|&depth| mode.is_covered(depth)) {
+            Some(depth) => linear(depth),
+            None => f32::INFINITY,
+        })
+        .collect()
+}
+
+// Parámetros del SSAO
+#[derive(Debug, Clone, Copy)]
+pub struct SsaoSettings {
+    pub kernel_size: usize,    // Muestras por pixel
+    pub radius: f32,           // Radio de muestreo en pixeles
+    pub depth_range: f32,      // Diferencia de distancia máxima que ocluye (unidades de la escena)
+    pub intensity: f32,        // 0 = sin efecto, 1 = oclusión completa en las zonas más cerradas
+    pub half_resolution: bool, // Calcular la oclusión a media resolución
+    pub debug_view: bool,      // Mostrar el buffer de oclusión crudo (sin desenfoque ni intensidad)
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        SsaoSettings {
+            kernel_size: 12,
+            radius: 8.0,
+            depth_range: 0.5,
+            intensity: 0.8,
+            half_resolution: true,
+            debug_view: false,
+        }
+    }
+}
+
+impl SsaoSettings {
+    // Lee la tabla [ssao] de shaders.toml; lo que falte queda con el valor por defecto
+    pub fn from_params(params: &ShaderParams) -> Self {
+        let default = SsaoSettings::default();
+        SsaoSettings {
+            kernel_size: params.float("ssao.kernel_size", default.kernel_size as f32).max(1.0) as usize,
+            radius: params.float("ssao.radius", default.radius).max(0.0),
+            depth_range: params.float("ssao.depth_range", default.depth_range).max(0.0),
+            intensity: params.float("ssao.intensity", default.intensity).clamp(0.0, 1.0),
+            half_resolution: params.flag("ssao.half_resolution", default.half_resolution),
+            debug_view: params.flag("ssao.debug_view", default.debug_view),
+        }
+    }
+}
+
+// Ambient occlusion en espacio de pantalla a partir del z-buffer, con la distancia lineal a
+// la cámara de `linear_depths` (en perspectiva la profundidad guardada es hiperbólica). Las
+// filas se procesan en paralelo
+pub fn apply_ssao(framebuffer: &mut Framebuffer, settings: &SsaoSettings) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    if width == 0 || height == 0 {
+        return;
+    }
+    let step = if settings.half_resolution { 2 } else { 1 };
+    let kernel_size = settings.kernel_size.max(1);
+    let depths = linear_depths(framebuffer);
+
+    // Kernel en espiral: offsets deterministas repartidos en un disco de radio `radius`
+    let kernel: Vec<(f32, f32)> = (0..kernel_size)
+        .map(|i| {
+            let t = (i as f32 + 0.5) / kernel_size as f32;
+            let angle = i as f32 * 2.399_963; // Ángulo dorado
+            let distance = settings.radius * t.sqrt();
+            (angle.cos() * distance, angle.sin() * distance)
+        })
+        .collect();
+
+    // Oclusión cruda (1 = totalmente ocluido), calculada cada `step` pixeles y replicada en
+    // su bloque (modo media resolución); cada tarea llena un bloque de `step` filas
+    let mut ao = vec![0.0f32; width * height];
+    ao.par_chunks_mut(width * step).enumerate().for_each(|(block, rows)| {
+        let y = block * step;
+        for x in (0..width).step_by(step) {
+            let depth = depths[y * width + x];
+            if !depth.is_finite() {
+                continue;
+            }
+
+            let mut occluded = 0;
+            for &(dx, dy) in &kernel {
+                let (sx, sy) = ((x as f32 + dx) as isize, (y as f32 + dy) as isize);
+                if sx < 0 || sy < 0 || sx >= width as isize || sy >= height as isize {
+                    continue;
+                }
+                // Ocluye si está más cerca que el centro, pero no demasiado lejos (evita halos)
+                let difference = depth - depths[sy as usize * width + sx as usize];
+                if difference > 0.01 && difference < settings.depth_range {
+                    occluded += 1;
+                }
+            }
+            let occlusion = occluded as f32 / kernel_size as f32;
+
+            for row in rows.chunks_mut(width) {
+                row[x..(x + step).min(width)].fill(occlusion);
+            }
+        }
+    });
+
+    // La vista de depuración muestra la oclusión cruda: blanco = sin oclusión
+    if settings.debug_view {
+        framebuffer.as_mut_slice().par_iter_mut().zip(ao).for_each(|(pixel, occlusion)| {
+            let value = ((1.0 - occlusion.clamp(0.0, 1.0)) * 255.0) as u8;
+            *pixel = Color::new(value, value, value).to_u32();
+        });
+        return;
+    }
+
+    // Desenfoque de caja 3x3 para eliminar el ruido del kernel
+    let mut blurred = vec![0.0f32; width * height];
+    blurred.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+        for (x, value) in row.iter_mut().enumerate() {
+            let (mut sum, mut count) = (0.0, 0.0);
+            for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    sum += ao[ny * width + nx];
+                    count += 1.0;
+                }
+            }
+            *value = sum / count;
+        }
+    });
+
+    framebuffer.as_mut_slice().par_iter_mut().zip(blurred).for_each(|(pixel, occlusion)| {
+        let factor = 1.0 - (occlusion * settings.intensity).clamp(0.0, 1.0);
+        *pixel = (Color::from_u32(*pixel) * factor).to_u32();
+    });
+}
+
+// Parámetros de la profundidad de campo, en unidades de la escena (distancias a lo largo
+// de la vista, ver `linear_depths`)
+#[derive(Debug, Clone, Copy)]
+pub struct DofSettings {
+    pub focal_distance: f32, // Distancia del plano enfocado (p. ej. la de la entidad seleccionada)
+    pub aperture: f32,       // Pixeles de desenfoque por unidad de distancia al plano focal
+    pub max_radius: f32,     // Radio máximo de desenfoque en pixeles
+}
+
+// Anillos de muestras del kernel de desenfoque (disco unitario)
+const DOF_SAMPLES: usize = 16;
+
+// Profundidad de campo en dos capas: los pixeles frente al plano focal (capa cercana) se
+// desenfocan con todas sus vecinas; los de detrás (capa lejana) solo con muestras que
+// también estén detrás del plano, ponderadas por similitud de profundidad, para que los
+// objetos enfocados no se "derramen" sobre el fondo desenfocado.
+pub fn apply_depth_of_field(framebuffer: &mut Framebuffer, settings: &DofSettings) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let source = framebuffer.as_slice().to_vec();
+    let depths = linear_depths(framebuffer);
+    let depth_at = |x: usize, y: usize| depths[y * width + x];
+
+    // Círculo de confusión: radio de desenfoque según la distancia al plano focal
+    let circle_of_confusion = |depth: f32| -> f32 {
+        if depth.is_finite() {
+            ((depth - settings.focal_distance).abs() * settings.aperture).min(settings.max_radius)
+        } else {
+            settings.max_radius // El fondo vacío se considera infinitamente lejos
+        }
+    };
+
+    let kernel: Vec<(f32, f32)> = (0..DOF_SAMPLES)
+        .map(|i| {
+            let t = (i as f32 + 0.5) / DOF_SAMPLES as f32;
+            let angle = i as f32 * 2.399_963;
+            (angle.cos() * t.sqrt(), angle.sin() * t.sqrt())
+        })
+        .collect();
+
+    let mut output = source.clone();
+    for y in 0..height {
+        for x in 0..width {
+            let depth = depth_at(x, y);
+            let radius = circle_of_confusion(depth);
+            if radius < 0.5 {
+                continue; // Dentro de la banda enfocada
+            }
+            let far_layer = depth > settings.focal_distance;
+
+            let center = Color::from_u32(source[y * width + x]);
+            let (mut r, mut g, mut b) = (center.r as f32, center.g as f32, center.b as f32);
+            let mut total_weight = 1.0;
+
+            for &(kx, ky) in &kernel {
+                let sx = (x as f32 + kx * radius).round();
+                let sy = (y as f32 + ky * radius).round();
+                if sx < 0.0 || sy < 0.0 || sx >= width as f32 || sy >= height as f32 {
+                    continue;
+                }
+                let (sx, sy) = (sx as usize, sy as usize);
+                let sample_depth = depth_at(sx, sy);
+
+                let weight = if far_layer {
+                    if sample_depth < settings.focal_distance {
+                        continue;
+                    }
+                    // Similitud de profundidad medida en pixeles de desenfoque
+                    if depth.is_finite() && sample_depth.is_finite() {
+                        1.0 / (1.0 + (sample_depth - depth).abs() * settings.aperture / settings.max_radius.max(1.0))
+                    } else {
+                        1.0
+                    }
+                } else {
+                    1.0
+                };
+
+                let sample = Color::from_u32(source[sy * width + sx]);
+                r += sample.r as f32 * weight;
+                g += sample.g as f32 * weight;
+                b += sample.b as f32 * weight;
+                total_weight += weight;
+            }
+
+            output[y * width + x] = Color::new(
+                (r / total_weight) as u8,
+                (g / total_weight) as u8,
+                (b / total_weight) as u8,
+            )
+            .to_u32();
+        }
+    }
+
+    framebuffer.as_mut_slice().copy_from_slice(&output);
+}
+
+// Parámetros del bloom
+#[derive(Debug, Clone, Copy)]
+pub struct BloomSettings {
+    pub threshold: f32, // Luminancia (0 a 1) desde la que un pixel brilla
+    pub intensity: f32, // Peso del halo al sumarlo al frame (0 = sin efecto)
+    pub radius: usize,  // Radio del desenfoque del halo en pixeles
+}
+
+// Bloom: lo que los pixeles superan de `threshold` se desenfoca (dos pasadas de caja
+// separables, que se acercan a una gaussiana) y se suma al frame, así las luces y las
+// superficies emisivas se derraman sobre lo que las rodea. Va después de la profundidad de
+// campo: los halos ya son borrosos y no se vuelven a desenfocar con el círculo de
+// confusión del pixel donde caen
+pub fn apply_bloom(framebuffer: &mut Framebuffer, settings: &BloomSettings) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    if width == 0 || height == 0 || settings.intensity <= 0.0 {
+        return;
+    }
+
+    // Pase de brillo: la parte de cada pixel por encima del umbral, en [0, 1] por canal
+    let threshold = settings.threshold.clamp(0.0, 1.0);
+    let mut halo: Vec<[f32; 3]> = framebuffer
+        .as_slice()
+        .par_iter()
+        .map(|&pixel| {
+            let color = Color::from_u32(pixel);
+            let rgb = [color.r as f32 / 255.0, color.g as f32 / 255.0, color.b as f32 / 255.0];
+            let luminance = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+            if luminance <= threshold {
+                return [0.0; 3];
+            }
+            let excess = (luminance - threshold) / luminance;
+            rgb.map(|channel| channel * excess)
+        })
+        .collect();
+
+    for _ in 0..2 {
+        halo = box_blur(&halo, width, height, settings.radius, true);
+        halo = box_blur(&halo, width, height, settings.radius, false);
+    }
+
+    framebuffer.as_mut_slice().par_iter_mut().zip(&halo).for_each(|(pixel, glow)| {
+        let color = Color::from_u32(*pixel);
+        let add = |channel: u8, glow: f32| (channel as f32 + glow * settings.intensity * 255.0).min(255.0) as u8;
+        *pixel = Color::new(add(color.r, glow[0]), add(color.g, glow[1]), add(color.b, glow[2])).to_u32();
+    });
+}
+
+// Promedio de caja de `radius` pixeles por lado, en horizontal o en vertical, por filas en
+// paralelo. En los bordes solo cuentan los pixeles dentro de la imagen
+fn box_blur(values: &[[f32; 3]], width: usize, height: usize, radius: usize, horizontal: bool) -> Vec<[f32; 3]> {
+    let mut output = vec![[0.0; 3]; values.len()];
+    output.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+        for (x, out) in row.iter_mut().enumerate() {
+            let (center, limit) = if horizontal { (x, width) } else { (y, height) };
+            let (start, end) = (center.saturating_sub(radius), (center + radius).min(limit - 1));
+            let mut sum = [0.0; 3];
+            for i in start..=end {
+                let value = if horizontal { values[y * width + i] } else { values[i * width + x] };
+                for channel in 0..3 {
+                    sum[channel] += value[channel];
+                }
+            }
+            let count = (end - start + 1) as f32;
+            *out = sum.map(|channel| channel / count);
+        }
+    });
+    output
+}
+
+// Vista de depuración del z-buffer: reemplaza el frame por la profundidad en escala de
+// grises, blanco lo más cercano y negro el fondo. Se normaliza con el rango de los pixeles
+// cubiertos, así que sirve igual con el modo estándar que con reversed-Z (`DepthMode`)
+pub fn visualize_depth(framebuffer: &mut Framebuffer) {
+    let mode = framebuffer.depth_mode();
+    let (width, height) = (framebuffer.width as isize, framebuffer.height as isize);
+    let depths: Vec<f32> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| framebuffer.get_depth(x, y).unwrap_or(mode.cleared_depth()))
+        .collect();
+
+    let (mut near, mut far) = (mode.cleared_depth(), mode.cleared_depth());
+    for &depth in depths.iter().filter(|&&d| mode.is_covered(d)) {
+        if !mode.is_covered(near) || mode.is_closer(depth, near) {
+            near = depth;
+        }
+        if !mode.is_covered(far) || mode.is_closer(far, depth) {
+            far = depth;
+        }
+    }
+    let range = (far - near).abs().max(f32::EPSILON);
+
+    for (pixel, &depth) in framebuffer.as_mut_slice().iter_mut().zip(&depths) {
+        *pixel = if mode.is_covered(depth) {
+            // 1 en el más cercano, 0.2 en el más lejano para distinguirlo del fondo
+            let closeness = 1.0 - (depth - near).abs() / range;
+            let gray = ((0.2 + 0.8 * closeness) * 255.0) as u8;
+            Color::new(gray, gray, gray).to_u32()
+        } else {
+            Color::black().to_u32()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, Projection};
+    use crate::framebuffer::{render, Rect};
+    use crate::material::Material;
+    use crate::uniforms::{viewport_matrix, CullMode, Matrices, Uniforms, UniformsBuilder};
+    use crate::vertex::Vertex;
+    use crate::viewport::Viewport;
+    use nalgebra_glm::Vec3;
+
+    // Cuadrado blanco de lado 1 centrado en el origen, mirando a la cámara
+    fn quad() -> Vec<Vertex> {
+        let corner = |x: f32, y: f32| {
+            let mut vertex = Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec2::zeros());
+            vertex.color = Color::new(255, 255, 255);
+            vertex
+        };
+        let (a, b, c, d) = (corner(-0.5, -0.5), corner(0.5, -0.5), corner(0.5, 0.5), corner(-0.5, 0.5));
+        vec![a.clone(), b, c.clone(), a, c, d]
+    }
+
+    fn average_luminance(framebuffer: &Framebuffer) -> f32 {
+        let pixels = framebuffer.as_slice();
+        pixels.iter().map(|&pixel| Color::from_u32(pixel).luminance()).sum::<f32>() / pixels.len() as f32
+    }
+
+    #[test]
+    fn auto_exposure_darkens_a_bright_frame_toward_the_target() {
+        let mut exposure = AutoExposure::new(0.3, 0.5);
+        let mut framebuffer = Framebuffer::new(16, 16);
+        let mut previous = f32::INFINITY;
+
+        // Cada frame llega igual de brillante, como si la escena no cambiara
+        for _ in 0..10 {
+            framebuffer.clear(Color::new(230, 230, 230));
+            exposure.apply(&mut framebuffer);
+            let luminance = average_luminance(&framebuffer);
+            assert!(luminance <= previous);
+            previous = luminance;
+        }
+
+        assert!(exposure.exposure() < 1.0);
+        assert!((previous - 0.3).abs() < 0.01, "luminancia final {previous}");
+    }
+
+    // El cuadrado se mueve 10 pixeles entre dos frames: donde estaba queda el fondo (el
+    // historial blanco se limita al negro de los vecinos), y donde está ahora el historial
+    // reproyectado con la velocidad mantiene el color del frame
+    #[test]
+    fn taa_leaves_no_ghost_behind_a_moving_quad() {
+        let material = Material { unlit: true, ..Material::default() };
+        let mut uniforms = UniformsBuilder::new().viewport(viewport_matrix(48, 32, 10.0)).material(material).build();
+        let mut framebuffer = Framebuffer::new(48, 32);
+        let frame = |framebuffer: &mut Framebuffer, uniforms: &Uniforms| {
+            framebuffer.clear(Color::black());
+            render(framebuffer, uniforms, &quad());
+            apply_taa(framebuffer, 0.1);
+        };
+
+        // El primer frame solo llena el historial
+        frame(&mut framebuffer, &uniforms);
+        assert_eq!(framebuffer.get_pixel(24, 16), Some(0xFFFF_FFFF));
+
+        let moved = Matrices { model: nalgebra_glm::translation(&Vec3::new(1.0, 0.0, 0.0)), ..uniforms.matrices() };
+        uniforms.next_frame(moved);
+        frame(&mut framebuffer, &uniforms);
+        for x in 20..=28isize {
+            assert_eq!(framebuffer.get_pixel(x - 10, 16), Some(0xFF00_0000), "estela en x = {}", x - 10);
+            let moved = Color::from_u32(framebuffer.get_pixel(x + 10, 16).unwrap());
+            assert!(moved.r >= 250 && moved.g >= 250 && moved.b >= 250, "x = {}", x + 10);
+        }
+
+        // Sin historial (corte de cámara) el frame pasa tal cual
+        framebuffer.history_buffer.clear();
+        let current = framebuffer.as_slice().to_vec();
+        apply_taa(&mut framebuffer, 0.1);
+        assert_eq!(framebuffer.as_slice(), current.as_slice());
+    }
+
+    // Rincón interior visto en perspectiva desde (0, 0, -3): una pared al fondo (z = 1) y
+    // otra perpendicular en x = 0.5 que llega hasta ella. En pantalla el pliegue cae en
+    // x ~ 39 y la pared lateral a su derecha
+    fn corner_scene(settings: &SsaoSettings) -> Framebuffer {
+        let wall = |corners: [Vec3; 4]| {
+            let vertices: Vec<Vertex> = corners.iter().map(|&corner| Vertex::new_with_color(corner, Color::new(255, 255, 255))).collect();
+            vec![vertices[0].clone(), vertices[1].clone(), vertices[2].clone(), vertices[0].clone(), vertices[2].clone(), vertices[3].clone()]
+        };
+        let back = wall([Vec3::new(-3.0, -3.0, 1.0), Vec3::new(3.0, -3.0, 1.0), Vec3::new(3.0, 3.0, 1.0), Vec3::new(-3.0, 3.0, 1.0)]);
+        let side = wall([Vec3::new(0.5, -3.0, -1.0), Vec3::new(0.5, -3.0, 1.0), Vec3::new(0.5, 3.0, 1.0), Vec3::new(0.5, 3.0, -1.0)]);
+
+        let camera = Camera::new(Vec3::new(0.0, 0.0, -3.0));
+        let viewport = Viewport::perspective(Rect::new(0, 0, 64, 64), camera.view_matrix(), &Projection::default());
+        let material = Material { unlit: true, ..Material::default() };
+        let uniforms = viewport.apply(&UniformsBuilder::new().material(material).cull_mode(CullMode::None).build());
+
+        let mut framebuffer = Framebuffer::new(64, 64);
+        framebuffer.clear(Color::black());
+        render(&mut framebuffer, &uniforms, &back);
+        render(&mut framebuffer, &uniforms, &side);
+        apply_ssao(&mut framebuffer, settings);
+        framebuffer
+    }
+
+    fn brightness(framebuffer: &Framebuffer, x: isize, y: isize) -> u8 {
+        Color::from_u32(framebuffer.get_pixel(x, y).unwrap()).r
+    }
+
+    #[test]
+    fn ssao_darkens_the_inner_corner_and_not_the_flat_wall() {
+        let settings = SsaoSettings { kernel_size: 16, radius: 6.0, depth_range: 2.0, half_resolution: false, ..SsaoSettings::default() };
+        let shaded = corner_scene(&settings);
+        let (corner, flat) = (brightness(&shaded, 37, 32), brightness(&shaded, 16, 32));
+        assert_eq!(flat, 255);
+        assert!(corner < 230, "rincón {corner}");
+
+        // La vista de depuración es la oclusión cruda: la misma con cualquier intensidad,
+        // blanca en la pared plana y más oscura en el rincón que con el desenfoque aplicado
+        let debug = corner_scene(&SsaoSettings { debug_view: true, ..settings });
+        let dimmed = corner_scene(&SsaoSettings { debug_view: true, intensity: 0.1, ..settings });
+        assert_eq!(debug.as_slice(), dimmed.as_slice());
+        assert_eq!(brightness(&debug, 16, 32), 255);
+        assert!(brightness(&debug, 37, 32) < 255);
+    }
+}
@@ -0,0 +1,51 @@
+// profiling.rs
+//
+// Instrumentación con `tracing` de las etapas del render (vertex shading, primitive
+// assembly, culling, rasterización, fragment shading y escritura al framebuffer).
+// Solo existe con el feature `trace`; sin él, `trace_span!` no genera código.
+//
+//   RUST_LOG=trace cargo run --release --features trace
+//
+// Al cerrar el programa queda un archivo `trace-<timestamp>.json` que se abre en
+// chrome://tracing o https://ui.perfetto.dev para ver cuánto tarda cada etapa por frame.
+
+// Abre un span que dura hasta el final del bloque: `let _span = trace_span!("etapa");`.
+// Acepta campos como `tracing::trace_span!`: `trace_span!("stage", name = stage.name())`
+#[cfg(feature = "trace")]
+macro_rules! trace_span {
+    ($name:expr $(, $($fields:tt)*)?) => {
+        tracing::trace_span!($name $(, $($fields)*)?).entered()
+    };
+}
+
+#[cfg(not(feature = "trace"))]
+macro_rules! trace_span {
+    ($name:expr $(, $($fields:tt)*)?) => {
+        ()
+    };
+}
+
+// Guarda que escribe el archivo de trazas al salir de `main`
+#[cfg(feature = "trace")]
+pub type TraceGuard = tracing_chrome::FlushGuard;
+
+#[cfg(not(feature = "trace"))]
+pub struct TraceGuard;
+
+// Registra el subscriber de Chrome trace, filtrado con RUST_LOG
+#[cfg(feature = "trace")]
+pub fn init() -> TraceGuard {
+    use tracing_subscriber::prelude::*;
+
+    let (chrome_layer, guard) = tracing_chrome::ChromeLayerBuilder::new().build();
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::from_default_env())
+        .with(chrome_layer)
+        .init();
+    guard
+}
+
+#[cfg(not(feature = "trace"))]
+pub fn init() -> TraceGuard {
+    TraceGuard
+}
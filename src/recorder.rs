@@ -0,0 +1,147 @@
+// recorder.rs
+
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Sender};
+use std::thread::JoinHandle;
+use crate::framebuffer::{write_png, Framebuffer};
+
+// Frame copiado para escribirse en el hilo de grabación
+struct CapturedFrame {
+    path: PathBuf,
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+// Graba frames como una secuencia numerada de PNG (frame_0000.png, frame_0001.png, ...).
+// La escritura a disco ocurre en un hilo aparte para no bloquear el render.
+pub struct FrameRecorder {
+    output_dir: PathBuf,
+    max_frames: usize,
+    frames_written: usize,
+    recording: bool,
+    sender: Option<Sender<CapturedFrame>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl FrameRecorder {
+    pub fn new(output_dir: &str, max_frames: usize) -> Self {
+        FrameRecorder {
+            output_dir: PathBuf::from(output_dir),
+            max_frames,
+            frames_written: 0,
+            recording: false,
+            sender: None,
+            worker: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn frames_written(&self) -> usize {
+        self.frames_written
+    }
+
+    // Inicia o detiene la grabación
+    pub fn toggle(&mut self) -> std::io::Result<()> {
+        if self.recording {
+            self.stop();
+            Ok(())
+        } else {
+            self.start()
+        }
+    }
+
+    pub fn start(&mut self) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.output_dir)?;
+
+        let (sender, receiver) = channel::<CapturedFrame>();
+        let worker = std::thread::spawn(move || {
+            for frame in receiver {
+                let path = frame.path.to_string_lossy().into_owned();
+                if let Err(e) = write_png(&path, frame.width, frame.height, &frame.pixels) {
+                    eprintln!("Error guardando {}: {}", path, e);
+                }
+            }
+        });
+
+        self.sender = Some(sender);
+        self.worker = Some(worker);
+        self.frames_written = 0;
+        self.recording = true;
+        Ok(())
+    }
+
+    // Detiene la grabación y espera a que se terminen de escribir los frames pendientes
+    pub fn stop(&mut self) {
+        self.recording = false;
+        self.sender = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+
+    // Copia el frame presentado y lo envía al hilo de escritura
+    pub fn capture(&mut self, framebuffer: &Framebuffer) {
+        self.capture_pixels(framebuffer.width, framebuffer.height, framebuffer.present_buffer());
+    }
+
+    // Como `capture`, para una imagen que no está en un framebuffer (p. ej. el frame ya
+    // ampliado al tamaño de la ventana)
+    pub fn capture_pixels(&mut self, width: usize, height: usize, pixels: &[u32]) {
+        if !self.recording {
+            return;
+        }
+
+        if let Some(sender) = &self.sender {
+            let frame = CapturedFrame {
+                path: self.output_dir.join(format!("frame_{:04}.png", self.frames_written)),
+                width,
+                height,
+                pixels: pixels.to_vec(),
+            };
+            if sender.send(frame).is_ok() {
+                self.frames_written += 1;
+            }
+        }
+
+        if self.frames_written >= self.max_frames {
+            self.stop();
+        }
+    }
+}
+
+impl Drop for FrameRecorder {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn records_three_frames_headless() {
+        let output_dir = std::env::temp_dir().join(format!("renderer_recorder_{}", std::process::id()));
+        let mut recorder = FrameRecorder::new(output_dir.to_str().unwrap(), 3);
+        let mut framebuffer = Framebuffer::new(8, 6);
+        recorder.start().unwrap();
+
+        // El cuarto frame llega con la grabación ya detenida y no se escribe
+        for frame in 0..4u8 {
+            framebuffer.clear(Color::new(frame * 60, 0, 0));
+            recorder.capture(&framebuffer);
+        }
+
+        assert!(!recorder.is_recording());
+        assert_eq!(recorder.frames_written(), 3);
+        let mut files: Vec<_> = std::fs::read_dir(&output_dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        files.sort();
+        assert_eq!(files, ["frame_0000.png", "frame_0001.png", "frame_0002.png"]);
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+// render_queue.rs
+//
+// Orden de dibujo de los objetos de un frame. Los objetos opacos se dibujan primero, de
+// adelante hacia atrás (lo más cercano llena el z-buffer antes y lo de atrás falla la
+// prueba de profundidad); los que se combinan con el pixel (BlendMode distinto de Opaque)
+// después, de atrás hacia adelante, para que cada capa se mezcle sobre lo que queda detrás
+// de ella. La distancia de cada objeto es la profundidad en pantalla del centro de su caja
+// envolvente, comparada según el DepthMode del framebuffer (vale también con reversed-Z).
+//
+// El orden es por objeto: los triángulos de una misma malla transparente se dibujan en el
+// orden de la malla, así que una malla que se tapa a sí misma (sin culling) puede mezclarse
+// mal. Con profundidades iguales se respeta el orden en que se agregaron.
+
+use std::cmp::Ordering;
+
+use nalgebra_glm::{Vec3, Vec4};
+
+use crate::framebuffer::{render, DepthMode, Framebuffer, RenderStats};
+use crate::material::BlendMode;
+use crate::math::BoundingBox;
+use crate::uniforms::Uniforms;
+use crate::vertex::Vertex;
+
+// Un objeto a dibujar: la malla con sus Uniforms (matrices y material)
+pub struct DrawItem<'a> {
+    pub uniforms: Uniforms,
+    pub vertices: &'a [Vertex],
+    center: Vec3, // Centro de la caja envolvente, en espacio del modelo
+}
+
+impl DrawItem<'_> {
+    // Profundidad en pantalla del centro del objeto (NaN si queda detrás de la cámara)
+    pub fn depth(&self) -> f32 {
+        let projected = self.uniforms.transformation_matrix * Vec4::new(self.center.x, self.center.y, self.center.z, 1.0);
+        if projected.w > f32::EPSILON { projected.z / projected.w } else { f32::NAN }
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.uniforms.material.blend_mode == BlendMode::Opaque
+    }
+}
+
+#[derive(Default)]
+pub struct RenderQueue<'a> {
+    items: Vec<DrawItem<'a>>,
+}
+
+impl<'a> RenderQueue<'a> {
+    pub fn new() -> Self {
+        RenderQueue { items: Vec::new() }
+    }
+
+    pub fn push(&mut self, uniforms: Uniforms, vertices: &'a [Vertex]) {
+        let center = BoundingBox::from_vertices(vertices).map_or(Vec3::zeros(), |bounds| (bounds.min + bounds.max) / 2.0);
+        self.items.push(DrawItem { uniforms, vertices, center });
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn items(&self) -> &[DrawItem<'a>] {
+        &self.items
+    }
+
+    // Índices de los objetos (en el orden en que se agregaron) en el orden de dibujo: los
+    // opacos de adelante hacia atrás y después los demás de atrás hacia adelante. Los que
+    // quedan detrás de la cámara van al final de su grupo
+    pub fn draw_order(&self, depth_mode: DepthMode) -> Vec<usize> {
+        let closer_first = |a: f32, b: f32| match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            _ if depth_mode.is_closer(a, b) => Ordering::Less,
+            _ if depth_mode.is_closer(b, a) => Ordering::Greater,
+            _ => Ordering::Equal,
+        };
+        let depths: Vec<f32> = self.items.iter().map(DrawItem::depth).collect();
+
+        let (mut opaque, mut blended): (Vec<usize>, Vec<usize>) = (0..self.items.len()).partition(|&i| self.items[i].is_opaque());
+        opaque.sort_by(|&a, &b| closer_first(depths[a], depths[b]));
+        blended.sort_by(|&a, &b| match (depths[a].is_nan(), depths[b].is_nan()) {
+            (false, false) => closer_first(depths[b], depths[a]),
+            _ => closer_first(depths[a], depths[b]),
+        });
+        let mut order = opaque;
+        order.extend(blended);
+        order
+    }
+
+    // Dibuja todos los objetos en `draw_order` con la prueba de profundidad del framebuffer
+    pub fn render(&self, framebuffer: &mut Framebuffer) -> RenderStats {
+        let mut stats = RenderStats::default();
+        for index in self.draw_order(framebuffer.depth_mode()) {
+            let item = &self.items[index];
+            stats += render(framebuffer, &item.uniforms, item.vertices);
+        }
+        stats
+    }
+}
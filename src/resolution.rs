@@ -0,0 +1,164 @@
+// resolution.rs
+//
+// Resolución dinámica: el frame se renderiza en un framebuffer más chico que la ventana
+// (escala de 0.5 a 1.0 por lado) y se amplía con filtrado bilineal al presentarlo. Una vez
+// por segundo se compara el tiempo promedio de frame con el del FPS objetivo y se ajusta la
+// escala. El costo del render es aproximadamente proporcional a los pixeles (escala al
+// cuadrado), así que el ajuste usa la raíz de la razón de tiempos.
+//
+// Para que no oscile: solo se ajusta si el tiempo sale de una banda muerta alrededor del
+// objetivo (más de 10% lento o menos de 75% del tiempo), se sube de a poco y la ventana
+// de medición que sigue a un cambio se descarta, porque mezcla frames de las dos escalas.
+
+pub const MIN_SCALE: f32 = 0.5;
+pub const MAX_SCALE: f32 = 1.0;
+
+// Banda muerta: razón tiempo medido / tiempo objetivo dentro de la cual no se ajusta
+const SLOW_RATIO: f32 = 1.1;
+const FAST_RATIO: f32 = 0.75;
+// Mayor subida por ajuste; las bajadas pueden ser de golpe
+const MAX_STEP_UP: f32 = 0.1;
+// La escala se redondea a múltiplos de este paso para que el tamaño no cambie por décimas
+const SCALE_QUANTUM: f32 = 0.05;
+// Segundos de frames que se promedian antes de decidir
+const MEASURE_WINDOW: f32 = 1.0;
+
+#[derive(Debug, Clone)]
+pub struct ResolutionScaler {
+    target_fps: f32, // <= 0 = sin escalado automático
+    scale: f32,
+    pinned: Option<f32>, // Escala fijada a mano (ignora las mediciones)
+    measured_time: f32,
+    measured_frames: u32,
+    skip_window: bool, // La ventana actual empezó con un cambio de escala
+}
+
+impl ResolutionScaler {
+    pub fn new(target_fps: f32) -> Self {
+        ResolutionScaler {
+            target_fps,
+            scale: MAX_SCALE,
+            pinned: None,
+            measured_time: 0.0,
+            measured_frames: 0,
+            skip_window: false,
+        }
+    }
+
+    pub fn set_target_fps(&mut self, target_fps: f32) {
+        self.target_fps = target_fps;
+        self.restart_measurement(false);
+    }
+
+    // Escala actual por lado, en [MIN_SCALE, MAX_SCALE]
+    pub fn scale(&self) -> f32 {
+        self.pinned.unwrap_or(self.scale)
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned.is_some()
+    }
+
+    // Fija la escala (None vuelve al ajuste automático desde la escala fijada)
+    pub fn pin(&mut self, scale: Option<f32>) {
+        if let Some(pinned) = self.pinned {
+            self.scale = pinned;
+        }
+        self.pinned = scale.map(quantize);
+        self.restart_measurement(true);
+    }
+
+    // Registra la duración de un frame (segundos). Devuelve la nueva escala si cambió
+    pub fn record_frame(&mut self, frame_time: f32) -> Option<f32> {
+        if self.pinned.is_some() || self.target_fps <= 0.0 || !frame_time.is_finite() {
+            return None;
+        }
+        self.measured_time += frame_time.max(0.0);
+        self.measured_frames += 1;
+        if self.measured_time < MEASURE_WINDOW {
+            return None;
+        }
+
+        let average = self.measured_time / self.measured_frames as f32;
+        let skip = self.skip_window;
+        self.restart_measurement(false);
+        if skip {
+            return None;
+        }
+
+        let ratio = average * self.target_fps;
+        if (FAST_RATIO..=SLOW_RATIO).contains(&ratio) {
+            return None;
+        }
+        let ideal = self.scale / ratio.sqrt();
+        let next = quantize(ideal.min(self.scale + MAX_STEP_UP));
+        if (next - self.scale).abs() < SCALE_QUANTUM / 2.0 {
+            return None;
+        }
+        self.scale = next;
+        self.skip_window = true;
+        Some(next)
+    }
+
+    // Tamaño del framebuffer interno para una ventana de width x height
+    pub fn internal_size(&self, width: usize, height: usize) -> (usize, usize) {
+        let scale = self.scale();
+        (((width as f32 * scale).round() as usize).max(1), ((height as f32 * scale).round() as usize).max(1))
+    }
+
+    fn restart_measurement(&mut self, skip_next: bool) {
+        self.measured_time = 0.0;
+        self.measured_frames = 0;
+        self.skip_window = skip_next;
+    }
+}
+
+fn quantize(scale: f32) -> f32 {
+    ((scale / SCALE_QUANTUM).round() * SCALE_QUANTUM).clamp(MIN_SCALE, MAX_SCALE)
+}
+
+// Amplía (o reduce) una imagen 0xAARRGGBB con filtrado bilineal, haciendo coincidir los
+// centros de los pixeles; `target` debe tener target_width * target_height pixeles. Con
+// el mismo tamaño es una copia
+pub fn upscale_bilinear(source: &[u32], source_width: usize, source_height: usize, target: &mut [u32], target_width: usize, target_height: usize) {
+    assert_eq!(source.len(), source_width * source_height, "La imagen de origen necesita width * height pixeles");
+    assert_eq!(target.len(), target_width * target_height, "La imagen de destino necesita width * height pixeles");
+    if source_width == target_width && source_height == target_height {
+        target.copy_from_slice(source);
+        return;
+    }
+    if source.is_empty() {
+        target.fill(0);
+        return;
+    }
+
+    // Posición en el origen en punto fijo de 8 bits de fracción
+    let sample_coordinate = |i: usize, source_size: usize, target_size: usize| {
+        let position = ((i as f32 + 0.5) * source_size as f32 / target_size as f32 - 0.5).max(0.0);
+        let base = (position as usize).min(source_size - 1);
+        let weight = ((position - base as f32) * 256.0) as u32;
+        (base, (base + 1).min(source_size - 1), weight.min(256))
+    };
+    let columns: Vec<(usize, usize, u32)> = (0..target_width).map(|x| sample_coordinate(x, source_width, target_width)).collect();
+
+    for (y, row) in target.chunks_exact_mut(target_width).enumerate() {
+        let (y0, y1, ty) = sample_coordinate(y, source_height, target_height);
+        let top = &source[y0 * source_width..(y0 + 1) * source_width];
+        let bottom = &source[y1 * source_width..(y1 + 1) * source_width];
+        for (pixel, &(x0, x1, tx)) in row.iter_mut().zip(&columns) {
+            let upper = lerp_pixel(top[x0], top[x1], tx);
+            let lower = lerp_pixel(bottom[x0], bottom[x1], tx);
+            *pixel = lerp_pixel(upper, lower, ty);
+        }
+    }
+}
+
+// Mezcla canal por canal: a * (256 - t) / 256 + b * t / 256
+fn lerp_pixel(a: u32, b: u32, t: u32) -> u32 {
+    let mut result = 0;
+    for shift in [0, 8, 16, 24] {
+        let (ca, cb) = ((a >> shift) & 0xFF, (b >> shift) & 0xFF);
+        result |= ((ca * (256 - t) + cb * t) >> 8) << shift;
+    }
+    result
+}
@@ -0,0 +1,79 @@
+// seed.rs
+//
+// Semilla maestra de la escena. Todo lo procedural (el ruido de los shaders, las estrellas
+// del fondo, la forma de las rocas, el cinturón de asteroides, las partículas) toma su
+// semilla de aquí, derivada de la maestra con un nombre fijo para cada uso. Con la misma
+// semilla maestra la escena se genera igual en cada ejecución; cambiarla cambia todo a la
+// vez sin que dos usos queden correlacionados.
+//
+// La semilla maestra sale de `--seed N` en la línea de comandos o de `seed` en la tabla
+// [scene] de shaders.toml; si no, la de siempre (`DEFAULT_SEED`).
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::shader_params::ShaderParams;
+
+pub const DEFAULT_SEED: u64 = 2312;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SceneSeed {
+    master: u64,
+}
+
+impl SceneSeed {
+    pub fn new(master: u64) -> Self {
+        SceneSeed { master }
+    }
+
+    // Semilla maestra nueva a partir del reloj del sistema
+    pub fn random() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        SceneSeed::new(splitmix64(nanos))
+    }
+
+    // `--seed N` de los argumentos, después [scene] seed de los parámetros, después la de
+    // siempre. Un `--seed` que no es un número es un error
+    pub fn from_args(args: impl IntoIterator<Item = String>, params: &ShaderParams) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let value = match arg.strip_prefix("--seed") {
+                Some("") => args.next().ok_or("Falta el valor de --seed")?,
+                Some(value) if value.starts_with('=') => value[1..].to_string(),
+                _ => continue,
+            };
+            return value.parse().map(SceneSeed::new).map_err(|_| format!("Semilla inválida: {}", value));
+        }
+        // Los parámetros son f32: los enteros son exactos hasta 2^24
+        let seed = params.float("scene.seed", -1.0);
+        Ok(SceneSeed::new(if seed >= 0.0 { seed as u64 } else { DEFAULT_SEED }))
+    }
+
+    pub fn master(&self) -> u64 {
+        self.master
+    }
+
+    // Semilla del uso `name` ("belt", "stars", ...)
+    pub fn derive(&self, name: &str) -> u64 {
+        splitmix64(self.master ^ fnv1a(name.as_bytes()))
+    }
+
+    // Semilla del uso `name` para los shaders, que la leen de un parámetro f32 del material
+    // ("seed"): se recorta a 24 bits para que el valor sea exacto
+    pub fn shader(&self, name: &str) -> u32 {
+        (self.derive(name) >> 40) as u32
+    }
+}
+
+// Paso de SplitMix64: mezcla los 64 bits, así semillas consecutivas dan valores sin relación
+pub fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// FNV-1a de 64 bits: estable entre ejecuciones y versiones de Rust, a diferencia del
+// hasher de la biblioteca estándar
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
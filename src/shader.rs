@@ -1,24 +1,704 @@
-use nalgebra_glm::{Vec3, Vec4};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use nalgebra_glm::{Mat4, Vec2, Vec3, Vec4};
+use crate::color::Color;
 use crate::vertex::Vertex;
-use crate::uniforms::Uniforms; // Importar Uniforms desde uniforms.rs
+use crate::fragment::Fragment;
+use crate::material::Material;
+use crate::uniforms::{NoiseSource, ShadingModel, Uniforms, Winding}; // Importar Uniforms desde uniforms.rs
+use crate::light::incident_light;
 
-pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
-    // Transform position
-    let position = Vec4::new(
-        vertex.position.x,
-        vertex.position.y,
-        vertex.position.z,
-        1.0
-    );
-    let transformed = uniforms.model_matrix * position;
+// Transform a position by a matrix and perform the perspective division
+fn project(matrix: &Mat4, position: &Vec3) -> Vec3 {
+    project_homogeneous(matrix, position).0
+}
+
+// Same as `project`, also returning the position before the division
+fn project_homogeneous(matrix: &Mat4, position: &Vec3) -> (Vec3, Vec4) {
+    let transformed = matrix * Vec4::new(position.x, position.y, position.z, 1.0);
 
-    // Perform perspective division
+    // Vertices with w <= 0 lie behind the camera and can't be divided, so they are marked
+    // as NaN; primitive assembly clips their triangles against the near plane using the
+    // undivided position
     let w = transformed.w;
-    let transformed_position = Vec3::new(
-        transformed.x / w,
-        transformed.y / w,
-        transformed.z / w
+    if w > f32::EPSILON {
+        let projected = Vec3::new(
+            transformed.x / w,
+            transformed.y / w,
+            transformed.z / w
+        );
+        (projected, transformed)
+    } else {
+        (Vec3::repeat(f32::NAN), transformed)
+    }
+}
+
+// The camera looks down +z in transformed space (smaller depth is closer)
+const VIEW_DIR: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+
+// Lambert diffuse term. With `two_sided`, normals of back-facing surfaces are
+// flipped toward the viewer so thin open meshes are lit on both sides.
+pub fn diffuse_intensity(normal: &Vec3, light_dir: &Vec3, two_sided: bool) -> f32 {
+    let facing_away = normal.dot(&VIEW_DIR) > 0.0;
+    let normal = if two_sided && facing_away { -normal } else { *normal };
+    normal.dot(light_dir).max(0.0)
+}
+
+// Direction the camera looks along to see `world_position`, in world space like the lit
+// normals. Orthographic views share one direction for every point; perspective views
+// diverge from the eye. Assumes a rigid view matrix.
+pub fn view_vector(world_position: &Vec3, uniforms: &Uniforms) -> Vec3 {
+    let rotation = uniforms.view_matrix.fixed_view::<3, 3>(0, 0).into_owned();
+    let perspective = uniforms.projection_matrix[(3, 2)] != 0.0;
+    let view_space = if perspective {
+        rotation * world_position + uniforms.view_matrix.fixed_view::<3, 1>(0, 3)
+    } else {
+        VIEW_DIR
+    };
+    let direction = rotation.transpose() * view_space;
+    let length = direction.norm();
+    if length > f32::EPSILON && length.is_finite() { direction / length } else { VIEW_DIR }
+}
+
+// Mirror direction of what the camera sees at a surface: the view vector reflected
+// about the normal. Environment maps are sampled in this direction.
+pub fn reflect_view(normal: &Vec3, view: &Vec3) -> Vec3 {
+    view - normal * (2.0 * normal.dot(view))
+}
+
+// Blend weights of the YZ, XZ and XY projections for triplanar mapping. Higher
+// `sharpness` narrows the transition between planes; the weights add up to 1.
+pub fn triplanar_weights(normal: &Vec3, sharpness: f32) -> Vec3 {
+    let weights = Vec3::new(
+        normal.x.abs().powf(sharpness),
+        normal.y.abs().powf(sharpness),
+        normal.z.abs().powf(sharpness),
     );
+    let total = weights.x + weights.y + weights.z;
+    if total > 0.0 { weights / total } else { Vec3::new(0.0, 0.0, 1.0) }
+}
+
+// Triplanar mapping: samples a 2D function three times with the position projected
+// onto the YZ, XZ and XY planes and blends by the normal. Unlike UV sampling there is
+// no seam and no pinching at the poles of a sphere. `sample` can wrap a texture lookup
+// or a 2D noise function.
+pub fn triplanar(position: &Vec3, normal: &Vec3, sharpness: f32, sample: impl Fn(Vec2) -> Color) -> Color {
+    let weights = triplanar_weights(normal, sharpness);
+    let x = sample(Vec2::new(position.y, position.z));
+    let y = sample(Vec2::new(position.x, position.z));
+    let z = sample(Vec2::new(position.x, position.y));
+    x * weights.x + y * weights.y + z * weights.z
+}
+
+// Same as `triplanar` for scalar functions (noise, height, masks)
+pub fn triplanar_scalar(position: &Vec3, normal: &Vec3, sharpness: f32, sample: impl Fn(Vec2) -> f32) -> f32 {
+    let weights = triplanar_weights(normal, sharpness);
+    sample(Vec2::new(position.y, position.z)) * weights.x
+        + sample(Vec2::new(position.x, position.z)) * weights.y
+        + sample(Vec2::new(position.x, position.y)) * weights.z
+}
+
+// Hermite interpolation between 0 and 1 as `x` goes from `edge0` to `edge1`
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Deterministic pseudo-random vector in [0, 1)^3 for an integer lattice cell
+fn hash_cell(x: i32, y: i32, z: i32) -> Vec3 {
+    hash_cell_seeded(x, y, z, 0)
+}
+
+// `hash_cell` with an extra seed; seed 0 gives the same vectors as `hash_cell`
+fn hash_cell_seeded(x: i32, y: i32, z: i32, seed: u32) -> Vec3 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f)
+        ^ seed.wrapping_mul(0x9e37_79b1);
+    let mut next = || {
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x2c1b_3c6d);
+        h ^= h >> 12;
+        h = h.wrapping_mul(0x297a_2d39);
+        h ^= h >> 15;
+        (h & 0x00ff_ffff) as f32 / 16_777_216.0
+    };
+    Vec3::new(next(), next(), next())
+}
+
+// Cellular (Worley) noise: distance from `position` to the nearest feature point,
+// with one random feature point per unit cell. Returns (distance, cell value in [0, 1)).
+pub fn cellular_noise(position: &Vec3) -> (f32, f32) {
+    cellular_noise_seeded(position, 0)
+}
+
+// `cellular_noise` with an independent set of feature points per seed (seed 0 is the
+// unseeded one)
+pub fn cellular_noise_seeded(position: &Vec3, seed: u32) -> (f32, f32) {
+    let cell = position.map(|c| c.floor());
+    let mut nearest = f32::INFINITY;
+    let mut nearest_value = 0.0;
+
+    for dz in -1..=1 {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (cx, cy, cz) = (cell.x as i32 + dx, cell.y as i32 + dy, cell.z as i32 + dz);
+                let jitter = hash_cell_seeded(cx, cy, cz, seed);
+                let feature = Vec3::new(cx as f32, cy as f32, cz as f32) + jitter;
+                let distance = (feature - position).norm();
+                if distance < nearest {
+                    nearest = distance;
+                    nearest_value = jitter.x;
+                }
+            }
+        }
+    }
+
+    (nearest, nearest_value)
+}
+
+// 2D Worley noise over a jittered grid: distance from (x, y) to the nearest feature
+// point, one per unit cell. `seed` picks an independent set of feature points.
+pub fn worley_2d(x: f32, y: f32, seed: u32) -> f32 {
+    worley_2d_cells(x, y, seed).0
+}
+
+// Distances to the nearest (F1) and second nearest (F2) feature points, plus a random
+// value in [0, 1) for the nearest point's cell. F2 - F1 is 0 on the borders between cells.
+fn worley_2d_cells(x: f32, y: f32, seed: u32) -> (f32, f32, f32) {
+    let (cell_x, cell_y) = (x.floor() as i32, y.floor() as i32);
+    let (mut f1, mut f2, mut value) = (f32::INFINITY, f32::INFINITY, 0.0);
+
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let (cx, cy) = (cell_x + dx, cell_y + dy);
+            let jitter = hash_cell(cx, cy, seed as i32);
+            let distance = Vec2::new(cx as f32 + jitter.x - x, cy as f32 + jitter.y - y).norm();
+            if distance < f1 {
+                (f2, f1, value) = (f1, distance, jitter.z);
+            } else if distance < f2 {
+                f2 = distance;
+            }
+        }
+    }
+
+    (f1, f2, value)
+}
+
+// Smooth 2D value noise in [0, 1): random values at the integer lattice, blended with
+// a smoothstep curve inside each cell
+pub fn value_noise_2d(x: f32, y: f32, seed: u32) -> f32 {
+    value_noise_2d_wrapped(x, y, seed, |cx, cy| (cx, cy))
+}
+
+// Value noise whose lattice repeats every `cells` cells, starting at lattice cell `origin`
+fn value_noise_2d_tiled(x: f32, y: f32, seed: u32, origin: (i32, i32), cells: i32) -> f32 {
+    value_noise_2d_wrapped(x, y, seed, |cx, cy| {
+        (origin.0 + (cx - origin.0).rem_euclid(cells), origin.1 + (cy - origin.1).rem_euclid(cells))
+    })
+}
+
+fn value_noise_2d_wrapped(x: f32, y: f32, seed: u32, lattice: impl Fn(i32, i32) -> (i32, i32)) -> f32 {
+    let (cell_x, cell_y) = (x.floor(), y.floor());
+    let (ix, iy) = (cell_x as i32, cell_y as i32);
+    let fade = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (fade(x - cell_x), fade(y - cell_y));
+
+    let corner = |dx: i32, dy: i32| {
+        let (cx, cy) = lattice(ix + dx, iy + dy);
+        hash_cell(cx, cy, seed as i32).x
+    };
+    let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * tx;
+    let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * tx;
+    top + (bottom - top) * ty
+}
+
+// Fractal Brownian motion: `octaves` layers of value noise, each at `lacunarity` times
+// the frequency and `gain` times the amplitude of the previous one, normalized by the
+// total amplitude and clamped to [-1, 1]. The lattice comes from the material's "seed" param.
+pub fn fbm(x: f32, y: f32, octaves: u32, lacunarity: f32, gain: f32, uniforms: &Uniforms) -> f32 {
+    let seed = uniforms.material.param("seed", 0.0) as u32;
+    fbm_seeded(x, y, octaves, lacunarity, gain, seed)
+}
+
+// `fbm` with the classic settings (lacunarity 2, gain 0.5) and an explicit seed,
+// remapped to [0, 1]
+pub fn fbm_2d(x: f32, y: f32, octaves: u32, seed: u32) -> f32 {
+    fbm_seeded(x, y, octaves, 2.0, 0.5, seed) * 0.5 + 0.5
+}
+
+fn fbm_seeded(x: f32, y: f32, octaves: u32, lacunarity: f32, gain: f32, seed: u32) -> f32 {
+    fbm_tiled(x, y, octaves, lacunarity, gain, seed, None)
+}
+
+// Square region over which noise is made to repeat, for baking it into a texture that
+// wraps without seams (`noise_bake::BakedNoise`): the lattice of every octave wraps every
+// `period` units starting at `origin`. Inside the tile the noise matches the untiled noise
+// (except for the last lattice cell of each octave) when `origin` and `period` are whole
+// numbers and the lacunarity is an integer; otherwise each octave's frequency is rounded
+// so a whole number of cells fits in the period.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseTile {
+    pub origin: Vec2,
+    pub period: f32,
+}
+
+fn fbm_tiled(x: f32, y: f32, octaves: u32, lacunarity: f32, gain: f32, seed: u32, tile: Option<&NoiseTile>) -> f32 {
+    let (mut sum, mut amplitude, mut frequency, mut total) = (0.0, 1.0, 1.0, 0.0);
+    for octave in 0..octaves.max(1) {
+        let seed = seed.wrapping_add(octave);
+        let noise = match tile {
+            None => value_noise_2d(x * frequency, y * frequency, seed),
+            Some(tile) => {
+                let cells = (tile.period * frequency).round().max(1.0);
+                let frequency = cells / tile.period;
+                let origin = ((tile.origin.x * frequency).floor() as i32, (tile.origin.y * frequency).floor() as i32);
+                value_noise_2d_tiled(x * frequency, y * frequency, seed, origin, cells as i32)
+            }
+        } * 2.0 - 1.0;
+        sum += noise * amplitude;
+        total += amplitude;
+        amplitude *= gain;
+        frequency *= lacunarity;
+    }
+    if total > 0.0 { (sum / total).clamp(-1.0, 1.0) } else { 0.0 }
+}
+
+// fBm settings of the noise shaders from the material params "noise_octaves",
+// "noise_lacunarity" (frequency multiplier per octave) and "noise_gain" (persistence:
+// amplitude multiplier per octave), as (octaves, lacunarity, gain). Each octave is one more
+// noise sample per fragment, so fewer octaves trade fine detail for speed
+pub fn fbm_settings(material: &Material, default_octaves: u32) -> (u32, f32, f32) {
+    let octaves = material.param("noise_octaves", default_octaves as f32).clamp(1.0, MAX_OCTAVES as f32) as u32;
+    (octaves, material.param("noise_lacunarity", 2.0), material.param("noise_gain", 0.5))
+}
+
+// Past this the octaves are finer than a pixel and only cost time
+const MAX_OCTAVES: u32 = 12;
+
+// Domain warping: displaces (x, y) by two fBm samples before the caller takes its main
+// noise sample there, which bends the pattern into swirls (nebulae, fluids, marble).
+// The offset drifts slowly with `uniforms.time` so the swirls move, and its noise follows
+// the material's "seed" param.
+pub fn domain_warp(x: f32, y: f32, warp_strength: f32, octaves: u32, uniforms: &Uniforms) -> (f32, f32) {
+    let seed = uniforms.material.param("seed", 0.0) as u32;
+    domain_warp_tiled(x, y, warp_strength, octaves, seed, uniforms.time, None)
+}
+
+fn domain_warp_tiled(x: f32, y: f32, warp_strength: f32, octaves: u32, seed: u32, time: f32, tile: Option<&NoiseTile>) -> (f32, f32) {
+    if warp_strength == 0.0 {
+        return (x, y);
+    }
+    let drift = time * 0.05;
+    // Different seeds and an offset decorrelate the two displacement components. The
+    // offset sample reads a tile shifted by the same offset, so a baked tile covering the
+    // model also covers it
+    let shifted = tile.map(|tile| NoiseTile { origin: tile.origin + WARP_OFFSET, ..*tile });
+    let wx = fbm_tiled(x + drift, y, octaves, 2.0, 0.5, seed.wrapping_add(11), tile);
+    let wy = fbm_tiled(x + WARP_OFFSET.x, y + WARP_OFFSET.y - drift, octaves, 2.0, 0.5, seed.wrapping_add(17), shifted.as_ref());
+    (x + wx * warp_strength, y + wy * warp_strength)
+}
+
+// Position offset of the second domain warp component
+const WARP_OFFSET: Vec2 = Vec2::new(5.2, 1.3);
+
+// Everything `noise_field` depends on, read once per fragment (or per bake) from the
+// material and uniforms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseSettings {
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub gain: f32,
+    pub seed: u32,
+    pub warp: f32, // Domain warp strength (0 = none, and then `time` doesn't matter)
+    pub time: f32,
+}
+
+impl NoiseSettings {
+    // Settings of `noise_based_fragment_shader`: 4 octaves by default, warped by
+    // `uniforms.domain_warp`
+    pub fn noise_shader(material: &Material, domain_warp: f32, time: f32) -> Self {
+        NoiseSettings::new(material, 4, domain_warp, time)
+    }
+
+    // Settings of `cloud_fragment_shader`: 5 octaves by default and no warp (the clouds
+    // drift by offsetting the sample position instead)
+    pub fn clouds(material: &Material) -> Self {
+        NoiseSettings::new(material, 5, 0.0, 0.0)
+    }
+
+    fn new(material: &Material, default_octaves: u32, warp: f32, time: f32) -> Self {
+        let (octaves, lacunarity, gain) = fbm_settings(material, default_octaves);
+        let seed = material.param("seed", 0.0) as u32;
+        NoiseSettings { octaves, lacunarity, gain, seed, warp, time }
+    }
+
+    // Same field at any time: the time only moves the pattern when it is warped
+    pub fn same_field(&self, other: &NoiseSettings) -> bool {
+        NoiseSettings { time: 0.0, ..*self } == NoiseSettings { time: 0.0, ..*other }
+    }
+}
+
+// Scalar noise field of the noise shaders in [0, 1], before triplanar mapping: fBm at
+// (x, y), domain-warped when `settings.warp` != 0. With a tile it repeats every period.
+pub fn noise_field(x: f32, y: f32, settings: &NoiseSettings, tile: Option<&NoiseTile>) -> f32 {
+    let (x, y) = domain_warp_tiled(x, y, settings.warp, settings.octaves, settings.seed, settings.time, tile);
+    fbm_tiled(x, y, settings.octaves, settings.lacunarity, settings.gain, settings.seed, tile) * 0.5 + 0.5
+}
+
+// Set once a stale bake has been reported
+static STALE_BAKE_WARNED: AtomicBool = AtomicBool::new(false);
+
+// `noise_field` for one fragment's samples: read from the material's baked texture when
+// it was baked with the same settings and `uniforms.noise_source` allows it, otherwise
+// evaluated live. A stale bake is reported once, since Baked then renders the live field
+fn noise_sampler<'a>(settings: &'a NoiseSettings, uniforms: &'a Uniforms) -> impl Fn(f32, f32) -> f32 + 'a {
+    let baked = match uniforms.noise_source {
+        NoiseSource::Baked => uniforms.material.baked_noise.as_deref().filter(|baked| {
+            let matches = baked.matches(settings);
+            if !matches && !STALE_BAKE_WARNED.swap(true, Ordering::Relaxed) {
+                eprintln!("El ruido horneado no coincide con los parámetros actuales; se evalúa en vivo (no se vuelve a avisar)");
+            }
+            matches
+        }),
+        NoiseSource::Live => None,
+    };
+    move |x, y| match baked {
+        Some(baked) => baked.sample(x, y, settings.time),
+        None => noise_field(x, y, settings, None),
+    }
+}
+
+// Surface color of a fragment before lighting, chosen per material
+// (`Material::fragment_shader`). The shader writes `fragment.color` (and `fragment.alpha`
+// for blended materials); lighting and reflections are applied afterwards.
+pub type FragmentShader = fn(&mut Fragment, &Uniforms);
+
+// Dry, cracked ground: irregular tiles from 2D Worley cells, each with a slightly
+// different shade of the base color and separated by dark cracks along the cell
+// borders. Mapped triplanarly so it works on any mesh. Material params:
+// "crack_frequency" (tiles per model unit), "crack_width" and "seed".
+pub fn cracked_earth_fragment_shader(fragment: &mut Fragment, uniforms: &Uniforms) {
+    let material = &uniforms.material;
+    let frequency = material.param("crack_frequency", 4.0) * material.noise_scale;
+    let width = material.param("crack_width", 0.06);
+    let seed = material.param("seed", 0.0) as u32;
+
+    let position = fragment.model_position * frequency;
+    // Border and shade come from the same Worley lookup: one per triplanar plane
+    let weights = triplanar_weights(&fragment.normal, 4.0);
+    let planes = [
+        Vec2::new(position.y, position.z),
+        Vec2::new(position.x, position.z),
+        Vec2::new(position.x, position.y),
+    ];
+    let (mut border, mut shade) = (0.0, 0.0);
+    for (p, weight) in planes.iter().zip(weights.iter()) {
+        let (f1, f2, value) = worley_2d_cells(p.x, p.y, seed);
+        border += (f2 - f1) * weight;
+        shade += value * weight;
+    }
+
+    let tile = material.base_color * (0.75 + 0.35 * shade);
+    let crack = material.base_color * 0.15;
+    let t = smoothstep(width * 0.5, width, border);
+    fragment.color = crack * (1.0 - t) + tile * t;
+}
+
+// fBm noise blending the base color into the rim color, mapped triplanarly. With
+// `uniforms.domain_warp` > 0 the sample position is warped first, turning the blobs into
+// swirling, organic shapes. Material params: "noise_octaves", "noise_lacunarity",
+// "noise_gain" and "seed".
+pub fn noise_based_fragment_shader(fragment: &mut Fragment, uniforms: &Uniforms) {
+    let material = &uniforms.material;
+    let settings = NoiseSettings::noise_shader(material, uniforms.domain_warp, uniforms.time);
+    let position = fragment.model_position * material.noise_scale;
+
+    let noise_at = noise_sampler(&settings, uniforms);
+    let noise = triplanar_scalar(&position, &fragment.normal, 4.0, |p| noise_at(p.x, p.y));
+
+    let t = smoothstep(0.3, 0.7, noise);
+    fragment.color = material.base_color * (1.0 - t) + material.rim_color * t;
+}
+
+// Wispy cloud cover for a planet's atmosphere layer, drawn on a slightly larger sphere
+// around the planet with `BlendMode::Alpha`. fBm noise above the coverage threshold is
+// cloud (rim color in thin wisps, base color where dense); below it the fragment is
+// transparent. The noise drifts with `uniforms.time`. Material params (see
+// `Material::clouds`): "cloud_coverage", "cloud_speed", "cloud_softness" and the fBm
+// settings of `fbm_settings` (5 octaves by default).
+pub fn cloud_fragment_shader(fragment: &mut Fragment, uniforms: &Uniforms) {
+    let material = &uniforms.material;
+    let coverage = material.param("cloud_coverage", 0.5).clamp(0.0, 1.0);
+    let speed = material.param("cloud_speed", 0.05);
+    let softness = material.param("cloud_softness", 0.15).max(1e-3);
+    let settings = NoiseSettings::clouds(material);
+
+    let drift = uniforms.time * speed;
+    let position = fragment.model_position * material.noise_scale;
+    let noise_at = noise_sampler(&settings, uniforms);
+    let noise = triplanar_scalar(&position, &fragment.normal, 4.0, |p| noise_at(p.x + drift, p.y));
+
+    // fBm clusters around 0.5, so the threshold covers roughly `coverage` of the sphere
+    let threshold = 0.5 + (0.5 - coverage) * 0.4;
+    let density = smoothstep(threshold - softness * 0.5, threshold + softness * 0.5, noise);
+    let dense = smoothstep(threshold, threshold + softness * 2.0, noise);
+    fragment.color = material.rim_color * (1.0 - dense) + material.base_color * dense;
+    fragment.alpha = density;
+}
+
+// Engine plume on the cone of `ship::plume_mesh`, drawn with `BlendMode::Additive`: hot
+// base color at the nozzle turning into the rim color toward the open end. Turbulent fBm
+// scrolls along the axis (tex_coords.y, 0 at the nozzle) with `uniforms.time` so the flame
+// flickers. Alpha fades toward the open end and toward the silhouette, where the view
+// grazes the cone, and scales with `uniforms.throttle` like the brightness. Material
+// params (see `Material::exhaust`): "exhaust_speed" (noise scroll per second),
+// "exhaust_falloff" (exponent of the fade along the axis) and the fBm settings of
+// `fbm_settings` (3 octaves by default).
+pub fn exhaust_fragment_shader(fragment: &mut Fragment, uniforms: &Uniforms) {
+    let material = &uniforms.material;
+    let speed = material.param("exhaust_speed", 4.0);
+    let falloff = material.param("exhaust_falloff", 1.5).max(0.0);
+    let (octaves, _, _) = fbm_settings(material, 3);
+    let seed = material.param("seed", 0.0) as u32;
+    let throttle = uniforms.throttle.clamp(0.0, 1.0);
+
+    let along = fragment.tex_coords.y.clamp(0.0, 1.0);
+    let position = fragment.model_position * material.noise_scale;
+    let turbulence = fbm_2d(position.x + position.z * 0.7, (along - uniforms.time * speed) * material.noise_scale * 2.0, octaves, seed);
+
+    let heat = material.base_color * (1.0 - along) + material.rim_color * along;
+    fragment.color = heat * ((0.5 + 0.8 * turbulence) * (0.4 + 0.6 * throttle));
+
+    let world_position = (uniforms.model_matrix * fragment.model_position.push(1.0)).xyz();
+    let rim = smoothstep(0.0, 0.6, fragment.normal.dot(&view_vector(&world_position, uniforms)).abs());
+    fragment.alpha = (1.0 - along).powf(falloff) * rim * (0.4 + 0.6 * turbulence) * throttle;
+}
+
+// Per-triangle stage between the vertex shader and primitive assembly
+// (`PipelineConfig::geometry_shader`): receives one transformed triangle (screen-space
+// positions) and emits zero or more triangles in its place. Emitted triangles should keep
+// the winding of `uniforms.front_face` to survive backface culling.
+pub type GeometryShader = fn(&[Vertex; 3], &Uniforms) -> Vec<[Vertex; 3]>;
+
+// Screen-space signed area, negative for counter-clockwise triangles in the model (the
+// screen y axis points down)
+fn screen_signed_area(triangle: &[Vertex; 3]) -> f32 {
+    let (a, b, c) = (&triangle[0].transformed_position, &triangle[1].transformed_position, &triangle[2].transformed_position);
+    (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)
+}
+
+// Reorders a generated triangle so it faces the camera with `uniforms.front_face`
+fn facing_camera(mut triangle: [Vertex; 3], uniforms: &Uniforms) -> [Vertex; 3] {
+    let counter_clockwise = screen_signed_area(&triangle) < 0.0;
+    if counter_clockwise != (uniforms.front_face == Winding::CounterClockwise) {
+        triangle.swap(1, 2);
+    }
+    triangle
+}
+
+fn centroid(triangle: &[Vertex; 3]) -> Vertex {
+    triangle[0].lerp(&triangle[1], 0.5).lerp(&triangle[2], 1.0 / 3.0)
+}
+
+// Moves a vertex in screen space (current and previous frame, so velocity is preserved)
+fn offset_on_screen(vertex: &Vertex, offset: Vec3) -> Vertex {
+    let mut moved = vertex.clone();
+    moved.transformed_position += offset;
+    moved.prev_transformed_position += offset;
+    moved
+}
+
+// Triangle to point: replaces each triangle with a small camera-facing square at its
+// centroid, for particle-like effects. Size in pixels from the shader param
+// "geometry.point_size" (default 3).
+pub fn point_sprite_geometry_shader(triangle: &[Vertex; 3], uniforms: &Uniforms) -> Vec<[Vertex; 3]> {
+    let half = uniforms.shader_params.float("geometry.point_size", 3.0).max(0.5) * 0.5;
+    let mut center = centroid(triangle);
+    center.transformed_normal = Vec3::new(0.0, 0.0, -1.0);
+    let corner = |x: f32, y: f32| offset_on_screen(&center, Vec3::new(x * half, y * half, 0.0));
+    let (top_left, top_right) = (corner(-1.0, -1.0), corner(1.0, -1.0));
+    let (bottom_left, bottom_right) = (corner(-1.0, 1.0), corner(1.0, 1.0));
+    vec![
+        facing_camera([top_left.clone(), bottom_left, bottom_right.clone()], uniforms),
+        facing_camera([top_left, bottom_right, top_right], uniforms),
+    ]
+}
+
+// Normal visualizer: keeps the triangle and adds a thin sliver from its centroid along the
+// averaged face normal. The normal is a model-space direction, so its screen direction is
+// the projected step from the centroid along it. Shader params: "geometry.normal_length"
+// in pixels (default 20) and "geometry.normal_color" (default yellow). Normals pointing
+// straight at the camera have no visible length on screen and get no sliver.
+pub fn normal_visualizer_geometry_shader(triangle: &[Vertex; 3], uniforms: &Uniforms) -> Vec<[Vertex; 3]> {
+    let params = &uniforms.shader_params;
+    let length = params.float("geometry.normal_length", 20.0);
+    let color = params.color("geometry.normal_color", Color::new(255, 230, 0));
+
+    let mut output = vec![triangle.clone()];
+    let normal = triangle.iter().fold(Vec3::zeros(), |sum, v| sum + v.normal);
+    let [a, b, c] = triangle;
+    // Step along the normal on the scale of the triangle, so the projection stays accurate
+    let step = (a.position - b.position).norm().max((b.position - c.position).norm()).max((c.position - a.position).norm());
+    if normal.norm() < f32::EPSILON || step < f32::EPSILON {
+        return output;
+    }
+    let center = (a.position + b.position + c.position) / 3.0;
+    let tip = center + normal.normalize() * step;
+    let delta = project(&uniforms.transformation_matrix, &tip) - project(&uniforms.transformation_matrix, &center);
+    let on_screen = delta.xy().norm();
+    if on_screen.is_nan() || on_screen < 1e-3 {
+        return output; // Toward the camera, or behind it (NaN)
+    }
+    let normal = Vec3::new(delta.x, delta.y, 0.0).normalize();
+    let side = Vec3::new(-normal.y, normal.x, 0.0).normalize() * 0.75;
+
+    let mut base = centroid(triangle);
+    base.color = color;
+    base.transformed_normal = Vec3::new(0.0, 0.0, -1.0);
+    let sliver = [offset_on_screen(&base, side), offset_on_screen(&base, -side), offset_on_screen(&base, normal * length)];
+    output.push(facing_camera(sliver, uniforms));
+    output
+}
+
+// One level of midpoint subdivision: four triangles with the original winding.
+pub fn subdivide_geometry_shader(triangle: &[Vertex; 3], _uniforms: &Uniforms) -> Vec<[Vertex; 3]> {
+    let [a, b, c] = triangle;
+    let (ab, bc, ca) = (a.lerp(b, 0.5), b.lerp(c, 0.5), c.lerp(a, 0.5));
+    vec![
+        [a.clone(), ab.clone(), ca.clone()],
+        [ab.clone(), b.clone(), bc.clone()],
+        [ca.clone(), bc.clone(), c.clone()],
+        [ab, bc, ca],
+    ]
+}
+
+// Fragment shaders by name, for picking one at runtime (number keys, config files)
+// without touching `render()`: the chosen shader goes into `Material::fragment_shader`.
+// Registration order is kept so the shaders can also be selected by index.
+#[derive(Debug, Clone, Default)]
+pub struct ShaderRegistry {
+    shaders: Vec<(String, FragmentShader)>,
+}
+
+impl ShaderRegistry {
+    pub fn new() -> Self {
+        ShaderRegistry::default()
+    }
+
+    // Registry with the shaders that ship with the renderer
+    pub fn with_builtin_shaders() -> Self {
+        let mut registry = ShaderRegistry::new();
+        registry.register("noise", noise_based_fragment_shader);
+        registry.register("cracked_earth", cracked_earth_fragment_shader);
+        registry.register("clouds", cloud_fragment_shader);
+        registry.register("exhaust", exhaust_fragment_shader);
+        registry
+    }
+
+    // Adds a shader, replacing (in place) any shader already registered under `name`
+    pub fn register(&mut self, name: &str, shader: FragmentShader) {
+        match self.shaders.iter_mut().find(|(existing, _)| existing == name) {
+            Some(entry) => entry.1 = shader,
+            None => self.shaders.push((name.to_string(), shader)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<FragmentShader> {
+        self.shaders.iter().find(|(existing, _)| existing == name).map(|&(_, shader)| shader)
+    }
+
+    // Name and shader at `index`, in registration order
+    pub fn get_index(&self, index: usize) -> Option<(&str, FragmentShader)> {
+        self.shaders.get(index).map(|(name, shader)| (name.as_str(), *shader))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.shaders.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.shaders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shaders.is_empty()
+    }
+}
+
+// Per-vertex transformation. `Sync` because the vertex stage calls it from several threads
+pub trait VertexShader: Sync {
+    fn transform(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex;
+}
+
+// Default shader: the standard model transformation done by `vertex_shader`
+pub struct DefaultVertexShader;
+
+impl VertexShader for DefaultVertexShader {
+    fn transform(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+        vertex_shader(vertex, uniforms)
+    }
+}
+
+// Outline pass: extrudes every vertex along its normal by a fixed number of pixels.
+// The extrusion happens in transformed (screen) space, so the rim keeps the same
+// thickness no matter how far the entity is from the camera.
+pub struct OutlineVertexShader {
+    pub thickness: f32,
+    pub color: Color,
+}
+
+impl VertexShader for OutlineVertexShader {
+    fn transform(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+        let mut outlined = vertex_shader(vertex, uniforms);
+
+        // Direction of the normal once projected to the screen
+        let tip = project(&uniforms.transformation_matrix, &(vertex.position + vertex.normal));
+        let direction = (tip - outlined.transformed_position).xy();
+        if direction.norm() > f32::EPSILON {
+            let offset = direction.normalize() * self.thickness;
+            outlined.transformed_position.x += offset.x;
+            outlined.transformed_position.y += offset.y;
+        }
+
+        outlined.color = self.color;
+        outlined
+    }
+}
+
+pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+    // Blend between the base shape and the morph target
+    let weight = uniforms.morph_weight;
+    let (position, normal) = if weight != 0.0 {
+        let normal = vertex.normal.lerp(&vertex.morph_normal, weight);
+        let normal = if normal.norm() > 0.0 { normal.normalize() } else { vertex.normal };
+        (vertex.position.lerp(&vertex.morph_position, weight), normal)
+    } else {
+        (vertex.position, vertex.normal)
+    };
+
+    let (position, normal) = skin(vertex, &position, &normal, &uniforms.bone_matrices);
+
+    // Transform position for the current and the previous frame
+    let (transformed_position, clip_position) = project_homogeneous(&uniforms.transformation_matrix, &position);
+    let prev_transformed_position = project(&uniforms.prev_transformation_matrix, &position);
+
+    // Normals use the inverse-transpose of the model matrix so they stay perpendicular to
+    // the surface under non-uniform scale. A degenerate result (singular model matrix)
+    // keeps the untransformed normal.
+    let transformed_normal = uniforms.normal_matrix * normal;
+    let transformed_normal = if transformed_normal.norm() > f32::EPSILON { transformed_normal.normalize() } else { normal };
+
+    // View-space data for screen-space effects, only when the pipeline asks for it. The
+    // view matrix is rigid, so its rotation alone carries the model-space normal over.
+    let (view_position, view_normal) = if uniforms.fragment_view_data {
+        let view_position = (uniforms.model_view_matrix * position.push(1.0)).xyz();
+        let view_normal = (uniforms.view_matrix * transformed_normal.push(0.0)).xyz();
+        let view_normal = if view_normal.norm() > f32::EPSILON { view_normal.normalize() } else { view_normal };
+        (view_position, view_normal)
+    } else {
+        (vertex.view_position, vertex.view_normal)
+    };
 
     // Return a new Vertex with transformed attributes
     Vertex {
@@ -27,6 +707,164 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
         tex_coords: vertex.tex_coords,
         color: vertex.color,
         transformed_position,
-        transformed_normal: vertex.normal,
+        clip_position,
+        transformed_normal,
+        prev_transformed_position,
+        view_position,
+        view_normal,
+        morph_position: vertex.morph_position,
+        morph_normal: vertex.morph_normal,
+        bone_indices: vertex.bone_indices,
+        bone_weights: vertex.bone_weights,
+        // Gouraud: the lighting is evaluated here and interpolated across the triangle
+        light: match uniforms.shading_model {
+            ShadingModel::Gouraud => incident_light(&transformed_position, &transformed_normal, uniforms),
+            ShadingModel::Phong => Vec3::repeat(1.0),
+        },
+    }
+}
+
+// Linear blend skinning: weighted sum of the vertex transformed by each of its bones.
+// Vertices without weights (or an empty bone palette) are returned unchanged.
+fn skin(vertex: &Vertex, position: &Vec3, normal: &Vec3, bone_matrices: &[Mat4]) -> (Vec3, Vec3) {
+    let total_weight: f32 = vertex.bone_weights.iter().sum();
+    if bone_matrices.is_empty() || total_weight <= 0.0 {
+        return (*position, *normal);
+    }
+
+    let mut skinned_position = Vec3::zeros();
+    let mut skinned_normal = Vec3::zeros();
+    for (&bone, &weight) in vertex.bone_indices.iter().zip(vertex.bone_weights.iter()) {
+        if weight == 0.0 {
+            continue;
+        }
+        if let Some(matrix) = bone_matrices.get(bone as usize) {
+            skinned_position += (matrix * Vec4::new(position.x, position.y, position.z, 1.0)).xyz() * weight;
+            skinned_normal += (matrix * Vec4::new(normal.x, normal.y, normal.z, 0.0)).xyz() * weight;
+        }
+    }
+
+    // Normalize in case the weights do not add up to 1
+    skinned_position /= total_weight;
+    let skinned_normal = if skinned_normal.norm() > 0.0 { skinned_normal.normalize() } else { *normal };
+    (skinned_position, skinned_normal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::{render_with_shader, Framebuffer};
+    use crate::uniforms::{viewport_matrix, CullMode, UniformsBuilder};
+
+    // Moves every vertex up by one unit before the standard transformation
+    struct RaiseShader;
+
+    impl VertexShader for RaiseShader {
+        fn transform(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+            let mut raised = vertex.clone();
+            raised.position.y += 1.0;
+            vertex_shader(&raised, uniforms)
+        }
+    }
+
+    fn triangle() -> Vec<Vertex> {
+        let normal = Vec3::new(0.0, 0.0, -1.0);
+        vec![
+            Vertex::new(Vec3::new(-0.5, -0.5, 0.0), normal, Vec2::zeros()),
+            Vertex::new(Vec3::new(0.5, -0.5, 0.0), normal, Vec2::zeros()),
+            Vertex::new(Vec3::new(0.0, 0.5, 0.0), normal, Vec2::zeros()),
+        ]
+    }
+
+    // Orthographic uniforms with 10 pixels per unit on a 64x64 target
+    fn uniforms() -> Uniforms {
+        UniformsBuilder::new().viewport(viewport_matrix(64, 64, 10.0)).cull_mode(CullMode::None).build()
+    }
+
+    #[test]
+    fn custom_vertex_shader_offsets_positions() {
+        let uniforms = uniforms();
+        for vertex in triangle() {
+            let standard = DefaultVertexShader.transform(&vertex, &uniforms);
+            let raised = RaiseShader.transform(&vertex, &uniforms);
+            assert_eq!(raised.transformed_position - standard.transformed_position, Vec3::new(0.0, 10.0, 0.0));
+        }
+
+        // The pipeline runs the custom shader: the drawn rows move by the same 10 pixels
+        let covered_rows = |shader: &dyn VertexShader| {
+            let mut framebuffer = Framebuffer::new(64, 64);
+            render_with_shader(&mut framebuffer, &uniforms, &triangle(), shader);
+            (0..64).filter(|&y| (0..64).any(|x| framebuffer.get_depth(x, y).is_some_and(f32::is_finite))).collect::<Vec<_>>()
+        };
+        let standard = covered_rows(&DefaultVertexShader);
+        let raised = covered_rows(&RaiseShader);
+        assert!(!standard.is_empty());
+        assert_eq!(raised, standard.iter().map(|y| y + 10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn morph_weight_blends_position_and_normal() {
+        let mut vertex = Vertex::new(Vec3::new(1.0, 0.0, 2.0), Vec3::new(1.0, 0.0, 0.0), Vec2::zeros());
+        vertex.morph_position = Vec3::new(3.0, 4.0, 2.0);
+        vertex.morph_normal = Vec3::new(0.0, 1.0, 0.0);
+        let mut uniforms = UniformsBuilder::new().build();
+        uniforms.morph_weight = 0.5;
+
+        let morphed = vertex_shader(&vertex, &uniforms);
+        assert_eq!(morphed.transformed_position, Vec3::new(2.0, 2.0, 2.0));
+        let expected_normal = Vec3::new(1.0, 1.0, 0.0).normalize();
+        assert!((morphed.transformed_normal - expected_normal).norm() < 1e-6);
+    }
+
+    #[test]
+    fn vertex_fully_weighted_to_one_bone_follows_it() {
+        let bone = nalgebra_glm::translation(&Vec3::new(0.5, -1.0, 2.0)) * nalgebra_glm::rotation(std::f32::consts::FRAC_PI_2, &Vec3::z());
+        let mut vertex = Vertex::new(Vec3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 0.0, 0.0), Vec2::zeros());
+        vertex.bone_indices = [1, 0, 0, 0];
+        vertex.bone_weights = [1.0, 0.0, 0.0, 0.0];
+        let mut uniforms = UniformsBuilder::new().build();
+        uniforms.bone_matrices = vec![Mat4::identity(), bone];
+
+        let skinned = vertex_shader(&vertex, &uniforms);
+        let expected = (bone * vertex.position.push(1.0)).xyz();
+        assert!((skinned.transformed_position - expected).norm() < 1e-6);
+        assert!((skinned.transformed_normal - Vec3::new(0.0, 1.0, 0.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn fbm_stays_within_unit_range() {
+        let uniforms = UniformsBuilder::new().build();
+        for (octaves, lacunarity, gain) in [(0, 2.0, 0.5), (1, 2.0, 0.5), (4, 2.0, 0.5), (8, 3.0, 0.9), (6, 1.5, 1.5), (5, 2.0, -0.7)] {
+            let samples: Vec<f32> = (0..400)
+                .map(|i| fbm((i % 20) as f32 * 0.37 - 3.1, (i / 20) as f32 * 0.53 + 7.9, octaves, lacunarity, gain, &uniforms))
+                .collect();
+            assert!(samples.iter().all(|n| (-1.0..=1.0).contains(n)), "octaves {octaves}, lacunarity {lacunarity}, gain {gain}");
+            // The noise actually varies across the samples
+            let (min, max) = samples.iter().fold((f32::MAX, f32::MIN), |(min, max), &n| (min.min(n), max.max(n)));
+            assert!(max - min > 0.2, "octaves {octaves}: range {min}..{max}");
+        }
+        assert!((0..100).map(|i| fbm_2d(i as f32 * 0.41, i as f32 * 0.13, 5, 3)).all(|n| (0.0..=1.0).contains(&n)));
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_non_uniform_scale() {
+        // Point of a unit sphere at 45 degrees between +x and +y, on a sphere stretched 2x in y
+        let direction = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let vertex = Vertex::new(direction, direction, Vec2::zeros());
+        let stretch = nalgebra_glm::scaling(&Vec3::new(1.0, 2.0, 1.0));
+        let uniforms = UniformsBuilder::new().model(stretch).build();
+
+        // Gradient of the ellipsoid x² + (y/2)² + z² = 1 at the stretched point
+        let expected = Vec3::new(1.0, 0.5, 0.0).normalize();
+        let normal = vertex_shader(&vertex, &uniforms).transformed_normal;
+        assert!((normal - expected).norm() < 1e-6, "{normal:?}");
+        // The plain model matrix would tilt it the other way
+        let naive = (stretch * direction.push(0.0)).xyz().normalize();
+        assert!((naive - expected).norm() > 0.5);
+
+        // A singular model matrix still yields a usable unit normal
+        let flat = UniformsBuilder::new().model(nalgebra_glm::scaling(&Vec3::new(1.0, 0.0, 1.0))).build();
+        let normal = vertex_shader(&vertex, &flat).transformed_normal;
+        assert!(normal.iter().all(|c| c.is_finite()) && (normal.norm() - 1.0).abs() < 1e-6);
     }
 }
@@ -0,0 +1,169 @@
+// shader_params.rs
+//
+// Parámetros de shaders ajustables sin recompilar: se leen de un archivo TOML
+// (`shaders.toml`) con una tabla por shader, y se recargan cuando el archivo cambia.
+//
+//   [outline]
+//   thickness = 3.0          # número -> float
+//   color = "ffc800"         # texto hex -> Color
+//   offset = [0.0, 1.0, 0.0] # arreglo de 3 números -> Vec3
+//   enabled = true           # booleano -> float 1/0 (se lee con `flag`)
+//
+// Los shaders piden cada parámetro como "tabla.nombre" con un valor por defecto.
+
+use std::collections::HashMap;
+use std::path::Path;
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ParamValue {
+    Float(f32),
+    Color(Color),
+    Vec3(Vec3),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ShaderParams {
+    values: HashMap<String, ParamValue>,
+}
+
+impl ShaderParams {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("No se pudo leer {}: {}", path.display(), e))?;
+        ShaderParams::from_toml_str(&contents)
+    }
+
+    pub fn from_toml_str(contents: &str) -> Result<Self, String> {
+        let table: toml::Table = contents.parse().map_err(|e: toml::de::Error| e.to_string())?;
+
+        let mut params = ShaderParams::default();
+        for (shader, entries) in &table {
+            let Some(entries) = entries.as_table() else {
+                return Err(format!("'{}' debe ser una tabla [{}]", shader, shader));
+            };
+            for (name, value) in entries {
+                let key = format!("{}.{}", shader, name);
+                let value = parse_value(value).ok_or_else(|| format!("Valor no soportado en {}", key))?;
+                params.values.insert(key, value);
+            }
+        }
+        Ok(params)
+    }
+
+    pub fn set(&mut self, name: &str, value: ParamValue) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    // Cada lookup devuelve `default` si el parámetro no existe o es de otro tipo
+    pub fn float(&self, name: &str, default: f32) -> f32 {
+        match self.values.get(name) {
+            Some(ParamValue::Float(value)) => *value,
+            _ => default,
+        }
+    }
+
+    // Booleano guardado como float: cualquier valor distinto de 0 es verdadero
+    pub fn flag(&self, name: &str, default: bool) -> bool {
+        match self.values.get(name) {
+            Some(ParamValue::Float(value)) => *value != 0.0,
+            _ => default,
+        }
+    }
+
+    pub fn color(&self, name: &str, default: Color) -> Color {
+        match self.values.get(name) {
+            Some(ParamValue::Color(value)) => *value,
+            _ => default,
+        }
+    }
+
+    pub fn vec3(&self, name: &str, default: Vec3) -> Vec3 {
+        match self.values.get(name) {
+            Some(ParamValue::Vec3(value)) => *value,
+            _ => default,
+        }
+    }
+}
+
+fn parse_value(value: &toml::Value) -> Option<ParamValue> {
+    let number = |value: &toml::Value| value.as_float().or_else(|| value.as_integer().map(|i| i as f64));
+
+    match value {
+        toml::Value::Float(_) | toml::Value::Integer(_) => number(value).map(|n| ParamValue::Float(n as f32)),
+        toml::Value::Boolean(flag) => Some(ParamValue::Float(if *flag { 1.0 } else { 0.0 })),
+        toml::Value::String(hex) => Color::from_hex(hex.trim_start_matches('#')).ok().map(ParamValue::Color),
+        toml::Value::Array(items) if items.len() == 3 => {
+            let components: Option<Vec<f64>> = items.iter().map(number).collect();
+            components.map(|c| ParamValue::Vec3(Vec3::new(c[0] as f32, c[1] as f32, c[2] as f32)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use watcher::ShaderParamsWatcher;
+
+// En WebAssembly no hay sistema de archivos que observar
+#[cfg(not(target_arch = "wasm32"))]
+mod watcher {
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::{channel, Receiver};
+    use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+    use super::ShaderParams;
+
+    // Observa el archivo de parámetros y lo vuelve a leer cuando cambia. Se observa el
+    // directorio que lo contiene y se filtra por nombre: los editores que guardan escribiendo
+    // otro archivo y renombrándolo encima reemplazan el archivo observado, y observarlo a él
+    // dejaría de avisar después del primer guardado
+    pub struct ShaderParamsWatcher {
+        path: PathBuf,
+        _watcher: RecommendedWatcher,
+        events: Receiver<notify::Result<notify::Event>>,
+    }
+
+    impl ShaderParamsWatcher {
+        pub fn new(path: &str) -> notify::Result<Self> {
+            let path = PathBuf::from(path);
+            let directory = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            let (sender, events) = channel();
+            let mut watcher = notify::recommended_watcher(move |event| {
+                let _ = sender.send(event);
+            })?;
+            watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+            Ok(ShaderParamsWatcher { path, _watcher: watcher, events })
+        }
+
+        fn is_params_file(&self, changed: &Path) -> bool {
+            changed.file_name().is_some_and(|name| Some(name) == self.path.file_name())
+        }
+
+        // Parámetros recargados si el archivo cambió desde la última llamada. Si el archivo
+        // quedó con errores se avisa y se mantienen los parámetros anteriores (None).
+        pub fn poll(&self) -> Option<ShaderParams> {
+            // Se vacía la cola entera (no `any`) para no recargar de nuevo en la siguiente llamada
+            let changes = self.events
+                .try_iter()
+                .filter_map(Result::ok)
+                .filter(|event| event.kind.is_modify() || event.kind.is_create())
+                .filter(|event| event.paths.iter().any(|changed| self.is_params_file(changed)))
+                .count();
+            if changes == 0 {
+                return None;
+            }
+
+            match ShaderParams::load(&self.path) {
+                Ok(params) => Some(params),
+                Err(e) => {
+                    eprintln!("Error recargando {}: {}", self.path.display(), e);
+                    None
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+// ship.rs
+//
+// Física simple de la nave controlable y predicción de su trayectoria. La predicción
+// usa exactamente la misma función `update` que el movimiento real, así que la línea
+// dibujada es la ruta que seguirá la nave si no cambia el empuje. También arma el penacho
+// del motor: un cono abierto detrás de la cola que se dibuja con `Material::exhaust`.
+
+use nalgebra_glm::{Mat4, Vec2, Vec3};
+
+use crate::color::Color;
+use crate::vertex::Vertex;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShipState {
+    pub position: Vec3,
+    pub velocity: Vec3,
+}
+
+// Cuerpo que atrae a la nave (sol, planetas). Sin atractores el movimiento es lineal.
+#[derive(Debug, Clone, Copy)]
+pub struct Attractor {
+    pub position: Vec3,
+    pub mass: f32, // G * masa, en unidades del modelo
+    pub radius: f32, // Dentro de este radio no se aplica gravedad (evita la singularidad)
+}
+
+// Avanza la nave `dt` segundos con el empuje (aceleración) dado
+pub fn update(state: &ShipState, thrust: &Vec3, attractors: &[Attractor], dt: f32) -> ShipState {
+    let mut acceleration = *thrust;
+    for attractor in attractors {
+        let offset = attractor.position - state.position;
+        let distance = offset.norm();
+        if distance > attractor.radius {
+            acceleration += offset * (attractor.mass / (distance * distance * distance));
+        }
+    }
+
+    // Euler semi-implícito: estable para órbitas con pasos fijos
+    let velocity = state.velocity + acceleration * dt;
+    ShipState {
+        position: state.position + velocity * dt,
+        velocity,
+    }
+}
+
+// Posiciones futuras de la nave durante `horizon` segundos, en `steps` pasos, sin empuje
+pub fn predict_trajectory(state: &ShipState, attractors: &[Attractor], horizon: f32, steps: usize) -> Vec<Vec3> {
+    let dt = horizon / steps.max(1) as f32;
+    let mut current = *state;
+    let mut points = Vec::with_capacity(steps + 1);
+    points.push(current.position);
+
+    for _ in 0..steps {
+        current = update(&current, &Vec3::zeros(), attractors, dt);
+        points.push(current.position);
+    }
+
+    points
+}
+
+// Cono del penacho, sin tapas: radio 1 en la tobera (y = 0) y `flare` en el extremo abierto
+// (y = 1), con `segments` caras alrededor del eje +y. La coordenada de textura x da la
+// vuelta al cono y la y va de 0 en la tobera a 1 en el extremo abierto
+pub fn plume_mesh(segments: usize, flare: f32) -> Vec<Vertex> {
+    let segments = segments.max(3);
+    let point = |segment: usize, along: f32| {
+        let angle = std::f32::consts::TAU * segment as f32 / segments as f32;
+        let (sin, cos) = angle.sin_cos();
+        let radius = 1.0 + (flare - 1.0) * along;
+        // Normal perpendicular a la pared inclinada del cono
+        let normal = Vec3::new(cos, 1.0 - flare, sin).normalize();
+        let mut vertex = Vertex::new(Vec3::new(cos * radius, along, sin * radius), normal, Vec2::new(segment as f32 / segments as f32, along));
+        vertex.color = Color::new(255, 255, 255);
+        vertex
+    };
+    let mut vertices = Vec::with_capacity(segments * 6);
+    for segment in 0..segments {
+        let (a, b, c, d) = (point(segment, 0.0), point(segment + 1, 0.0), point(segment + 1, 1.0), point(segment, 1.0));
+        vertices.extend([a.clone(), b, c.clone(), a, c, d]);
+    }
+    vertices
+}
+
+// Matriz de modelo del penacho: `emitter` pone la tobera en la cola con +y hacia atrás (la
+// misma matriz que el emisor de partículas del escape); el cono mide `radius` en la tobera
+// y su largo es `length` por el acelerador
+pub fn plume_matrix(emitter: &Mat4, throttle: f32, length: f32, radius: f32) -> Mat4 {
+    emitter * nalgebra_glm::scaling(&Vec3::new(radius, length * throttle.clamp(0.0, 1.0), radius))
+}
@@ -0,0 +1,95 @@
+// texture.rs
+//
+// Textura 2D de colores que los materiales muestrean con las coordenadas de textura
+// interpoladas de cada fragmento (`Material::texture`). Se puede crear a partir del
+// buffer de color de un `Framebuffer`, para usar una vista renderizada fuera de pantalla
+// como textura (render-to-texture, ver `RenderTarget`).
+
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+#[derive(Debug, Clone)]
+pub struct Texture {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>, // 0xAARRGGBB, fila por fila desde arriba
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, pixels: Vec<u32>) -> Self {
+        assert_eq!(pixels.len(), width * height, "La textura necesita width * height pixeles");
+        Texture { width, height, pixels }
+    }
+
+    // Copia del buffer de color del framebuffer (la fila 0 queda en v = 0)
+    pub fn from_framebuffer(framebuffer: &Framebuffer) -> Self {
+        Texture::new(framebuffer.width, framebuffer.height, framebuffer.as_slice().to_vec())
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn texel(&self, x: usize, y: usize) -> Color {
+        Color::from_u32(self.pixels[y.min(self.height - 1) * self.width + x.min(self.width - 1)])
+    }
+
+    // Color en (u, v) de [0, 1] (u hacia la derecha, v hacia abajo) con filtrado bilineal;
+    // fuera de ese rango se repite el borde
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        if self.pixels.is_empty() || !u.is_finite() || !v.is_finite() {
+            return Color::black();
+        }
+        // Centros de los texels en (i + 0.5) / tamaño
+        let x = (u.clamp(0.0, 1.0) * self.width as f32 - 0.5).max(0.0);
+        let y = (v.clamp(0.0, 1.0) * self.height as f32 - 0.5).max(0.0);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (tx, ty) = (x.fract(), y.fract());
+
+        let top = self.texel(x0, y0) * (1.0 - tx) + self.texel(x0 + 1, y0) * tx;
+        let bottom = self.texel(x0, y0 + 1) * (1.0 - tx) + self.texel(x0 + 1, y0 + 1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+// Destino de render fuera de pantalla: un framebuffer propio cuyo contenido se publica como
+// textura con `resolve`. La última textura publicada sigue disponible mientras se dibuja la
+// siguiente, así que un pase puede leer el frame anterior y escribir el nuevo (estelas,
+// distorsión por calor)
+pub struct RenderTarget {
+    framebuffer: Framebuffer,
+    texture: Option<Arc<Texture>>,
+}
+
+impl RenderTarget {
+    pub fn new(width: usize, height: usize) -> Self {
+        RenderTarget { framebuffer: Framebuffer::new(width, height), texture: None }
+    }
+
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    // Framebuffer donde se dibuja el próximo contenido
+    pub fn framebuffer_mut(&mut self) -> &mut Framebuffer {
+        &mut self.framebuffer
+    }
+
+    // Copia el framebuffer a una textura nueva y la publica en lugar de la anterior
+    pub fn resolve(&mut self) -> Arc<Texture> {
+        let texture = Arc::new(Texture::from_framebuffer(&self.framebuffer));
+        self.texture = Some(Arc::clone(&texture));
+        texture
+    }
+
+    // Última textura publicada (None antes del primer `resolve`)
+    pub fn texture(&self) -> Option<Arc<Texture>> {
+        self.texture.clone()
+    }
+}
@@ -1,14 +1,78 @@
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
-use crate::line::line;
+use crate::line::{line_with_mode, LineMode};
+use nalgebra_glm::Vec3;
 
 pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
+    triangle_with_mode(v1, v2, v3, LineMode::Aliased)
+}
+
+// Wireframe del triángulo con el modo de línea indicado
+pub fn triangle_with_mode(v1: &Vertex, v2: &Vertex, v3: &Vertex, mode: LineMode) -> Vec<Fragment> {
+    let edge = |a: &Vertex, b: &Vertex| line_with_mode(a, b, mode);
+
     let mut fragments = Vec::new(); // Corregido el nombre de la variable
 
     // Dibujar los bordes del triángulo
-    fragments.extend(line(v1, v2));
-    fragments.extend(line(v2, v3));
-    fragments.extend(line(v3, v1));
+    fragments.extend(edge(v1, v2));
+    fragments.extend(edge(v2, v3));
+    fragments.extend(edge(v3, v1));
 
     fragments // Retorna el vector de fragmentos
 }
+
+// Tramo de pixeles de una fila que puede cubrir un triángulo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub y: i32,
+    pub min_x: i32,
+    pub max_x: i32,
+}
+
+// Rasterizador por scanlines: ordena los vértices por y, divide el triángulo en una parte
+// de base plana abajo (top -> middle) y otra de base plana arriba (middle -> bottom), y
+// avanza fila por fila los bordes izquierdo y derecho. Los tramos se redondean hacia afuera,
+// así que la prueba baricéntrica sigue decidiendo la cobertura exacta de cada pixel. Solo se
+// generan las filas de [min_row, max_row], aunque el triángulo sea mucho más alto.
+pub fn triangle_scanline(v1: &Vec3, v2: &Vec3, v3: &Vec3, min_row: i32, max_row: i32, spans: &mut Vec<Span>) {
+    let mut sorted = [v1, v2, v3];
+    sorted.sort_by(|a, b| a.y.total_cmp(&b.y));
+    let [top, middle, bottom] = sorted;
+
+    // Los pixeles se muestrean en coordenadas enteras
+    let first_row = top.y.ceil() as i32;
+    let middle_row = middle.y.ceil() as i32;
+    let last_row = bottom.y.floor() as i32;
+
+    // El borde largo (top -> bottom) es compartido por las dos mitades
+    let long_slope = edge_slope(top, bottom);
+
+    // Parte superior: filas antes del vértice del medio
+    scan_half(top, middle, top, long_slope, first_row.max(min_row), (middle_row - 1).min(max_row), spans);
+    // Parte inferior: desde el vértice del medio hasta el final
+    scan_half(middle, bottom, top, long_slope, middle_row.max(first_row).max(min_row), last_row.min(max_row), spans);
+}
+
+// Recorre las filas [first_row, last_row] entre el borde corto (from -> to) y el borde largo
+fn scan_half(from: &Vec3, to: &Vec3, top: &Vec3, long_slope: f32, first_row: i32, last_row: i32, spans: &mut Vec<Span>) {
+    if first_row > last_row {
+        return;
+    }
+
+    let short_slope = edge_slope(from, to);
+    let mut short_x = from.x + (first_row as f32 - from.y) * short_slope;
+    let mut long_x = top.x + (first_row as f32 - top.y) * long_slope;
+
+    for y in first_row..=last_row {
+        let (left, right) = if short_x < long_x { (short_x, long_x) } else { (long_x, short_x) };
+        spans.push(Span { y, min_x: left.floor() as i32, max_x: right.ceil() as i32 });
+        short_x += short_slope;
+        long_x += long_slope;
+    }
+}
+
+// Cuánto avanza x por cada fila a lo largo del borde (0 para bordes horizontales)
+fn edge_slope(a: &Vec3, b: &Vec3) -> f32 {
+    let dy = b.y - a.y;
+    if dy != 0.0 { (b.x - a.x) / dy } else { 0.0 }
+}
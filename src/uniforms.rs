@@ -1,5 +1,546 @@
-use nalgebra_glm::Mat4;
+use nalgebra_glm::{Mat3, Mat4, Vec3};
+use crate::material::Material;
+use std::sync::Arc;
+use crate::environment::Cubemap;
+use crate::framebuffer::DepthMode;
+use crate::light::{AmbientLight, Light};
+use crate::pipeline::PipelineConfig;
+use crate::shader_params::ShaderParams;
 
+// Qué caras descartar antes de rasterizar. Las caras frontales son las que tienen
+// winding antihorario en el modelo (área con signo negativa en pantalla)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+// Winding de las caras frontales en el modelo, vistas desde afuera. Lo habitual (y lo que
+// exporta la mayoría de las herramientas) es antihorario; `math::detect_winding` lo estima
+// para mallas de otra procedencia
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Winding {
+    CounterClockwise,
+    Clockwise,
+}
+
+// Dónde se evalúa la iluminación. Phong: en cada fragmento con la normal interpolada.
+// Gouraud: en cada vértice (en el vertex shader) y se interpola el resultado, más barato
+// pero con los brillos y bordes de las sombras facetados en mallas de pocos polígonos
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadingModel {
+    Phong,
+    Gouraud,
+}
+
+// Cómo se recorren los pixeles de cada triángulo. BoundingBox prueba todo el rectángulo
+// que lo contiene; Scanline solo el tramo [x izquierda, x derecha] de cada fila, lo que
+// ahorra trabajo en triángulos delgados y alargados a cambio de un setup por triángulo más
+// caro; Quads recorre el rectángulo en cuadros de 2x2 con SIMD e interpola la profundidad
+// de los pixeles cubiertos en los mismos registros. Los tres cubren los mismos pixeles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RasterizerMode {
+    BoundingBox,
+    Scanline,
+    Quads,
+}
+
+// De dónde leen el ruido los shaders de ruido. Baked usa la textura horneada del material
+// (`Material::baked_noise`) si la tiene y coincide con sus parámetros; Live siempre evalúa
+// el fBm, para comparar calidad y velocidad
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseSource {
+    Live,
+    Baked,
+}
+
+// Cómo se agrupan los vértices del draw en triángulos. TriangleList: cada tres vértices son
+// un triángulo. TriangleStrip: cada vértice desde el tercero forma un triángulo con los dos
+// anteriores, alternando el orden para conservar el winding. TriangleFan: cada par de
+// vértices consecutivos desde el segundo forma un triángulo con el primero
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveTopology {
+    TriangleList,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl PrimitiveTopology {
+    // Índices de los vértices de cada triángulo en un draw de `vertex_count` vértices
+    pub fn triangles(self, vertex_count: usize) -> impl Iterator<Item = [usize; 3]> {
+        let count = match self {
+            PrimitiveTopology::TriangleList => vertex_count / 3,
+            PrimitiveTopology::TriangleStrip | PrimitiveTopology::TriangleFan => vertex_count.saturating_sub(2),
+        };
+        (0..count).map(move |i| match self {
+            PrimitiveTopology::TriangleList => [i * 3, i * 3 + 1, i * 3 + 2],
+            PrimitiveTopology::TriangleStrip if i % 2 == 0 => [i, i + 1, i + 2],
+            PrimitiveTopology::TriangleStrip => [i + 1, i, i + 2],
+            PrimitiveTopology::TriangleFan => [0, i + 1, i + 2],
+        })
+    }
+}
+
+// Qué luz se ve en el fragment shading. AmbientOnly deja solo la luz ambiental y de
+// hemisferio, para ajustarlas sin que las luces direccionales las tapen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightingView {
+    Full,
+    AmbientOnly,
+}
+
+// Polygon offset: desplazamiento que se suma a la profundidad de cada fragmento antes de la
+// prueba de profundidad, para que calcomanías y overlays coplanares ganen sin z-fighting.
+// Como en glPolygonOffset, el desplazamiento es `slope` veces la pendiente máxima de la
+// profundidad del triángulo (por pixel) más `constant` veces el paso mínimo que distingue el
+// float en la profundidad del triángulo. Se suma tal cual: con la profundidad estándar los
+// valores negativos acercan a la cámara; `toward_camera` elige el signo según el modo
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DepthBias {
+    pub constant: f32,
+    pub slope: f32,
+}
+
+impl DepthBias {
+    pub fn none() -> Self {
+        DepthBias::default()
+    }
+
+    // Bias que acerca a la cámara `constant` pasos y `slope` pendientes con el modo dado
+    pub fn toward_camera(constant: f32, slope: f32, depth_mode: DepthMode) -> Self {
+        let sign = match depth_mode {
+            DepthMode::Standard => -1.0,
+            DepthMode::Reversed => 1.0,
+        };
+        DepthBias { constant: constant.abs() * sign, slope: slope.abs() * sign }
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.constant == 0.0 && self.slope == 0.0
+    }
+
+    // Desplazamiento para un triángulo con vértices `a`, `b` y `c` en pantalla
+    pub fn offset(&self, a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
+        if self.is_none() {
+            return 0.0;
+        }
+        // Pendiente de la profundidad a partir del plano del triángulo: dz/dx y dz/dy
+        let normal = (b - a).cross(&(c - a));
+        let max_slope = if normal.z.abs() > f32::EPSILON {
+            (normal.x / normal.z).abs().max((normal.y / normal.z).abs())
+        } else {
+            0.0
+        };
+        // Paso mínimo del float en la profundidad más lejana del origen
+        let max_depth = a.z.abs().max(b.z.abs()).max(c.z.abs());
+        let resolution = (max_depth * f32::EPSILON).max(f32::MIN_POSITIVE);
+        self.slope * max_slope + self.constant * resolution
+    }
+}
+
+// Comparación de la prueba de stencil: `reference` <op> valor del stencil buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StencilCompare {
+    Always,
+    Never,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+}
+
+impl StencilCompare {
+    pub fn passes(self, reference: u8, stored: u8) -> bool {
+        match self {
+            StencilCompare::Always => true,
+            StencilCompare::Never => false,
+            StencilCompare::Equal => reference == stored,
+            StencilCompare::NotEqual => reference != stored,
+            StencilCompare::Less => reference < stored,
+            StencilCompare::Greater => reference > stored,
+        }
+    }
+}
+
+// Qué se escribe en el stencil buffer después de la prueba
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep,
+    Replace,   // Escribe `reference`
+    Increment, // Suma 1, saturando en 255
+}
+
+impl StencilOp {
+    pub fn apply(self, reference: u8, stored: u8) -> u8 {
+        match self {
+            StencilOp::Keep => stored,
+            StencilOp::Replace => reference,
+            StencilOp::Increment => stored.saturating_add(1),
+        }
+    }
+}
+
+// Estado de stencil de un draw. `fail` se aplica a los fragmentos que no pasan la
+// comparación (y no se dibujan) y `pass` a los que pasan también la prueba de profundidad;
+// los que pasan el stencil pero no la profundidad no lo cambian
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+    pub compare: StencilCompare,
+    pub reference: u8,
+    pub fail: StencilOp,
+    pub pass: StencilOp,
+}
+
+impl StencilState {
+    // Dibujar solo donde el stencil vale `reference`
+    pub fn equal(reference: u8) -> Self {
+        StencilState { compare: StencilCompare::Equal, reference, fail: StencilOp::Keep, pass: StencilOp::Keep }
+    }
+
+    // Dibujar solo donde el stencil no vale `reference`
+    pub fn not_equal(reference: u8) -> Self {
+        StencilState { compare: StencilCompare::NotEqual, reference, fail: StencilOp::Keep, pass: StencilOp::Keep }
+    }
+
+    // Dibujar normalmente y marcar con `reference` lo que quede visible
+    pub fn write(reference: u8) -> Self {
+        StencilState { compare: StencilCompare::Always, reference, fail: StencilOp::Keep, pass: StencilOp::Replace }
+    }
+}
+
+#[derive(Clone)]
 pub struct Uniforms {
-    pub model_matrix: Mat4, // Solo necesitamos la matriz de modelo en este caso
+    // Matrices por separado (modelo -> mundo -> vista -> proyección -> pantalla) y ya
+    // compuestas en `transformation_matrix`, que es la que usa el vertex shader.
+    // Se calculan juntas con `UniformsBuilder` para que el orden sea siempre el mismo
+    pub model_matrix: Mat4,
+    pub view_matrix: Mat4,
+    pub projection_matrix: Mat4,
+    pub viewport_matrix: Mat4,
+    pub transformation_matrix: Mat4, // viewport * projection * view * model
+    pub model_view_matrix: Mat4, // view * model, para los datos en espacio de vista
+    pub normal_matrix: Mat3, // Inversa transpuesta de la parte 3x3 del modelo
+    pub prev_transformation_matrix: Mat4, // Matriz del frame anterior, para calcular velocidades
+    pub time: f32, // Tiempo en segundos, proveniente del Clock del render loop
+    // Tiempo del frame anterior, para las velocidades de lo animado con `time`. Lo actualiza
+    // quien avanza el reloj, junto con `time`
+    pub prev_time: f32,
+    pub throttle: f32, // Acelerador del motor (0 = apagado, 1 = empuje máximo)
+    pub lights: Vec<Light>, // Luces de la escena; sus aportes por canal se suman
+    pub ambient: AmbientLight, // Luz ambiental y de hemisferio, sumada a la de `lights`
+    pub lighting_view: LightingView,
+    pub shading_model: ShadingModel,
+    pub environment: Option<Arc<Cubemap>>, // Entorno que reflejan los materiales reflectivos
+    pub material: Material,
+    pub cull_mode: CullMode,
+    pub front_face: Winding, // Qué winding cuenta como cara frontal para el culling
+    pub rasterizer_mode: RasterizerMode,
+    pub ssaa_factor: usize, // Supersampling: se renderiza a N veces la resolución (1 = sin SSAA)
+    pub depth_bias: DepthBias, // Polygon offset del draw (calcomanías, overlays coplanares)
+    // Pasos que se suman a `depth_bias.constant` (ver `effective_depth_bias`): empuja o acerca
+    // el draw sin armar un DepthBias. Con la profundidad estándar los negativos acercan
+    pub polygon_offset: f32,
+    pub stencil: Option<StencilState>, // Prueba de stencil del draw (None = sin prueba)
+    pub pipeline_config: PipelineConfig, // Etapas opcionales del render (geometry shader)
+    pub topology: PrimitiveTopology, // Cómo se arman los triángulos con los vértices del draw
+    pub morph_weight: f32, // 0 = forma base, 1 = morph target
+    pub bone_matrices: Vec<Mat4>, // Paleta de huesos para skinning (vacía = sin skinning)
+    // Interpolar posición y normal en espacio de vista en cada fragmento (`Fragment::view`).
+    // Apagado por defecto: es trabajo extra por vértice y por fragmento
+    pub fragment_view_data: bool,
+    pub domain_warp: f32, // Intensidad del domain warping en los shaders de ruido (0 = apagado)
+    pub noise_source: NoiseSource,
+    pub shader_params: ShaderParams, // Parámetros ajustables desde shaders.toml
+}
+
+impl Uniforms {
+    // Copia con otra matriz de modelo y la misma cámara, para dibujar otra entidad en el
+    // mismo frame. Como no se conoce el modelo anterior de esa entidad, queda sin movimiento
+    pub fn with_model(&self, model: Mat4) -> Uniforms {
+        let transformation = self.viewport_matrix * self.projection_matrix * self.view_matrix * model;
+        Uniforms {
+            model_matrix: model,
+            normal_matrix: normal_matrix(&model),
+            transformation_matrix: transformation,
+            model_view_matrix: self.view_matrix * model,
+            prev_transformation_matrix: transformation,
+            ..self.clone()
+        }
+    }
+
+    // Pasa al frame siguiente sin copiar el resto de la configuración: la transformación
+    // actual queda como la del frame anterior (para las velocidades del motion blur) y se
+    // recalculan las matrices derivadas de las nuevas
+    pub fn next_frame(&mut self, matrices: Matrices) {
+        self.prev_transformation_matrix = self.transformation_matrix;
+        self.model_matrix = matrices.model;
+        self.view_matrix = matrices.view;
+        self.projection_matrix = matrices.projection;
+        self.viewport_matrix = matrices.viewport;
+        self.transformation_matrix = matrices.transformation();
+        self.model_view_matrix = matrices.view * matrices.model;
+        self.normal_matrix = normal_matrix(&matrices.model);
+    }
+
+    // Bias que se aplica a la profundidad de cada fragmento antes de la prueba: `depth_bias`
+    // con `polygon_offset` sumado a su parte constante
+    pub fn effective_depth_bias(&self) -> DepthBias {
+        DepthBias { constant: self.depth_bias.constant + self.polygon_offset, ..self.depth_bias }
+    }
+
+    pub fn matrices(&self) -> Matrices {
+        Matrices { model: self.model_matrix, view: self.view_matrix, projection: self.projection_matrix, viewport: self.viewport_matrix }
+    }
+}
+
+// Las cuatro matrices de un draw por separado, para inspeccionarlas (herramientas, pruebas);
+// ver `Framebuffer::current_matrices`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrices {
+    pub model: Mat4,
+    pub view: Mat4,
+    pub projection: Mat4,
+    pub viewport: Mat4,
+}
+
+impl Matrices {
+    // viewport * projection * view * model, como `Uniforms::transformation_matrix`
+    pub fn transformation(&self) -> Mat4 {
+        self.viewport * self.projection * self.view * self.model
+    }
+}
+
+// Matriz de viewport: el origen del mundo al centro de la pantalla y `pixels_per_unit`
+// pixeles por unidad (también en z, para conservar la escala de la profundidad)
+pub fn viewport_matrix(width: usize, height: usize, pixels_per_unit: f32) -> Mat4 {
+    let center = Vec3::new(width as f32 / 2.0, height as f32 / 2.0, 0.0);
+    nalgebra_glm::translation(&center) * nalgebra_glm::scaling(&Vec3::repeat(pixels_per_unit))
+}
+
+// Matriz de viewport para una proyección: de coordenadas normalizadas ([-1, 1] en x/y) a
+// pixeles, sin cambiar la profundidad. Sin invertir y, igual que `viewport_matrix`
+pub fn ndc_viewport_matrix(width: usize, height: usize) -> Mat4 {
+    let (half_width, half_height) = (width as f32 / 2.0, height as f32 / 2.0);
+    Mat4::new(
+        half_width, 0.0,         0.0, half_width,
+        0.0,        half_height, 0.0, half_height,
+        0.0,        0.0,         1.0, 0.0,
+        0.0,        0.0,         0.0, 1.0,
+    )
+}
+
+// Matriz para transformar normales: inversa transpuesta de la parte 3x3 del modelo, que
+// mantiene las normales perpendiculares a la superficie con escalas no uniformes. Si el
+// modelo es singular (escala 0 en un eje) se usa la parte 3x3 tal cual.
+pub fn normal_matrix(model: &Mat4) -> Mat3 {
+    let linear = model.fixed_view::<3, 3>(0, 0).into_owned();
+    linear.try_inverse().map_or(linear, |inverse| inverse.transpose())
+}
+
+// Construye los Uniforms de un frame. Todo lo que no se indique queda con un valor por
+// defecto (matrices identidad, una luz blanca desde la cámara, material por defecto...).
+//
+//   let uniforms = UniformsBuilder::new().model(model).viewport(viewport).time(t).build();
+//
+// Para el frame siguiente, `Uniforms::next_frame` conserva la configuración y toma la
+// transformación actual como la del frame anterior.
+#[derive(Clone)]
+pub struct UniformsBuilder {
+    model: Mat4,
+    view: Mat4,
+    projection: Mat4,
+    viewport: Mat4,
+    settings: Uniforms, // Todo lo demás; sus matrices se recalculan en `build`
+}
+
+impl UniformsBuilder {
+    pub fn new() -> Self {
+        let identity = Mat4::identity();
+        UniformsBuilder {
+            model: identity,
+            view: identity,
+            projection: identity,
+            viewport: identity,
+            settings: Uniforms {
+                model_matrix: identity,
+                view_matrix: identity,
+                projection_matrix: identity,
+                viewport_matrix: identity,
+                transformation_matrix: identity,
+                model_view_matrix: identity,
+                normal_matrix: Mat3::identity(),
+                prev_transformation_matrix: identity,
+                time: 0.0,
+                prev_time: 0.0,
+                throttle: 0.0,
+                lights: vec![Light::white(Vec3::new(0.0, 0.0, -1.0))],
+                ambient: AmbientLight::none(),
+                lighting_view: LightingView::Full,
+                shading_model: ShadingModel::Phong,
+                environment: None,
+                material: Material::default(),
+                cull_mode: CullMode::None,
+                front_face: Winding::CounterClockwise,
+                rasterizer_mode: RasterizerMode::BoundingBox,
+                ssaa_factor: 1,
+                depth_bias: DepthBias::none(),
+                polygon_offset: 0.0,
+                stencil: None,
+                pipeline_config: PipelineConfig::default(),
+                topology: PrimitiveTopology::TriangleList,
+                morph_weight: 0.0,
+                bone_matrices: Vec::new(),
+                fragment_view_data: false,
+                domain_warp: 0.0,
+                noise_source: NoiseSource::Baked,
+                shader_params: ShaderParams::default(),
+            },
+        }
+    }
+
+    pub fn model(mut self, model: Mat4) -> Self {
+        self.model = model;
+        self
+    }
+
+    pub fn view(mut self, view: Mat4) -> Self {
+        self.view = view;
+        self
+    }
+
+    pub fn projection(mut self, projection: Mat4) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn viewport(mut self, viewport: Mat4) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    pub fn time(mut self, time: f32) -> Self {
+        self.settings.time = time;
+        self
+    }
+
+    pub fn throttle(mut self, throttle: f32) -> Self {
+        self.settings.throttle = throttle;
+        self
+    }
+
+    pub fn lights(mut self, lights: Vec<Light>) -> Self {
+        self.settings.lights = lights;
+        self
+    }
+
+    pub fn ambient(mut self, ambient: AmbientLight) -> Self {
+        self.settings.ambient = ambient;
+        self
+    }
+
+    pub fn environment(mut self, environment: Option<Arc<Cubemap>>) -> Self {
+        self.settings.environment = environment;
+        self
+    }
+
+    pub fn material(mut self, material: Material) -> Self {
+        self.settings.material = material;
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: CullMode) -> Self {
+        self.settings.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn stencil(mut self, stencil: Option<StencilState>) -> Self {
+        self.settings.stencil = stencil;
+        self
+    }
+
+    pub fn front_face(mut self, front_face: Winding) -> Self {
+        self.settings.front_face = front_face;
+        self
+    }
+
+    pub fn depth_bias(mut self, depth_bias: DepthBias) -> Self {
+        self.settings.depth_bias = depth_bias;
+        self
+    }
+
+    pub fn polygon_offset(mut self, polygon_offset: f32) -> Self {
+        self.settings.polygon_offset = polygon_offset;
+        self
+    }
+
+    pub fn shader_params(mut self, shader_params: ShaderParams) -> Self {
+        self.settings.shader_params = shader_params;
+        self
+    }
+
+    pub fn build(self) -> Uniforms {
+        let transformation = self.viewport * self.projection * self.view * self.model;
+        Uniforms {
+            model_matrix: self.model,
+            view_matrix: self.view,
+            projection_matrix: self.projection,
+            viewport_matrix: self.viewport,
+            transformation_matrix: transformation,
+            model_view_matrix: self.view * self.model,
+            normal_matrix: normal_matrix(&self.model),
+            // Sin frame anterior (el primero) no hay movimiento
+            prev_transformation_matrix: transformation,
+            prev_time: self.settings.time,
+            ..self.settings
+        }
+    }
+}
+
+impl Default for UniformsBuilder {
+    fn default() -> Self {
+        UniformsBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, Projection};
+
+    fn matrices(time: f32) -> Matrices {
+        Matrices {
+            model: nalgebra_glm::translation(&Vec3::new(time, 2.0, 0.0)) * nalgebra_glm::scaling(&Vec3::new(1.0, 3.0, 1.0)),
+            view: Camera::new(Vec3::new(0.5, -1.0, -6.0)).view_matrix(),
+            projection: Projection::default().matrix(4.0 / 3.0),
+            viewport: ndc_viewport_matrix(800, 600),
+        }
+    }
+
+    #[test]
+    fn builder_composes_viewport_projection_view_model() {
+        let Matrices { model, view, projection, viewport } = matrices(1.0);
+        let uniforms = UniformsBuilder::new().model(model).view(view).projection(projection).viewport(viewport).time(1.0).build();
+
+        assert_eq!(uniforms.transformation_matrix, viewport * projection * view * model);
+        assert_eq!(uniforms.model_view_matrix, view * model);
+        assert_eq!(uniforms.normal_matrix, normal_matrix(&model));
+        // En el primer frame no hay movimiento
+        assert_eq!(uniforms.prev_transformation_matrix, uniforms.transformation_matrix);
+        assert_eq!(uniforms.prev_time, 1.0);
+    }
+
+    #[test]
+    fn next_frame_keeps_the_previous_transformation() {
+        let first = matrices(1.0);
+        let mut uniforms = UniformsBuilder::new().model(first.model).view(first.view).projection(first.projection).viewport(first.viewport).build();
+        let second = matrices(2.0);
+        uniforms.next_frame(second);
+
+        assert_eq!(uniforms.prev_transformation_matrix, first.transformation());
+        assert_eq!(uniforms.transformation_matrix, second.transformation());
+        assert_eq!(uniforms.model_view_matrix, second.view * second.model);
+        assert_eq!(uniforms.normal_matrix, normal_matrix(&second.model));
+        assert_eq!(uniforms.matrices(), second);
+    }
 }
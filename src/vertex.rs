@@ -1,6 +1,6 @@
 // vertex.rs
 
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -10,7 +10,20 @@ pub struct Vertex {
   pub tex_coords: Vec2,
   pub color: Color,
   pub transformed_position: Vec3,
+  pub clip_position: Vec4, // Antes de la división de perspectiva (w = 1 sin proyección)
   pub transformed_normal: Vec3,
+  pub prev_transformed_position: Vec3,
+  // Posición y normal en espacio de vista (solo con `Uniforms::fragment_view_data`)
+  pub view_position: Vec3,
+  pub view_normal: Vec3,
+  // Posición y normal del morph target (iguales a las base si no hay target)
+  pub morph_position: Vec3,
+  pub morph_normal: Vec3,
+  // Skinning: hasta 4 huesos por vértice (pesos en 0 = vértice sin skinning)
+  pub bone_indices: [u8; 4],
+  pub bone_weights: [f32; 4],
+  // Luz del vértice con ShadingModel::Gouraud (blanca si no se calcula)
+  pub light: Vec3,
 }
 
 impl Vertex {
@@ -21,7 +34,16 @@ impl Vertex {
       tex_coords,
       color: Color::black(),
       transformed_position: position,
+      clip_position: position.push(1.0),
       transformed_normal: normal,
+      prev_transformed_position: position,
+      view_position: position,
+      view_normal: normal,
+      morph_position: position,
+      morph_normal: normal,
+      bone_indices: [0; 4],
+      bone_weights: [0.0; 4],
+      light: Vec3::repeat(1.0),
     }
   }
 
@@ -32,7 +54,16 @@ impl Vertex {
       tex_coords: Vec2::new(0.0, 0.0),
       color,
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
       transformed_normal: Vec3::new(0.0, 0.0, 0.0),
+      prev_transformed_position: Vec3::new(0.0, 0.0, 0.0),
+      view_position: position,
+      view_normal: Vec3::new(0.0, 0.0, 0.0),
+      morph_position: position,
+      morph_normal: Vec3::new(0.0, 0.0, 0.0),
+      bone_indices: [0; 4],
+      bone_weights: [0.0; 4],
+      light: Vec3::repeat(1.0),
     }
   }
 
@@ -40,6 +71,29 @@ impl Vertex {
     self.transformed_position = position;
     self.transformed_normal = normal;
   }
+
+  // Interpolación lineal de todos los atributos (t = 0 -> self, t = 1 -> other). Los huesos
+  // son los de `self`, que no se pueden mezclar por índice
+  pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+    let mix = |a: Vec3, b: Vec3| a + (b - a) * t;
+    Vertex {
+      position: mix(self.position, other.position),
+      normal: mix(self.normal, other.normal),
+      tex_coords: self.tex_coords + (other.tex_coords - self.tex_coords) * t,
+      color: self.color * (1.0 - t) + other.color * t,
+      transformed_position: mix(self.transformed_position, other.transformed_position),
+      clip_position: self.clip_position + (other.clip_position - self.clip_position) * t,
+      transformed_normal: mix(self.transformed_normal, other.transformed_normal),
+      prev_transformed_position: mix(self.prev_transformed_position, other.prev_transformed_position),
+      view_position: mix(self.view_position, other.view_position),
+      view_normal: mix(self.view_normal, other.view_normal),
+      morph_position: mix(self.morph_position, other.morph_position),
+      morph_normal: mix(self.morph_normal, other.morph_normal),
+      bone_indices: self.bone_indices,
+      bone_weights: self.bone_weights,
+      light: mix(self.light, other.light),
+    }
+  }
 }
 
 impl Default for Vertex {
@@ -50,7 +104,16 @@ impl Default for Vertex {
       tex_coords: Vec2::new(0.0, 0.0),
       color: Color::black(),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+      prev_transformed_position: Vec3::new(0.0, 0.0, 0.0),
+      view_position: Vec3::new(0.0, 0.0, 0.0),
+      view_normal: Vec3::new(0.0, 1.0, 0.0),
+      morph_position: Vec3::new(0.0, 0.0, 0.0),
+      morph_normal: Vec3::new(0.0, 1.0, 0.0),
+      bone_indices: [0; 4],
+      bone_weights: [0.0; 4],
+      light: Vec3::repeat(1.0),
     }
   }
 }
\ No newline at end of file
@@ -0,0 +1,101 @@
+// viewport.rs
+//
+// Varias vistas en un mismo frame (pantalla dividida, cámara de persecución): cada
+// `Viewport` es un rectángulo del framebuffer con su propia vista, proyección y matriz de
+// viewport. Al dibujar una vista el framebuffer queda con un scissor en su rectángulo, así
+// que ni el color ni la profundidad de una vista se escriben en la otra aunque el bounding
+// box de un triángulo cruce el borde.
+
+use nalgebra_glm::{Mat4, Vec3};
+
+use crate::camera::Projection;
+use crate::framebuffer::{render, Framebuffer, Rect, RenderStats};
+use crate::uniforms::{ndc_viewport_matrix, viewport_matrix, Uniforms};
+use crate::vertex::Vertex;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Viewport {
+    pub rect: Rect,
+    pub view_matrix: Mat4,
+    pub projection_matrix: Mat4,
+    pub viewport_matrix: Mat4, // Lleva a pixeles dentro de `rect` (ya incluye su esquina)
+}
+
+impl Viewport {
+    // Vista ortográfica: el origen de la vista al centro del rectángulo, con
+    // `pixels_per_unit` pixeles por unidad (como `viewport_matrix`)
+    pub fn orthographic(rect: Rect, view: Mat4, pixels_per_unit: f32) -> Self {
+        Viewport {
+            rect,
+            view_matrix: view,
+            projection_matrix: Mat4::identity(),
+            viewport_matrix: corner_offset(&rect) * viewport_matrix(rect.width, rect.height, pixels_per_unit),
+        }
+    }
+
+    // Vista en perspectiva con el aspecto del rectángulo
+    pub fn perspective(rect: Rect, view: Mat4, projection: &Projection) -> Self {
+        Viewport {
+            rect,
+            view_matrix: view,
+            projection_matrix: projection.matrix(rect.width as f32 / rect.height.max(1) as f32),
+            viewport_matrix: corner_offset(&rect) * ndc_viewport_matrix(rect.width, rect.height),
+        }
+    }
+
+    // Del mundo a pixeles del framebuffer: viewport * proyección * vista
+    pub fn world_to_screen(&self) -> Mat4 {
+        self.viewport_matrix * self.projection_matrix * self.view_matrix
+    }
+
+    // Si el pixel (x, y) del framebuffer cae en la vista (p. ej. para saber qué vista tiene
+    // el foco al hacer click)
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        self.rect.contains(x.floor() as isize, y.floor() as isize)
+    }
+
+    // Copia de `uniforms` con las matrices de esta vista. La transformación del frame
+    // anterior se reproyecta a esta vista suponiendo que su cámara no se movió, para que
+    // las velocidades del motion blur sigan siendo las del modelo
+    pub fn apply(&self, uniforms: &Uniforms) -> Uniforms {
+        let camera = self.world_to_screen();
+        let previous_camera = uniforms.viewport_matrix * uniforms.projection_matrix * uniforms.view_matrix;
+
+        let mut viewport_uniforms = uniforms.clone();
+        viewport_uniforms.view_matrix = self.view_matrix;
+        viewport_uniforms.projection_matrix = self.projection_matrix;
+        viewport_uniforms.viewport_matrix = self.viewport_matrix;
+        viewport_uniforms.transformation_matrix = camera * uniforms.model_matrix;
+        viewport_uniforms.model_view_matrix = self.view_matrix * uniforms.model_matrix;
+        viewport_uniforms.prev_transformation_matrix = match previous_camera.try_inverse() {
+            Some(inverse) => camera * inverse * uniforms.prev_transformation_matrix,
+            None => viewport_uniforms.transformation_matrix,
+        };
+        viewport_uniforms
+    }
+
+    // Como `apply`, con la vista `previous` que tenía esta misma vista en el frame anterior y
+    // el modelo de ese frame: la transformación anterior sale de la cámara anterior, así las
+    // velocidades del motion blur incluyen el movimiento de la cámara (una cámara que sigue
+    // a la nave no la desenfoca)
+    pub fn apply_with_previous(&self, uniforms: &Uniforms, previous: &Viewport, previous_model: &Mat4) -> Uniforms {
+        let mut viewport_uniforms = self.apply(uniforms);
+        viewport_uniforms.prev_transformation_matrix = previous.world_to_screen() * previous_model;
+        viewport_uniforms
+    }
+}
+
+// Traslada el origen de una matriz de viewport a la esquina del rectángulo
+fn corner_offset(rect: &Rect) -> Mat4 {
+    nalgebra_glm::translation(&Vec3::new(rect.x as f32, rect.y as f32, 0.0))
+}
+
+// Dibuja la malla en la vista: scissor en su rectángulo y sus matrices en los uniforms. El
+// scissor anterior del framebuffer se restaura al terminar
+pub fn render_viewport(framebuffer: &mut Framebuffer, viewport: &Viewport, uniforms: &Uniforms, vertex_array: &[Vertex]) -> RenderStats {
+    let previous_scissor = framebuffer.scissor();
+    framebuffer.set_scissor(Some(viewport.rect));
+    let stats = render(framebuffer, &viewport.apply(uniforms), vertex_array);
+    framebuffer.set_scissor(previous_scissor);
+    stats
+}
@@ -0,0 +1,191 @@
+// web.rs
+//
+// Versión para navegador: en lugar de la ventana de minifb, cada frame se copia a un
+// <canvas id="renderer"> como ImageData. Se compila a WebAssembly con el feature `wasm`:
+//
+//   wasm-pack build --target web --features wasm
+//
+// y se abre `index.html` desde un servidor local (p. ej. `python3 -m http.server`).
+// Controles: flechas para el empuje de la nave, W para el wireframe y S para el SSAA 2x.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use nalgebra_glm::Vec3;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData, KeyboardEvent};
+
+use crate::color::Color;
+use crate::framebuffer::{render, render_wireframe, Framebuffer};
+use crate::line::LineMode;
+use crate::material::Material;
+use crate::obj::Obj;
+use crate::ship::{self, ShipState};
+use crate::uniforms::{viewport_matrix, Matrices, Uniforms, UniformsBuilder};
+use crate::vertex::Vertex;
+
+const WIDTH: usize = 800;
+const HEIGHT: usize = 600;
+const SCALE: f32 = 100.0;
+const THRUST_ACCELERATION: f32 = 2.0;
+
+// Closure del frame, compartido para que pueda volver a pedirse a sí mismo
+type FrameCallback = Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>>;
+
+// Teclas presionadas (KeyboardEvent.code) y teclas que se presionaron desde el último frame
+#[derive(Default)]
+struct Keys {
+    down: HashSet<String>,
+    pressed: HashSet<String>,
+}
+
+struct WebApp {
+    context: CanvasRenderingContext2d,
+    framebuffer: Framebuffer,
+    uniforms: Uniforms,
+    vertices: Vec<Vertex>,
+    ship_state: ShipState,
+    wireframe: bool,
+    keys: Rc<RefCell<Keys>>,
+    last_time: Option<f64>,
+    rgba: Vec<u8>,
+}
+
+impl WebApp {
+    // Avanza la simulación y dibuja un frame; `now` en milisegundos (requestAnimationFrame)
+    fn frame(&mut self, now: f64) -> Result<(), JsValue> {
+        let dt = self.last_time.map_or(0.0, |last| ((now - last) / 1000.0) as f32);
+        self.last_time = Some(now);
+        self.uniforms.prev_time = self.uniforms.time;
+        self.uniforms.time = (now / 1000.0) as f32;
+
+        let mut thrust = Vec3::zeros();
+        {
+            let mut keys = self.keys.borrow_mut();
+            for (code, direction) in [
+                ("ArrowLeft", Vec3::new(-1.0, 0.0, 0.0)),
+                ("ArrowRight", Vec3::new(1.0, 0.0, 0.0)),
+                ("ArrowUp", Vec3::new(0.0, -1.0, 0.0)),
+                ("ArrowDown", Vec3::new(0.0, 1.0, 0.0)),
+            ] {
+                if keys.down.contains(code) {
+                    thrust += direction * THRUST_ACCELERATION;
+                }
+            }
+            if keys.pressed.contains("KeyW") {
+                self.wireframe = !self.wireframe;
+            }
+            if keys.pressed.contains("KeyS") {
+                self.uniforms.ssaa_factor = if self.uniforms.ssaa_factor == 1 { 2 } else { 1 };
+            }
+            keys.pressed.clear();
+        }
+        self.ship_state = ship::update(&self.ship_state, &thrust, &[], dt);
+
+        let model = nalgebra_glm::translation(&self.ship_state.position);
+        self.uniforms.next_frame(Matrices { model, ..self.uniforms.matrices() });
+
+        self.framebuffer.clear(Color::new(0, 0, 20));
+        render(&mut self.framebuffer, &self.uniforms, &self.vertices);
+        if self.wireframe {
+            render_wireframe(&mut self.framebuffer, &self.uniforms, &self.vertices, LineMode::Aliased, None, None);
+        }
+        self.present()
+    }
+
+    // Copia el framebuffer al canvas. minifb usa pixeles 0xAARRGGBB y ImageData espera
+    // bytes RGBA, así que cada pixel se reordena
+    fn present(&mut self) -> Result<(), JsValue> {
+        self.rgba.clear();
+        for &pixel in self.framebuffer.present_buffer() {
+            let [b, g, r, _] = pixel.to_le_bytes();
+            self.rgba.extend_from_slice(&[r, g, b, 255]);
+        }
+        let image = ImageData::new_with_u8_clamped_array_and_sh(Clamped(&self.rgba), WIDTH as u32, HEIGHT as u32)?;
+        self.context.put_image_data(&image, 0.0, 0.0)
+    }
+}
+
+fn request_animation_frame(callback: &Closure<dyn FnMut(f64)>) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or("sin window")?;
+    window.request_animation_frame(callback.as_ref().unchecked_ref())?;
+    Ok(())
+}
+
+// Punto de entrada del módulo: prepara el canvas, los eventos de teclado y el bucle de frames
+#[wasm_bindgen(start)]
+pub fn start() -> Result<(), JsValue> {
+    let document = web_sys::window().and_then(|w| w.document()).ok_or("sin document")?;
+    let canvas: HtmlCanvasElement = document
+        .get_element_by_id("renderer")
+        .ok_or("no hay un <canvas id=\"renderer\">")?
+        .dyn_into()?;
+    canvas.set_width(WIDTH as u32);
+    canvas.set_height(HEIGHT as u32);
+    let context: CanvasRenderingContext2d = canvas.get_context("2d")?.ok_or("sin contexto 2d")?.dyn_into()?;
+
+    // Teclado: se registra qué teclas están abajo; el frame las lee
+    let keys = Rc::new(RefCell::new(Keys::default()));
+    {
+        let keys = Rc::clone(&keys);
+        let on_down = Closure::<dyn FnMut(KeyboardEvent)>::new(move |event: KeyboardEvent| {
+            let mut keys = keys.borrow_mut();
+            if !event.repeat() {
+                keys.pressed.insert(event.code());
+            }
+            keys.down.insert(event.code());
+        });
+        document.add_event_listener_with_callback("keydown", on_down.as_ref().unchecked_ref())?;
+        on_down.forget();
+    }
+    {
+        let keys = Rc::clone(&keys);
+        let on_up = Closure::<dyn FnMut(KeyboardEvent)>::new(move |event: KeyboardEvent| {
+            keys.borrow_mut().down.remove(&event.code());
+        });
+        document.add_event_listener_with_callback("keyup", on_up.as_ref().unchecked_ref())?;
+        on_up.forget();
+    }
+
+    // No hay sistema de archivos: el modelo va incluido en el binario
+    let obj = Obj::from_source(include_str!("../assets/cube.obj")).map_err(|e| e.to_string())?;
+    let material = Material::default();
+    let mut vertices = obj.get_vertex_array();
+    for vertex in vertices.iter_mut() {
+        vertex.color = material.base_color;
+    }
+
+    let uniforms = UniformsBuilder::new()
+        .viewport(viewport_matrix(WIDTH, HEIGHT, SCALE))
+        .material(material)
+        .build();
+
+    let mut app = WebApp {
+        context,
+        framebuffer: Framebuffer::new(WIDTH, HEIGHT),
+        uniforms,
+        vertices,
+        ship_state: ShipState { position: Vec3::zeros(), velocity: Vec3::zeros() },
+        wireframe: false,
+        keys,
+        last_time: None,
+        rgba: Vec::with_capacity(WIDTH * HEIGHT * 4),
+    };
+
+    // Bucle con requestAnimationFrame: el closure se vuelve a pedir a sí mismo cada frame
+    let callback: FrameCallback = Rc::new(RefCell::new(None));
+    let next = Rc::clone(&callback);
+    *callback.borrow_mut() = Some(Closure::new(move |now: f64| {
+        if let Err(e) = app.frame(now) {
+            web_sys::console::error_1(&e);
+            return;
+        }
+        if let Some(next) = next.borrow().as_ref() {
+            let _ = request_animation_frame(next);
+        }
+    }));
+    let first = callback.borrow();
+    request_animation_frame(first.as_ref().ok_or("sin callback")?)
+}
@@ -0,0 +1,56 @@
+// auto_frame.rs
+//
+// Encuadre automático (tecla F en el renderer) con `Camera::frame` a partir de la esfera
+// envolvente del modelo y el campo de visión de la proyección.
+
+mod common;
+
+use common::covered;
+use nalgebra_glm::Vec3;
+use renderer::camera::{Camera, Projection};
+use renderer::color::Color;
+use renderer::framebuffer::{render, Framebuffer};
+use renderer::math::transform_sphere;
+use renderer::obj::Obj;
+use renderer::uniforms::{ndc_viewport_matrix, UniformsBuilder};
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+
+// El mismo cubo a escala 10 y a escala 0.1 cubre prácticamente la misma fracción de la
+// imagen, y la esfera no se sale de la vista
+#[test]
+fn framing_is_independent_of_the_model_scale() {
+    let obj = Obj::load(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/cube.obj")).expect("assets/cube.obj se carga");
+    let mut vertices = obj.get_vertex_array();
+    vertices.iter_mut().for_each(|vertex| vertex.color = Color::new(200, 200, 200));
+    let (center, radius) = obj.bounding_sphere();
+    let projection = Projection::default();
+    let aspect = WIDTH as f32 / HEIGHT as f32;
+
+    let coverage = |scale: f32| {
+        // El modelo lejos del origen, para que el encuadre también tenga que moverse
+        let model = nalgebra_glm::translation(&Vec3::new(3.0, -2.0, 5.0)) * nalgebra_glm::scaling(&Vec3::repeat(scale));
+        let (world_center, world_radius) = transform_sphere(&model, center, radius);
+        let camera = Camera::frame(world_center, world_radius, projection.fit_fov(aspect));
+        let uniforms = UniformsBuilder::new()
+            .model(model)
+            .view(camera.view_matrix())
+            .projection(projection.matrix(aspect))
+            .viewport(ndc_viewport_matrix(WIDTH, HEIGHT))
+            .build();
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(Color::black());
+        render(&mut framebuffer, &uniforms, &vertices);
+
+        let touches_border = framebuffer.rows().enumerate().any(|(y, row)| {
+            row.iter().enumerate().any(|(x, &pixel)| pixel & 0x00FF_FFFF != 0 && (x == 0 || y == 0 || x == WIDTH - 1 || y == HEIGHT - 1))
+        });
+        assert!(!touches_border, "escala {scale}");
+        covered(framebuffer.as_slice()) as f32 / (WIDTH * HEIGHT) as f32
+    };
+
+    let (large, small) = (coverage(10.0), coverage(0.1));
+    assert!(large > 0.0);
+    assert!((large - small).abs() <= 0.02 * large.max(small), "{large} y {small}");
+}
@@ -0,0 +1,62 @@
+// blend_modes.rs
+//
+// Modos de `BlendMode` sobre una escena completa: entidades opacas y sombras multiplicativas.
+
+mod common;
+
+use common::{image_hash, sphere};
+use nalgebra_glm::Vec3;
+use renderer::color::Color;
+use renderer::fragment::Fragment;
+use renderer::framebuffer::{render, Framebuffer};
+use renderer::material::{BlendMode, Material};
+use renderer::uniforms::{viewport_matrix, Uniforms, UniformsBuilder};
+
+// FNV-1a de la escena opaca, tomado antes de separar los modos en `BlendMode::combine`
+const OPAQUE_HASH: u64 = 0x66bd_2966_3878_e251;
+
+// Opacidad del parámetro "opacity" del material
+fn opacity_shader(fragment: &mut Fragment, uniforms: &Uniforms) {
+    fragment.alpha = uniforms.material.param("opacity", 1.0);
+}
+
+fn opaque_scene(material: &Material) -> Framebuffer {
+    let (width, height) = (200, 150);
+    let mut framebuffer = Framebuffer::new(width, height);
+    framebuffer.clear(Color::new(10, 20, 40));
+    let uniforms = UniformsBuilder::new().viewport(viewport_matrix(width, height, 40.0)).material(material.clone()).build();
+    let color = Color::new(200, 160, 120);
+    render(&mut framebuffer, &uniforms, &sphere(Vec3::new(-0.6, 0.0, 0.0), 1.0, 16, 32, color));
+    render(&mut framebuffer, &uniforms, &sphere(Vec3::new(0.6, 0.2, -0.5), 0.8, 16, 32, color));
+    framebuffer
+}
+
+// Dos esferas opacas dan la misma imagen que antes de separar los modos aunque el shader
+// ponga alpha en 0.3, y escriben profundidad
+#[test]
+fn opaque_entities_are_unaffected() {
+    let plain = opaque_scene(&Material::default());
+    let translucent = Material { fragment_shader: Some(opacity_shader), ..Material::default() }.with_param("opacity", 0.3);
+    assert_eq!(image_hash(plain.as_slice()), OPAQUE_HASH);
+    assert!(plain.as_slice() == opaque_scene(&translucent).as_slice());
+    assert!(plain.get_depth(76, 75).is_some_and(f32::is_finite));
+}
+
+// Una esfera con Multiply sobre la escena solo oscurece pixeles
+#[test]
+fn multiply_only_darkens() {
+    let plain = opaque_scene(&Material::default());
+    let mut shadowed = opaque_scene(&Material::default());
+    let shadow_color = Color::new(90, 90, 110);
+    let shadow = Material { base_color: shadow_color, unlit: true, blend_mode: BlendMode::Multiply, ..Material::default() };
+    let uniforms = UniformsBuilder::new().viewport(viewport_matrix(200, 150, 40.0)).material(shadow).build();
+    render(&mut shadowed, &uniforms, &sphere(Vec3::new(0.0, -0.3, -2.0), 0.7, 12, 24, shadow_color));
+
+    let mut darker = 0;
+    for (&after, &before) in shadowed.as_slice().iter().zip(plain.as_slice()) {
+        let (after, before) = (Color::from_u32(after), Color::from_u32(before));
+        assert!(after.r <= before.r && after.g <= before.g && after.b <= before.b, "{before} -> {after}");
+        darker += (after.luminance() < before.luminance()) as usize;
+    }
+    assert!(darker > 0);
+}
@@ -0,0 +1,70 @@
+// common/mod.rs
+//
+// Geometría y utilidades compartidas por las pruebas de integración. Cada archivo de
+// tests/ es su propio crate y usa solo una parte, de ahí el allow.
+
+#![allow(dead_code)]
+
+use nalgebra_glm::{Vec2, Vec3};
+use renderer::color::Color;
+use renderer::vertex::Vertex;
+
+// Vértice de `color` con la normal hacia la cámara
+pub fn vertex(position: Vec3, color: Color) -> Vertex {
+    let mut vertex = Vertex::new(position, Vec3::new(0.0, 0.0, -1.0), Vec2::zeros());
+    vertex.color = color;
+    vertex
+}
+
+// Cuadrado de lado `size` centrado en `center`, en el plano z = center.z y mirando a la cámara
+pub fn quad(center: Vec3, size: f32, color: Color) -> Vec<Vertex> {
+    let h = size / 2.0;
+    let corner = |dx: f32, dy: f32| vertex(center + Vec3::new(dx, dy, 0.0), color);
+    let (a, b, c, d) = (corner(-h, -h), corner(h, -h), corner(h, h), corner(-h, h));
+    vec![a.clone(), b, c.clone(), a, c, d]
+}
+
+// Esfera UV de radio `radius` centrada en `center`, en triángulos sueltos
+pub fn sphere(center: Vec3, radius: f32, rings: usize, segments: usize, color: Color) -> Vec<Vertex> {
+    let point = |ring: usize, segment: usize| {
+        let (theta, phi) = (std::f32::consts::PI * ring as f32 / rings as f32, std::f32::consts::TAU * segment as f32 / segments as f32);
+        let normal = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+        let mut vertex = Vertex::new(center + normal * radius, normal, Vec2::zeros());
+        vertex.color = color;
+        vertex
+    };
+    let mut vertices = Vec::new();
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let (a, b, c, d) = (point(ring, segment), point(ring + 1, segment), point(ring + 1, segment + 1), point(ring, segment + 1));
+            vertices.extend([a.clone(), b, c.clone(), a, c, d]);
+        }
+    }
+    vertices
+}
+
+// Pixeles con algún canal distinto de negro
+pub fn covered(image: &[u32]) -> usize {
+    image.iter().filter(|&&pixel| pixel & 0x00FF_FFFF != 0).count()
+}
+
+// FNV-1a de los pixeles
+pub fn image_hash(image: &[u32]) -> u64 {
+    image.iter().flat_map(|pixel| pixel.to_le_bytes()).fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+// xorshift64*: suficiente para puntos y triángulos de prueba reproducibles
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> f32 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+}
@@ -0,0 +1,109 @@
+// deferred.rs
+//
+// Deferred shading con `GBuffer` contra el render directo, sobre tres esferas superpuestas
+// con materiales distintos (ruido, rampa de color y espejo que refleja el entorno) y dos luces.
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{covered, sphere};
+use nalgebra_glm::Vec3;
+use renderer::color::{Color, Gradient};
+use renderer::environment::Cubemap;
+use renderer::fragment::Fragment;
+use renderer::framebuffer::{render, Framebuffer};
+use renderer::gbuffer::{gbuffer_shader, lighting_pass, render_gbuffer, GBuffer, GBufferFragment, GBufferShader};
+use renderer::light::{AmbientLight, Light};
+use renderer::material::Material;
+use renderer::shader::noise_based_fragment_shader;
+use renderer::uniforms::{viewport_matrix, Uniforms, UniformsBuilder};
+use renderer::vertex::Vertex;
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+
+fn scene_uniforms(material: Material) -> Uniforms {
+    UniformsBuilder::new()
+        .viewport(viewport_matrix(WIDTH, HEIGHT, 45.0))
+        .lights(vec![Light::white(Vec3::new(-1.0, 1.0, 1.0)), Light::new(Vec3::new(1.0, -0.5, 1.0), Color::new(255, 120, 60), 0.6)])
+        .ambient(AmbientLight { color: Color::new(255, 255, 255), intensity: 0.08, ..AmbientLight::default() })
+        .environment(Some(Arc::new(Cubemap::space(64, 0))))
+        .material(material)
+        .build()
+}
+
+fn scene() -> ([Vec<Vertex>; 3], [Material; 3]) {
+    let color = Color::new(200, 180, 160);
+    let spheres = [
+        sphere(Vec3::new(-1.3, 0.2, 0.0), 1.0, 24, 48, color),
+        sphere(Vec3::new(0.0, -0.2, 0.5), 1.0, 24, 48, color),
+        sphere(Vec3::new(1.3, 0.2, 0.0), 1.0, 24, 48, color),
+    ];
+    let materials = [
+        Material { fragment_shader: Some(noise_based_fragment_shader), noise_scale: 2.0, ..Material::default() },
+        Material { gradient: Gradient::preset("lava"), ..Material::default() }.with_param("material_id", 1.0),
+        Material { reflectivity: 0.6, ..Material::default() }.with_param("material_id", 2.0),
+    ];
+    (spheres, materials)
+}
+
+// Pase de geometría de las tres esferas y después un solo pase de iluminación
+fn deferred(spheres: &[Vec<Vertex>], materials: &[Material], shader: GBufferShader) -> (GBuffer, Framebuffer) {
+    let mut gbuffer = GBuffer::new(WIDTH, HEIGHT);
+    gbuffer.clear();
+    for (vertices, material) in spheres.iter().zip(materials) {
+        render_gbuffer(&mut gbuffer, &scene_uniforms(material.clone()), vertices, shader);
+    }
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    framebuffer.clear(Color::black());
+    lighting_pass(&gbuffer, &scene_uniforms(Material::default()), materials, &mut framebuffer);
+    (gbuffer, framebuffer)
+}
+
+#[test]
+fn deferred_matches_forward_rendering() {
+    let (spheres, materials) = scene();
+    let mut forward = Framebuffer::new(WIDTH, HEIGHT);
+    forward.clear(Color::black());
+    for (vertices, material) in spheres.iter().zip(&materials) {
+        render(&mut forward, &scene_uniforms(material.clone()), vertices);
+    }
+    let (gbuffer, deferred) = deferred(&spheres, &materials, gbuffer_shader);
+
+    assert!(covered(forward.as_slice()) > 0);
+    assert!(forward.as_slice() == deferred.as_slice());
+    for id in 1..3u8 {
+        assert!(gbuffer.material_id.contains(&id), "material {id}");
+    }
+}
+
+// Franjas horizontales emisivas sobre el color del material
+fn striped_gbuffer_shader(fragment: &Fragment, uniforms: &Uniforms) -> GBufferFragment {
+    let mut output = gbuffer_shader(fragment, uniforms);
+    if (fragment.model_position.y * 6.0).rem_euclid(1.0) < 0.25 {
+        output.emission = Color::new(90, 200, 255);
+    }
+    output
+}
+
+// La capa de emisión solo tiene color en las franjas, que quedan más brillantes que sin
+// emisión, y el resto de la imagen no cambia
+#[test]
+fn custom_gbuffer_shader_adds_emission() {
+    let (spheres, materials) = scene();
+    let (_, plain) = deferred(&spheres, &materials, gbuffer_shader);
+    let (gbuffer, striped) = deferred(&spheres, &materials, striped_gbuffer_shader);
+
+    let emission = gbuffer.emission.as_slice();
+    assert!(covered(emission) > 0);
+    let mut brighter = 0;
+    for ((&lit, &unlit), &emission) in striped.as_slice().iter().zip(plain.as_slice()).zip(emission) {
+        if emission & 0x00FF_FFFF == 0 {
+            assert_eq!(lit, unlit);
+        } else if Color::from_u32(lit).luminance() > Color::from_u32(unlit).luminance() {
+            brighter += 1;
+        }
+    }
+    assert!(brighter > 0);
+}
@@ -0,0 +1,99 @@
+// exhaust_plume.rs
+//
+// Penacho aditivo del motor: largo y brillo según el acelerador, degradé hacia el extremo
+// abierto y prueba de profundidad sin escribirla.
+
+mod common;
+
+use common::sphere;
+use nalgebra_glm::{Mat4, Vec3};
+use renderer::color::Color;
+use renderer::framebuffer::{render, Framebuffer};
+use renderer::material::Material;
+use renderer::ship::{plume_matrix, plume_mesh};
+use renderer::uniforms::{viewport_matrix, CullMode, UniformsBuilder};
+
+// Penacho con la tobera en (0, -2) y el cono de 3.5 unidades hacia +y
+fn draw_plume(framebuffer: &mut Framebuffer, throttle: f32, time: f32) {
+    let emitter = Mat4::new_translation(&Vec3::new(0.0, -2.0, 0.0));
+    let uniforms = UniformsBuilder::new()
+        .viewport(viewport_matrix(320, 240, 50.0))
+        .model(plume_matrix(&emitter, throttle, 3.5, 0.4))
+        .material(Material::exhaust())
+        .cull_mode(CullMode::None)
+        .throttle(throttle)
+        .time(time)
+        .build();
+    render(framebuffer, &uniforms, &plume_mesh(24, 0.45));
+}
+
+fn plume_image(throttle: f32, time: f32) -> Vec<u32> {
+    let mut framebuffer = Framebuffer::new(320, 240);
+    framebuffer.clear(Color::black());
+    draw_plume(&mut framebuffer, throttle, time);
+    framebuffer.as_slice().to_vec()
+}
+
+// Filas con algún pixel encendido, de arriba hacia abajo
+fn lit_rows(image: &[u32]) -> Vec<&[u32]> {
+    image.chunks(320).filter(|row| row.iter().any(|&pixel| pixel & 0x00FF_FFFF != 0)).collect()
+}
+
+fn brightness(pixels: &[u32]) -> f32 {
+    pixels.iter().map(|&pixel| Color::from_u32(pixel).luminance()).sum()
+}
+
+// Con el acelerador al máximo el penacho es más largo y más brillante que a la mitad, y en
+// 0 no cambia ningún pixel
+#[test]
+fn plume_grows_with_throttle() {
+    let (full, half) = (plume_image(1.0, 0.0), plume_image(0.5, 0.0));
+    assert!(lit_rows(&half).len() < lit_rows(&full).len());
+    assert!(brightness(&half) < brightness(&full));
+    assert!(lit_rows(&plume_image(0.0, 0.0)).is_empty());
+}
+
+// El brillo medio por pixel encendido baja de la tobera hacia el extremo abierto, y el
+// ruido se mueve con `time`
+#[test]
+fn plume_fades_toward_the_open_end_and_animates() {
+    let image = plume_image(1.0, 0.0);
+    let rows = lit_rows(&image);
+    let quarter = (rows.len() / 4).max(1);
+    let mean = |rows: &[&[u32]]| {
+        let lit: Vec<u32> = rows.iter().flat_map(|row| row.iter().copied()).filter(|&pixel| pixel & 0x00FF_FFFF != 0).collect();
+        brightness(&lit) / lit.len().max(1) as f32
+    };
+    // La tobera está en y = -2, que en pantalla queda arriba o abajo según el viewport
+    let nozzle_row = (viewport_matrix(320, 240, 50.0) * nalgebra_glm::vec4(0.0, -2.0, 0.0, 1.0)).y;
+    let (top, bottom) = (mean(&rows[..quarter]), mean(&rows[rows.len() - quarter..]));
+    let (nozzle, open_end) = if nozzle_row < 120.0 { (top, bottom) } else { (bottom, top) };
+    assert!(nozzle > open_end * 1.5, "tobera {nozzle}, extremo abierto {open_end}");
+
+    assert!(image != plume_image(1.0, 0.5));
+}
+
+// Una esfera opaca delante tapa el penacho, y el penacho no tapa a lo que se dibuja
+// después detrás de él
+#[test]
+fn plume_respects_depth_without_writing_it() {
+    let opaque = |framebuffer: &mut Framebuffer, z: f32| {
+        let uniforms = UniformsBuilder::new().viewport(viewport_matrix(320, 240, 50.0)).build();
+        render(framebuffer, &uniforms, &sphere(Vec3::new(0.0, 0.0, z), 0.5, 16, 32, Color::new(60, 60, 60)));
+    };
+    let center = (160, 120);
+
+    let mut occluded = Framebuffer::new(320, 240);
+    occluded.clear(Color::black());
+    opaque(&mut occluded, -3.0);
+    let before = occluded.get_pixel(center.0, center.1);
+    draw_plume(&mut occluded, 1.0, 0.0);
+    assert_eq!(occluded.get_pixel(center.0, center.1), before);
+
+    let mut behind = Framebuffer::new(320, 240);
+    behind.clear(Color::black());
+    draw_plume(&mut behind, 1.0, 0.0);
+    let plume_only = behind.get_pixel(center.0, center.1);
+    opaque(&mut behind, 3.0);
+    assert_ne!(behind.get_pixel(center.0, center.1), plume_only);
+}
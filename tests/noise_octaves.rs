@@ -0,0 +1,32 @@
+// noise_octaves.rs
+//
+// Octavas de fBm: cada una suma detalle más fino.
+
+use renderer::shader::fbm_2d;
+
+// Cada octava suma ruido al doble de frecuencia, así que con más octavas el ruido cambia más
+// entre puntos cercanos: la varianza local (promedio del cuadrado de la diferencia entre
+// muestras vecinas de una grilla fina) crece con cada octava
+#[test]
+fn local_variance_grows_with_each_octave() {
+    const GRID: usize = 128;
+    const STEP: f32 = 0.01; // Más fina que la octava más alta
+    let local_variance = |octaves: u32| {
+        let sample = |i: usize, j: usize| fbm_2d(i as f32 * STEP, j as f32 * STEP, octaves, 7);
+        let mut sum = 0.0;
+        for j in 0..GRID {
+            for i in 0..GRID {
+                let center = sample(i, j);
+                sum += [sample(i + 1, j), sample(i, j + 1)].iter().map(|neighbor| (neighbor - center) * (neighbor - center)).sum::<f32>();
+            }
+        }
+        sum / (GRID * GRID * 2) as f32
+    };
+
+    let mut previous = 0.0;
+    for octaves in 1..=8 {
+        let variance = local_variance(octaves);
+        assert!(variance > previous, "{octaves} octavas: {variance:e} después de {previous:e}");
+        previous = variance;
+    }
+}
@@ -0,0 +1,85 @@
+// parallel_vertices.rs
+//
+// El vertex shader en paralelo da exactamente los mismos bits que en un solo hilo.
+
+mod common;
+
+use common::covered;
+use nalgebra_glm::{Vec2, Vec3};
+use renderer::color::Color;
+use renderer::framebuffer::{render, Framebuffer, RenderStats};
+use renderer::light::Light;
+use renderer::pipeline::{PipelineStage, StageContext, StageData, VertexShaderStage};
+use renderer::shader::DefaultVertexShader;
+use renderer::uniforms::{viewport_matrix, ShadingModel, UniformsBuilder};
+use renderer::vertex::Vertex;
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+
+// Triángulos chicos sobre una esfera de radio 2.5, cada uno en una dirección de la espiral
+// de Fibonacci
+fn synthetic_mesh(triangles: usize) -> Vec<Vertex> {
+    let golden = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+    let mut vertices = Vec::with_capacity(triangles * 3);
+    for i in 0..triangles {
+        let y = 1.0 - 2.0 * (i as f32 + 0.5) / triangles as f32;
+        let ring = (1.0 - y * y).sqrt();
+        let normal = Vec3::new(ring * (golden * i as f32).cos(), y, ring * (golden * i as f32).sin());
+        let tangent = normal.cross(&Vec3::new(0.3, 1.0, 0.2)).normalize();
+        let bitangent = normal.cross(&tangent);
+        for offset in [tangent * 0.03, bitangent * 0.03, -tangent * 0.03] {
+            let mut vertex = Vertex::new(normal * 2.5 + offset, normal, Vec2::new(y, ring));
+            vertex.color = Color::new(200, 180, 160);
+            vertices.push(vertex);
+        }
+    }
+    vertices
+}
+
+// Bits de todo lo que calcula el vertex shader
+fn vertex_bits(vertex: &Vertex) -> Vec<u32> {
+    [vertex.transformed_position, vertex.transformed_normal, vertex.prev_transformed_position, vertex.view_position, vertex.view_normal, vertex.light]
+        .iter()
+        .flat_map(|v| v.iter().map(|c| c.to_bits()).collect::<Vec<_>>())
+        .chain(vertex.clip_position.iter().map(|v| v.to_bits()))
+        .collect()
+}
+
+// Con sombreado Gouraud y datos de vista cada vértice hace todo el trabajo del vertex shader
+#[test]
+fn parallel_vertex_stage_is_bit_identical() {
+    let vertices = synthetic_mesh(20_000);
+    let mut uniforms = UniformsBuilder::new()
+        .viewport(viewport_matrix(WIDTH, HEIGHT, 40.0))
+        .lights(vec![Light::new(Vec3::new(-1.0, -1.0, 1.0), Color::new(255, 255, 255), 1.0)])
+        .build();
+    uniforms.shading_model = ShadingModel::Gouraud;
+    uniforms.fragment_view_data = true;
+
+    let run = || {
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(Color::black());
+        let mut context = StageContext { uniforms: &uniforms, framebuffer: &mut framebuffer, stats: RenderStats::default() };
+        let transformed = match (VertexShaderStage { shader: &DefaultVertexShader }).process(StageData::Vertices(vertices.clone()), &mut context) {
+            StageData::Vertices(transformed) => transformed.iter().map(vertex_bits).collect::<Vec<_>>(),
+            _ => unreachable!("el vertex shader devuelve vértices"),
+        };
+        render(&mut framebuffer, &uniforms, &vertices);
+        let depths: Vec<u32> = (0..HEIGHT as isize)
+            .flat_map(|y| (0..WIDTH as isize).map(move |x| (x, y)))
+            .map(|(x, y)| framebuffer.get_depth(x, y).unwrap_or(f32::NAN).to_bits())
+            .collect();
+        (transformed, framebuffer.as_slice().to_vec(), depths)
+    };
+    let in_pool = |threads: usize| rayon::ThreadPoolBuilder::new().num_threads(threads).build().expect("pool de prueba").install(run);
+
+    let serial = in_pool(1);
+    // Al menos 4 hilos para que el trabajo se reparta aunque la máquina tenga un núcleo
+    let parallel = in_pool(std::thread::available_parallelism().map_or(1, |count| count.get()).max(4));
+    assert_eq!(serial.0.len(), vertices.len());
+    assert!(covered(&serial.1) > 0);
+    assert!(serial.0 == parallel.0, "vértices distintos");
+    assert!(serial.1 == parallel.1, "pixeles distintos");
+    assert!(serial.2 == parallel.2, "profundidades distintas");
+}
@@ -0,0 +1,87 @@
+// quad_raster.rs
+//
+// Rasterización por cuadros de 2x2 contra la referencia escalar fila por fila.
+
+mod common;
+
+use common::{covered, Rng};
+use nalgebra_glm::{Vec2, Vec3};
+use renderer::color::Color;
+use renderer::edge_function::{covered_in_quads, covered_in_row_scalar, CoveredPixel, QuadPixel, TriangleSetup};
+use renderer::framebuffer::{render, Framebuffer};
+use renderer::material::Material;
+use renderer::uniforms::{viewport_matrix, CullMode, RasterizerMode, UniformsBuilder};
+use renderer::vertex::Vertex;
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+
+// Pixeles de un triángulo en pantalla, fila por fila con la ruta escalar y por cuadros
+fn rows_and_quads(a: Vec3, b: Vec3, c: Vec3) -> (Vec<QuadPixel>, Vec<QuadPixel>) {
+    let Some(setup) = TriangleSetup::new(&a, &b, &c) else { return (Vec::new(), Vec::new()) };
+    let depths = Vec3::new(a.z, b.z, c.z);
+    let (min_x, min_y) = (a.x.min(b.x).min(c.x).floor() as i32, a.y.min(b.y).min(c.y).floor() as i32);
+    let (max_x, max_y) = (a.x.max(b.x).max(c.x).ceil() as i32, a.y.max(b.y).max(c.y).ceil() as i32);
+
+    let (mut rows, mut row) = (Vec::new(), Vec::new());
+    for y in min_y..=max_y {
+        row.clear();
+        covered_in_row_scalar(&setup, y, min_x, max_x, &mut row);
+        for &CoveredPixel { x, u, v, w } in &row {
+            rows.push(QuadPixel { x, y, u, v, w, depth: u * depths.x + v * depths.y + w * depths.z });
+        }
+    }
+    let mut quads = Vec::new();
+    for y in (min_y..=max_y).step_by(2) {
+        covered_in_quads(&setup, &depths, y, min_x, max_x, max_y, &mut quads);
+    }
+
+    // Los cuadros salen en otro orden que las filas
+    let key = |pixel: &QuadPixel| (pixel.y, pixel.x);
+    rows.sort_by_key(key);
+    quads.sort_by_key(key);
+    (rows, quads)
+}
+
+// Triángulos aleatorios, muchos delgados y muchos fuera de pantalla en parte
+#[test]
+fn quads_cover_the_same_pixels_as_scalar_rows() {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    let mut covered = 0;
+    for _ in 0..500 {
+        let mut point = || Vec3::new(rng.range(-40.0, 360.0), rng.range(-40.0, 280.0), rng.range(0.0, 1.0));
+        let (a, b, c) = (point(), point(), point());
+        let (rows, quads) = rows_and_quads(a, b, c);
+        assert_eq!(rows, quads, "{a:?} {b:?} {c:?}");
+        covered += rows.len();
+    }
+    assert!(covered > 0);
+}
+
+
+#[test]
+fn quad_rasterizer_renders_the_same_image() {
+    let corner = |x: f32, y: f32| {
+        let mut vertex = Vertex::new(Vec3::new(x, y, 0.5 + 0.1 * x), Vec3::new(0.0, 0.0, -1.0), Vec2::zeros());
+        vertex.color = Color::new((128.0 + 100.0 * x) as u8, (128.0 + 100.0 * y) as u8, 200);
+        vertex
+    };
+    let (a, b, c, d) = (corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0));
+    let full_screen = vec![a.clone(), b, c.clone(), a, c, d];
+
+    let image = |mode: RasterizerMode| {
+        let mut uniforms = UniformsBuilder::new()
+            .viewport(viewport_matrix(WIDTH, HEIGHT, WIDTH as f32 / 2.0))
+            .material(Material { unlit: true, ..Material::default() })
+            .cull_mode(CullMode::None)
+            .build();
+        uniforms.rasterizer_mode = mode;
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(Color::black());
+        render(&mut framebuffer, &uniforms, &full_screen);
+        framebuffer.as_slice().to_vec()
+    };
+    let reference = image(RasterizerMode::BoundingBox);
+    assert_eq!(covered(&reference), WIDTH * HEIGHT);
+    assert!(reference == image(RasterizerMode::Quads));
+}
@@ -0,0 +1,83 @@
+// raster_bounds.rs
+//
+// Bounding Box de los triángulos recortado al framebuffer (o a un scissor) con vértices muy
+// lejos de la pantalla.
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use nalgebra_glm::{Vec2, Vec3};
+use renderer::framebuffer::{primitive_assembly_rasterization, Rect, RenderStats};
+use renderer::shader::vertex_shader;
+use renderer::uniforms::{viewport_matrix, CullMode, RasterizerMode, Uniforms, UniformsBuilder};
+use renderer::vertex::Vertex;
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+
+// Triángulo con vértices en pixeles de pantalla (y hacia abajo, origen en la esquina)
+fn screen_triangle(points: [(f32, f32); 3], uniforms: &Uniforms) -> Vec<Vertex> {
+    points
+        .iter()
+        .map(|&(x, y)| {
+            let position = Vec3::new((x - WIDTH as f32 / 2.0) / 50.0, (y - HEIGHT as f32 / 2.0) / 50.0, 0.0);
+            vertex_shader(&Vertex::new(position, Vec3::new(0.0, 0.0, -1.0), Vec2::zeros()), uniforms)
+        })
+        .collect()
+}
+
+// Pixeles cubiertos, ordenados
+fn fragments_in(vertices: &[Vertex], uniforms: &Uniforms, bounds: &Rect) -> Vec<(i32, i32)> {
+    let mut stats = RenderStats::default();
+    let mut pixels: Vec<(i32, i32)> = primitive_assembly_rasterization(vertices, uniforms, bounds, &mut stats)
+        .iter()
+        .map(|fragment| (fragment.position.x as i32, fragment.position.y as i32))
+        .collect();
+    pixels.sort_unstable();
+    pixels
+}
+
+fn inside(rect: &Rect, (x, y): (i32, i32)) -> bool {
+    x >= rect.x as i32 && y >= rect.y as i32 && x < (rect.x + rect.width) as i32 && y < (rect.y + rect.height) as i32
+}
+
+// Triángulos con un vértice a 10^8 pixeles, como los que deja un vértice detrás de la
+// cámara antes del clipping: el render termina enseguida en lugar de recorrer un Bounding
+// Box de 10^16 pixeles, y los fragmentos quedan dentro de los límites
+fn check_bounds(mode: RasterizerMode) {
+    let mut uniforms = UniformsBuilder::new().viewport(viewport_matrix(WIDTH, HEIGHT, 50.0)).cull_mode(CullMode::None).build();
+    uniforms.rasterizer_mode = mode;
+    let full = Rect::new(0, 0, WIDTH, HEIGHT);
+    let small = Rect::new(50, 40, 100, 80);
+
+    // Cuña que cubre casi toda la pantalla y sigue hacia la derecha y abajo
+    let huge = screen_triangle([(-20.0, 30.0), (1e8, 60.0), (40.0, 1e8)], &uniforms);
+    let all = fragments_in(&huge, &uniforms, &full);
+    assert!(!all.is_empty() && all.iter().all(|&pixel| inside(&full, pixel)), "{mode:?}");
+    // Con límites más chicos (como los de un scissor) son los mismos fragmentos recortados
+    let expected: Vec<(i32, i32)> = all.iter().copied().filter(|&pixel| inside(&small, pixel)).collect();
+    assert!(!expected.is_empty());
+    assert_eq!(fragments_in(&huge, &uniforms, &small), expected, "{mode:?}");
+
+    // Con coordenadas tan grandes la cobertura exacta de una franja casi degenerada queda al
+    // límite de la precisión de f32, así que solo se comprueban los límites
+    let sliver = screen_triangle([(-20.0, 30.0), (1e8, 1e8 + 80.0), (1e8, 1e8 + 30.0)], &uniforms);
+    assert!(fragments_in(&sliver, &uniforms, &full).iter().all(|&pixel| inside(&full, pixel)), "{mode:?}");
+
+    let away = screen_triangle([(-1e8, -1e8), (-1e8 + 10.0, 1e8), (-5.0, 0.0)], &uniforms);
+    assert!(fragments_in(&away, &uniforms, &full).is_empty(), "{mode:?}");
+}
+
+#[test]
+fn huge_triangles_stay_within_the_bounds() {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for mode in [RasterizerMode::BoundingBox, RasterizerMode::Scanline, RasterizerMode::Quads] {
+            check_bounds(mode);
+        }
+        let _ = sender.send(());
+    });
+    // Sin respuesta: entró en pánico (el canal se cierra) o no terminó a tiempo
+    receiver.recv_timeout(Duration::from_secs(20)).expect("el rasterizador termina sin errores");
+}
@@ -0,0 +1,62 @@
+// render_target.rs
+//
+// Render a una textura con `RenderTarget` y realimentación de un frame al siguiente.
+
+mod common;
+
+use common::vertex;
+use nalgebra_glm::{Vec2, Vec3};
+use renderer::color::Color;
+use renderer::framebuffer::render;
+use renderer::material::Material;
+use renderer::texture::{RenderTarget, Texture};
+use renderer::uniforms::{ndc_viewport_matrix, CullMode, UniformsBuilder};
+use renderer::vertex::Vertex;
+
+const SIZE: usize = 64;
+
+// Cuadrado que cubre todo el viewport, con coordenadas de textura de 0 a 1 (v hacia abajo)
+fn full_screen_quad(color: Color) -> Vec<Vertex> {
+    let corner = |x: f32, y: f32| {
+        let mut vertex = vertex(Vec3::new(x, y, 0.5), color);
+        vertex.tex_coords = Vec2::new((x + 1.0) / 2.0, (1.0 - y) / 2.0);
+        vertex
+    };
+    let (a, b, c, d) = (corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0));
+    vec![a.clone(), b, c.clone(), a, c, d]
+}
+
+fn draw(target: &mut RenderTarget, material: Material, color: Color) {
+    let uniforms = UniformsBuilder::new().viewport(ndc_viewport_matrix(SIZE, SIZE)).material(material).cull_mode(CullMode::None).build();
+    let framebuffer = target.framebuffer_mut();
+    framebuffer.clear(Color::black());
+    render(framebuffer, &uniforms, &full_screen_quad(color));
+}
+
+// Cuántas muestras de la textura (en una grilla, incluidos los bordes) no tienen `expected`
+fn mismatches(texture: &Texture, expected: Color) -> usize {
+    let steps = 8;
+    (0..=steps)
+        .flat_map(|i| (0..=steps).map(move |j| (i as f32 / steps as f32, j as f32 / steps as f32)))
+        .filter(|&(u, v)| texture.sample(u, v).to_u32() != expected.to_u32())
+        .count()
+}
+
+#[test]
+fn resolved_texture_feeds_the_next_frame() {
+    let mut target = RenderTarget::new(SIZE, SIZE);
+    let unlit = Material { unlit: true, ..Material::default() };
+
+    // Un cuadrado de un color a pantalla completa: la textura publicada tiene ese color
+    let color = Color::new(40, 180, 90);
+    draw(&mut target, unlit.clone(), color);
+    let first = target.resolve();
+    assert_eq!(mismatches(&first, color), 0);
+
+    // El frame siguiente limpia el destino y dibuja un cuadrado negro con la textura del
+    // anterior: vuelve a tener su color, y la textura anterior no cambia
+    let previous = target.texture().expect("ya hay una textura publicada");
+    draw(&mut target, Material { texture: Some(previous), ..unlit }, Color::black());
+    assert_eq!(mismatches(&target.resolve(), color), 0);
+    assert_eq!(mismatches(&first, color), 0);
+}
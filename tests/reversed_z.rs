@@ -0,0 +1,48 @@
+// reversed_z.rs
+//
+// Reversed-Z contra el z-fighting de la geometría lejana con un plano lejano muy grande.
+
+mod common;
+
+use common::{covered, quad};
+use nalgebra_glm::Vec3;
+use renderer::camera::Projection;
+use renderer::color::Color;
+use renderer::framebuffer::{render, DepthMode, Framebuffer};
+use renderer::uniforms::{ndc_viewport_matrix, Uniforms, UniformsBuilder};
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+
+fn uniforms(projection: &Projection) -> Uniforms {
+    UniformsBuilder::new()
+        .projection(projection.matrix(WIDTH as f32 / HEIGHT as f32))
+        .viewport(ndc_viewport_matrix(WIDTH, HEIGHT))
+        .build()
+}
+
+fn framebuffer(depth_mode: DepthMode) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    framebuffer.set_depth_mode(depth_mode);
+    framebuffer.clear(Color::black());
+    framebuffer
+}
+
+// Dos cuadrados a ~5000 unidades de la cámara y separados 1 unidad, con el plano lejano en
+// 100000: con la profundidad estándar la diferencia cae por debajo de la precisión del
+// float cerca de 1 y el de atrás se cuela; con reversed-Z el de adelante gana en todos
+#[test]
+fn reversed_z_resolves_distant_z_fighting() {
+    let mut vertices = quad(Vec3::new(0.0, 0.0, 5000.0), 3000.0, Color::new(220, 40, 40));
+    vertices.extend(quad(Vec3::new(0.0, 0.0, 5001.0), 3000.0, Color::new(40, 40, 220)));
+
+    let fighting = |depth_mode: DepthMode| {
+        let mut framebuffer = framebuffer(depth_mode);
+        render(&mut framebuffer, &uniforms(&Projection { near: 0.1, far: 100000.0, depth_mode, ..Projection::default() }), &vertices);
+        assert!(covered(framebuffer.as_slice()) > 0);
+        // Pixeles donde domina el azul del cuadrado de atrás
+        framebuffer.as_slice().iter().map(|&pixel| Color::from_u32(pixel)).filter(|color| color.b > color.r).count()
+    };
+    assert!(fighting(DepthMode::Standard) > 0);
+    assert_eq!(fighting(DepthMode::Reversed), 0);
+}
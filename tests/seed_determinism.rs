@@ -0,0 +1,111 @@
+// seed_determinism.rs
+//
+// Renders headless de una escena armada solo a partir de la semilla maestra (`SceneSeed`):
+// una esfera con el shader de ruido (horneado), otra que refleja el fondo de estrellas, el
+// cinturón de asteroides y las partículas del motor después de un segundo simulado.
+
+mod common;
+
+use std::process::Command;
+use std::sync::Arc;
+
+use common::{covered, image_hash, sphere};
+use nalgebra_glm::{Mat4, Vec3};
+use renderer::color::Color;
+use renderer::environment::Cubemap;
+use renderer::framebuffer::{render, Framebuffer};
+use renderer::instancing::{generate_belt, render_instanced, rock_mesh};
+use renderer::material::Material;
+use renderer::math::BoundingBox;
+use renderer::noise_bake::BakedNoise;
+use renderer::particles::{render_particles, EmitterConfig, ParticleSystem};
+use renderer::seed::SceneSeed;
+use renderer::shader::{noise_based_fragment_shader, NoiseSettings};
+use renderer::uniforms::{viewport_matrix, UniformsBuilder};
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+const SEED: u64 = 2312;
+// Variable con la que el proceso hijo recibe su semilla
+const CHILD_SEED: &str = "RENDERER_TEST_SCENE_SEED";
+
+fn render_scene(seed: SceneSeed) -> Vec<u32> {
+    let viewport = viewport_matrix(WIDTH, HEIGHT, 50.0);
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    framebuffer.clear(Color::black());
+
+    // Esfera con ruido horneado, con la semilla del ruido del modelo
+    let noisy = sphere(Vec3::new(-1.2, 0.0, 0.0), 1.0, 24, 48, Color::new(255, 255, 255));
+    let mut material = Material { fragment_shader: Some(noise_based_fragment_shader), noise_scale: 2.0, ..Material::default() }
+        .with_param("seed", seed.shader("model_noise") as f32)
+        .with_param("noise_bake_size", 128.0);
+    if let Some(bounds) = BoundingBox::from_vertices(&noisy) {
+        let settings = NoiseSettings::noise_shader(&material, 0.0, 0.0);
+        material.baked_noise = Some(Arc::new(BakedNoise::for_material(&settings, &material, &bounds, None)));
+    }
+    render(&mut framebuffer, &UniformsBuilder::new().viewport(viewport).material(material).build(), &noisy);
+
+    // Esfera que refleja las estrellas
+    let environment = Arc::new(Cubemap::space(64, seed.shader("stars")));
+    let uniforms = UniformsBuilder::new()
+        .viewport(viewport)
+        .environment(Some(environment))
+        .material(Material { reflectivity: 1.0, ..Material::default() })
+        .build();
+    render(&mut framebuffer, &uniforms, &sphere(Vec3::new(1.2, 0.0, 0.0), 1.0, 24, 48, Color::new(255, 255, 255)));
+
+    // Cinturón de asteroides
+    let rock = rock_mesh(seed.derive("rock"), 0.05);
+    let belt = generate_belt(seed.derive("belt"), 500, 1.5, 2.2, 0.3);
+    render_instanced(&mut framebuffer, &UniformsBuilder::new().viewport(viewport).time(3.0).build(), &rock, &belt, 0.5);
+
+    // Partículas del motor después de un segundo
+    let mut exhaust = ParticleSystem::new(EmitterConfig::exhaust(), seed.derive("exhaust"));
+    let emitter = Mat4::new_translation(&Vec3::new(0.0, 1.5, 0.0));
+    for _ in 0..60 {
+        exhaust.update(1.0 / 60.0, &emitter, Vec3::zeros());
+    }
+    render_particles(&mut framebuffer, &UniformsBuilder::new().viewport(viewport).build(), &exhaust);
+
+    framebuffer.as_slice().to_vec()
+}
+
+// Lo ejecuta `same_seed_matches_across_processes` en un proceso hijo: imprime el hash de la
+// escena con la semilla de CHILD_SEED
+#[test]
+#[ignore = "solo corre como proceso hijo de same_seed_matches_across_processes"]
+fn child_scene_hash() {
+    if let Some(seed) = std::env::var(CHILD_SEED).ok().and_then(|seed| seed.parse().ok()) {
+        println!("hash {}", image_hash(&render_scene(SceneSeed::new(seed))));
+    }
+}
+
+// Hash de la imagen que renderiza un proceso hijo con la semilla `seed`
+fn child_hash(seed: u64) -> u64 {
+    let output = Command::new(std::env::current_exe().expect("ejecutable de las pruebas"))
+        .args(["child_scene_hash", "--exact", "--ignored", "--nocapture", "--test-threads=1"])
+        .env(CHILD_SEED, seed.to_string())
+        .output()
+        .expect("el proceso hijo arranca");
+    // La salida del hijo va en la misma línea que el nombre de la prueba
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .find_map(|line| line.rsplit_once("hash ")?.1.trim().parse().ok())
+        .unwrap_or_else(|| panic!("el proceso hijo no imprimió un hash:\n{stdout}"))
+}
+
+// Dos procesos con la misma semilla dan la misma imagen, y otra semilla la cambia
+#[test]
+fn same_seed_matches_across_processes() {
+    let first = child_hash(SEED);
+    assert_eq!(child_hash(SEED), first);
+    assert_ne!(child_hash(SEED + 1), first);
+}
+
+#[test]
+fn same_seed_matches_within_a_process() {
+    let first = render_scene(SceneSeed::new(SEED));
+    assert!(covered(&first) > 0);
+    assert!(first == render_scene(SceneSeed::new(SEED)));
+}
@@ -0,0 +1,77 @@
+// topology.rs
+//
+// Tiras y abanicos de triángulos contra la misma geometría como lista.
+
+mod common;
+
+use common::covered;
+use nalgebra_glm::{Vec2, Vec3};
+use renderer::color::Color;
+use renderer::framebuffer::{render, Framebuffer};
+use renderer::material::Material;
+use renderer::uniforms::{viewport_matrix, CullMode, PrimitiveTopology, UniformsBuilder};
+use renderer::vertex::Vertex;
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+
+fn topology_vertex(x: f32, y: f32) -> Vertex {
+    let mut vertex = Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec2::zeros());
+    vertex.color = Color::new((128.0 + 80.0 * x) as u8, (128.0 + 80.0 * y) as u8, 200);
+    vertex
+}
+
+
+fn draw_topology(vertices: &[Vertex], topology: PrimitiveTopology, cull_mode: CullMode) -> Vec<u32> {
+    let mut uniforms = UniformsBuilder::new()
+        .viewport(viewport_matrix(WIDTH, HEIGHT, 60.0))
+        .material(Material { unlit: true, ..Material::default() })
+        .cull_mode(cull_mode)
+        .build();
+    uniforms.topology = topology;
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    framebuffer.clear(Color::black());
+    render(&mut framebuffer, &uniforms, vertices);
+    framebuffer.as_slice().to_vec()
+}
+
+// Una franja de 12 cuadrados como tira (26 vértices) y como lista (72 vértices)
+#[test]
+fn triangle_strip_matches_the_triangle_list() {
+    const QUADS: usize = 12;
+    // De x = -2 a 2, entre y = -0.5 (arriba) e y = 0.5 (abajo)
+    let x = |i: usize| -2.0 + 4.0 * i as f32 / QUADS as f32;
+    let strip: Vec<Vertex> = (0..=QUADS).flat_map(|i| [topology_vertex(x(i), -0.5), topology_vertex(x(i), 0.5)]).collect();
+    let mut list = Vec::new();
+    for i in 0..QUADS {
+        let (top, bottom) = (topology_vertex(x(i), -0.5), topology_vertex(x(i), 0.5));
+        let (next_top, next_bottom) = (topology_vertex(x(i + 1), -0.5), topology_vertex(x(i + 1), 0.5));
+        list.extend([top, bottom.clone(), next_top.clone(), next_top, bottom, next_bottom]);
+    }
+    let strip_image = draw_topology(&strip, PrimitiveTopology::TriangleStrip, CullMode::None);
+    assert!(covered(&strip_image) > 0);
+    assert!(strip_image == draw_topology(&list, PrimitiveTopology::TriangleList, CullMode::None));
+
+    // Todos los triángulos de la tira tienen el mismo winding pese a que alterna el orden de
+    // los vértices: se ve completa con un culling y desaparece con el otro
+    let back = covered(&draw_topology(&strip, PrimitiveTopology::TriangleStrip, CullMode::Back));
+    let front = covered(&draw_topology(&strip, PrimitiveTopology::TriangleStrip, CullMode::Front));
+    assert!((back == 0) != (front == 0), "{back} y {front}");
+    assert_eq!(back + front, covered(&strip_image));
+}
+
+// Un polígono de 10 lados como abanico desde el centro y como lista
+#[test]
+fn triangle_fan_matches_the_triangle_list() {
+    const SIDES: usize = 10;
+    let corner = |i: usize| {
+        let angle = std::f32::consts::TAU * (i % SIDES) as f32 / SIDES as f32;
+        topology_vertex(1.5 * angle.cos(), 1.5 * angle.sin())
+    };
+    let fan: Vec<Vertex> = std::iter::once(topology_vertex(0.0, 0.0)).chain((0..=SIDES).map(corner)).collect();
+    let list: Vec<Vertex> = (0..SIDES).flat_map(|i| [topology_vertex(0.0, 0.0), corner(i), corner(i + 1)]).collect();
+
+    let fan_image = draw_topology(&fan, PrimitiveTopology::TriangleFan, CullMode::None);
+    assert!(covered(&fan_image) > 0);
+    assert!(fan_image == draw_topology(&list, PrimitiveTopology::TriangleList, CullMode::None));
+}
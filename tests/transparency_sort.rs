@@ -0,0 +1,98 @@
+// transparency_sort.rs
+//
+// Orden de dibujo de `RenderQueue`: opacos de adelante hacia atrás y transparentes de atrás
+// hacia adelante.
+
+mod common;
+
+use common::quad;
+use nalgebra_glm::Vec3;
+use renderer::color::Color;
+use renderer::fragment::Fragment;
+use renderer::framebuffer::{render, DepthMode, Framebuffer};
+use renderer::material::{BlendMode, Material};
+use renderer::render_queue::RenderQueue;
+use renderer::uniforms::{viewport_matrix, Uniforms, UniformsBuilder};
+use renderer::vertex::Vertex;
+
+// Opacidad del parámetro "opacity" del material
+fn opacity_shader(fragment: &mut Fragment, uniforms: &Uniforms) {
+    fragment.alpha = uniforms.material.param("opacity", 1.0);
+}
+
+struct Object {
+    vertices: Vec<Vertex>,
+    color: Color,
+    blend_mode: BlendMode,
+    opacity: f32,
+}
+
+impl Object {
+    fn uniforms(&self) -> Uniforms {
+        let material = Material { unlit: true, blend_mode: self.blend_mode, fragment_shader: Some(opacity_shader), ..Material::default() }
+            .with_param("opacity", self.opacity);
+        UniformsBuilder::new().viewport(viewport_matrix(160, 120, 40.0)).material(material).build()
+    }
+}
+
+// Dos cuadrados semitransparentes a distintas profundidades que se superponen, sobre un
+// cuadrado opaco más atrás
+fn transparent_scene() -> [Object; 3] {
+    let (near, far, wall) = (Color::new(255, 60, 40), Color::new(40, 120, 255), Color::new(90, 200, 90));
+    [
+        Object { vertices: quad(Vec3::new(-0.3, 0.0, -1.0), 1.2, near), color: near, blend_mode: BlendMode::Alpha, opacity: 0.5 },
+        Object { vertices: quad(Vec3::new(0.3, 0.0, 1.0), 1.2, far), color: far, blend_mode: BlendMode::Alpha, opacity: 0.6 },
+        Object { vertices: quad(Vec3::new(0.0, 0.0, 2.0), 1.0, wall), color: wall, blend_mode: BlendMode::Opaque, opacity: 1.0 },
+    ]
+}
+
+// Con cualquier orden de entrada la cola dibuja el opaco, después el transparente lejano y
+// encima el cercano; dibujarlos en el orden de entrada sin la cola da otro color
+#[test]
+fn render_queue_sorts_transparent_layers() {
+    const BACKGROUND: Color = Color { r: 10, g: 10, b: 30 };
+    let objects = transparent_scene();
+    let [near, far, wall] = &objects;
+    let expected = BlendMode::Alpha.combine(near.color, near.opacity, BlendMode::Alpha.combine(far.color, far.opacity, wall.color)).to_u32();
+
+    let mut reference: Option<Vec<u32>> = None;
+    for order in [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]] {
+        let mut framebuffer = Framebuffer::new(160, 120);
+        framebuffer.clear(BACKGROUND);
+        let mut queue = RenderQueue::new();
+        for index in order {
+            queue.push(objects[index].uniforms(), &objects[index].vertices);
+        }
+        queue.render(&mut framebuffer);
+        assert_eq!(framebuffer.get_pixel(80, 60), Some(expected), "entrada {order:?}");
+        let image = framebuffer.as_slice().to_vec();
+        assert!(reference.get_or_insert_with(|| image.clone()) == &image, "entrada {order:?}");
+    }
+
+    let mut unsorted = Framebuffer::new(160, 120);
+    unsorted.clear(BACKGROUND);
+    for object in [wall, near, far] {
+        render(&mut unsorted, &object.uniforms(), &object.vertices);
+    }
+    assert_ne!(unsorted.get_pixel(80, 60), Some(expected));
+}
+
+// Con reversed-Z las profundidades se invierten (z -> -z), así que el cuadrado cercano
+// pasa a ser el de mayor profundidad y el orden de dibujo es el mismo
+#[test]
+fn draw_order_with_reversed_depth() {
+    let [near, far, wall] = transparent_scene();
+    let flipped = |vertices: &[Vertex]| -> Vec<Vertex> {
+        vertices.iter().cloned().map(|mut vertex| { vertex.position.z = -vertex.position.z; vertex }).collect()
+    };
+    let small_wall = quad(Vec3::new(0.0, 0.0, -3.0), 0.5, wall.color);
+    let meshes = [flipped(&near.vertices), flipped(&far.vertices), flipped(&wall.vertices), flipped(&small_wall)];
+    let uniforms = [near.uniforms(), far.uniforms(), wall.uniforms(), wall.uniforms()];
+    let mut queue = RenderQueue::new();
+    for (uniforms, vertices) in uniforms.into_iter().zip(&meshes) {
+        queue.push(uniforms, vertices);
+    }
+    // Opacos de adelante hacia atrás (el chico, ahora en z = 3, antes del grande en z = -2) y
+    // después los transparentes de atrás hacia adelante
+    assert_eq!(queue.draw_order(DepthMode::Reversed), [3, 2, 1, 0]);
+}